@@ -0,0 +1,292 @@
+//! The borrow checker: a reference must not be used once what it points to has been
+//! moved out or has gone out of scope. There is no &/&mut split and no lifetime in the
+//! types, so the checker follows the references themselves through the control-flow
+//! graph of each body.
+//!
+//! Each borrow (&x, or a method taking &self) makes a loan of its place. A binding
+//! given a value made from a borrow, or from another binding holding loans, holds them
+//! too, if its type has references in it: let r = &x; let m = Map::from(1, r); leaves
+//! both r and m holding the loan of x. Moving x, or leaving its scope, ends the loan,
+//! which is only an error if something holding it is used afterwards. Returning a
+//! reference to a place the function owns is always one.
+
+use std::path::PathBuf;
+
+use crate::analysis::cfg::{self, BlockId, Cfg, Event, Place, Projection};
+use crate::analysis::moves;
+use crate::analysis::typeck::{BoundInfo, TypeCx, TypeckResults};
+use crate::analysis::types::Type;
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BorrowErrKind {
+    /// PLACE, borrowed at BORROWED, is moved while the borrow is still used at USED
+    MovedWhileBorrowed { place: String, borrowed: Span, used: Span },
+    /// PLACE goes out of scope at DROPPED while the borrow is still used at USED
+    DroppedWhileBorrowed { place: String, dropped: Span, used: Span },
+    /// A reference to PLACE, which the function owns, is returned
+    ReturnsLocal { place: String, borrowed: Span }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BorrowErr {
+    pub kind: BorrowErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl BorrowErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match &self.kind {
+            BorrowErrKind::MovedWhileBorrowed { place, borrowed, used } => {
+                Diagnostic::error(&format!("cannot move out of {} while it is borrowed", place), self.span, "moved here")
+                    .with_label(*borrowed, &format!("{} is borrowed here", place))
+                    .with_label(*used, "the borrow is used here, later")
+            }
+            BorrowErrKind::DroppedWhileBorrowed { place, dropped, used } => {
+                Diagnostic::error(&format!("{} does not live long enough", place), self.span, "borrowed here")
+                    .with_label(*dropped, &format!("{} goes out of scope here, while still borrowed", place))
+                    .with_label(*used, "the borrow is used here, later")
+            }
+            BorrowErrKind::ReturnsLocal { place, borrowed } => {
+                Diagnostic::error(&format!("cannot return a reference to {}", place), self.span, "returned here")
+                    .with_label(*borrowed, &format!("{} is borrowed here", place))
+                    .with_note(&format!("{} belongs to the function, and goes out of scope when it returns", place))
+            }
+        }
+    }
+}
+
+/// A borrow of PLACE
+struct Loan {
+    place: Place,
+    span: Span
+}
+
+/// What holds the loans at some point of a body, and the loans which have ended: by a
+/// move (or the end of the scope, if DROPPED) at a span
+#[derive(Debug, Clone, Default)]
+struct Loans {
+    held: Vec<(NodeId, usize)>,
+    ended: Vec<(usize, Span, bool)>
+}
+
+impl Loans {
+    /// Adds what reaches a block along one more edge. Whether anything changed
+    fn join(&mut self, other: &Loans) -> bool {
+        let before = (self.held.len(), self.ended.len());
+        for held in &other.held {
+            if !self.held.contains(held) {
+                self.held.push(*held);
+            }
+        }
+        for ended in &other.ended {
+            if !self.ended.contains(ended) {
+                self.ended.push(*ended);
+            }
+        }
+        before != (self.held.len(), self.ended.len())
+    }
+}
+
+pub(crate) struct BorrowChecker<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    env: &'a [BoundInfo],
+    loans: Vec<Loan>,
+    /// The loan each borrowing expression makes
+    borrows: NodeMap<usize>,
+    /// The local each using expression uses
+    uses: NodeMap<NodeId>,
+    /// The locals whose type has references in it
+    holders: Vec<NodeId>,
+    /// The loans reported, with where they ended
+    reported: Vec<(usize, Span)>,
+    file: PathBuf,
+    errs: Vec<BorrowErr>
+}
+
+impl<'a, 't> BorrowChecker<'a, 't> {
+    pub fn check(cx: &'a TypeCx<'t>, results: &'a TypeckResults) -> Vec<BorrowErr> {
+        let mut errs = Vec::new();
+        for (fun, file) in cfg::bodies(cx.tree) {
+            let env = cx.bounds.get(fun.id).map_or(&[][..], |bounds| &bounds[..]);
+            let mut checker = BorrowChecker {
+                cx, results, env, loans: Vec::new(), borrows: NodeMap::new(), uses: NodeMap::new(), holders: Vec::new(),
+                reported: Vec::new(), file: file.clone(), errs: Vec::new()
+            };
+            let cfg = Cfg::build(cx, results, fun);
+            for (local, _, _) in &cfg.locals {
+                let ty = cfg::local_type(cx, results, fun.id, *local);
+                if ty.is_some_and(|ty| ty.any(&|ty| matches!(ty, Type::Ref(_)))) {
+                    checker.holders.push(*local);
+                }
+            }
+            checker.check_body(&cfg);
+            errs.append(&mut checker.errs);
+        }
+        errs
+    }
+
+    fn check_body(&mut self, cfg: &Cfg) {
+        for event in cfg.blocks.iter().flat_map(|block| &block.events) {
+            match event {
+                // A deferred borrow is lowered once for each way out of its scope
+                Event::Borrow { place, expr, span } if !self.borrows.contains(*expr) => {
+                    self.borrows.insert(*expr, self.loans.len());
+                    self.loans.push(Loan { place: place.clone(), span: *span });
+                }
+                Event::Use { place, expr, .. } => {
+                    self.uses.insert(*expr, place.local);
+                }
+                _ => {}
+            }
+        }
+        if self.loans.is_empty() {
+            return;
+        }
+
+        let mut entries: Vec<Option<Loans>> = vec![None; cfg.blocks.len()];
+        entries[0] = Some(Loans::default());
+        let mut work: Vec<BlockId> = vec![0];
+        while let Some(block) = work.pop() {
+            let mut state = entries[block].clone().unwrap_or_default();
+            for event in &cfg.blocks[block].events {
+                self.apply(&mut state, event, false);
+            }
+            for next in cfg.successors(block) {
+                let changed = match &mut entries[next] {
+                    Some(entry) => entry.join(&state),
+                    entry => {
+                        *entry = Some(state.clone());
+                        true
+                    }
+                };
+                if changed && !work.contains(&next) {
+                    work.push(next);
+                }
+            }
+        }
+        for (block, entry) in entries.into_iter().enumerate() {
+            if let Some(mut state) = entry {
+                for event in &cfg.blocks[block].events {
+                    self.apply(&mut state, event, true);
+                }
+            }
+        }
+    }
+
+    /// The loans a value made up by the expressions SOURCES holds
+    fn loans_of(&self, state: &Loans, sources: &[NodeId]) -> Vec<usize> {
+        let mut loans = Vec::new();
+        for source in sources {
+            if let Some(loan) = self.borrows.get(*source) {
+                loans.push(*loan);
+            }
+            if let Some(local) = self.uses.get(*source) {
+                loans.extend(state.held.iter().filter(|(holder, _)| holder == local).map(|(_, loan)| *loan));
+            }
+        }
+        loans
+    }
+
+    /// Gives LOCAL the loans of SOURCES, on top of those it holds unless REPLACE
+    fn hold(&self, state: &mut Loans, local: NodeId, sources: &[NodeId], replace: bool) {
+        let loans = self.loans_of(state, sources);
+        if replace {
+            state.held.retain(|(holder, _)| *holder != local);
+        }
+        if self.holders.contains(&local) {
+            for loan in loans {
+                if !state.held.contains(&(local, loan)) {
+                    state.held.push((local, loan));
+                }
+            }
+        }
+    }
+
+    /// Ends the loans of the places for which F holds, at SPAN
+    fn end(&self, state: &mut Loans, span: Span, dropped: bool, f: impl Fn(&Place) -> bool) {
+        for (index, loan) in self.loans.iter().enumerate() {
+            if f(&loan.place) && !state.ended.iter().any(|(ended, _, _)| *ended == index) {
+                state.ended.push((index, span, dropped));
+            }
+        }
+    }
+
+    fn apply(&mut self, state: &mut Loans, event: &Event, report: bool) {
+        match event {
+            Event::Bind { local, sources, .. } => self.hold(state, *local, sources, true),
+            Event::Use { place, expr, span } => {
+                if report {
+                    self.check_holder(state, place.local, *span);
+                }
+                let moved = !place.is_indirect() && self.results.expr_types.get(*expr).is_some_and(|ty| moves::moves(self.cx, self.env, ty));
+                if moved {
+                    self.end(state, *span, false, |borrowed| place.contains(borrowed) || borrowed.contains(place));
+                }
+            }
+            Event::Borrow { place, expr, span } => {
+                if report {
+                    self.check_holder(state, place.local, *span);
+                }
+                // Borrowed anew, in a loop
+                if let Some(loan) = self.borrows.get(*expr) {
+                    state.ended.retain(|(ended, _, _)| ended != loan);
+                }
+            }
+            Event::Assign { place, sources, span } => {
+                if place.is_indirect() {
+                    if report {
+                        self.check_holder(state, place.local, *span);
+                    }
+                } else {
+                    self.hold(state, place.local, sources, place.projections.is_empty());
+                }
+            }
+            Event::Return { sources, span } => {
+                if !report {
+                    return;
+                }
+                for loan in self.loans_of(state, sources) {
+                    let Loan { place, span: borrowed } = &self.loans[loan];
+                    if !place.projections.contains(&Projection::Deref) && !self.reported.contains(&(loan, *span)) {
+                        self.reported.push((loan, *span));
+                        let kind = BorrowErrKind::ReturnsLocal { place: place.to_string(), borrowed: *borrowed };
+                        self.errs.push(BorrowErr { kind, file: self.file.clone(), span: *span });
+                    }
+                }
+            }
+            Event::End { local, span } => {
+                state.held.retain(|(holder, _)| holder != local);
+                // What a reference points to doesn't go out of scope with it
+                self.end(state, *span, true, |borrowed| borrowed.local == *local && !borrowed.projections.contains(&Projection::Deref));
+            }
+            Event::Drop { .. } | Event::SetFlag { .. } => {}
+        }
+    }
+
+    /// Reports the loans held by LOCAL, used at SPAN, which have ended
+    fn check_holder(&mut self, state: &Loans, local: NodeId, span: Span) {
+        for (_, loan) in state.held.iter().filter(|(holder, _)| *holder == local) {
+            let (ended_at, dropped) = match state.ended.iter().find(|(ended, _, _)| ended == loan) {
+                Some((_, ended_at, dropped)) => (*ended_at, *dropped),
+                None => continue
+            };
+            if self.reported.contains(&(*loan, ended_at)) {
+                continue;
+            }
+            self.reported.push((*loan, ended_at));
+            let Loan { place, span: borrowed } = &self.loans[*loan];
+            let place = place.to_string();
+            let (kind, primary) = if dropped {
+                (BorrowErrKind::DroppedWhileBorrowed { place, dropped: ended_at, used: span }, *borrowed)
+            } else {
+                (BorrowErrKind::MovedWhileBorrowed { place, borrowed: *borrowed, used: span }, ended_at)
+            };
+            self.errs.push(BorrowErr { kind, file: self.file.clone(), span: primary });
+        }
+    }
+}
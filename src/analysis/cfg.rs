@@ -0,0 +1,538 @@
+//! Control-flow graphs of function bodies, for the passes which need to know in what
+//! order things happen: the move checker and those after it. A body is lowered to basic
+//! blocks of events (a binding comes into scope, a place is used, borrowed or assigned,
+//! a binding goes out of scope) ended by a jump, a branch or a return.
+//!
+//! Leaving a scope, by falling through its end or by return, break or continue, runs
+//! what the scope deferred and ends its bindings, in the reverse of the order they were
+//! declared: after let i; let j; defer(drop(j)); let b; the scope ends b, runs
+//! drop(j) and ends i. The deferred expressions are lowered again on every such path.
+
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+use crate::analysis::modules::ModuleTree;
+use crate::analysis::resolve::Res;
+use crate::analysis::typeck::{TypeCx, TypeckResults};
+use crate::analysis::types::Type;
+use crate::ast::declarations::{DefMember, FunDecl, InterfaceMember, Item};
+use crate::ast::expressions::{Expr, ExprKind, UnaryOp};
+use crate::ast::ids::NodeId;
+use crate::ast::statements::{Block, Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::Span;
+
+pub(crate) type BlockId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Projection {
+    Field(String),
+    Deref,
+    Index
+}
+
+/// Somewhere a value lives: a local (a parameter, self or a binding of a pattern) or
+/// a part of one, like p.name or *r
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Place {
+    pub local: NodeId,
+    pub name: String,
+    pub projections: Vec<Projection>
+}
+
+impl Place {
+    pub fn local(local: NodeId, name: &str) -> Self {
+        Self { local, name: name.to_string(), projections: Vec::new() }
+    }
+
+    /// Whether OTHER is this place or a part of it
+    pub fn contains(&self, other: &Place) -> bool {
+        self.local == other.local && other.projections.starts_with(&self.projections)
+    }
+
+    /// Whether the place is reached through a reference, a pointer or an index, and
+    /// so isn't owned by its local
+    pub fn is_indirect(&self) -> bool {
+        self.projections.iter().any(|projection| matches!(projection, Projection::Deref | Projection::Index))
+    }
+
+    pub fn project(mut self, projection: Projection) -> Self {
+        self.projections.push(projection);
+        self
+    }
+}
+
+impl Display for Place {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut place = self.name.clone();
+        for projection in &self.projections {
+            place = match projection {
+                Projection::Field(field) => format!("{}.{}", place, field),
+                Projection::Deref => format!("*{}", place),
+                Projection::Index => format!("{}[_]", place)
+            };
+        }
+        f.write_str(&place)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Event {
+    /// The binding LOCAL comes into scope, with a value or (let x;) without one. The
+    /// value is made of what the uses and borrows by the expressions SOURCES give
+    Bind { local: NodeId, name: String, init: bool, sources: Vec<NodeId>, span: Span },
+    /// The value of PLACE is read by the expression EXPR: moved out, or copied if its
+    /// type is Copy
+    Use { place: Place, expr: NodeId, span: Span },
+    /// PLACE is borrowed (&place, or a method taking &self) by the expression EXPR
+    Borrow { place: Place, expr: NodeId, span: Span },
+    /// PLACE is given a new value, made of what the expressions SOURCES give
+    Assign { place: Place, sources: Vec<NodeId>, span: Span },
+    /// The body gives back its value, made of what the expressions SOURCES give: that
+    /// of a return, or the value of the body
+    Return { sources: Vec<NodeId>, span: Span },
+    /// The binding LOCAL goes out of scope, at the end of the scope around it
+    End { local: NodeId, span: Span },
+    /// PLACE is destroyed: FUN, the drop of the def of Destroy for its type, is called on
+    /// it. Without a FUN the type is generic, or an array, and destroying it is left to
+    /// what it is once monomorphized, or to its elements. When there is a drop FLAG, this
+    /// only happens if the flag is set. Added by drop elaboration, see drops.rs
+    Drop { place: Place, ty: Type, fun: Option<NodeId>, flag: Option<usize>, span: Span },
+    /// Sets the drop flag FLAG to VALUE: whether its place holds a value to destroy
+    SetFlag { flag: usize, value: bool }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Terminator {
+    Goto(BlockId),
+    /// Goes to either block, depending on a condition
+    Branch(BlockId, BlockId),
+    Return,
+    /// The end of a block nothing reaches, like the code after a return
+    Unreachable
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BasicBlock {
+    pub events: Vec<Event>,
+    pub terminator: Terminator
+}
+
+/// The graph of a body, entered at the first block
+#[derive(Debug, Clone)]
+pub(crate) struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    /// The locals of the body, by id, with their names and where they are declared
+    pub locals: Vec<(NodeId, String, Span)>
+}
+
+impl Cfg {
+    /// The graph of the body of FUN, which must have one
+    pub fn build(cx: &TypeCx, results: &TypeckResults, fun: &FunDecl) -> Cfg {
+        let mut builder = Builder {
+            cx, results, owner: fun.id, blocks: Vec::new(), current: 0, scopes: Vec::new(), loops: Vec::new(), locals: Vec::new(), sources: Vec::new()
+        };
+        builder.current = builder.new_block();
+        builder.scopes.push(Vec::new());
+        if fun.receiver.is_some() {
+            // self is a local whose id is that of the function
+            builder.bind(fun.id, "self", true, Vec::new(), fun.span);
+        }
+        for param in &fun.params {
+            builder.bind(param.id, &param.name, true, Vec::new(), param.span);
+        }
+        let end = fun.body.as_ref().map_or(fun.span, |body| body.end);
+        if let Some(body) = &fun.body {
+            builder.sources.push(Vec::new());
+            builder.block(body);
+            let sources = builder.sources.pop().unwrap_or_default();
+            builder.push(Event::Return { sources, span: body.tail().map_or(end, |tail| tail.span) });
+        }
+        builder.exit_scopes(0, end);
+        builder.scopes.pop();
+        builder.terminate(Terminator::Return);
+        Cfg { blocks: builder.blocks, locals: builder.locals }
+    }
+
+    pub fn successors(&self, block: BlockId) -> Vec<BlockId> {
+        match self.blocks[block].terminator {
+            Terminator::Goto(next) => vec![next],
+            Terminator::Branch(then, otherwise) => vec![then, otherwise],
+            Terminator::Return | Terminator::Unreachable => Vec::new()
+        }
+    }
+
+    /// The name and declaration of the local ID
+    pub fn local(&self, id: NodeId) -> Option<&(NodeId, String, Span)> {
+        self.locals.iter().find(|(local, _, _)| *local == id)
+    }
+}
+
+/// The type of the local ID of the body of FUN: self has the id of the function
+pub(crate) fn local_type(cx: &TypeCx, results: &TypeckResults, fun: NodeId, local: NodeId) -> Option<Type> {
+    if local == fun {
+        cx.funs.get(fun).and_then(|sig| sig.receiver.clone())
+    } else {
+        results.local_types.get(local).cloned()
+    }
+}
+
+/// What a scope holds, in the order it was declared
+#[derive(Clone, Copy)]
+enum ScopeEntry<'b> {
+    Local(NodeId),
+    Defer(&'b Expr)
+}
+
+struct Loop {
+    header: BlockId,
+    exit: BlockId,
+    /// How many scopes were open outside the loop
+    depth: usize
+}
+
+struct Builder<'a, 'b, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    /// The function whose body it is
+    owner: NodeId,
+    blocks: Vec<BasicBlock>,
+    current: BlockId,
+    scopes: Vec<Vec<ScopeEntry<'b>>>,
+    loops: Vec<Loop>,
+    locals: Vec<(NodeId, String, Span)>,
+    /// The expressions whose uses and borrows make up the values being lowered, innermost last
+    sources: Vec<Vec<NodeId>>
+}
+
+impl<'a, 'b, 't> Builder<'a, 'b, 't> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock { events: Vec::new(), terminator: Terminator::Unreachable });
+        self.blocks.len() - 1
+    }
+
+    fn push(&mut self, event: Event) {
+        if let (Event::Use { expr, .. } | Event::Borrow { expr, .. }, Some(sources)) = (&event, self.sources.last_mut()) {
+            sources.push(*expr);
+        }
+        let current = self.current;
+        self.blocks[current].events.push(event);
+    }
+
+    /// Ends the current block with TERMINATOR and goes on in a new one, which nothing
+    /// reaches unless the terminator was a jump to it
+    fn terminate(&mut self, terminator: Terminator) {
+        let current = self.current;
+        self.blocks[current].terminator = terminator;
+        self.current = self.new_block();
+    }
+
+    fn goto(&mut self, target: BlockId) {
+        let current = self.current;
+        self.blocks[current].terminator = Terminator::Goto(target);
+        self.current = target;
+    }
+
+    fn bind(&mut self, local: NodeId, name: &str, init: bool, sources: Vec<NodeId>, span: Span) {
+        self.push(Event::Bind { local, name: name.to_string(), init, sources, span });
+        self.locals.push((local, name.to_string(), span));
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(ScopeEntry::Local(local));
+        }
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern, init: bool, sources: &[NodeId]) {
+        match &pattern.kind {
+            PatternKind::Binding(name) => self.bind(pattern.id, name, init, sources.to_vec(), pattern.span),
+            PatternKind::Wildcard => {}
+            PatternKind::Tuple(elems) => elems.iter().for_each(|elem| self.bind_pattern(elem, init, sources)),
+            PatternKind::Pack(_, fields) => fields.iter().for_each(|(_, field)| self.bind_pattern(field, init, sources))
+        }
+    }
+
+    /// Evaluates EXPR for its value, giving the expressions whose uses and borrows make it up
+    fn value(&mut self, expr: &'b Expr) -> Vec<NodeId> {
+        self.sources.push(Vec::new());
+        self.operand(expr);
+        self.sources.pop().unwrap_or_default()
+    }
+
+    /// Leaves the scopes above the DEPTH outermost ones, without closing them: runs
+    /// what they deferred and ends their bindings, innermost first
+    fn exit_scopes(&mut self, depth: usize, span: Span) {
+        let entries: Vec<ScopeEntry<'b>> = self.scopes[depth..].iter().rev().flat_map(|scope| scope.iter().rev().copied()).collect();
+        for entry in entries {
+            match entry {
+                ScopeEntry::Local(local) => self.push(Event::End { local, span }),
+                ScopeEntry::Defer(expr) => self.operand(expr)
+            }
+        }
+    }
+
+    fn block(&mut self, block: &'b Block) {
+        self.scopes.push(Vec::new());
+        // Only the tail makes up the value of the block
+        let tail = block.tail().map(|tail| tail.id);
+        for stmt in &block.stmts {
+            match &stmt.kind {
+                StmtKind::Expr(expr) if Some(expr.id) == tail => self.stmt(stmt),
+                _ => {
+                    self.sources.push(Vec::new());
+                    self.stmt(stmt);
+                    self.sources.pop();
+                }
+            }
+        }
+        let depth = self.scopes.len() - 1;
+        self.sources.push(Vec::new());
+        self.exit_scopes(depth, block.end);
+        self.sources.pop();
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, stmt: &'b Stmt) {
+        match &stmt.kind {
+            StmtKind::Let(local) => {
+                let sources = local.init.as_ref().map(|init| self.value(init)).unwrap_or_default();
+                self.bind_pattern(&local.pattern, local.init.is_some(), &sources);
+            }
+            StmtKind::Defer(expr) => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push(ScopeEntry::Defer(expr));
+                }
+            }
+            StmtKind::Return(value) => {
+                let sources = value.as_ref().map(|value| self.value(value)).unwrap_or_default();
+                self.push(Event::Return { sources, span: value.as_ref().map_or(stmt.span, |value| value.span) });
+                self.exit_scopes(0, stmt.span);
+                self.terminate(Terminator::Return);
+            }
+            StmtKind::While(cond, body) => {
+                let header = self.new_block();
+                self.goto(header);
+                self.operand(cond);
+                let (start, exit) = (self.new_block(), self.new_block());
+                self.blocks[self.current].terminator = Terminator::Branch(start, exit);
+                self.current = start;
+                self.loops.push(Loop { header, exit, depth: self.scopes.len() });
+                self.block(body);
+                self.loops.pop();
+                self.goto(header);
+                self.current = exit;
+            }
+            StmtKind::For(pattern, iter, body) => {
+                self.operand(iter);
+                let header = self.new_block();
+                self.goto(header);
+                let (start, exit) = (self.new_block(), self.new_block());
+                self.blocks[header].terminator = Terminator::Branch(start, exit);
+                self.current = start;
+                self.loops.push(Loop { header, exit, depth: self.scopes.len() });
+                // The pattern is bound anew by every iteration, in a scope around the body
+                self.scopes.push(Vec::new());
+                self.bind_pattern(pattern, true, &[]);
+                self.block(body);
+                let depth = self.scopes.len() - 1;
+                self.exit_scopes(depth, body.end);
+                self.scopes.pop();
+                self.loops.pop();
+                self.goto(header);
+                self.current = exit;
+            }
+            StmtKind::Break | StmtKind::Continue => {
+                // Outside a loop, which the type checker reports, there is nowhere to go
+                let target = self.loops.last().map(|lp| {
+                    (if matches!(stmt.kind, StmtKind::Break) { lp.exit } else { lp.header }, lp.depth)
+                });
+                match target {
+                    Some((target, depth)) => {
+                        self.exit_scopes(depth, stmt.span);
+                        self.terminate(Terminator::Goto(target));
+                    }
+                    None => self.terminate(Terminator::Unreachable)
+                }
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.operand(expr)
+        }
+    }
+
+    /// The place EXPR denotes, if it is a local or a part of one. A field of a
+    /// reference or pointer is a field of what it points to: r.x is (*r).x
+    fn place(&self, expr: &Expr) -> Option<Place> {
+        match &expr.kind {
+            ExprKind::Path(path) => match self.cx.resolutions.path(expr.id) {
+                Some(partial) if partial.unresolved == 0 => match partial.res {
+                    Res::Local(id) | Res::SelfValue(id) => Some(Place::local(id, path.last())),
+                    _ => None
+                },
+                _ => None
+            },
+            ExprKind::Field(base, field) => {
+                let mut place = self.place(base)?;
+                let mut ty = self.results.expr_types.get(base.id);
+                while let Some(Type::Ref(inner) | Type::Ptr(inner)) = ty.map(Type::without_nodrop) {
+                    place = place.project(Projection::Deref);
+                    ty = Some(inner);
+                }
+                Some(place.project(Projection::Field(field.clone())))
+            }
+            ExprKind::Unary(UnaryOp::Deref, base) => Some(self.place(base)?.project(Projection::Deref)),
+            ExprKind::Index(base, _) => Some(self.place(base)?.project(Projection::Index)),
+            _ => None
+        }
+    }
+
+    /// Evaluates what a place expression needs besides its place: the indices
+    fn place_operands(&mut self, expr: &'b Expr) {
+        match &expr.kind {
+            ExprKind::Field(base, _) | ExprKind::Unary(_, base) => self.place_operands(base),
+            ExprKind::Index(base, index) => {
+                self.place_operands(base);
+                self.operand(index);
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a method whose receiver is RECEIVER borrows the value it is called on:
+    /// &self and *self take the place of a receiver which isn't already a reference
+    fn borrows_receiver(&self, receiver: Option<&Type>, expr: &Expr) -> bool {
+        let by_ref = matches!(receiver, Some(Type::Ref(_) | Type::Ptr(_)));
+        let is_ref = matches!(self.results.expr_types.get(expr.id).map(Type::without_nodrop), Some(Type::Ref(_) | Type::Ptr(_)));
+        by_ref && !is_ref
+    }
+
+    /// Evaluates EXPR for its value
+    fn operand(&mut self, expr: &'b Expr) {
+        match &expr.kind {
+            ExprKind::Path(_) | ExprKind::Field(..) | ExprKind::Index(..) | ExprKind::Unary(UnaryOp::Deref, _) => {
+                match self.place(expr) {
+                    Some(place) => {
+                        self.place_operands(expr);
+                        self.push(Event::Use { place, expr: expr.id, span: expr.span });
+                    }
+                    None => match &expr.kind {
+                        ExprKind::Field(base, _) | ExprKind::Unary(_, base) => self.operand(base),
+                        ExprKind::Index(base, index) => {
+                            self.operand(base);
+                            self.operand(index);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ExprKind::Unary(UnaryOp::Ref, inner) => self.borrow(inner, expr),
+            ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => self.operand(inner),
+            ExprKind::Literal(_) => {}
+            ExprKind::Call(callee, args) => {
+                self.operand(callee);
+                args.iter().for_each(|arg| self.operand(arg));
+            }
+            ExprKind::MethodCall(receiver, _, args) => {
+                let sig = self.results.calls.get(expr.id).and_then(|fun| self.cx.funs.get(*fun));
+                if self.borrows_receiver(sig.and_then(|sig| sig.receiver.as_ref()), receiver) {
+                    self.borrow(receiver, expr);
+                } else {
+                    self.operand(receiver);
+                }
+                args.iter().for_each(|arg| self.operand(arg));
+            }
+            ExprKind::PackLit(_, fields) => fields.iter().for_each(|field| self.operand(&field.value)),
+            ExprKind::Tuple(elems) | ExprKind::Array(elems) => elems.iter().for_each(|elem| self.operand(elem)),
+            ExprKind::Binary(op, lhs, rhs) if op == "&&" || op == "||" => {
+                self.operand(lhs);
+                let (right, join) = (self.new_block(), self.new_block());
+                self.blocks[self.current].terminator = Terminator::Branch(right, join);
+                self.current = right;
+                self.operand(rhs);
+                self.goto(join);
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                // An associated operator is a method of the left operand
+                let receiver = self.cx.operator(op, self.owner).and_then(|(interface, method)| {
+                    self.cx.funs.iter().find(|(_, sig)| sig.container == Some(interface) && sig.name == method)
+                }).and_then(|(_, sig)| sig.receiver.as_ref());
+                if self.borrows_receiver(receiver, lhs) {
+                    self.borrow(lhs, expr);
+                } else {
+                    self.operand(lhs);
+                }
+                self.operand(rhs);
+            }
+            ExprKind::Assign(target, value) => {
+                let sources = self.value(value);
+                self.assign(target, sources);
+            }
+            ExprKind::AssignOp(_, target, value) => {
+                self.operand(value);
+                match self.place(target) {
+                    Some(place) => {
+                        self.place_operands(target);
+                        self.push(Event::Use { place: place.clone(), expr: target.id, span: target.span });
+                        self.push(Event::Assign { place, sources: Vec::new(), span: target.span });
+                    }
+                    None => self.operand(target)
+                }
+            }
+            ExprKind::Block(block) | ExprKind::Unsafe(block) => self.block(block),
+            ExprKind::If(cond, then, otherwise) => {
+                self.operand(cond);
+                let (start, other, join) = (self.new_block(), self.new_block(), self.new_block());
+                self.blocks[self.current].terminator = Terminator::Branch(start, other);
+                self.current = start;
+                self.block(then);
+                self.goto(join);
+                self.current = other;
+                if let Some(otherwise) = otherwise {
+                    self.operand(otherwise);
+                }
+                self.goto(join);
+            }
+        }
+    }
+
+    /// Borrows INNER for the expression EXPR, &inner or a method call on it
+    fn borrow(&mut self, inner: &'b Expr, expr: &Expr) {
+        match self.place(inner) {
+            Some(place) => {
+                self.place_operands(inner);
+                self.push(Event::Borrow { place, expr: expr.id, span: inner.span });
+            }
+            // A reference to a temporary
+            None => self.operand(inner)
+        }
+    }
+
+    fn assign(&mut self, target: &'b Expr, sources: Vec<NodeId>) {
+        match self.place(target) {
+            Some(place) => {
+                self.place_operands(target);
+                self.push(Event::Assign { place, sources, span: target.span });
+            }
+            None => self.operand(target)
+        }
+    }
+}
+
+/// The functions of the program which have a body, with the file each is in
+pub(crate) fn bodies(tree: &ModuleTree) -> Vec<(&FunDecl, &PathBuf)> {
+    let mut funs = Vec::new();
+    for (_, module) in tree.modules() {
+        for item in &module.items {
+            let members: Vec<&FunDecl> = match item {
+                Item::Fun(fun) => vec![fun],
+                Item::Impls(impls) => impls.funs.iter().collect(),
+                Item::Def(def) => def.members.iter().filter_map(|member| match member {
+                    DefMember::Fun(fun) => Some(fun),
+                    _ => None
+                }).collect(),
+                Item::Interface(interface) => interface.members.iter().filter_map(|member| match member {
+                    InterfaceMember::Fun(fun) => Some(fun),
+                    _ => None
+                }).collect(),
+                _ => Vec::new()
+            };
+            funs.extend(members.into_iter().filter(|fun| fun.body.is_some()).map(|fun| (fun, &module.file)));
+        }
+    }
+    funs
+}
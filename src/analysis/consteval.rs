@@ -0,0 +1,230 @@
+//! Compile-time evaluation of the values of const and static items. They are run by
+//! the interpreter (see interpret.rs) on their checked bodies, like the #compile_time
+//! functions, so a constant may call functions and hold packs. Integers are evaluated
+//! in the type the type checker gave them, so that const X: u8 = 200 + 100; fails to
+//! compile instead of wrapping around, and casts between integers truncate or extend
+//! without errors. A const can't refer to a static, whose value is only known at runtime.
+//!
+//! An error is reported once, by the constant it happens in: not again by the
+//! constants which use that one.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::analysis::interpret::{Constant, EvalErr, EvalErrKind, Interpreter, Limits};
+use crate::analysis::typeck::{TypeCx, TypeckResults};
+use crate::analysis::types::Type;
+use crate::ast::declarations::Item;
+use crate::ast::types::PrimitiveType;
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+/// An integer of one of the integer types. The bits are kept in a u128, sign
+/// extended for the signed types, so that widening casts are just a change of type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ConstInt {
+    bits: u128,
+    ty: PrimitiveType
+}
+
+impl ConstInt {
+    fn bits_of(ty: PrimitiveType) -> u32 {
+        ty.int_bits().expect("ConstInt of a type which is not an integer")
+    }
+
+    /// N as an integer of type TY, if it fits
+    pub fn from_u128(n: u128, ty: PrimitiveType) -> Option<Self> {
+        let fits = if ty.is_signed() { n <= i128::MAX as u128 } else { true };
+        if fits { Self::from_i128_or_u128(n as i128, n, ty) } else { None }
+    }
+
+    /// -N as an integer of type TY, if it fits
+    pub fn from_negated(n: u128, ty: PrimitiveType) -> Option<Self> {
+        if !ty.is_signed() {
+            return if n == 0 { Some(Self { bits: 0, ty }) } else { None };
+        }
+        // -(2^127) is the only negation which doesn't come from a positive i128
+        let value = if n == 1 << 127 { i128::MIN } else if n <= i128::MAX as u128 { -(n as i128) } else { return None };
+        Self::from_i128(value, ty)
+    }
+
+    fn from_i128(value: i128, ty: PrimitiveType) -> Option<Self> {
+        Self::from_i128_or_u128(value, value as u128, ty)
+    }
+
+    /// The integer of type TY which is SIGNED if TY is signed, UNSIGNED otherwise
+    fn from_i128_or_u128(signed: i128, unsigned: u128, ty: PrimitiveType) -> Option<Self> {
+        let bits = Self::bits_of(ty);
+        if ty.is_signed() {
+            let min = i128::MIN >> (128 - bits);
+            let max = i128::MAX >> (128 - bits);
+            (min..=max).contains(&signed).then_some(Self { bits: signed as u128, ty })
+        } else {
+            let max = u128::MAX >> (128 - bits);
+            (unsigned <= max).then_some(Self { bits: unsigned, ty })
+        }
+    }
+
+    /// Keeps the low bits which fit in TY, like a cast does
+    pub fn truncate(bits: u128, ty: PrimitiveType) -> Self {
+        let width = Self::bits_of(ty);
+        let shift = 128 - width;
+        let bits = if ty.is_signed() { (((bits << shift) as i128) >> shift) as u128 } else { (bits << shift) >> shift };
+        Self { bits, ty }
+    }
+
+    pub fn ty(&self) -> PrimitiveType {
+        self.ty
+    }
+
+    fn signed(&self) -> i128 {
+        self.bits as i128
+    }
+
+    /// The value as an u128, if it's not negative
+    pub fn to_u128(self) -> Option<u128> {
+        if self.ty.is_signed() && self.signed() < 0 { None } else { Some(self.bits) }
+    }
+
+    /// Converts to TY like a cast does
+    pub fn cast(self, ty: PrimitiveType) -> Self {
+        Self::truncate(self.bits, ty)
+    }
+
+    /// Flips every bit, like ! does
+    pub fn not(self) -> Self {
+        Self::truncate(!self.bits, self.ty)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        if self.ty.is_signed() { self.signed() as f64 } else { self.bits as f64 }
+    }
+
+    /// F as an integer of type TY, like a cast from a float does: the fraction is
+    /// dropped and what doesn't fit saturates to the closest value which does
+    pub fn from_f64(f: f64, ty: PrimitiveType) -> Self {
+        let bits = Self::bits_of(ty);
+        if ty.is_signed() {
+            let (min, max) = (i128::MIN >> (128 - bits), i128::MAX >> (128 - bits));
+            Self { bits: (f as i128).clamp(min, max) as u128, ty }
+        } else {
+            Self { bits: (f as u128).min(u128::MAX >> (128 - bits)), ty }
+        }
+    }
+
+    /// Applies the arithmetic or bitwise operator OP, None if the result overflows
+    pub fn arith(self, op: &str, rhs: ConstInt) -> Option<ConstInt> {
+        let ty = self.ty;
+        match op {
+            "&" => return Some(Self::truncate(self.bits & rhs.bits, ty)),
+            "|" => return Some(Self::truncate(self.bits | rhs.bits, ty)),
+            "^" => return Some(Self::truncate(self.bits ^ rhs.bits, ty)),
+            _ => {}
+        }
+        if ty.is_signed() {
+            let (a, b) = (self.signed(), rhs.signed());
+            let result = match op {
+                "+" => a.checked_add(b),
+                "-" => a.checked_sub(b),
+                "*" => a.checked_mul(b),
+                "/" => a.checked_div(b),
+                _ => a.checked_rem(b)
+            }?;
+            Self::from_i128(result, ty)
+        } else {
+            let (a, b) = (self.bits, rhs.bits);
+            let result = match op {
+                "+" => a.checked_add(b),
+                "-" => a.checked_sub(b),
+                "*" => a.checked_mul(b),
+                "/" => a.checked_div(b),
+                _ => a.checked_rem(b)
+            }?;
+            Self::from_i128_or_u128(result as i128, result, ty)
+        }
+    }
+
+    /// Shifts by AMOUNT, None if it is at least the width of the type (or negative)
+    pub fn shift(self, op: &str, amount: ConstInt) -> Option<ConstInt> {
+        let amount = amount.to_u128().filter(|amount| *amount < Self::bits_of(self.ty) as u128)? as u32;
+        let bits = if op == "<<" {
+            self.bits << amount
+        } else if self.ty.is_signed() {
+            (self.signed() >> amount) as u128
+        } else {
+            self.bits >> amount
+        };
+        Some(Self::truncate(bits, self.ty))
+    }
+
+    pub fn compare(self, rhs: ConstInt) -> std::cmp::Ordering {
+        if self.ty.is_signed() { self.signed().cmp(&rhs.signed()) } else { self.bits.cmp(&rhs.bits) }
+    }
+}
+
+impl Display for ConstInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.ty.is_signed() { write!(f, "{}", self.signed()) } else { write!(f, "{}", self.bits) }
+    }
+}
+
+/// The error of a constant, with the type it is declared with and where
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ConstErr {
+    pub err: EvalErr,
+    pub declared: (Type, Span)
+}
+
+impl ConstErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = self.err.to_diagnostic();
+        match &self.err.kind {
+            EvalErrKind::Overflow { ty, .. } if self.err.calls.is_empty() && self.declared.0 == Type::Prim(*ty) => {
+                diagnostic.with_label(self.declared.1, &format!("because {} is declared as {}", self.err.fun, ty))
+            }
+            _ => diagnostic
+        }
+    }
+}
+
+/// Evaluates the const and static items of the program, giving the values of the ones
+/// which could be evaluated and the errors of the others, in the order they are declared
+pub(crate) fn eval_consts(cx: &TypeCx, results: &TypeckResults) -> (Vec<Constant>, Vec<ConstErr>) {
+    let mut decls = Vec::new();
+    for (_, module) in cx.tree.modules() {
+        for item in &module.items {
+            if let Item::Const(decl) = item {
+                decls.push((decl, &module.file));
+            }
+        }
+    }
+    let mut interpreter = Interpreter::new(cx, results, Limits::default());
+    let outcomes: Vec<Result<Constant, Box<EvalErr>>> = decls.iter().map(|(decl, file)| interpreter.run_const(decl, file)).collect();
+    let mut constants = Vec::new();
+    let mut errs = Vec::new();
+    for (index, outcome) in outcomes.iter().enumerate() {
+        let err = match outcome {
+            Ok(constant) => {
+                constants.push(constant.clone());
+                continue;
+            }
+            Err(err) => err
+        };
+        let decl = decls[index].0;
+        // The other constants which were being evaluated when it failed
+        let inner: Vec<usize> = err.calls.iter().filter_map(|(callee, ..)| decls.iter().position(|(decl, _)| decl.name == *callee)).collect();
+        let own = match &err.kind {
+            // Each constant of a cycle finds it, the first one declared reports it
+            EvalErrKind::Cycle(name) => *name == decl.name && inner.iter().all(|inner| *inner > index),
+            _ => inner.iter().all(|inner| outcomes[*inner].is_ok())
+        };
+        if own {
+            let ty = cx.consts.get(decl.id).cloned().unwrap_or(Type::Error);
+            errs.push(ConstErr { err: (**err).clone(), declared: (ty, decl.ty.span) });
+        }
+    }
+    (constants, errs)
+}
@@ -0,0 +1,372 @@
+//! Drop elaboration: makes the destruction of values explicit in the control-flow graph
+//! of each body. When a binding goes out of scope, on every way out of its scope (the
+//! graph already runs what the scope deferred there), what it still owns is destroyed:
+//! the drop of the def of Destroy for its type is called, then its fields are destroyed
+//! from the last to the first. Bindings are destroyed in the reverse of the order they
+//! were declared, and values of a -T type are never destroyed.
+//!
+//! What was moved out isn't destroyed. A binding moved out on only some of the paths
+//! to the end of its scope gets a drop flag, set when it receives a value and cleared
+//! when it is moved, and is only destroyed if the flag is set. A pack whose fields were
+//! only partly moved out has its other fields destroyed one by one, which is why the
+//! fields of a pack with a def of Destroy can't be moved out: its drop needs them all.
+
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+use crate::analysis::cfg::{self, BlockId, Cfg, Event, Place, Projection};
+use crate::analysis::moves;
+use crate::analysis::resolve::Builtin;
+use crate::analysis::typeck::{BoundInfo, InterfaceRef, TypeCx, TypeckResults};
+use crate::analysis::types::{Substitution, Type};
+use crate::ast::declarations::FunDecl;
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+/// The graph of a body with its drops. Monomorphization finds the drops it calls
+#[derive(Debug, Clone)]
+pub(crate) struct ElaboratedBody {
+    pub fun: NodeId,
+    pub cfg: Cfg,
+    /// The place each drop flag is for
+    pub flags: Vec<Place>
+}
+
+/// The drop flags, then the drops and the changes of the flags of each block
+impl Display for ElaboratedBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (flag, place) in self.flags.iter().enumerate() {
+            writeln!(f, "flag{}: {}", flag, place)?;
+        }
+        for (index, block) in self.cfg.blocks.iter().enumerate() {
+            for event in &block.events {
+                match event {
+                    Event::Drop { place, ty, flag: Some(flag), .. } => writeln!(f, "block{}: drop {}: {} if flag{}", index, place, ty, flag)?,
+                    Event::Drop { place, ty, .. } => writeln!(f, "block{}: drop {}: {}", index, place, ty)?,
+                    Event::SetFlag { flag, value } => writeln!(f, "block{}: flag{} = {}", index, flag, value)?,
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DropErrKind {
+    /// PLACE is moved out of a value of type TY, which has a def of Destroy
+    MoveOutOfDestroy { place: String, ty: Type }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DropErr {
+    pub kind: DropErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl DropErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match &self.kind {
+            DropErrKind::MoveOutOfDestroy { place, ty } => {
+                Diagnostic::error(&format!("cannot move out of {}", place), self.span, "moved here")
+                    .with_note(&format!("{} has a def of Destroy, whose drop needs all of its fields", ty))
+            }
+        }
+    }
+}
+
+/// The places which may have been moved out at some point of a body, and those which
+/// have been on every path to it. A binding without a value counts as moved
+#[derive(Debug, Clone, Default)]
+struct Moves {
+    maybe: Vec<Place>,
+    must: Vec<Place>
+}
+
+impl Moves {
+    fn add(&mut self, place: &Place) {
+        if !self.maybe.contains(place) {
+            self.maybe.push(place.clone());
+        }
+        if !self.must.contains(place) {
+            self.must.push(place.clone());
+        }
+    }
+
+    fn remove(&mut self, f: impl Fn(&Place) -> bool) {
+        self.maybe.retain(|place| !f(place));
+        self.must.retain(|place| !f(place));
+    }
+
+    /// Adds what reaches a block along one more edge. Whether anything changed
+    fn join(&mut self, other: &Moves) -> bool {
+        let before = (self.maybe.len(), self.must.len());
+        for place in &other.maybe {
+            if !self.maybe.contains(place) {
+                self.maybe.push(place.clone());
+            }
+        }
+        self.must.retain(|place| other.must.contains(place));
+        before != (self.maybe.len(), self.must.len())
+    }
+}
+
+/// The drop of the def of Destroy for TY, if it has one, with what the generic
+/// parameters of the def stand for
+pub(crate) fn destroy_def(cx: &TypeCx, ty: &Type) -> Option<(NodeId, Substitution)> {
+    let ty = ty.without_nodrop();
+    if !matches!(ty, Type::Pack { .. }) {
+        return None;
+    }
+    cx.impls.iter().filter(|info| info.interface == Some(InterfaceRef::Builtin(Builtin::Destroy))).find_map(|info| {
+        let mut subst = Substitution::new();
+        if cx.match_type(&info.target, ty, info.id, &mut subst) { Some((*info.funs.get("drop")?, subst)) } else { None }
+    })
+}
+
+pub(crate) struct DropElaborator<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    env: &'a [BoundInfo],
+    /// The type of each local of the body
+    locals: NodeMap<Type>,
+    file: PathBuf,
+    errs: Vec<DropErr>
+}
+
+impl<'a, 't> DropElaborator<'a, 't> {
+    pub fn elaborate(cx: &'a TypeCx<'t>, results: &'a TypeckResults) -> (Vec<ElaboratedBody>, Vec<DropErr>) {
+        let mut bodies = Vec::new();
+        let mut errs = Vec::new();
+        for (fun, file) in cfg::bodies(cx.tree) {
+            let env = cx.bounds.get(fun.id).map_or(&[][..], |bounds| &bounds[..]);
+            let mut elaborator = DropElaborator { cx, results, env, locals: NodeMap::new(), file: file.clone(), errs: Vec::new() };
+            bodies.push(elaborator.body(fun));
+            errs.append(&mut elaborator.errs);
+        }
+        (bodies, errs)
+    }
+
+    /// The drop of the def of Destroy for TY, if it has one
+    pub fn destroy_fun(&self, ty: &Type) -> Option<NodeId> {
+        destroy_def(self.cx, ty).map(|(fun, _)| fun)
+    }
+
+    /// The fields of a pack or tuple type, with their types
+    fn fields(&self, ty: &Type) -> Option<Vec<(String, Type)>> {
+        match ty.without_nodrop() {
+            Type::Pack { id, args, .. } => {
+                let pack = self.cx.packs.get(*id)?;
+                let subst: Substitution = (0..pack.generics.len()).map(|index| (*id, index)).zip(args.iter().cloned()).collect();
+                Some(pack.fields.iter().map(|(name, ty)| (name.clone(), self.cx.normalize(&ty.substitute(&subst)))).collect())
+            }
+            Type::Tuple(elems) => Some(elems.iter().enumerate().map(|(index, elem)| (index.to_string(), elem.clone())).collect()),
+            _ => None
+        }
+    }
+
+    /// Whether a value of type TY may have something to destroy. A generic type which
+    /// isn't Copy may turn out to, once monomorphized
+    pub fn needs_drop(&self, ty: &Type) -> bool {
+        match ty {
+            Type::NoDrop(_) => false,
+            Type::Pack { .. } => {
+                self.destroy_fun(ty).is_some() || self.fields(ty).unwrap_or_default().iter().any(|(_, field)| self.needs_drop(field))
+            }
+            Type::Tuple(elems) => elems.iter().any(|elem| self.needs_drop(elem)),
+            Type::Array(elem, len) => *len > 0 && self.needs_drop(elem),
+            Type::Param { .. } | Type::Assoc(..) => moves::moves(self.cx, self.env, ty),
+            Type::Prim(_) | Type::StrSlice | Type::Ref(_) | Type::Ptr(_) | Type::Fun(..) | Type::Var(_) | Type::Never | Type::Error => false
+        }
+    }
+
+    /// The type of the place, as far as it can be followed
+    fn place_ty(&self, local: NodeId, projections: &[Projection]) -> Option<Type> {
+        let mut ty = self.locals.get(local)?.clone();
+        for projection in projections {
+            ty = match (projection, ty.without_nodrop()) {
+                (Projection::Field(name), _) => self.fields(&ty)?.into_iter().find(|(field, _)| field == name)?.1,
+                (Projection::Deref, Type::Ref(inner) | Type::Ptr(inner)) => (**inner).clone(),
+                (Projection::Index, Type::Array(elem, _)) => (**elem).clone(),
+                _ => return None
+            };
+        }
+        Some(ty)
+    }
+
+    /// Whether the use of PLACE by the expression EXPR moves it out
+    fn is_move(&self, place: &Place, expr: NodeId) -> bool {
+        !place.is_indirect() && self.results.expr_types.get(expr).is_some_and(|ty| moves::moves(self.cx, self.env, ty))
+    }
+
+    fn transfer(&self, state: &mut Moves, event: &Event) {
+        match event {
+            Event::Bind { local, name, init, .. } => {
+                state.remove(|place| place.local == *local);
+                if !init {
+                    state.add(&Place::local(*local, name));
+                }
+            }
+            Event::Use { place, expr, .. } if self.is_move(place, *expr) => state.add(place),
+            Event::Assign { place, .. } if !place.is_indirect() => state.remove(|moved| place.contains(moved)),
+            Event::End { local, .. } => state.remove(|place| place.local == *local),
+            _ => {}
+        }
+    }
+
+    fn body(&mut self, fun: &FunDecl) -> ElaboratedBody {
+        let mut cfg = Cfg::build(self.cx, self.results, fun);
+        for (local, _, _) in &cfg.locals {
+            if let Some(ty) = cfg::local_type(self.cx, self.results, fun.id, *local) {
+                self.locals.insert(*local, ty);
+            }
+        }
+
+        let mut entries: Vec<Option<Moves>> = vec![None; cfg.blocks.len()];
+        entries[0] = Some(Moves::default());
+        let mut work: Vec<BlockId> = vec![0];
+        while let Some(block) = work.pop() {
+            let mut state = entries[block].clone().unwrap_or_default();
+            for event in &cfg.blocks[block].events {
+                self.transfer(&mut state, event);
+            }
+            for next in cfg.successors(block) {
+                let changed = match &mut entries[next] {
+                    Some(entry) => entry.join(&state),
+                    entry => {
+                        *entry = Some(state.clone());
+                        true
+                    }
+                };
+                if changed && !work.contains(&next) {
+                    work.push(next);
+                }
+            }
+        }
+
+        // What each end of a binding destroys, and whether only if a flag is set
+        let mut drops: Vec<Vec<Vec<(Place, Type, bool)>>> = cfg.blocks.iter().map(|block| vec![Vec::new(); block.events.len()]).collect();
+        let mut flags: Vec<Place> = Vec::new();
+        for (block, entry) in entries.iter().enumerate() {
+            let mut state = match entry {
+                Some(state) => state.clone(),
+                None => continue
+            };
+            for (index, event) in cfg.blocks[block].events.iter().enumerate() {
+                match event {
+                    Event::End { local, .. } => {
+                        if let Some(ty) = self.locals.get(*local).cloned() {
+                            let name = cfg.local(*local).map_or("", |(_, name, _)| name.as_str());
+                            self.drops_of(Place::local(*local, name), &ty, &state, &mut drops[block][index]);
+                        }
+                        for (place, _, conditional) in &drops[block][index] {
+                            if *conditional && !flags.contains(place) {
+                                flags.push(place.clone());
+                            }
+                        }
+                    }
+                    Event::Use { place, expr, span } if self.is_move(place, *expr) => self.check_move(place, *span),
+                    _ => {}
+                }
+                self.transfer(&mut state, event);
+            }
+        }
+
+        for (block, drops) in cfg.blocks.iter_mut().zip(drops) {
+            let mut events = Vec::new();
+            for (event, drops) in block.events.drain(..).zip(drops) {
+                let set = |events: &mut Vec<Event>, place: &Place, value: bool| {
+                    for (flag, flagged) in flags.iter().enumerate() {
+                        if place.contains(flagged) {
+                            events.push(Event::SetFlag { flag, value });
+                        }
+                    }
+                };
+                match &event {
+                    Event::Bind { local, name, init, .. } => {
+                        let (local, init) = (*local, *init);
+                        let place = Place::local(local, name);
+                        events.push(event);
+                        set(&mut events, &place, init);
+                    }
+                    Event::Use { place, expr, .. } if self.is_move(place, *expr) => {
+                        let place = place.clone();
+                        events.push(event);
+                        set(&mut events, &place, false);
+                    }
+                    Event::Assign { place, .. } if !place.is_indirect() => {
+                        let place = place.clone();
+                        events.push(event);
+                        set(&mut events, &place, true);
+                    }
+                    Event::End { span, .. } => {
+                        let span = *span;
+                        for (place, ty, conditional) in drops {
+                            let flag = if conditional { flags.iter().position(|flagged| *flagged == place) } else { None };
+                            self.destroy(place, &ty, flag, span, &mut events);
+                        }
+                        events.push(event);
+                    }
+                    _ => events.push(event)
+                }
+            }
+            block.events = events;
+        }
+        ElaboratedBody { fun: fun.id, cfg, flags }
+    }
+
+    /// What destroying PLACE of type TY at the end of its binding amounts to, given the
+    /// moves out of it in STATE
+    fn drops_of(&self, place: Place, ty: &Type, state: &Moves, drops: &mut Vec<(Place, Type, bool)>) {
+        if !self.needs_drop(ty) || state.must.iter().any(|moved| moved.contains(&place)) {
+            return;
+        }
+        if state.maybe.iter().any(|moved| place.contains(moved) && *moved != place) {
+            // Partly moved out: the rest is destroyed field by field
+            for (name, field) in self.fields(ty).unwrap_or_default().into_iter().rev() {
+                self.drops_of(place.clone().project(Projection::Field(name)), &field, state, drops);
+            }
+            return;
+        }
+        let conditional = state.maybe.iter().any(|moved| moved.contains(&place));
+        drops.push((place, ty.clone(), conditional));
+    }
+
+    /// Adds the events destroying PLACE of type TY: the drop of its def of Destroy, then
+    /// its fields from the last to the first
+    fn destroy(&self, place: Place, ty: &Type, flag: Option<usize>, span: Span, events: &mut Vec<Event>) {
+        match self.fields(ty) {
+            Some(fields) => {
+                if let Some(fun) = self.destroy_fun(ty) {
+                    events.push(Event::Drop { place: place.clone(), ty: ty.clone(), fun: Some(fun), flag, span });
+                }
+                for (name, field) in fields.into_iter().rev() {
+                    if self.needs_drop(&field) {
+                        self.destroy(place.clone().project(Projection::Field(name)), &field, flag, span, events);
+                    }
+                }
+            }
+            None => events.push(Event::Drop { place, ty: ty.clone(), fun: None, flag, span })
+        }
+    }
+
+    /// Reports a move out of a field of a pack which has a def of Destroy
+    fn check_move(&mut self, place: &Place, span: Span) {
+        for (index, projection) in place.projections.iter().enumerate() {
+            if !matches!(projection, Projection::Field(_)) {
+                continue;
+            }
+            if let Some(ty) = self.place_ty(place.local, &place.projections[..index]) {
+                if self.destroy_fun(&ty).is_some() {
+                    let err = DropErr { kind: DropErrKind::MoveOutOfDestroy { place: place.to_string(), ty }, file: self.file.clone(), span };
+                    if !self.errs.contains(&err) {
+                        self.errs.push(err);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Checks the attributes of a program and the feature gates. Some syntax is
+//! experimental and can only be used once its feature is enabled, either for the
+//! whole file with #![feature(name)] at its start or for a single item or field
+//! with #feature(name) in front of it. Unknown attributes and features, and
+//! attributes put where they mean nothing, are errors too.
+
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+use crate::analysis::modules::ModuleTree;
+use crate::ast::attributes::{self, AttrStyle, Attribute};
+use crate::ast::declarations::{AssocDecl, ConstDecl, DefDecl, FieldDecl, FunDecl, ImplsDecl, InterfaceDecl, ModDecl, Operator, PackDecl, UseDecl};
+#[cfg(test)]
+use crate::ast::declarations::Program;
+use crate::ast::statements::Stmt;
+use crate::ast::types::TyKind;
+use crate::ast::visit::{self, Visitor};
+use crate::diagnostics::Diagnostic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Feature {
+    MinusAttrs, // Fields of type -T, which are not dropped with the pack holding them
+    CustomOperators // Associating symbols other than the usual operators, like <=> or ++
+}
+
+impl Feature {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "minus_attrs" => Some(Feature::MinusAttrs),
+            "custom_operators" => Some(Feature::CustomOperators),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::MinusAttrs => "minus_attrs",
+            Feature::CustomOperators => "custom_operators"
+        }
+    }
+}
+
+/// What an attribute is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AttrTarget {
+    File,
+    Fun,
+    Item, // Any item other than a function
+    Field,
+    Stmt
+}
+
+impl Display for AttrTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AttrTarget::File => f.write_str("the file"),
+            AttrTarget::Fun => f.write_str("a function"),
+            AttrTarget::Item => f.write_str("an item"),
+            AttrTarget::Field => f.write_str("a field"),
+            AttrTarget::Stmt => f.write_str("a statement")
+        }
+    }
+}
+
+/// The attributes the compiler knows, and what each of them can be attached to
+const ATTRIBUTES: [(&str, &[AttrTarget]); 3] = [
+    ("feature", &[AttrTarget::File, AttrTarget::Fun, AttrTarget::Item, AttrTarget::Field]),
+    ("compile_time", &[AttrTarget::Fun]),
+    ("allow", &[AttrTarget::Fun, AttrTarget::Stmt])
+];
+
+/// The lints #allow can silence
+const LINTS: [&str; 1] = ["leaks"];
+
+/// The operators which can be associated without custom_operators
+const USUAL_OPERATORS: [&str; 18] = ["+", "-", "*", "/", "%", "==", "!=", "<", ">", "<=", ">=", "&&", "||", "&", "|", "^", "<<", ">>"];
+
+/// Checks the attributes and the feature gates of a program, reporting everything
+/// which is wrong. Features enabled on an item hold for everything inside it
+#[derive(Debug)]
+pub(crate) struct FeatureChecker {
+    /// The features enabled for the whole file
+    file_features: Vec<Feature>,
+    /// The features enabled on the item being checked
+    item_features: Vec<Feature>,
+    diagnostics: Vec<Diagnostic>
+}
+
+impl FeatureChecker {
+    #[cfg(test)]
+    pub fn check_program(program: &Program) -> Vec<Diagnostic> {
+        let mut checker = Self::new();
+        checker.visit_program(program);
+        checker.diagnostics
+    }
+
+    /// Checks every module of TREE, giving the file of each diagnostic. The items of
+    /// an inline module have the features of the file it is in
+    pub fn check_tree(tree: &ModuleTree) -> Vec<(PathBuf, Diagnostic)> {
+        let mut diagnostics = Vec::new();
+        for (_, module) in tree.modules() {
+            let mut file = module;
+            while let Some(parent) = file.parent.map(|parent| tree.module(parent)).filter(|parent| parent.file == module.file) {
+                file = parent;
+            }
+            let mut checker = Self::new();
+            checker.file_features = checker.check_attrs(&file.attrs, AttrTarget::File);
+            // The attributes of the file are reported with its outermost module
+            if !std::ptr::eq(file, module) {
+                checker.diagnostics.clear();
+            }
+            for item in &module.items {
+                checker.visit_item(item);
+            }
+            diagnostics.extend(checker.diagnostics.into_iter().map(|diagnostic| (module.file.clone(), diagnostic)));
+        }
+        diagnostics
+    }
+
+    fn new() -> Self {
+        Self { file_features: Vec::new(), item_features: Vec::new(), diagnostics: Vec::new() }
+    }
+
+    /// Checks ATTRS, which are attached to TARGET, and gives the features they enable
+    fn check_attrs(&mut self, attrs: &[Attribute], target: AttrTarget) -> Vec<Feature> {
+        let mut features = Vec::new();
+        for attr in attrs {
+            let targets = match ATTRIBUTES.iter().find(|(name, _)| *name == attr.name) {
+                Some((_, targets)) => targets,
+                None => {
+                    let known: Vec<&str> = ATTRIBUTES.iter().map(|(name, _)| *name).collect();
+                    self.diagnostics.push(Diagnostic::error(&format!("unknown attribute #{}", attr.name), attr.span, "not an attribute the compiler knows")
+                        .with_note(&format!("the known attributes are {}", known.join(", "))));
+                    continue;
+                }
+            };
+            if !targets.contains(&target) {
+                self.diagnostics.push(Diagnostic::error(&format!("#{} can't be attached to {}", attr.name, target), attr.span, "misplaced attribute"));
+                continue;
+            }
+            match attr.name.as_str() {
+                "feature" if attr.args.is_empty() => {
+                    self.diagnostics.push(Diagnostic::error("#feature needs the features to enable", attr.span, "no features given")
+                        .with_note("for example #feature(minus_attrs)"));
+                }
+                "feature" => {
+                    for arg in &attr.args {
+                        match Feature::from_name(arg) {
+                            Some(feature) => features.push(feature),
+                            None => self.diagnostics.push(Diagnostic::error(&format!("unknown feature {}", arg), attr.span, "not a feature the compiler knows"))
+                        }
+                    }
+                }
+                "allow" if attr.args.is_empty() => {
+                    self.diagnostics.push(Diagnostic::error("#allow needs the lints to silence", attr.span, "no lints given")
+                        .with_note("for example #allow(leaks)"));
+                }
+                "allow" => {
+                    for arg in attr.args.iter().filter(|arg| !LINTS.contains(&arg.as_str())) {
+                        self.diagnostics.push(Diagnostic::error(&format!("unknown lint {}", arg), attr.span, "not a lint the compiler knows")
+                            .with_note(&format!("the known lints are {}", LINTS.join(", "))));
+                    }
+                }
+                _ if !attr.args.is_empty() => {
+                    self.diagnostics.push(Diagnostic::error(&format!("#{} takes no arguments", attr.name), attr.span, "remove the arguments"));
+                }
+                _ => {}
+            }
+        }
+        features
+    }
+
+    /// Checks the attributes of an item other than a function, and its insides with
+    /// the features they enable
+    fn check_item_attrs(&mut self, attrs: &[Attribute], walk: impl FnOnce(&mut Self)) {
+        let enabled = self.check_attrs(attrs, AttrTarget::Item);
+        let outer = std::mem::replace(&mut self.item_features, enabled);
+        walk(self);
+        self.item_features = outer;
+    }
+
+    /// Whether FEATURE is enabled for the whole file, on the enclosing item or in SCOPE
+    fn is_enabled(&self, feature: Feature, scope: &[Feature]) -> bool {
+        self.file_features.contains(&feature) || self.item_features.contains(&feature) || scope.contains(&feature)
+    }
+}
+
+impl<'ast> Visitor<'ast> for FeatureChecker {
+    #[cfg(test)]
+    fn visit_program(&mut self, program: &'ast Program) {
+        self.file_features = self.check_attrs(&program.attrs, AttrTarget::File);
+        visit::walk_program(self, program);
+    }
+
+    fn visit_const(&mut self, decl: &'ast ConstDecl) {
+        self.check_item_attrs(&decl.attrs, |checker| visit::walk_const(checker, decl));
+    }
+
+    fn visit_pack(&mut self, pack: &'ast PackDecl) {
+        self.check_item_attrs(&pack.attrs, |checker| visit::walk_pack(checker, pack));
+    }
+
+    fn visit_impls(&mut self, impls: &'ast ImplsDecl) {
+        self.check_item_attrs(&impls.attrs, |checker| visit::walk_impls(checker, impls));
+    }
+
+    fn visit_def(&mut self, def: &'ast DefDecl) {
+        self.check_item_attrs(&def.attrs, |checker| visit::walk_def(checker, def));
+    }
+
+    fn visit_interface(&mut self, interface: &'ast InterfaceDecl) {
+        self.check_item_attrs(&interface.attrs, |checker| visit::walk_interface(checker, interface));
+    }
+
+    fn visit_mod(&mut self, module: &'ast ModDecl) {
+        self.check_item_attrs(&module.attrs, |checker| visit::walk_mod(checker, module));
+    }
+
+    fn visit_use(&mut self, decl: &'ast UseDecl) {
+        self.check_attrs(&decl.attrs, AttrTarget::Item);
+    }
+
+    fn visit_fun(&mut self, fun: &'ast FunDecl) {
+        self.check_attrs(&fun.attrs, AttrTarget::Fun);
+        visit::walk_fun(self, fun);
+    }
+
+    fn visit_field(&mut self, field: &'ast FieldDecl) {
+        let enabled = self.check_attrs(&field.attrs, AttrTarget::Field);
+        if let TyKind::NoDrop(_) = field.ty.kind {
+            if !self.is_enabled(Feature::MinusAttrs, &enabled) {
+                let label = format!("{} has type {}", field.name, field.ty);
+                self.diagnostics.push(Diagnostic::error("fields which are never dropped (-T) are experimental", field.span, &label)
+                    .with_note(&format!("add #feature({0}) to the field or the item holding it, or #![feature({0})] at the start of the file to enable it", Feature::MinusAttrs.name())));
+            }
+        }
+    }
+
+    fn visit_assoc(&mut self, assoc: &'ast AssocDecl) {
+        if let Operator::Binary(symbol) = &assoc.op {
+            if !USUAL_OPERATORS.contains(&symbol.as_str()) && !self.is_enabled(Feature::CustomOperators, &[]) {
+                let message = format!("associating the symbol {} is experimental", symbol);
+                self.diagnostics.push(Diagnostic::error(&message, assoc.span, "not one of the usual operators")
+                    .with_note(&format!("add #![feature({})] at the start of the file to enable it", Feature::CustomOperators.name())));
+            }
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        self.check_attrs(&stmt.attrs, AttrTarget::Stmt);
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+/// Whether ATTRS (attached to a function) ask for it to be run at compile time
+pub(crate) fn is_compile_time(attrs: &[Attribute]) -> bool {
+    attributes::named(attrs, "compile_time").any(|attr| attr.style == AttrStyle::Outer)
+}
+
+/// Whether ATTRS silence the lint LINT
+pub(crate) fn is_allowed(attrs: &[Attribute], lint: &str) -> bool {
+    attributes::named(attrs, "allow").any(|attr| attr.args.iter().any(|arg| arg == lint))
+}
@@ -0,0 +1,1458 @@
+//! The compile-time interpreter: runs the functions marked #compile_time while the
+//! program is compiled, and keeps what each one returns as a constant, which the
+//! compilation gives out written as a const item. Nothing puts the constant in place
+//! of the calls to the function yet: there is no backend to compile them. It walks the checked bodies, with the types the type checker
+//! gave them made concrete by what the generic parameters of each call stand for, so
+//! the functions of an interface go to the def of the type they are called on.
+//!
+//! Memory is made of allocations: one for every binding, temporary and alloc(), each
+//! counted for the size of its type (or the size alloc() asked for). Pointers and
+//! references point into them, so a pointer to freed memory, a null pointer or a read
+//! of memory never written are caught and reported, with the overflows, divisions by
+//! zero and indexes out of bounds, as errors of the compilation. Values are moved and
+//! destroyed the way the compiled program does it. A run which goes over its limits
+//! of steps (expressions evaluated), memory or nested calls is stopped.
+//!
+//! The values of const and static items are evaluated the same way, see consteval.rs.
+//! A constant can't hold pointers, references or functions: they only mean something
+//! while compiling.
+//!
+//! The functions declared without a body are external: calling one is IO, which only
+//! goes through the sandbox. It gives compile-time code the files the compiler lets it
+//! read, and keeps what it prints instead of writing it out.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::cfg;
+use crate::analysis::consteval::ConstInt;
+use crate::analysis::drops;
+use crate::analysis::features;
+use crate::analysis::layout::{self, LayoutErr};
+use crate::analysis::mono;
+use crate::analysis::moves;
+use crate::analysis::resolve::{Builtin, Res};
+use crate::analysis::typeck::{ImplicitArg, Instantiation, TypeCx, TypeckResults};
+use crate::analysis::types::{Substitution, Type};
+use crate::ast::declarations::{ConstDecl, ConstKind, FunDecl, Item};
+use crate::ast::expressions::{Expr, ExprKind, Literal, UnaryOp};
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::statements::{Block, Pattern, PatternKind, StmtKind};
+use crate::ast::types::PrimitiveType;
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+/// How far a compile-time function may go before it is stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Limits {
+    /// The expressions it may evaluate
+    pub steps: u64,
+    /// The bytes it may have allocated at once
+    pub memory: u64,
+    /// How deeply calls may nest
+    pub depth: usize
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { steps: 1_000_000, memory: 16 << 20, depth: 128 }
+    }
+}
+
+/// The stack of a thread the interpreter runs on, enough for the nested calls it allows
+pub(crate) const STACK_SIZE: usize = 256 << 20;
+
+/// A place in memory: an allocation, and the fields or elements to follow in its value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Pointer {
+    alloc: usize,
+    path: Vec<usize>
+}
+
+impl Pointer {
+    fn project(&self, index: usize) -> Pointer {
+        let mut path = self.path.clone();
+        path.push(index);
+        Pointer { alloc: self.alloc, path }
+    }
+}
+
+/// A value of the interpreter
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Int(ConstInt),
+    Float(f64, PrimitiveType),
+    Bool(bool),
+    Char(char),
+    /// A string literal, a &StrSlice to data which lives as long as the program
+    Str(String),
+    Unit,
+    Pack { id: NodeId, name: String, fields: Vec<(String, Value)> },
+    Tuple(Vec<Value>),
+    Array(Vec<Value>),
+    /// A reference or a pointer, None for a null pointer
+    Ptr(Option<Pointer>),
+    /// A function, with what its generic parameters stand for
+    Fun(NodeId, Substitution),
+    /// What a place holds once its value was moved out, or before it is given one
+    Empty
+}
+
+impl Value {
+    fn unsigned(n: u128, ty: PrimitiveType) -> Value {
+        Value::Int(ConstInt::truncate(n, ty))
+    }
+
+    /// What in the value can't outlive the compilation, if anything
+    fn transient(&self) -> Option<&'static str> {
+        match self {
+            Value::Ptr(_) => Some("a pointer or a reference"),
+            Value::Fun(..) => Some("a function"),
+            Value::Pack { fields, .. } => fields.iter().find_map(|(_, field)| field.transient()),
+            Value::Tuple(elems) | Value::Array(elems) => elems.iter().find_map(Value::transient),
+            _ => None
+        }
+    }
+}
+
+/// Written as the expression which builds the value
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let write_all = |f: &mut Formatter<'_>, values: &[Value]| {
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", value)?;
+            }
+            Ok(())
+        };
+        match self {
+            Value::Int(int) => write!(f, "{}", int),
+            Value::Float(float, _) => write!(f, "{:?}", float),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Char(c) => write!(f, "{}", Literal::Char(*c)),
+            Value::Str(s) => write!(f, "{}", Literal::Str(s.clone())),
+            Value::Unit => f.write_str("()"),
+            Value::Pack { name, fields, .. } if fields.is_empty() => write!(f, "{} {{}}", name),
+            Value::Pack { name, fields, .. } => {
+                write!(f, "{} {{ ", name)?;
+                for (index, (field, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", field, value)?;
+                }
+                f.write_str(" }")
+            }
+            Value::Tuple(elems) => {
+                f.write_str("(")?;
+                write_all(f, elems)?;
+                if elems.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            Value::Array(elems) => {
+                f.write_str("[")?;
+                write_all(f, elems)?;
+                f.write_str("]")
+            }
+            Value::Ptr(None) => f.write_str("0 as *u8"),
+            Value::Ptr(Some(_)) => f.write_str("<pointer>"),
+            Value::Fun(..) => f.write_str("<function>"),
+            Value::Empty => f.write_str("<moved>")
+        }
+    }
+}
+
+/// Where the IO of compile-time code goes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Sandbox {
+    /// The files which can be read, by path
+    pub files: HashMap<String, String>,
+    /// What was printed
+    pub output: String
+}
+
+impl Sandbox {
+    /// Runs the external function NAME on VALUES, giving a value of type RET: None if
+    /// the sandbox doesn't provide it, the error if it fails
+    fn call(&mut self, name: &str, values: &[Value], ret: &Type) -> Option<Result<Value, String>> {
+        let result = match (name, values) {
+            // print(t: T) writes t, a string as it is
+            ("print", [Value::Str(s)]) => {
+                self.output.push_str(s);
+                Ok(Value::Unit)
+            }
+            ("print", [value]) => {
+                self.output.push_str(&value.to_string());
+                Ok(Value::Unit)
+            }
+            // file_size(path: &StrSlice): u64
+            ("file_size", [Value::Str(path)]) => match (self.files.get(path), ret.without_nodrop()) {
+                (Some(file), Type::Prim(prim)) => Ok(Value::unsigned(file.len() as u128, *prim)),
+                _ => Err(format!("there is no file {} in the sandbox", path))
+            },
+            _ => return None
+        };
+        Some(result)
+    }
+}
+
+/// What a #compile_time function returned, or the value of a const, kept once the
+/// compilation is over
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Constant {
+    /// The function or the const
+    pub item: NodeId,
+    pub name: String,
+    pub ty: Type,
+    pub value: Value
+}
+
+/// Written as the const item holding it
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "const {}: {} = {};", self.name, self.ty, self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum EvalErrKind {
+    /// The operation (printed with its operands) doesn't fit in the type
+    Overflow { operation: String, ty: PrimitiveType },
+    DivisionByZero,
+    OutOfBounds { index: String, len: usize },
+    NullPointer,
+    /// A pointer to memory which was freed, or whose binding went out of scope
+    Dangling,
+    /// Reading memory nothing was written to (or whose value was moved out)
+    Uninitialized,
+    /// dealloc of a pointer alloc didn't give
+    InvalidDealloc,
+    StepLimit(u64),
+    MemoryLimit(u64),
+    DepthLimit(usize),
+    /// Something the interpreter can't do (described)
+    Unsupported(String),
+    /// The result of the function holds WHAT, which can't be kept in a constant
+    Transient { what: &'static str, ty: Type },
+    /// The function can't be run at compile time the way it is declared
+    Signature(String),
+    /// The sandbox refused some IO (described)
+    Io(String),
+    /// The const needs its own value to be evaluated
+    Cycle(String),
+    /// A const uses the static, whose value is only known at runtime
+    StaticInConst(String),
+    /// sizeof of a type without a size
+    Layout(LayoutErr)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EvalErr {
+    pub kind: EvalErrKind,
+    pub file: PathBuf,
+    pub span: Span,
+    /// The #compile_time function which was run, or the const which was evaluated
+    pub fun: String,
+    /// The calls which lead to the error, the innermost first: the function called,
+    /// and where it was called
+    pub calls: Vec<(String, PathBuf, Span)>,
+    /// What the implicit conversions among the calls do, the innermost first, since
+    /// nothing in the source shows those calls
+    pub conversions: Vec<String>
+}
+
+/// The most places of calls the diagnostic of an EvalErr shows, the innermost ones
+const MAX_FRAMES_SHOWN: usize = 8;
+
+impl EvalErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        use EvalErrKind::*;
+        let diagnostic = match &self.kind {
+            Overflow { operation, ty } => Diagnostic::error(&format!("evaluating {} overflows {}", operation, ty), self.span, &format!("this overflows {}", ty)),
+            DivisionByZero => Diagnostic::error("division by zero", self.span, "this divides by zero"),
+            OutOfBounds { index, len } => {
+                Diagnostic::error(&format!("index {} is out of bounds for an array of length {}", index, len), self.span, "out of bounds")
+            }
+            NullPointer => Diagnostic::error("use of a null pointer", self.span, "this pointer is null"),
+            Dangling => Diagnostic::error("use of a pointer to freed memory", self.span, "what this points to was freed, or went out of scope"),
+            Uninitialized => Diagnostic::error("read of memory which holds no value", self.span, "nothing was written here, or it was moved out"),
+            InvalidDealloc => Diagnostic::error("dealloc of a pointer alloc didn't give", self.span, "not the start of an allocation"),
+            StepLimit(limit) => Diagnostic::error("the compile-time evaluation takes too long", self.span, &format!("stopped here, after {} steps", limit)),
+            MemoryLimit(limit) => Diagnostic::error("the compile-time evaluation uses too much memory", self.span, &format!("this goes over {} bytes", limit)),
+            DepthLimit(limit) => Diagnostic::error("the compile-time evaluation recurses too deeply", self.span, &format!("this goes over {} nested calls", limit)),
+            Unsupported(what) => Diagnostic::error(&format!("{} can't be done at compile time", what), self.span, "not supported at compile time"),
+            Transient { what, ty } => {
+                return Diagnostic::error(&format!("the result of {} can't be kept after the compilation", self.fun), self.span, &format!("returns {}", ty))
+                    .with_note(&format!("it holds {}, which only means something while compiling", what));
+            }
+            Signature(problem) => return Diagnostic::error(problem, self.span, "run at compile time"),
+            Io(problem) => Diagnostic::error(problem, self.span, "in this compile-time IO"),
+            Cycle(name) => Diagnostic::error(&format!("the value of {} depends on itself", name), self.span, "used while being evaluated"),
+            StaticInConst(name) => Diagnostic::error(&format!("the constant {} refers to the static {}", self.fun, name), self.span, "static used here")
+                .with_note("statics live at runtime, use a const instead"),
+            Layout(LayoutErr::Recursive(name)) => Diagnostic::error(&format!("{} contains itself, so it has no size", name), self.span, "in this sizeof"),
+            Layout(LayoutErr::Unsized(ty)) => Diagnostic::error(&format!("{} has no size", ty), self.span, "in this sizeof")
+        };
+        let mut diagnostic = diagnostic;
+        // A recursion calls from the same place over and over, which is shown once
+        let mut frames: Vec<(&(String, PathBuf, Span), usize)> = Vec::new();
+        for call in &self.calls {
+            match frames.last_mut() {
+                Some((last, count)) if *last == call => *count += 1,
+                _ => frames.push((call, 1))
+            }
+        }
+        for ((callee, file, span), count) in frames.iter().take(MAX_FRAMES_SHOWN) {
+            let (here, there) = match count {
+                1 => (format!("this call to {}", callee), format!("the call to {}", callee)),
+                count => (format!("{} nested calls to {}", count, callee), format!("{} nested calls to {}", count, callee))
+            };
+            if *file == self.file {
+                diagnostic = diagnostic.with_label(*span, &format!("inside {}", here));
+            } else {
+                diagnostic = diagnostic.with_note(&format!("inside {} at {}:{}:{}", there, file.display(), span.row, span.col));
+            }
+        }
+        if frames.len() > MAX_FRAMES_SHOWN {
+            let hidden: usize = frames[MAX_FRAMES_SHOWN..].iter().map(|(_, count)| count).sum();
+            diagnostic = diagnostic.with_note(&format!("inside {} more calls", hidden));
+        }
+        let diagnostic = self.conversions.iter().fold(diagnostic, |diagnostic, conversion| diagnostic.with_note(conversion));
+        diagnostic.with_note(&format!("while evaluating {} at compile time", self.fun))
+    }
+}
+
+/// How the evaluation of an expression or statement ends when it doesn't give a value
+enum Unwind {
+    Break,
+    Continue,
+    Return(Value),
+    Error(Box<EvalErr>)
+}
+
+type Eval<T> = Result<T, Unwind>;
+
+struct Allocation {
+    value: Value,
+    size: u64,
+    /// Whether alloc() made it, rather than a binding or a temporary
+    heap: bool,
+    live: bool
+}
+
+/// What a scope holds, in the order it was declared: the bindings and temporaries
+/// (with their allocations and types), and the deferred expressions
+enum ScopeEntry<'t> {
+    Local(NodeId, usize, Type),
+    Temp(usize, Type),
+    Defer(&'t Expr)
+}
+
+/// A call being run
+struct Frame<'t> {
+    /// The function (or const) whose body it runs, which owns its instantiations
+    owner: NodeId,
+    name: String,
+    /// What the generic parameters stand for
+    args: Substitution,
+    file: &'t PathBuf,
+    /// Where it was called
+    span: Span,
+    scopes: Vec<Vec<ScopeEntry<'t>>>
+}
+
+pub(crate) struct Interpreter<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    limits: Limits,
+    funs: NodeMap<(&'t FunDecl, &'t PathBuf)>,
+    consts: NodeMap<(&'t ConstDecl, &'t PathBuf)>,
+    /// The functions each body uses
+    uses: NodeMap<Vec<&'a Instantiation>>,
+    /// Whether values of a type have something to destroy
+    needs_drop: HashMap<Type, bool>,
+    memory: Vec<Allocation>,
+    /// The bytes allocated and not freed yet
+    used: u64,
+    steps: u64,
+    frames: Vec<Frame<'t>>,
+    /// The #compile_time function being run
+    running: String,
+    /// Where IO goes, None if the functions run can't do any
+    sandbox: Option<Sandbox>
+}
+
+/// Whether FUN is a function of an interface, rather than one which can be external
+pub(crate) fn in_interface(cx: &TypeCx, fun: NodeId) -> bool {
+    cx.funs.get(fun).and_then(|sig| sig.container).is_some_and(|container| cx.interfaces.contains(container))
+}
+
+/// The #compile_time functions of the program, in the order they are declared
+pub(crate) fn compile_time_funs<'t>(cx: &TypeCx<'t>) -> Vec<(&'t FunDecl, &'t PathBuf)> {
+    cfg::bodies(cx.tree).into_iter().filter(|(fun, _)| features::is_compile_time(&fun.attrs)).collect()
+}
+
+impl<'a, 't> Interpreter<'a, 't> {
+    pub fn new(cx: &'a TypeCx<'t>, results: &'a TypeckResults, limits: Limits) -> Self {
+        let mut funs = NodeMap::new();
+        for (fun, file) in cfg::bodies(cx.tree) {
+            funs.insert(fun.id, (fun, file));
+        }
+        let mut consts = NodeMap::new();
+        for (_, module) in cx.tree.modules() {
+            for item in &module.items {
+                if let Item::Const(decl) = item {
+                    consts.insert(decl.id, (decl, &module.file));
+                }
+            }
+        }
+        let mut uses: NodeMap<Vec<&Instantiation>> = NodeMap::new();
+        for instantiation in &results.instantiations {
+            uses.get_or_default(instantiation.owner).push(instantiation);
+        }
+        Interpreter {
+            cx, results, limits, funs, consts, uses, needs_drop: HashMap::new(), memory: Vec::new(), used: 0, steps: 0,
+            frames: Vec::new(), running: String::new(), sandbox: None
+        }
+    }
+
+    /// Lets the functions run do IO, in SANDBOX
+    pub fn with_sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// The sandbox, with what the functions run left in it
+    pub fn into_sandbox(self) -> Option<Sandbox> {
+        self.sandbox
+    }
+
+    /// Runs the #compile_time function FUN, declared in FILE, from a clean state
+    pub fn run_fun(&mut self, fun: &'t FunDecl, file: &Path) -> Result<Constant, Box<EvalErr>> {
+        self.memory.clear();
+        self.used = 0;
+        self.steps = 0;
+        self.running = fun.name.clone();
+        let problem = if fun.receiver.is_some() {
+            Some("a method")
+        } else if !fun.params.is_empty() {
+            Some("a function with parameters")
+        } else if !fun.generics.is_empty() {
+            Some("a generic function")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            let kind = EvalErrKind::Signature(format!("{} can't be run at compile time", problem));
+            return Err(Box::new(EvalErr { kind, file: file.to_path_buf(), span: fun.span, fun: fun.name.clone(), calls: Vec::new(), conversions: Vec::new() }));
+        }
+        let ty = self.cx.funs.get(fun.id).map_or(Type::Error, |sig| sig.ret.clone());
+        let value = match self.call(fun.id, Substitution::new(), Vec::new(), fun.span) {
+            Ok(value) => value,
+            Err(Unwind::Error(err)) => return Err(err),
+            Err(_) => Value::Unit
+        };
+        if let Some(what) = value.transient() {
+            let kind = EvalErrKind::Transient { what, ty };
+            return Err(Box::new(EvalErr { kind, file: file.to_path_buf(), span: fun.span, fun: fun.name.clone(), calls: Vec::new(), conversions: Vec::new() }));
+        }
+        Ok(Constant { item: fun.id, name: fun.name.clone(), ty, value })
+    }
+
+    /// Evaluates the value of the const or static DECL, declared in FILE, from a clean state
+    pub fn run_const(&mut self, decl: &'t ConstDecl, file: &Path) -> Result<Constant, Box<EvalErr>> {
+        self.memory.clear();
+        self.used = 0;
+        self.steps = 0;
+        self.running = decl.name.clone();
+        let ty = self.cx.consts.get(decl.id).cloned().unwrap_or(Type::Error);
+        let value = match self.eval_const(decl.id, decl.span) {
+            Ok(value) => value,
+            Err(Unwind::Error(err)) => return Err(err),
+            Err(_) => Value::Unit
+        };
+        if let Some(what) = value.transient() {
+            let kind = EvalErrKind::Transient { what, ty };
+            return Err(Box::new(EvalErr { kind, file: file.to_path_buf(), span: decl.span, fun: decl.name.clone(), calls: Vec::new(), conversions: Vec::new() }));
+        }
+        Ok(Constant { item: decl.id, name: decl.name.clone(), ty, value })
+    }
+
+    fn frame(&self) -> &Frame<'t> {
+        self.frames.last().expect("no function is being run")
+    }
+
+    fn error<T>(&self, kind: EvalErrKind, span: Span) -> Eval<T> {
+        let file = self.frames.last().map_or(PathBuf::new(), |frame| frame.file.clone());
+        // The outermost frame is the #compile_time function itself
+        let calls = self.frames.iter().skip(1).rev().zip(self.frames.iter().rev().skip(1))
+            .map(|(callee, caller)| (callee.name.clone(), caller.file.clone(), callee.span))
+            .collect();
+        Err(Unwind::Error(Box::new(EvalErr { kind, file, span, fun: self.running.clone(), calls, conversions: Vec::new() })))
+    }
+
+    /// The type the type checker gave to the expression, parameter or pattern ID, in
+    /// the call being run
+    fn ty(&self, id: NodeId) -> Type {
+        let ty = self.results.expr_types.get(id).or_else(|| self.results.local_types.get(id)).cloned().unwrap_or(Type::Error);
+        self.cx.normalize(&ty.substitute(&self.frame().args))
+    }
+
+    fn moves(&self, ty: &Type) -> bool {
+        moves::moves(self.cx, &[], ty)
+    }
+
+    /// The function the use of FUN at SPAN in the body being run calls, with what its
+    /// generic parameters stand for
+    fn callee(&self, fun: NodeId, span: Span) -> (NodeId, Substitution) {
+        let frame = self.frame();
+        let args = self.uses.get(frame.owner).and_then(|uses| uses.iter().find(|used| used.fun == fun && used.span == span))
+            .map(|used| used.args.iter().map(|(param, ty)| (*param, self.cx.normalize(&ty.substitute(&frame.args)))).collect())
+            .unwrap_or_default();
+        mono::dispatch(self.cx, fun, args)
+    }
+
+    fn step(&mut self, span: Span) -> Eval<()> {
+        self.steps += 1;
+        if self.steps > self.limits.steps {
+            return self.error(EvalErrKind::StepLimit(self.limits.steps), span);
+        }
+        Ok(())
+    }
+
+    fn allocate(&mut self, value: Value, size: u64, heap: bool, span: Span) -> Eval<usize> {
+        if self.used + size > self.limits.memory {
+            return self.error(EvalErrKind::MemoryLimit(self.limits.memory), span);
+        }
+        self.used += size;
+        self.memory.push(Allocation { value, size, heap, live: true });
+        Ok(self.memory.len() - 1)
+    }
+
+    fn free(&mut self, alloc: usize) {
+        let allocation = &mut self.memory[alloc];
+        if allocation.live {
+            allocation.live = false;
+            allocation.value = Value::Empty;
+            self.used -= allocation.size;
+        }
+    }
+
+    /// Gives the value a place of its own in the innermost scope, until it ends
+    fn temporary(&mut self, value: Value, ty: Type, span: Span) -> Eval<Pointer> {
+        let size = layout::type_layout(self.cx, &ty).map_or(0, |layout| layout.size);
+        let alloc = self.allocate(value, size, false, span)?;
+        self.frames.last_mut().and_then(|frame| frame.scopes.last_mut()).expect("no scope is open").push(ScopeEntry::Temp(alloc, ty));
+        Ok(Pointer { alloc, path: Vec::new() })
+    }
+
+    fn bind_local(&mut self, local: NodeId, value: Value, ty: Type, span: Span) -> Eval<()> {
+        let size = layout::type_layout(self.cx, &ty).map_or(0, |layout| layout.size);
+        let alloc = self.allocate(value, size, false, span)?;
+        self.frames.last_mut().and_then(|frame| frame.scopes.last_mut()).expect("no scope is open").push(ScopeEntry::Local(local, alloc, ty));
+        Ok(())
+    }
+
+    fn local(&self, local: NodeId) -> Option<Pointer> {
+        self.frame().scopes.iter().rev().flatten().find_map(|entry| match entry {
+            ScopeEntry::Local(id, alloc, _) if *id == local => Some(Pointer { alloc: *alloc, path: Vec::new() }),
+            _ => None
+        })
+    }
+
+    /// The value at POINTER, which may be Empty
+    fn value_at(&self, pointer: &Pointer, span: Span) -> Eval<&Value> {
+        let allocation = match self.memory.get(pointer.alloc) {
+            Some(allocation) if allocation.live => allocation,
+            _ => return self.error(EvalErrKind::Dangling, span)
+        };
+        let mut value = &allocation.value;
+        for index in &pointer.path {
+            value = match value {
+                Value::Pack { fields, .. } => &fields[*index].1,
+                Value::Tuple(elems) | Value::Array(elems) => &elems[*index],
+                _ => return self.error(EvalErrKind::Uninitialized, span)
+            };
+        }
+        Ok(value)
+    }
+
+    fn read(&self, pointer: &Pointer, span: Span) -> Eval<Value> {
+        match self.value_at(pointer, span)? {
+            Value::Empty => self.error(EvalErrKind::Uninitialized, span),
+            value => Ok(value.clone())
+        }
+    }
+
+    fn write(&mut self, pointer: &Pointer, new: Value, span: Span) -> Eval<()> {
+        if !self.memory.get(pointer.alloc).is_some_and(|allocation| allocation.live) {
+            return self.error(EvalErrKind::Dangling, span);
+        }
+        let mut value = &mut self.memory[pointer.alloc].value;
+        for index in &pointer.path {
+            value = match value {
+                Value::Pack { fields, .. } => &mut fields[*index].1,
+                Value::Tuple(elems) | Value::Array(elems) => &mut elems[*index],
+                _ => return self.error(EvalErrKind::Uninitialized, span)
+            };
+        }
+        *value = new;
+        Ok(())
+    }
+
+    /// Runs FUN with ARGS for its generic parameters, on the values VALUES (the
+    /// receiver first, for a method), called at SPAN
+    fn call(&mut self, fun: NodeId, args: Substitution, values: Vec<Value>, span: Span) -> Eval<Value> {
+        let sig = self.cx.funs.get(fun);
+        let name = sig.map_or(String::new(), |sig| sig.name.clone());
+        let (decl, file) = match self.funs.get(fun) {
+            Some(found) => *found,
+            None if sig.is_some() && !in_interface(self.cx, fun) => return self.external(fun, &name, &args, &values, span),
+            None => return self.error(EvalErrKind::Unsupported(format!("calling {}, which has no body,", name)), span)
+        };
+        if self.frames.len() >= self.limits.depth {
+            return self.error(EvalErrKind::DepthLimit(self.limits.depth), span);
+        }
+        self.frames.push(Frame { owner: fun, name, args, file, span, scopes: vec![Vec::new()] });
+        let mut params: Vec<(NodeId, Type)> = Vec::new();
+        if let Some(receiver) = sig.and_then(|sig| sig.receiver.as_ref()) {
+            // self is a local whose id is that of the function
+            params.push((fun, self.cx.normalize(&receiver.substitute(&self.frame().args))));
+        }
+        params.extend(decl.params.iter().map(|param| (param.id, self.ty(param.id))));
+        let mut result = Ok(Value::Unit);
+        for ((local, ty), value) in params.into_iter().zip(values) {
+            result = self.bind_local(local, value, ty, span).map(|_| Value::Unit);
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            if let Some(body) = &decl.body {
+                result = match self.eval_block(body) {
+                    Err(Unwind::Return(value)) => Ok(value),
+                    result => result
+                };
+            }
+        }
+        let end = decl.body.as_ref().map_or(decl.span, |body| body.end);
+        let result = self.exit_scope(result, end);
+        self.frames.pop();
+        result
+    }
+
+    /// Has the sandbox run the external function FUN, named NAME, on VALUES
+    fn external(&mut self, fun: NodeId, name: &str, args: &Substitution, values: &[Value], span: Span) -> Eval<Value> {
+        let ret = self.cx.funs.get(fun).map_or(Type::Error, |sig| self.cx.normalize(&sig.ret.substitute(args)));
+        let result = match &mut self.sandbox {
+            Some(sandbox) => sandbox.call(name, values, &ret),
+            None => return self.error(EvalErrKind::Unsupported(format!("calling {} outside of the sandbox", name)), span)
+        };
+        match result {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(problem)) => self.error(EvalErrKind::Io(problem), span),
+            None => self.error(EvalErrKind::Unsupported(format!("calling {}, which the sandbox doesn't provide,", name)), span)
+        }
+    }
+
+    /// The value of the const or static ID, evaluated from its declaration
+    fn eval_const(&mut self, id: NodeId, span: Span) -> Eval<Value> {
+        let (decl, file) = match self.consts.get(id) {
+            Some(found) => *found,
+            None => return self.error(EvalErrKind::Unsupported("using this constant".to_string()), span)
+        };
+        if self.frames.iter().any(|frame| frame.owner == id) {
+            return self.error(EvalErrKind::Cycle(decl.name.clone()), span);
+        }
+        // A const is inlined where it is used, so it can't refer to what only lives at runtime
+        let innermost = self.frames.iter().rev().find_map(|frame| self.consts.get(frame.owner));
+        if decl.kind == ConstKind::Static && innermost.is_some_and(|(outer, _)| outer.kind == ConstKind::Const) {
+            return self.error(EvalErrKind::StaticInConst(decl.name.clone()), span);
+        }
+        if self.frames.len() >= self.limits.depth {
+            return self.error(EvalErrKind::DepthLimit(self.limits.depth), span);
+        }
+        self.frames.push(Frame { owner: id, name: decl.name.clone(), args: Substitution::new(), file, span, scopes: vec![Vec::new()] });
+        let result = self.eval(&decl.value);
+        let result = self.exit_scope(result, decl.span);
+        self.frames.pop();
+        result
+    }
+
+    fn eval_block(&mut self, block: &'t Block) -> Eval<Value> {
+        self.frames.last_mut().expect("no function is being run").scopes.push(Vec::new());
+        let result = self.eval_stmts(block);
+        self.exit_scope(result, block.end)
+    }
+
+    fn eval_stmts(&mut self, block: &'t Block) -> Eval<Value> {
+        let tail = block.tail().map(|tail| tail as *const Expr);
+        for stmt in &block.stmts {
+            match &stmt.kind {
+                StmtKind::Expr(expr) if Some(expr as *const Expr) == tail => return self.eval(expr),
+                StmtKind::Let(binding) => {
+                    match &binding.init {
+                        Some(init) => {
+                            let value = self.eval(init)?;
+                            self.bind_pattern(&binding.pattern, value, stmt.span)?;
+                        }
+                        None => self.bind_pattern(&binding.pattern, Value::Empty, stmt.span)?
+                    }
+                }
+                StmtKind::Defer(expr) => {
+                    self.frames.last_mut().and_then(|frame| frame.scopes.last_mut()).expect("no scope is open").push(ScopeEntry::Defer(expr));
+                }
+                StmtKind::Return(expr) => {
+                    let value = match expr {
+                        Some(expr) => self.eval(expr)?,
+                        None => Value::Unit
+                    };
+                    return Err(Unwind::Return(value));
+                }
+                StmtKind::While(cond, body) => {
+                    while self.eval_bool(cond)? {
+                        match self.eval_block(body) {
+                            Err(Unwind::Break) => break,
+                            Ok(_) | Err(Unwind::Continue) => {}
+                            Err(unwind) => return Err(unwind)
+                        }
+                    }
+                }
+                StmtKind::For(pattern, iter, body) => self.eval_for(pattern, iter, body)?,
+                StmtKind::Break => return Err(Unwind::Break),
+                StmtKind::Continue => return Err(Unwind::Continue),
+                StmtKind::Expr(expr) | StmtKind::Semi(expr) => {
+                    let value = self.eval(expr)?;
+                    let ty = self.ty(expr.id);
+                    self.drop_value(value, ty, expr.span)?;
+                }
+            }
+        }
+        Ok(Value::Unit)
+    }
+
+    /// Leaves the innermost scope of the call being run, at SPAN: runs what it deferred
+    /// (unless it is left by an error), then destroys its bindings and temporaries, the
+    /// last declared first
+    fn exit_scope(&mut self, mut result: Eval<Value>, span: Span) -> Eval<Value> {
+        // Each entry leaves the scope as it is handled, so a deferred expression sees those before it
+        while let Some(entry) = self.frames.last_mut().and_then(|frame| frame.scopes.last_mut()).and_then(|scope| scope.pop()) {
+            match entry {
+                ScopeEntry::Defer(expr) => {
+                    if matches!(result, Err(Unwind::Error(_))) {
+                        continue;
+                    }
+                    // The deferred expression runs in the scope, with what is still in it
+                    self.frames.last_mut().expect("no function is being run").scopes.push(Vec::new());
+                    let deferred = self.eval(expr).and_then(|value| {
+                        let ty = self.ty(expr.id);
+                        self.drop_value(value, ty, expr.span)
+                    });
+                    let deferred = self.exit_scope(deferred.map(|_| Value::Unit), span);
+                    if let Err(unwind) = deferred {
+                        result = Err(unwind);
+                    }
+                }
+                ScopeEntry::Local(_, alloc, ty) | ScopeEntry::Temp(alloc, ty) => {
+                    if !matches!(result, Err(Unwind::Error(_))) {
+                        if let Err(unwind) = self.drop_place(&Pointer { alloc, path: Vec::new() }, &ty, span) {
+                            result = Err(unwind);
+                        }
+                    }
+                    self.free(alloc);
+                }
+            }
+        }
+        self.frames.last_mut().and_then(|frame| frame.scopes.pop());
+        result
+    }
+
+    fn eval_for(&mut self, pattern: &'t Pattern, iter: &'t Expr, body: &'t Block) -> Eval<()> {
+        let value = self.eval(iter)?;
+        // An array gives its elements, a reference to one references to them
+        let elems: Vec<Value> = match value {
+            Value::Array(elems) => elems,
+            Value::Ptr(Some(pointer)) => match self.read(&pointer, iter.span)? {
+                Value::Array(elems) => (0..elems.len()).map(|index| Value::Ptr(Some(pointer.project(index)))).collect(),
+                _ => return self.error(EvalErrKind::Uninitialized, iter.span)
+            },
+            Value::Ptr(None) => return self.error(EvalErrKind::NullPointer, iter.span),
+            _ => return self.error(EvalErrKind::Unsupported("iterating over this".to_string()), iter.span)
+        };
+        for elem in elems {
+            self.frames.last_mut().expect("no function is being run").scopes.push(Vec::new());
+            let result = self.bind_pattern(pattern, elem, pattern.span).and_then(|_| self.eval_block(body));
+            match self.exit_scope(result, body.end) {
+                Err(Unwind::Break) => break,
+                Ok(_) | Err(Unwind::Continue) => {}
+                Err(unwind) => return Err(unwind)
+            }
+        }
+        Ok(())
+    }
+
+    fn bind_pattern(&mut self, pattern: &'t Pattern, value: Value, span: Span) -> Eval<()> {
+        match &pattern.kind {
+            PatternKind::Binding(_) => {
+                let ty = self.ty(pattern.id);
+                self.bind_local(pattern.id, value, ty, span)
+            }
+            PatternKind::Wildcard => {
+                let ty = self.ty(pattern.id);
+                self.drop_value(value, ty, span)
+            }
+            PatternKind::Tuple(elems) => {
+                let values = match value {
+                    Value::Tuple(values) => values,
+                    _ => return self.error(EvalErrKind::Uninitialized, span)
+                };
+                for (elem, value) in elems.iter().zip(values) {
+                    self.bind_pattern(elem, value, span)?;
+                }
+                Ok(())
+            }
+            PatternKind::Pack(_, fields) => {
+                let mut values = match value {
+                    Value::Pack { fields, .. } => fields,
+                    _ => return self.error(EvalErrKind::Uninitialized, span)
+                };
+                for (name, field) in fields {
+                    let value = values.iter_mut().find(|(field, _)| field == name).map_or(Value::Empty, |(_, value)| std::mem::replace(value, Value::Empty));
+                    self.bind_pattern(field, value, span)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn eval_bool(&mut self, expr: &'t Expr) -> Eval<bool> {
+        match self.eval(expr)? {
+            Value::Bool(b) => Ok(b),
+            _ => self.error(EvalErrKind::Uninitialized, expr.span)
+        }
+    }
+
+    fn eval(&mut self, expr: &'t Expr) -> Eval<Value> {
+        self.step(expr.span)?;
+        let value = match &expr.kind {
+            ExprKind::Literal(Literal::Int(n)) => match self.ty(expr.id) {
+                Type::Prim(prim @ (PrimitiveType::Float32 | PrimitiveType::Float64)) => Value::Float(*n as f64, prim),
+                Type::Prim(prim) if prim.int_bits().is_some() => match ConstInt::from_u128(*n, prim) {
+                    Some(int) => Value::Int(int),
+                    None => return self.error(EvalErrKind::Overflow { operation: n.to_string(), ty: prim }, expr.span)
+                },
+                _ => Value::unsigned(*n, PrimitiveType::SInt32)
+            },
+            ExprKind::Literal(Literal::Bool(b)) => Value::Bool(*b),
+            ExprKind::Literal(Literal::Char(c)) => Value::Char(*c),
+            ExprKind::Literal(Literal::Str(s)) => Value::Str(s.clone()),
+            ExprKind::Literal(Literal::Unit) => Value::Unit,
+            ExprKind::Path(_) => match self.cx.resolutions.path(expr.id).map(|partial| partial.res) {
+                Some(Res::Item(id)) if self.consts.contains(id) => self.eval_const(id, expr.span)?,
+                Some(Res::Local(_) | Res::SelfValue(_)) => self.take(expr)?,
+                _ => match self.results.calls.get(expr.id) {
+                    Some(fun) => {
+                        let (fun, args) = self.callee(*fun, expr.span);
+                        Value::Fun(fun, args)
+                    }
+                    None => return self.error(EvalErrKind::Unsupported("using this path".to_string()), expr.span)
+                }
+            },
+            ExprKind::Field(..) | ExprKind::Index(..) => self.take(expr)?,
+            ExprKind::Call(callee, args) => self.eval_call(expr, callee, args)?,
+            ExprKind::MethodCall(receiver, _, args) => {
+                let fun = match self.results.calls.get(expr.id) {
+                    Some(fun) => *fun,
+                    None => return self.error(EvalErrKind::Unsupported("this call".to_string()), expr.span)
+                };
+                let (callee, args_subst) = self.callee(fun, expr.span);
+                let mut values = vec![self.receiver(fun, receiver)?];
+                for arg in args {
+                    values.push(self.eval(arg)?);
+                }
+                values.extend(self.implicit_args(expr)?);
+                self.call(callee, args_subst, values, expr.span)?
+            }
+            ExprKind::PackLit(_, inits) => {
+                let (id, name) = match self.ty(expr.id) {
+                    Type::Pack { id, name, .. } => (id, name),
+                    _ => return self.error(EvalErrKind::Unsupported("this pack literal".to_string()), expr.span)
+                };
+                let mut given = Vec::new();
+                for init in inits {
+                    given.push((init.name.as_str(), self.eval(&init.value)?));
+                }
+                let declared: Vec<String> = self.cx.packs.get(id).map_or(Vec::new(), |info| info.fields.iter().map(|(name, _)| name.clone()).collect());
+                let fields = declared.into_iter().map(|field| {
+                    let value = given.iter_mut().find(|(name, _)| *name == field).map_or(Value::Empty, |(_, value)| std::mem::replace(value, Value::Empty));
+                    (field, value)
+                }).collect();
+                Value::Pack { id, name, fields }
+            }
+            ExprKind::Tuple(elems) => Value::Tuple(self.eval_all(elems)?),
+            ExprKind::Array(elems) => Value::Array(self.eval_all(elems)?),
+            ExprKind::Unary(UnaryOp::Ref, operand) => Value::Ptr(Some(self.place(operand)?)),
+            ExprKind::Unary(UnaryOp::Deref, _) => {
+                let pointer = self.place(expr)?;
+                self.read(&pointer, expr.span)?
+            }
+            ExprKind::Unary(op, operand) => match (op, &operand.kind, self.ty(expr.id)) {
+                // -128 is a negative literal, not the negation of 128 (which doesn't fit in i8)
+                (UnaryOp::Neg, ExprKind::Literal(Literal::Int(n)), Type::Prim(prim)) if prim.int_bits().is_some() => {
+                    match ConstInt::from_negated(*n, prim) {
+                        Some(int) => Value::Int(int),
+                        None => return self.error(EvalErrKind::Overflow { operation: format!("-{}", n), ty: prim }, expr.span)
+                    }
+                }
+                _ => {
+                    let value = self.eval(operand)?;
+                    self.unary(*op, value, expr.span)?
+                }
+            },
+            ExprKind::Binary(op, lhs, rhs) if op == "&&" || op == "||" => {
+                let lhs = self.eval_bool(lhs)?;
+                if lhs == (op == "||") { Value::Bool(lhs) } else { Value::Bool(self.eval_bool(rhs)?) }
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                if self.builtin_operator(op, &self.ty(lhs.id)) {
+                    let (a, b) = (self.eval(lhs)?, self.eval(rhs)?);
+                    self.binary(op, a, b, expr.span)?
+                } else {
+                    let receiver = match self.operator_fun(op, expr.span) {
+                        Some(fun) => self.receiver(fun, lhs)?,
+                        None => return self.error(EvalErrKind::Unsupported(format!("the operator {}", op)), expr.span)
+                    };
+                    let rhs = self.eval(rhs)?;
+                    self.call_operator(op, receiver, rhs, expr.span)?
+                }
+            }
+            ExprKind::Cast(operand, _) => match self.operator_fun("as", expr.span) {
+                // A cast the language doesn't know calls Cast::as
+                Some(fun) => {
+                    let receiver = self.receiver(fun, operand)?;
+                    let (fun, args) = self.callee(fun, expr.span);
+                    self.call(fun, args, vec![receiver], expr.span)?
+                }
+                None => {
+                    let value = self.eval(operand)?;
+                    let to = self.ty(expr.id);
+                    self.cast(value, &to, expr.span)?
+                }
+            }
+            ExprKind::Assign(place, value) => {
+                let value = self.eval(value)?;
+                let pointer = self.place(place)?;
+                let ty = self.ty(place.id);
+                // The old value is destroyed, if there is one
+                if !matches!(self.value_at(&pointer, place.span)?, Value::Empty) {
+                    self.drop_place(&pointer, &ty, expr.span)?;
+                }
+                self.write(&pointer, value, expr.span)?;
+                Value::Unit
+            }
+            ExprKind::AssignOp(op, place, value) => {
+                let pointer = self.place(place)?;
+                let ty = self.ty(place.id);
+                let result = if self.builtin_operator(op, &ty) {
+                    let current = self.read(&pointer, place.span)?;
+                    let rhs = self.eval(value)?;
+                    self.binary(op, current, rhs, expr.span)?
+                } else {
+                    let by_ref = self.operator_fun(op, expr.span).and_then(|fun| self.cx.funs.get(fun))
+                        .is_some_and(|sig| matches!(sig.receiver, Some(Type::Ref(_) | Type::Ptr(_))));
+                    let receiver = if by_ref { Value::Ptr(Some(pointer.clone())) } else { self.take_at(&pointer, &ty, place.span)? };
+                    let rhs = self.eval(value)?;
+                    self.call_operator(op, receiver, rhs, expr.span)?
+                };
+                self.write(&pointer, result, expr.span)?;
+                Value::Unit
+            }
+            ExprKind::Block(block) | ExprKind::Unsafe(block) => self.eval_block(block)?,
+            ExprKind::If(cond, then, otherwise) => {
+                if self.eval_bool(cond)? {
+                    self.eval_block(then)?
+                } else if let Some(otherwise) = otherwise {
+                    self.eval(otherwise)?
+                } else {
+                    Value::Unit
+                }
+            }
+        };
+        // The value converted to the type its context expects
+        match self.results.conversions.get(expr.id) {
+            Some(&conversion) => {
+                let (fun, args) = self.callee(conversion, expr.span);
+                self.call(fun, args, vec![value], expr.span).map_err(|unwind| match unwind {
+                    Unwind::Error(mut err) => {
+                        err.conversions.extend(self.cx.explain_implicit(conversion, &self.ty(expr.id)));
+                        Unwind::Error(err)
+                    }
+                    unwind => unwind
+                })
+            }
+            None => Ok(value)
+        }
+    }
+
+    fn eval_all(&mut self, exprs: &'t [Expr]) -> Eval<Vec<Value>> {
+        exprs.iter().map(|expr| self.eval(expr)).collect()
+    }
+
+    fn eval_call(&mut self, call: &'t Expr, callee: &'t Expr, args: &'t [Expr]) -> Eval<Value> {
+        if let Some(Res::Builtin(builtin)) = self.cx.resolutions.path(callee.id).map(|partial| partial.res) {
+            let values = self.eval_all(args)?;
+            return self.builtin(builtin, callee.id, values, args, call.span);
+        }
+        let (fun, subst) = match self.results.calls.get(callee.id) {
+            Some(fun) => self.callee(*fun, callee.span),
+            None => match self.eval(callee)? {
+                Value::Fun(fun, subst) => (fun, subst),
+                _ => return self.error(EvalErrKind::Unsupported("calling this".to_string()), callee.span)
+            }
+        };
+        let mut values = self.eval_all(args)?;
+        values.extend(self.implicit_args(call)?);
+        self.call(fun, subst, values, call.span)
+    }
+
+    /// The values of the implicit arguments the call leaves out
+    fn implicit_args(&mut self, call: &'t Expr) -> Eval<Vec<Value>> {
+        let mut values = Vec::new();
+        for arg in self.results.implicit_args.get(call.id).cloned().unwrap_or_default() {
+            let value = match arg {
+                ImplicitArg::Param(param) => match self.local(param) {
+                    Some(pointer) => self.read(&pointer, call.span)?,
+                    None => return self.error(EvalErrKind::Uninitialized, call.span)
+                },
+                ImplicitArg::Fun(fun) => {
+                    let (fun, args) = self.callee(fun, call.span);
+                    self.call(fun, args, Vec::new(), call.span)?
+                }
+            };
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn builtin(&mut self, builtin: Builtin, path: NodeId, mut values: Vec<Value>, args: &'t [Expr], span: Span) -> Eval<Value> {
+        match builtin {
+            Builtin::Sizeof => {
+                let ty = self.results.builtin_args.get(path).map(|ty| self.cx.normalize(&ty.substitute(&self.frame().args)));
+                match layout::type_layout(self.cx, &ty.unwrap_or(Type::Error)) {
+                    Ok(layout) => Ok(Value::unsigned(layout.size as u128, PrimitiveType::UInt64)),
+                    Err(err) => self.error(EvalErrKind::Layout(err), span)
+                }
+            }
+            Builtin::Alloc => {
+                let size = match values.first() {
+                    Some(Value::Int(int)) => int.to_u128().unwrap_or(0) as u64,
+                    _ => 0
+                };
+                let alloc = self.allocate(Value::Empty, size, true, span)?;
+                Ok(Value::Ptr(Some(Pointer { alloc, path: Vec::new() })))
+            }
+            Builtin::Dealloc => match values.pop() {
+                Some(Value::Ptr(None)) => Ok(Value::Unit),
+                Some(Value::Ptr(Some(pointer))) => {
+                    match self.memory.get(pointer.alloc) {
+                        Some(allocation) if !allocation.live => return self.error(EvalErrKind::Dangling, span),
+                        Some(allocation) if allocation.heap && pointer.path.is_empty() => {}
+                        _ => return self.error(EvalErrKind::InvalidDealloc, span)
+                    }
+                    self.free(pointer.alloc);
+                    Ok(Value::Unit)
+                }
+                _ => self.error(EvalErrKind::InvalidDealloc, span)
+            },
+            Builtin::Read | Builtin::Write => {
+                let new = if builtin == Builtin::Write { values.pop() } else { None };
+                let pointer = match values.pop() {
+                    Some(Value::Ptr(Some(pointer))) => pointer,
+                    Some(Value::Ptr(None)) => return self.error(EvalErrKind::NullPointer, span),
+                    _ => return self.error(EvalErrKind::Unsupported(format!("calling {} on this", builtin.name())), span)
+                };
+                match new {
+                    Some(new) => self.write(&pointer, new, span).map(|_| Value::Unit),
+                    None => self.read(&pointer, span)
+                }
+            }
+            // Only pointers and references are kept as they are, nothing gives
+            // the bytes of the other values
+            Builtin::Transmute => match values.pop() {
+                Some(value @ Value::Ptr(_)) => Ok(value),
+                _ => self.error(EvalErrKind::Unsupported("transmuting a value which is not a pointer".to_string()), span)
+            },
+            Builtin::Drop => {
+                let ty = args.first().map_or(Type::Error, |arg| self.ty(arg.id));
+                let value = values.pop().unwrap_or(Value::Empty);
+                self.drop_value(value, ty, span)?;
+                Ok(Value::Unit)
+            }
+            Builtin::Copy | Builtin::Destroy | Builtin::StrSlice => self.error(EvalErrKind::Unsupported(format!("using {}", builtin.name())), span)
+        }
+    }
+
+    /// The value of the receiver RECEIVER gives to FUN: a reference to it if FUN takes
+    /// &self (or *self) and it isn't one already, and the value it points to if FUN
+    /// takes self and it is reached through a reference
+    fn receiver(&mut self, fun: NodeId, receiver: &'t Expr) -> Eval<Value> {
+        let by_ref = self.cx.funs.get(fun).is_some_and(|sig| matches!(sig.receiver, Some(Type::Ref(_) | Type::Ptr(_))));
+        let ty = self.ty(receiver.id);
+        let indirect = matches!(ty.without_nodrop(), Type::Ref(_) | Type::Ptr(_));
+        match (by_ref, indirect) {
+            (true, false) => Ok(Value::Ptr(Some(self.place(receiver)?))),
+            (false, true) => {
+                let mut value = self.eval(receiver)?;
+                let mut ty = ty;
+                while let (Value::Ptr(pointer), Type::Ref(inner) | Type::Ptr(inner)) = (&value, ty.without_nodrop().clone()) {
+                    let pointer = match pointer {
+                        Some(pointer) => pointer.clone(),
+                        None => return self.error(EvalErrKind::NullPointer, receiver.span)
+                    };
+                    value = self.read(&pointer, receiver.span)?;
+                    ty = *inner;
+                }
+                Ok(value)
+            }
+            _ => self.eval(receiver)
+        }
+    }
+
+    /// Whether OP is the builtin operator for operands of type TY, rather than a
+    /// function associated with it
+    fn builtin_operator(&self, op: &str, ty: &Type) -> bool {
+        let ty = ty.without_nodrop();
+        ty.is_numeric() || (["==", "!=", "<", ">", "<=", ">="].contains(&op) && matches!(ty, Type::Prim(_) | Type::Ptr(_)))
+    }
+
+    /// The function of an interface associated with OP, used at SPAN
+    fn operator_fun(&self, op: &str, span: Span) -> Option<NodeId> {
+        let owner = self.frame().owner;
+        let (_, method) = self.cx.operator(op, owner)?;
+        let uses = self.uses.get(owner)?;
+        uses.iter().find(|used| used.span == span && self.cx.funs.get(used.fun).is_some_and(|sig| sig.name == method)).map(|used| used.fun)
+    }
+
+    fn call_operator(&mut self, op: &str, receiver: Value, rhs: Value, span: Span) -> Eval<Value> {
+        match self.operator_fun(op, span) {
+            Some(fun) => {
+                let (fun, args) = self.callee(fun, span);
+                self.call(fun, args, vec![receiver, rhs], span)
+            }
+            None => self.error(EvalErrKind::Unsupported(format!("the operator {}", op)), span)
+        }
+    }
+
+    /// The place the expression EXPR is, or a temporary holding its value if it isn't one
+    fn place(&mut self, expr: &'t Expr) -> Eval<Pointer> {
+        match &expr.kind {
+            ExprKind::Path(_) => match self.cx.resolutions.path(expr.id).map(|partial| partial.res) {
+                Some(Res::Local(local)) => self.local(local).map_or_else(|| self.error(EvalErrKind::Uninitialized, expr.span), Ok),
+                Some(Res::SelfValue(_)) => {
+                    let owner = self.frame().owner;
+                    self.local(owner).map_or_else(|| self.error(EvalErrKind::Uninitialized, expr.span), Ok)
+                }
+                _ => {
+                    let value = self.eval(expr)?;
+                    let ty = self.ty(expr.id);
+                    self.temporary(value, ty, expr.span)
+                }
+            },
+            ExprKind::Field(base, field) => {
+                let pointer = self.through(base)?;
+                let index = match self.value_at(&pointer, base.span)? {
+                    Value::Pack { fields, .. } => fields.iter().position(|(name, _)| name == field),
+                    Value::Tuple(_) => field.parse().ok(),
+                    Value::Empty => return self.error(EvalErrKind::Uninitialized, base.span),
+                    _ => None
+                };
+                match index {
+                    Some(index) => Ok(pointer.project(index)),
+                    None => self.error(EvalErrKind::Unsupported(format!("reaching the field {}", field)), expr.span)
+                }
+            }
+            ExprKind::Index(base, index) if matches!(self.pointee(&self.ty(base.id)), Type::Array(..)) => {
+                let pointer = self.through(base)?;
+                let index = match self.eval(index)? {
+                    Value::Int(int) => int,
+                    _ => return self.error(EvalErrKind::Uninitialized, index.span)
+                };
+                let len = match self.value_at(&pointer, base.span)? {
+                    Value::Array(elems) => elems.len(),
+                    _ => return self.error(EvalErrKind::Uninitialized, base.span)
+                };
+                match index.to_u128().filter(|index| *index < len as u128) {
+                    Some(index) => Ok(pointer.project(index as usize)),
+                    None => self.error(EvalErrKind::OutOfBounds { index: index.to_string(), len }, expr.span)
+                }
+            }
+            ExprKind::Index(base, index) => {
+                // base[index] calls the function associated with [], whose reference is the place
+                let receiver = match self.operator_fun("[]", expr.span) {
+                    Some(fun) => self.receiver(fun, base)?,
+                    None => return self.error(EvalErrKind::Unsupported("this index".to_string()), expr.span)
+                };
+                let index = self.eval(index)?;
+                match self.call_operator("[]", receiver, index, expr.span)? {
+                    Value::Ptr(Some(pointer)) if !matches!(self.ty(expr.id), Type::Ref(_) | Type::Ptr(_)) => Ok(pointer),
+                    Value::Ptr(None) => self.error(EvalErrKind::NullPointer, expr.span),
+                    value => {
+                        let ty = self.ty(expr.id);
+                        self.temporary(value, ty, expr.span)
+                    }
+                }
+            }
+            ExprKind::Unary(UnaryOp::Deref, operand) => match self.eval(operand)? {
+                Value::Ptr(Some(pointer)) => Ok(pointer),
+                Value::Ptr(None) => self.error(EvalErrKind::NullPointer, expr.span),
+                _ => self.error(EvalErrKind::Unsupported("this dereference".to_string()), expr.span)
+            },
+            _ => {
+                let value = self.eval(expr)?;
+                let ty = self.ty(expr.id);
+                self.temporary(value, ty, expr.span)
+            }
+        }
+    }
+
+    /// What TY points to, through any number of references and pointers
+    fn pointee(&self, ty: &Type) -> Type {
+        match ty.without_nodrop() {
+            Type::Ref(inner) | Type::Ptr(inner) => self.pointee(inner),
+            ty => ty.clone()
+        }
+    }
+
+    /// The place a field access or an index on BASE goes into: BASE itself, or what
+    /// it points to through any number of references and pointers
+    fn through(&mut self, base: &'t Expr) -> Eval<Pointer> {
+        let mut ty = self.ty(base.id);
+        if !matches!(ty.without_nodrop(), Type::Ref(_) | Type::Ptr(_)) {
+            return self.place(base);
+        }
+        let mut value = self.eval(base)?;
+        loop {
+            let pointer = match value {
+                Value::Ptr(Some(pointer)) => pointer,
+                Value::Ptr(None) => return self.error(EvalErrKind::NullPointer, base.span),
+                _ => return self.error(EvalErrKind::Uninitialized, base.span)
+            };
+            ty = match ty.without_nodrop() {
+                Type::Ref(inner) | Type::Ptr(inner) => (**inner).clone(),
+                _ => return Ok(pointer)
+            };
+            if !matches!(ty.without_nodrop(), Type::Ref(_) | Type::Ptr(_)) {
+                return Ok(pointer);
+            }
+            value = self.read(&pointer, base.span)?;
+        }
+    }
+
+    /// Whether the place EXPR is, if it is one, belongs to the function: a binding, a
+    /// temporary or a field of one, rather than something behind a reference or an index
+    fn owned(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Path(_) => matches!(self.cx.resolutions.path(expr.id).map(|partial| partial.res), Some(Res::Local(_) | Res::SelfValue(_))),
+            ExprKind::Field(base, _) => !matches!(self.ty(base.id).without_nodrop(), Type::Ref(_) | Type::Ptr(_)) && self.owned(base),
+            ExprKind::Index(..) | ExprKind::Unary(UnaryOp::Deref, _) => false,
+            _ => true
+        }
+    }
+
+    /// The value of the place EXPR, moved out if its type isn't Copy
+    fn take(&mut self, expr: &'t Expr) -> Eval<Value> {
+        let pointer = self.place(expr)?;
+        let ty = self.ty(expr.id);
+        if self.owned(expr) {
+            self.take_at(&pointer, &ty, expr.span)
+        } else {
+            self.read(&pointer, expr.span)
+        }
+    }
+
+    fn take_at(&mut self, pointer: &Pointer, ty: &Type, span: Span) -> Eval<Value> {
+        let value = self.read(pointer, span)?;
+        if self.moves(ty) {
+            self.write(pointer, Value::Empty, span)?;
+        }
+        Ok(value)
+    }
+
+    fn needs_drop(&mut self, ty: &Type) -> bool {
+        if let Some(needs) = self.needs_drop.get(ty) {
+            return *needs;
+        }
+        let needs = match ty {
+            Type::Pack { .. } => drops::destroy_def(self.cx, ty).is_some() || self.fields(ty).iter().any(|(_, field)| self.needs_drop(field)),
+            Type::Tuple(elems) => elems.iter().any(|elem| self.needs_drop(elem)),
+            Type::Array(elem, _) => self.needs_drop(elem),
+            _ => false
+        };
+        self.needs_drop.insert(ty.clone(), needs);
+        needs
+    }
+
+    /// The fields of a pack type, with their types
+    fn fields(&self, ty: &Type) -> Vec<(String, Type)> {
+        match ty {
+            Type::Pack { id, args, .. } => {
+                let subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((*id, index), arg)).collect();
+                self.cx.packs.get(*id).map_or(Vec::new(), |info| {
+                    info.fields.iter().map(|(name, field)| (name.clone(), self.cx.normalize(&field.substitute(&subst)))).collect()
+                })
+            }
+            _ => Vec::new()
+        }
+    }
+
+    fn drop_value(&mut self, value: Value, ty: Type, span: Span) -> Eval<()> {
+        if matches!(value, Value::Empty) || !self.needs_drop(&ty) {
+            return Ok(());
+        }
+        let pointer = self.temporary(value, ty, span)?;
+        // Destroyed when the scope ends, as a temporary, but it should be now
+        let entry = self.frames.last_mut().and_then(|frame| frame.scopes.last_mut()).and_then(|scope| scope.pop());
+        if let Some(ScopeEntry::Temp(alloc, ty)) = entry {
+            let result = self.drop_place(&pointer, &ty, span);
+            self.free(alloc);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Destroys what is left at POINTER, of type TY: the drop of the def of Destroy
+    /// for its type runs first, then its fields are destroyed from the last to the first
+    fn drop_place(&mut self, pointer: &Pointer, ty: &Type, span: Span) -> Eval<()> {
+        if !self.needs_drop(ty) || matches!(self.value_at(pointer, span)?, Value::Empty) {
+            return Ok(());
+        }
+        match ty {
+            Type::Pack { .. } => {
+                if let Some((fun, args)) = drops::destroy_def(self.cx, ty) {
+                    self.call(fun, args, vec![Value::Ptr(Some(pointer.clone()))], span)?;
+                }
+                for (index, (_, field)) in self.fields(ty).into_iter().enumerate().rev() {
+                    self.drop_place(&pointer.project(index), &field, span)?;
+                }
+            }
+            Type::Tuple(elems) => {
+                for (index, elem) in elems.iter().enumerate().rev() {
+                    self.drop_place(&pointer.project(index), elem, span)?;
+                }
+            }
+            Type::Array(elem, len) => {
+                for index in (0..*len as usize).rev() {
+                    self.drop_place(&pointer.project(index), elem, span)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn unary(&self, op: UnaryOp, value: Value, span: Span) -> Eval<Value> {
+        match (op, value) {
+            (UnaryOp::Neg, Value::Int(int)) => match ConstInt::from_u128(0, int.ty()).and_then(|zero| zero.arith("-", int)) {
+                Some(negated) => Ok(Value::Int(negated)),
+                None => self.error(EvalErrKind::Overflow { operation: format!("-({})", int), ty: int.ty() }, span)
+            },
+            (UnaryOp::Neg, Value::Float(float, ty)) => Ok(Value::Float(-float, ty)),
+            (UnaryOp::Not, Value::Int(int)) => Ok(Value::Int(int.not())),
+            (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+            _ => self.error(EvalErrKind::Uninitialized, span)
+        }
+    }
+
+    fn binary(&self, op: &str, a: Value, b: Value, span: Span) -> Eval<Value> {
+        use std::cmp::Ordering;
+        let ordering = match (&a, &b) {
+            (Value::Int(a), Value::Int(b)) => Some(a.compare(*b)),
+            (Value::Float(a, _), Value::Float(b, _)) => a.partial_cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            (Value::Unit, Value::Unit) => Some(Ordering::Equal),
+            (Value::Ptr(a), Value::Ptr(b)) if op == "==" || op == "!=" => Some(if a == b { Ordering::Equal } else { Ordering::Less }),
+            (Value::Ptr(_), Value::Ptr(_)) => return self.error(EvalErrKind::Unsupported("ordering pointers".to_string()), span),
+            _ => None
+        };
+        let compared = match op {
+            "==" => Some(ordering == Some(Ordering::Equal)),
+            "!=" => Some(ordering != Some(Ordering::Equal)),
+            "<" => Some(ordering == Some(Ordering::Less)),
+            ">" => Some(ordering == Some(Ordering::Greater)),
+            "<=" => Some(matches!(ordering, Some(Ordering::Less | Ordering::Equal))),
+            ">=" => Some(matches!(ordering, Some(Ordering::Greater | Ordering::Equal))),
+            _ => None
+        };
+        if let Some(compared) = compared {
+            return Ok(Value::Bool(compared));
+        }
+        match (a, b) {
+            (Value::Int(a), Value::Int(b)) => {
+                let ty = a.ty();
+                let result = if op == "<<" || op == ">>" {
+                    a.shift(op, b)
+                } else if (op == "/" || op == "%") && b.is_zero() {
+                    return self.error(EvalErrKind::DivisionByZero, span);
+                } else {
+                    a.arith(op, b)
+                };
+                match result {
+                    Some(int) => Ok(Value::Int(int)),
+                    None => self.error(EvalErrKind::Overflow { operation: format!("{} {} {}", a, op, b), ty }, span)
+                }
+            }
+            (Value::Float(a, ty), Value::Float(b, _)) => {
+                let result = match op {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" => a / b,
+                    "%" => a % b,
+                    _ => return self.error(EvalErrKind::Unsupported(format!("the operator {} on floats", op)), span)
+                };
+                // An f32 keeps the precision of one
+                Ok(Value::Float(if ty == PrimitiveType::Float32 { result as f32 as f64 } else { result }, ty))
+            }
+            (Value::Bool(a), Value::Bool(b)) => match op {
+                "&" => Ok(Value::Bool(a & b)),
+                "|" => Ok(Value::Bool(a | b)),
+                "^" => Ok(Value::Bool(a ^ b)),
+                _ => self.error(EvalErrKind::Unsupported(format!("the operator {} on bools", op)), span)
+            },
+            _ => self.error(EvalErrKind::Uninitialized, span)
+        }
+    }
+
+    fn cast(&self, value: Value, to: &Type, span: Span) -> Eval<Value> {
+        let to_prim = match to.without_nodrop() {
+            Type::Prim(prim) => Some(*prim),
+            _ => None
+        };
+        let is_float = |prim: PrimitiveType| matches!(prim, PrimitiveType::Float32 | PrimitiveType::Float64);
+        let value = match (value, to_prim) {
+            (Value::Int(int), Some(prim)) if is_float(prim) => Value::Float(int.to_f64(), prim),
+            (Value::Int(int), Some(PrimitiveType::Char)) => Value::Char(int.to_u128().unwrap_or(0) as u8 as char),
+            (Value::Int(int), Some(prim)) if prim.int_bits().is_some() => Value::Int(int.cast(prim)),
+            (Value::Float(float, _), Some(prim)) if is_float(prim) => Value::Float(if prim == PrimitiveType::Float32 { float as f32 as f64 } else { float }, prim),
+            (Value::Float(float, _), Some(prim)) if prim.int_bits().is_some() => Value::Int(ConstInt::from_f64(float, prim)),
+            (Value::Bool(b), Some(prim)) if prim.int_bits().is_some() => Value::unsigned(b as u128, prim),
+            (Value::Char(c), Some(prim)) if prim.int_bits().is_some() => Value::unsigned(c as u128, prim),
+            (Value::Ptr(_), Some(_)) => return self.error(EvalErrKind::Unsupported("turning a pointer into an integer".to_string()), span),
+            (Value::Int(int), None) if matches!(to.without_nodrop(), Type::Ptr(_)) => {
+                if !int.is_zero() {
+                    return self.error(EvalErrKind::Unsupported(format!("making a pointer from the address {}", int)), span);
+                }
+                Value::Ptr(None)
+            }
+            (value, _) => value
+        };
+        Ok(value)
+    }
+}
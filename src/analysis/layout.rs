@@ -0,0 +1,89 @@
+//! Sizes and alignments of types, laid out like C does: the fields of a pack go in
+//! declaration order, each one aligned, and the pack is padded to its alignment
+
+use crate::analysis::typeck::TypeCx;
+use crate::analysis::types::{Substitution, Type};
+use crate::ast::ids::NodeId;
+use crate::ast::types::PrimitiveType;
+
+/// Everything is laid out for a 64 bit target
+const POINTER_SIZE: u64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub size: u64,
+    pub align: u64
+}
+
+impl Layout {
+    fn scalar(size: u64) -> Self {
+        Self { size, align: size.max(1) }
+    }
+
+    fn primitive(prim: PrimitiveType) -> Self {
+        use PrimitiveType::*;
+        match prim {
+            UInt8 | SInt8 | Bool => Layout::scalar(1),
+            UInt16 | SInt16 => Layout::scalar(2),
+            UInt32 | SInt32 | Float32 | Char => Layout::scalar(4),
+            UInt64 | SInt64 | Float64 => Layout::scalar(8),
+            UInt128 | SInt128 => Layout::scalar(16),
+            Unit => Layout { size: 0, align: 1 },
+            // A pointer and a length
+            RefStaticStr => Layout { size: 2 * POINTER_SIZE, align: POINTER_SIZE }
+        }
+    }
+
+    /// Lays out FIELDS one after the other, like the fields of a pack
+    fn fields(fields: impl IntoIterator<Item = Layout>) -> Self {
+        let mut size: u64 = 0;
+        let mut align: u64 = 1;
+        for field in fields {
+            size = size.next_multiple_of(field.align) + field.size;
+            align = align.max(field.align);
+        }
+        Layout { size: size.next_multiple_of(align), align }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LayoutErr {
+    /// The pack contains itself (not behind a pointer), so it has no size
+    Recursive(String),
+    /// A type without a size, like StrSlice, or which is still generic
+    Unsized(Type)
+}
+
+/// The layout of TY, a type the type checker gave which has nothing generic left in it
+pub(crate) fn type_layout(cx: &TypeCx, ty: &Type) -> Result<Layout, LayoutErr> {
+    type_layout_inside(cx, ty, &mut Vec::new())
+}
+
+/// The layout of TY, which is a field of the packs in OUTER (innermost last)
+fn type_layout_inside(cx: &TypeCx, ty: &Type, outer: &mut Vec<NodeId>) -> Result<Layout, LayoutErr> {
+    let layout = match ty {
+        Type::Prim(prim) => Layout::primitive(*prim),
+        Type::Ref(inner) if **inner == Type::StrSlice => Layout::primitive(PrimitiveType::RefStaticStr),
+        Type::Ref(_) | Type::Ptr(_) | Type::Fun(..) => Layout::scalar(POINTER_SIZE),
+        // Not dropping a value doesn't change how it is stored
+        Type::NoDrop(inner) => type_layout_inside(cx, inner, outer)?,
+        Type::Tuple(elems) => Layout::fields(elems.iter().map(|elem| type_layout_inside(cx, elem, outer)).collect::<Result<Vec<Layout>, LayoutErr>>()?),
+        Type::Array(elem, len) => {
+            let elem = type_layout_inside(cx, elem, outer)?;
+            Layout { size: elem.size * len, align: elem.align }
+        }
+        Type::Pack { id, name, args } => {
+            let info = cx.packs.get(*id).ok_or_else(|| LayoutErr::Unsized(ty.clone()))?;
+            if outer.contains(id) {
+                return Err(LayoutErr::Recursive(name.clone()));
+            }
+            let subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((*id, index), arg)).collect();
+            outer.push(*id);
+            let fields = info.fields.iter().map(|(_, field)| type_layout_inside(cx, &cx.normalize(&field.substitute(&subst)), outer)).collect::<Result<Vec<Layout>, LayoutErr>>();
+            outer.pop();
+            Layout::fields(fields?)
+        }
+        Type::Param { .. } | Type::StrSlice | Type::Assoc(..) | Type::Var(_) | Type::Never | Type::Error => return Err(LayoutErr::Unsized(ty.clone()))
+    };
+    Ok(layout)
+}
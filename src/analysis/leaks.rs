@@ -0,0 +1,218 @@
+//! The leak lint: a value of a -T type is never dropped, so a binding holding one has
+//! to give it away before its scope ends, by moving it somewhere (passing it to a
+//! function which frees it, returning it, storing it) or by deferring that. A binding
+//! which may still hold its value when it goes out of scope, or when it is given a new
+//! one, leaks it and gets a warning. Copy values own nothing, so they can't leak.
+//!
+//! #allow(leaks) on a let, or on the function, says the leak is intended.
+
+use std::path::PathBuf;
+
+use crate::analysis::cfg::{self, BlockId, Cfg, Event};
+use crate::analysis::features;
+use crate::analysis::moves;
+use crate::analysis::typeck::{BoundInfo, TypeCx, TypeckResults};
+use crate::analysis::types::Type;
+use crate::ast::ids::NodeId;
+use crate::ast::statements::{Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::visit::{self, Visitor};
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LeakErrKind {
+    /// PLACE, declared at DECLARED with the type TY, goes out of scope with its value
+    OutOfScope { place: String, ty: Type, declared: Span },
+    /// PLACE, declared at DECLARED with the type TY, is given a new value over its old one
+    Overwritten { place: String, ty: Type, declared: Span }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LeakErr {
+    pub kind: LeakErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl LeakErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let (message, label, place, ty, declared) = match &self.kind {
+            LeakErrKind::OutOfScope { place, ty, declared } => {
+                (format!("{} is never freed", place), format!("{} goes out of scope here, still holding its value", place), place, ty, declared)
+            }
+            LeakErrKind::Overwritten { place, ty, declared } => {
+                (format!("the old value of {} is never freed", place), format!("{} is given a new value here", place), place, ty, declared)
+            }
+        };
+        Diagnostic::warning(&message, self.span, &label)
+            .with_label(*declared, &format!("{} is declared here with type {}", place, ty))
+            .with_note("-T values aren't dropped: move it out, pass it to a function which frees it or defer that, or put #allow(leaks) on the let if the leak is intended")
+    }
+}
+
+/// The locals bound by a let with #allow(leaks) on it
+#[derive(Default)]
+struct AllowedLets {
+    locals: Vec<NodeId>
+}
+
+impl AllowedLets {
+    fn bind(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Binding(_) => self.locals.push(pattern.id),
+            PatternKind::Wildcard => {}
+            PatternKind::Tuple(elems) => elems.iter().for_each(|elem| self.bind(elem)),
+            PatternKind::Pack(_, fields) => fields.iter().for_each(|(_, field)| self.bind(field))
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for AllowedLets {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        if let StmtKind::Let(binding) = &stmt.kind {
+            if features::is_allowed(&stmt.attrs, "leaks") {
+                self.bind(&binding.pattern);
+            }
+        }
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+pub(crate) struct LeakChecker<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    env: &'a [BoundInfo],
+    /// The locals of the body which hold a -T value that isn't Copy, with its type
+    owners: Vec<(NodeId, Type)>,
+    file: PathBuf,
+    errs: Vec<LeakErr>
+}
+
+impl<'a, 't> LeakChecker<'a, 't> {
+    pub fn check(cx: &'a TypeCx<'t>, results: &'a TypeckResults) -> Vec<LeakErr> {
+        let mut errs = Vec::new();
+        for (fun, file) in cfg::bodies(cx.tree) {
+            if features::is_allowed(&fun.attrs, "leaks") {
+                continue;
+            }
+            let mut allowed = AllowedLets::default();
+            allowed.visit_fun(fun);
+            let env = cx.bounds.get(fun.id).map_or(&[][..], |bounds| &bounds[..]);
+            let mut checker = LeakChecker { cx, results, env, owners: Vec::new(), file: file.clone(), errs: Vec::new() };
+            let cfg = Cfg::build(cx, results, fun);
+            for (local, _, _) in &cfg.locals {
+                if allowed.locals.contains(local) {
+                    continue;
+                }
+                match cfg::local_type(cx, results, fun.id, *local) {
+                    Some(ty @ Type::NoDrop(_)) if moves::moves(cx, env, &ty) => checker.owners.push((*local, ty)),
+                    _ => {}
+                }
+            }
+            if !checker.owners.is_empty() {
+                checker.check_body(&cfg);
+            }
+            errs.append(&mut checker.errs);
+        }
+        errs
+    }
+
+    fn check_body(&mut self, cfg: &Cfg) {
+        // The owners which may hold their value when each block starts
+        let mut entries: Vec<Option<Vec<NodeId>>> = vec![None; cfg.blocks.len()];
+        entries[0] = Some(Vec::new());
+        let mut work: Vec<BlockId> = vec![0];
+        while let Some(block) = work.pop() {
+            let mut state = entries[block].clone().unwrap_or_default();
+            for event in &cfg.blocks[block].events {
+                self.apply(cfg, &mut state, event, false);
+            }
+            for next in cfg.successors(block) {
+                let changed = match &mut entries[next] {
+                    Some(entry) => {
+                        let before = entry.len();
+                        for local in &state {
+                            if !entry.contains(local) {
+                                entry.push(*local);
+                            }
+                        }
+                        entry.len() > before
+                    }
+                    entry => {
+                        *entry = Some(state.clone());
+                        true
+                    }
+                };
+                if changed && !work.contains(&next) {
+                    work.push(next);
+                }
+            }
+        }
+        for (block, entry) in entries.into_iter().enumerate() {
+            if let Some(mut state) = entry {
+                for event in &cfg.blocks[block].events {
+                    self.apply(cfg, &mut state, event, true);
+                }
+            }
+        }
+    }
+
+    fn is_owner(&self, local: NodeId) -> bool {
+        self.owners.iter().any(|(owner, _)| *owner == local)
+    }
+
+    /// Whether the use by the expression EXPR moves its value
+    fn moves(&self, expr: NodeId) -> bool {
+        self.results.expr_types.get(expr).is_some_and(|ty| moves::moves(self.cx, self.env, ty))
+    }
+
+    fn apply(&mut self, cfg: &Cfg, state: &mut Vec<NodeId>, event: &Event, report: bool) {
+        match event {
+            Event::Bind { local, init, .. } if self.is_owner(*local) => {
+                state.retain(|held| held != local);
+                if *init {
+                    state.push(*local);
+                }
+            }
+            // Moving the value, or a part of it, out gives it away
+            Event::Use { place, expr, .. } if self.is_owner(place.local) && !place.is_indirect() && self.moves(*expr) => {
+                state.retain(|held| *held != place.local);
+            }
+            Event::Assign { place, span, .. } if self.is_owner(place.local) && place.projections.is_empty() => {
+                if state.contains(&place.local) {
+                    if report {
+                        self.leak(cfg, place.local, *span, true);
+                    }
+                } else {
+                    state.push(place.local);
+                }
+            }
+            Event::End { local, span } if state.contains(local) => {
+                if report {
+                    self.leak(cfg, *local, *span, false);
+                }
+                state.retain(|held| held != local);
+            }
+            _ => {}
+        }
+    }
+
+    /// Reports the value of LOCAL leaking at SPAN, when it is OVERWRITTEN or goes out of scope
+    fn leak(&mut self, cfg: &Cfg, local: NodeId, span: Span, overwritten: bool) {
+        let (place, declared) = match cfg.local(local) {
+            Some((_, name, declared)) => (name.clone(), *declared),
+            None => return
+        };
+        let ty = self.owners.iter().find(|(owner, _)| *owner == local).map_or(Type::Error, |(_, ty)| ty.clone());
+        let kind = if overwritten {
+            LeakErrKind::Overwritten { place, ty, declared }
+        } else {
+            LeakErrKind::OutOfScope { place, ty, declared }
+        };
+        let err = LeakErr { kind, file: self.file.clone(), span };
+        // The end of a scope is lowered once for each way out of it
+        if !self.errs.contains(&err) {
+            self.errs.push(err);
+        }
+    }
+}
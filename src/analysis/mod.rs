@@ -0,0 +1,20 @@
+//! The passes which run on the parsed program: each one checks (or computes)
+//! something about it, reporting what is wrong as diagnostics
+
+pub mod layout;
+pub mod borrows;
+pub mod cfg;
+pub mod consteval;
+pub mod drops;
+pub mod features;
+pub mod interpret;
+pub mod leaks;
+pub mod modules;
+pub mod mono;
+pub mod moves;
+pub mod resolve;
+pub mod schedule;
+pub mod solver;
+pub mod types;
+pub mod typeck;
+pub mod unsafety;
@@ -0,0 +1,499 @@
+//! The module tree of a program spread over many files. The root file declares its
+//! modules with mod name; (loaded from name.tx or name/mod.tx next to it) or inline
+//! with mod name { items }, and so on recursively: the files of the modules
+//! declared by a.tx go in the directory a/.
+//!
+//! Paths are resolved like in Rust 2015. The paths of use declarations start from
+//! the root module, unless they start with self (the current module) or super (its
+//! parent). The paths in the code (ptr::read<T>, HashMap::new) start from the current
+//! module: their first segment is an item of the module or something it imported.
+//! Everything is private to the module defining it (and the modules inside it)
+//! unless it is public, and a public use makes the imported name public too.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::attributes::Attribute;
+use crate::ast::declarations::{AssocDecl, Item, Operator, Program, UseTree, UseTreeKind, Visibility};
+use crate::ast::ids::{IdAssigner, NodeId};
+use crate::ast::mut_visit::MutVisitor;
+use crate::ast::operators::OperatorRegistry;
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+use crate::parsing::declarations::ProgramParser;
+use crate::parsing::{Parser, ParsingBaggage, ParsingContext, SyntaxErr};
+
+/// The extension of gold source files
+pub(crate) const EXTENSION: &str = "tx";
+
+/// The sources of a program by path. The files which weren't added up front are
+/// read from the disk the first time they are needed
+#[derive(Debug, Default)]
+pub(crate) struct SourceFiles {
+    files: HashMap<PathBuf, String>
+}
+
+impl SourceFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, path: impl Into<PathBuf>, src: &str) {
+        self.files.insert(path.into(), src.to_string());
+    }
+
+    /// The source of the file at PATH, if it was added or can be read
+    pub fn load(&mut self, path: &Path) -> Option<&str> {
+        if !self.files.contains_key(path) {
+            let src = fs::read_to_string(path).ok()?;
+            self.files.insert(path.to_path_buf(), src);
+        }
+        self.get(path)
+    }
+
+    /// The source of a file which was already loaded, to render its diagnostics
+    pub fn get(&self, path: &Path) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct ModuleId(pub usize);
+
+/// What a path resolves to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Def {
+    Module(ModuleId),
+    Item(ModuleId, usize) // The index of the item in the items of the module
+}
+
+/// One name brought in by a use declaration (or all the public ones for a glob)
+#[derive(Debug, Clone)]
+pub(crate) struct Import {
+    pub path: Vec<String>,
+    pub name: Option<String>, // None for a glob
+    pub vis: Visibility,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Module {
+    /// The module stands for the program of its file (or for the items of an inline
+    /// module), so it takes the program's id and span
+    pub id: NodeId,
+    pub name: String,
+    pub parent: Option<ModuleId>,
+    /// The file the items are in: the parent's file for inline modules
+    pub file: PathBuf,
+    pub attrs: Vec<Attribute>,
+    pub items: Vec<Item>,
+    pub children: HashMap<String, ModuleId>,
+    pub imports: Vec<Import>,
+    pub span: Span
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ModuleErrKind {
+    Syntax(SyntaxErr),
+    /// None of the files the module could be in exist
+    FileNotFound { module: String, candidates: Vec<PathBuf> },
+    /// The name is defined twice in the same module, the first definition is given
+    Duplicate { name: String, first: Span },
+    Unresolved { name: String, scope: String },
+    Private { name: String, scope: String },
+    SuperOfRoot,
+    /// The path goes on after something which has no items
+    NotAModule(String),
+    ImportCycle(String),
+    /// The name comes from more than one glob, which bring in different things
+    Ambiguous { name: String, globs: Vec<Span> },
+    /// use self; or use super; which don't give the import a name
+    Unnamed
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ModuleErr {
+    pub kind: ModuleErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl ModuleErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        self.kind.to_diagnostic(self.span)
+    }
+}
+
+impl ModuleErrKind {
+    /// The diagnostic for the error found at SPAN
+    pub fn to_diagnostic(&self, span: Span) -> Diagnostic {
+        use ModuleErrKind::*;
+        match self {
+            Syntax(err) => Diagnostic::error(&format!("expected {}, found {}", err.expected, err.found), span, &format!("expected {}", err.expected)),
+            FileNotFound { module, candidates } => {
+                let candidates: Vec<String> = candidates.iter().map(|path| path.display().to_string()).collect();
+                Diagnostic::error(&format!("file not found for module {}", module), span, "declared here")
+                    .with_note(&format!("looked for {}", candidates.join(" and ")))
+            }
+            Duplicate { name, first } => Diagnostic::error(&format!("{} is defined more than once", name), span, "defined again here")
+                .with_label(*first, "first defined here"),
+            Unresolved { name, scope } => Diagnostic::error(&format!("can't find {} in {}", name, scope), span, "not found"),
+            Private { name, scope } => Diagnostic::error(&format!("{} is private to {}", name, scope), span, "private")
+                .with_note(&format!("mark {} as public to use it outside of {}", name, scope)),
+            SuperOfRoot => Diagnostic::error("the root module has no super", span, "there is no module above this one"),
+            NotAModule(name) => Diagnostic::error(&format!("{} is not a module", name), span, "only modules have items to import"),
+            ImportCycle(name) => Diagnostic::error(&format!("the import of {} refers to itself", name), span, "cyclic import"),
+            Ambiguous { name, globs } => globs.iter().fold(Diagnostic::error(&format!("{} is ambiguous", name), span, "used here"), |diag, glob| diag.with_label(*glob, &format!("{} could come from here", name)))
+                .with_note(&format!("import {} by name to say which one it is", name)),
+            Unnamed => Diagnostic::error("the import has no name", span, "give it one with as")
+        }
+    }
+}
+
+/// The span of ROW:COL in SRC, one character wide, for the errors which only have a position
+fn span_at(src: &str, row: usize, col: usize) -> Span {
+    let line_start: usize = src.split_inclusive('\n').take(row.saturating_sub(1)).map(str::len).sum();
+    let start = src[line_start..].char_indices().nth(col.saturating_sub(1)).map(|(i, _)| line_start + i).unwrap_or(src.len());
+    let end = src[start..].chars().next().map(|c| start + c.len_utf8()).unwrap_or(start);
+    Span { start, end, row, col }
+}
+
+/// The program of a module with no items: one which couldn't be loaded, or an
+/// inline module before its items are put in
+fn empty_program(span: Span) -> Program {
+    Program { id: NodeId::DUMMY, attrs: Vec::new(), items: Vec::new(), span }
+}
+
+/// The names TREE imports, each one with the full path it comes from
+fn flatten(tree: &UseTree, outer: &[String], vis: Visibility, imports: &mut Vec<Import>) -> Result<(), Span> {
+    let mut path = outer.to_vec();
+    path.extend(tree.prefix.iter().cloned());
+    match &tree.kind {
+        UseTreeKind::Glob => imports.push(Import { path, name: None, vis, span: tree.span }),
+        UseTreeKind::Nested(trees) => {
+            for tree in trees {
+                flatten(tree, &path, vis, imports)?;
+            }
+        }
+        UseTreeKind::Simple(rename) => {
+            // a::{self} imports a itself
+            if path.len() > 1 && path[path.len() - 1] == "self" {
+                path.pop();
+            }
+            let name = match (rename, path.last()) {
+                (Some(rename), _) => rename.clone(),
+                (None, Some(last)) if last != "self" && last != "super" => last.clone(),
+                _ => return Err(tree.span)
+            };
+            imports.push(Import { path, name: Some(name), vis, span: tree.span });
+        }
+    }
+    Ok(())
+}
+
+/// All the modules of a program, the root first
+#[derive(Debug)]
+pub(crate) struct ModuleTree {
+    modules: Vec<Module>,
+    /// The operators associated in the whole program
+    operators: OperatorRegistry
+}
+
+impl ModuleTree {
+    /// Loads the module tree whose root is the file at ROOT, with every module it
+    /// declares. Errors don't stop the loading: a module which fails to parse or
+    /// which can't be found is left empty. The items of inline modules are moved to
+    /// their module (leaving the mod declaration with no items), and the nodes of all
+    /// the modules are numbered together, so their ids are unique in the whole program
+    pub fn load(files: &mut SourceFiles, root: &Path) -> (Self, Vec<ModuleErr>) {
+        // The operators associated in any module can be used in all of them, so the
+        // tree is scanned for its assoc declarations before it is parsed for good
+        let (scanned, _) = Self::load_with(files, root, OperatorRegistry::scanning());
+        let symbols = scanned.modules.iter().flat_map(|module| &module.items).filter_map(|item| match item {
+            Item::Assoc(AssocDecl { op: Operator::Binary(symbol), .. }) => Some(symbol.clone()),
+            _ => None
+        });
+        Self::load_with(files, root, OperatorRegistry::declaring(symbols.collect()))
+    }
+
+    /// Loads the module tree, parsing its files with OPERATORS
+    fn load_with(files: &mut SourceFiles, root: &Path, operators: OperatorRegistry) -> (Self, Vec<ModuleErr>) {
+        let mut tree = Self { modules: Vec::new(), operators: OperatorRegistry::new() };
+        let mut errs = Vec::new();
+        let baggage = ParsingBaggage::init();
+        baggage.operators.replace(operators);
+        let dir = root.parent().map(Path::to_path_buf).unwrap_or_default();
+        let program = match files.load(root) {
+            Some(src) => Self::parse(src, root, &baggage, &mut errs),
+            None => {
+                let kind = ModuleErrKind::FileNotFound { module: "the root module".to_string(), candidates: vec![root.to_path_buf()] };
+                errs.push(ModuleErr { kind, file: root.to_path_buf(), span: Span::default() });
+                empty_program(Span::default())
+            }
+        };
+        tree.add_module(String::new(), None, root.to_path_buf(), dir, program, files, &baggage, &mut errs);
+        let mut ids = IdAssigner::new();
+        for module in &mut tree.modules {
+            ids.visit_id(&mut module.id);
+            for attr in &mut module.attrs {
+                ids.visit_attribute(attr);
+            }
+            for item in &mut module.items {
+                ids.visit_item(item);
+            }
+        }
+        tree.operators = baggage.operators.into_inner();
+        for module in &tree.modules {
+            for item in &module.items {
+                if let Item::Assoc(assoc) = item {
+                    tree.operators.bind(assoc);
+                }
+            }
+        }
+        (tree, errs)
+    }
+
+    fn parse(src: &str, file: &Path, baggage: &ParsingBaggage, errs: &mut Vec<ModuleErr>) -> Program {
+        match ProgramParser.parse(baggage, &mut ParsingContext::new(src)) {
+            Ok(program) => program,
+            Err(err) => {
+                let span = span_at(src, err.row, err.col);
+                errs.push(ModuleErr { kind: ModuleErrKind::Syntax(err), file: file.to_path_buf(), span });
+                empty_program(Span::default())
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_module(&mut self, name: String, parent: Option<ModuleId>, file: PathBuf, dir: PathBuf, program: Program,
+                  files: &mut SourceFiles, baggage: &ParsingBaggage, errs: &mut Vec<ModuleErr>) -> ModuleId {
+        let Program { id: node, attrs, mut items, span } = program;
+        let id = ModuleId(self.modules.len());
+        let mut imports = Vec::new();
+        let mut defined: HashMap<String, Span> = HashMap::new();
+        for item in &items {
+            let mut names = Vec::new();
+            if let Item::Use(decl) = item {
+                let start = imports.len();
+                if let Err(span) = flatten(&decl.tree, &[], decl.vis, &mut imports) {
+                    errs.push(ModuleErr { kind: ModuleErrKind::Unnamed, file: file.clone(), span });
+                }
+                names.extend(imports[start..].iter().filter_map(|import| import.name.clone().map(|name| (name, import.span))));
+            } else if let Some(name) = item.name() {
+                names.push((name.to_string(), item.span()));
+            }
+            for (name, span) in names {
+                match defined.get(&name) {
+                    Some(first) => {
+                        let kind = ModuleErrKind::Duplicate { name, first: *first };
+                        errs.push(ModuleErr { kind, file: file.clone(), span });
+                    }
+                    None => { defined.insert(name, span); }
+                }
+            }
+        }
+        let module = Module { id: node, name, parent, file: file.clone(), attrs, items: Vec::new(), children: HashMap::new(), imports, span };
+        self.modules.push(module);
+        for item in &mut items {
+            if let Item::Mod(decl) = item {
+                let child_dir = dir.join(&decl.name);
+                let child = match &mut decl.items {
+                    Some(inline) => {
+                        let inline = Program { items: std::mem::take(inline), ..empty_program(decl.span) };
+                        self.add_module(decl.name.clone(), Some(id), file.clone(), child_dir, inline, files, baggage, errs)
+                    }
+                    None => {
+                        let candidates = vec![dir.join(format!("{}.{}", decl.name, EXTENSION)), child_dir.join(format!("mod.{}", EXTENSION))];
+                        let found = candidates.iter().find(|path| files.load(path).is_some()).cloned();
+                        let (child_file, program) = match found {
+                            Some(path) => {
+                                let parsed = Self::parse(files.get(&path).unwrap_or(""), &path, baggage, errs);
+                                (path, parsed)
+                            }
+                            None => {
+                                let kind = ModuleErrKind::FileNotFound { module: decl.name.clone(), candidates };
+                                errs.push(ModuleErr { kind, file: file.clone(), span: decl.span });
+                                (file.clone(), empty_program(decl.span))
+                            }
+                        };
+                        self.add_module(decl.name.clone(), Some(id), child_file, child_dir, program, files, baggage, errs)
+                    }
+                };
+                self.modules[id.0].children.insert(decl.name.clone(), child);
+            }
+        }
+        self.modules[id.0].items = items;
+        id
+    }
+
+    pub fn root(&self) -> ModuleId {
+        ModuleId(0)
+    }
+
+    pub fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id.0]
+    }
+
+    pub fn operators(&self) -> &OperatorRegistry {
+        &self.operators
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = (ModuleId, &Module)> {
+        self.modules.iter().enumerate().map(|(i, module)| (ModuleId(i), module))
+    }
+
+    /// The item a definition refers to, None for modules
+    pub fn item(&self, def: Def) -> Option<&Item> {
+        match def {
+            Def::Module(_) => None,
+            Def::Item(module, index) => Some(&self.modules[module.0].items[index])
+        }
+    }
+
+    /// The path of the module from the root, like ptr or collections::map
+    pub fn path_of(&self, id: ModuleId) -> String {
+        let mut names = Vec::new();
+        let mut current = Some(id);
+        while let Some(module) = current.map(|id| &self.modules[id.0]) {
+            if module.parent.is_some() {
+                names.push(module.name.as_str());
+            }
+            current = module.parent;
+        }
+        names.reverse();
+        names.join("::")
+    }
+
+    fn describe(&self, id: ModuleId) -> String {
+        if id == self.root() {
+            "the root module".to_string()
+        } else {
+            format!("module {}", self.path_of(id))
+        }
+    }
+
+    /// Whether FROM is MODULE or inside it, so that it sees what is private to MODULE
+    pub fn is_inside(&self, from: ModuleId, module: ModuleId) -> bool {
+        let mut current = Some(from);
+        while let Some(id) = current {
+            if id == module {
+                return true;
+            }
+            current = self.modules[id.0].parent;
+        }
+        false
+    }
+
+    /// Resolves a path written in the code of FROM. Segments after the first
+    /// which isn't a module (from in String::from) are not resolved here, so the
+    /// definition is given with how many segments it took
+    pub fn resolve_path(&self, from: ModuleId, segments: &[String]) -> Result<(Def, usize), ModuleErrKind> {
+        self.walk(from, segments, true, &mut Vec::new())
+    }
+
+    /// Resolves the path of an import in FROM, which has to be resolved in full
+    pub fn resolve_import(&self, from: ModuleId, path: &[String]) -> Result<Def, ModuleErrKind> {
+        self.import_target(from, path, &mut Vec::new())
+    }
+
+    fn import_target(&self, from: ModuleId, path: &[String], stack: &mut Vec<(ModuleId, String)>) -> Result<Def, ModuleErrKind> {
+        let (def, taken) = self.walk(from, path, false, stack)?;
+        if taken < path.len() {
+            return Err(ModuleErrKind::NotAModule(path[taken - 1].clone()));
+        }
+        Ok(def)
+    }
+
+    /// Follows SEGMENTS from the current module if RELATIVE, from the root otherwise
+    /// (but self and super are always relative to FROM)
+    fn walk(&self, from: ModuleId, segments: &[String], relative: bool, stack: &mut Vec<(ModuleId, String)>) -> Result<(Def, usize), ModuleErrKind> {
+        let mut module = if relative { from } else { self.root() };
+        let mut taken = 0;
+        if let Some("self") | Some("super") = segments.first().map(String::as_str) {
+            module = from;
+        }
+        while let Some(segment) = segments.get(taken) {
+            match segment.as_str() {
+                "self" if taken == 0 => {}
+                "super" => module = self.modules[module.0].parent.ok_or(ModuleErrKind::SuperOfRoot)?,
+                _ => break
+            }
+            taken += 1;
+        }
+        let mut def = Def::Module(module);
+        while let (Def::Module(module), Some(name)) = (def, segments.get(taken)) {
+            def = self.lookup(module, name, from, stack)?;
+            taken += 1;
+        }
+        Ok((def, taken))
+    }
+
+    /// Finds NAME among the items and imports of MODULE, as seen from FROM
+    fn lookup(&self, module: ModuleId, name: &str, from: ModuleId, stack: &mut Vec<(ModuleId, String)>) -> Result<Def, ModuleErrKind> {
+        let key = (module, name.to_string());
+        if stack.contains(&key) {
+            return Err(ModuleErrKind::ImportCycle(name.to_string()));
+        }
+        stack.push(key);
+        let found = self.lookup_inner(module, name, from, stack);
+        stack.pop();
+        found
+    }
+
+    fn lookup_inner(&self, module: ModuleId, name: &str, from: ModuleId, stack: &mut Vec<(ModuleId, String)>) -> Result<Def, ModuleErrKind> {
+        let m = &self.modules[module.0];
+        let visible = |vis: Visibility| vis == Visibility::Public || self.is_inside(from, module);
+        let private = || ModuleErrKind::Private { name: name.to_string(), scope: self.describe(module) };
+        if let Some(index) = m.items.iter().position(|item| item.name() == Some(name)) {
+            let item = &m.items[index];
+            if !visible(item.vis()) {
+                return Err(private());
+            }
+            return Ok(match item {
+                Item::Mod(_) => Def::Module(m.children[name]),
+                _ => Def::Item(module, index)
+            });
+        }
+        if let Some(import) = m.imports.iter().find(|import| import.name.as_deref() == Some(name)) {
+            if !visible(import.vis) {
+                return Err(private());
+            }
+            return self.import_target(module, &import.path, stack);
+        }
+        // Named items and imports shadow the ones coming from globs, and two globs
+        // can bring in the same name as long as it is the same thing
+        let mut found: Option<Def> = None;
+        let mut globs = Vec::new();
+        for glob in m.imports.iter().filter(|import| import.name.is_none() && visible(import.vis)) {
+            if let Ok(Def::Module(target)) = self.import_target(module, &glob.path, stack) {
+                if let Ok(def) = self.lookup(target, name, module, stack) {
+                    if found.is_some() && found != Some(def) {
+                        globs.push(glob.span);
+                        return Err(ModuleErrKind::Ambiguous { name: name.to_string(), globs });
+                    }
+                    found = Some(def);
+                    globs.push(glob.span);
+                }
+            }
+        }
+        found.ok_or_else(|| ModuleErrKind::Unresolved { name: name.to_string(), scope: self.describe(module) })
+    }
+
+    /// Resolves every import of every module, reporting those which fail
+    pub fn check_imports(&self) -> Vec<ModuleErr> {
+        let mut errs = Vec::new();
+        for (id, module) in self.modules() {
+            for import in &module.imports {
+                let result = match self.resolve_import(id, &import.path) {
+                    Ok(Def::Item(..)) if import.name.is_none() => Err(ModuleErrKind::NotAModule(import.path.join("::"))),
+                    result => result
+                };
+                if let Err(kind) = result {
+                    errs.push(ModuleErr { kind, file: module.file.clone(), span: import.span });
+                }
+            }
+        }
+        errs
+    }
+}
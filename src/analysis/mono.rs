@@ -0,0 +1,482 @@
+//! Monomorphization: finds every concrete instance of the generic functions, packs and
+//! impls which the program reaches from main, so that each can be compiled on its own.
+//! Starting from main, the functions each instance uses (as the type checker recorded
+//! them) are instantiated with the concrete types of the instance, and the calls to the
+//! functions of an interface go to the def of the type they are called on. The packs
+//! are those the instances' types mention, with the packs in their fields.
+//!
+//! A function which instantiates itself with a new type every time, like
+//! fun f<T>(t: T) { f(Box::new(t)); }, would need infinitely many instances: it is
+//! reported as soon as an instance leads back to the function with arguments which
+//! contain its own, or once the function appears INSTANCE_LIMIT times in a chain of
+//! uses. The same goes for a pack containing itself with ever larger generic arguments.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::analysis::cfg::Event;
+use crate::analysis::drops::{self, ElaboratedBody};
+use crate::analysis::typeck::{Instantiation, InterfaceRef, TypeCx, TypeckResults};
+use crate::analysis::types::{Substitution, Type};
+use crate::ast::declarations::{DefMember, FunDecl, InterfaceMember, Item};
+use crate::ast::expressions::Expr;
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::statements::Pattern;
+use crate::ast::visit::{self, Visitor};
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+/// How many times a function (or pack) may appear in one chain of instances
+const INSTANCE_LIMIT: usize = 32;
+
+/// A function with concrete types for its generic parameters
+#[derive(Debug, Clone)]
+pub(crate) struct FunInstance {
+    pub fun: NodeId,
+    /// What the generic parameters of the function and of its container are
+    pub args: Substitution,
+    /// The name of the instance: Box<i32>::new or execute<Job>
+    pub symbol: String,
+    pub receiver: Option<Type>,
+    pub params: Vec<Type>,
+    pub ret: Type,
+    /// The instances the body uses, by where it uses them
+    pub callees: Vec<(Span, usize)>,
+    /// The instance which first used this one, None for main
+    pub parent: Option<usize>
+}
+
+/// A pack with concrete generic arguments, and the types of its fields for them
+#[derive(Debug, Clone)]
+pub(crate) struct PackInstance {
+    pub id: NodeId,
+    pub args: Vec<Type>,
+    pub fields: Vec<(String, Type)>,
+    parent: Option<usize>
+}
+
+/// An impls or def whose functions are used with concrete generic arguments
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ImplsInstance {
+    pub id: NodeId,
+    pub args: Vec<Type>,
+    pub target: Type
+}
+
+/// Everything the program reaches from main, in the order it is found
+#[derive(Debug, Default)]
+pub(crate) struct MonoItems {
+    pub funs: Vec<FunInstance>,
+    pub packs: Vec<PackInstance>,
+    pub impls: Vec<ImplsInstance>
+}
+
+impl MonoItems {
+    /// The type of the expression, parameter or pattern ID in the body of the INSTANCE-th function
+    pub fn type_of(&self, cx: &TypeCx, results: &TypeckResults, instance: usize, id: NodeId) -> Option<Type> {
+        let ty = results.expr_types.get(id).or_else(|| results.local_types.get(id))?;
+        Some(cx.normalize(&ty.substitute(&self.funs[instance].args)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MonoErrKind {
+    /// The function or pack NAME needs a new instance of itself every time. CHAIN holds
+    /// its first instances, each needing (or containing, for packs) the next one
+    Infinite { name: String, chain: Vec<String>, pack: bool }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MonoErr {
+    pub kind: MonoErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl MonoErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match &self.kind {
+            MonoErrKind::Infinite { name, chain, pack } => {
+                let (what, verb) = if *pack { ("pack", "contains") } else { ("function", "needs") };
+                let label = if *pack { "contains itself with new generic arguments" } else { "instantiates it again with new types" };
+                let mut note = chain[0].clone();
+                for (i, instance) in chain[1..].iter().enumerate() {
+                    let link = if i == 0 { format!(" {} ", verb) } else { format!(", which {} ", verb) };
+                    note.push_str(&link);
+                    note.push_str(instance);
+                }
+                Diagnostic::error(&format!("infinite instantiation of the {} {}", what, name), self.span, label)
+                    .with_note(&format!("{}, and so on", note))
+            }
+        }
+    }
+}
+
+/// The ids of the parameters, patterns and expressions of a function
+#[derive(Default)]
+struct BodyIds(Vec<NodeId>);
+
+impl<'ast> Visitor<'ast> for BodyIds {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        self.0.push(expr.id);
+        visit::walk_expr(self, expr);
+    }
+
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        self.0.push(pattern.id);
+        visit::walk_pattern(self, pattern);
+    }
+}
+
+/// A function with its generic arguments, as a key: substitutions can't be hashed
+type InstanceKey = (NodeId, Vec<((NodeId, usize), Type)>);
+
+fn key(fun: NodeId, args: &Substitution) -> InstanceKey {
+    let mut args: Vec<((NodeId, usize), Type)> = args.iter().map(|(param, ty)| (*param, ty.clone())).collect();
+    args.sort_by_key(|(param, _)| *param);
+    (fun, args)
+}
+
+/// Whether TY has nothing left to infer or instantiate
+fn concrete(ty: &Type) -> bool {
+    !ty.any(&|ty| matches!(ty, Type::Var(_) | Type::Param { .. } | Type::Assoc(..) | Type::Error))
+}
+
+/// The function a use of FUN with the concrete ARGS calls: the function of the def
+/// for the type a function of an interface is called on (or the interface's own if
+/// the def doesn't give it)
+pub(crate) fn dispatch(cx: &TypeCx, fun: NodeId, args: Substitution) -> (NodeId, Substitution) {
+    let sig = match cx.funs.get(fun) {
+        Some(sig) => sig,
+        None => return (fun, args)
+    };
+    let (interface, generics) = match sig.container.and_then(|container| cx.interfaces.get(container).map(|info| (container, info.generics))) {
+        Some(found) => found,
+        None => return (fun, args)
+    };
+    let self_ty = match args.get(&(interface, generics)) {
+        Some(ty) => ty.clone(),
+        None => return (fun, args)
+    };
+    let interface_args: Vec<Type> = (0..generics).filter_map(|index| args.get(&(interface, index)).cloned()).collect();
+    for info in &cx.impls {
+        let def_args = match &info.interface {
+            Some(InterfaceRef::Declared(id, def_args)) if *id == interface => def_args,
+            _ => continue
+        };
+        let mut subst = Substitution::new();
+        let matches = def_args.iter().zip(&interface_args).all(|(pattern, arg)| cx.match_type(pattern, arg, info.id, &mut subst))
+            && cx.match_type(&info.target, &self_ty, info.id, &mut subst);
+        if let (true, Some(def_fun)) = (matches, info.funs.get(&sig.name)) {
+            for index in 0..sig.generics {
+                if let Some(ty) = args.get(&(fun, index)) {
+                    subst.insert((*def_fun, index), ty.clone());
+                }
+            }
+            return (*def_fun, subst);
+        }
+    }
+    (fun, args)
+}
+
+pub(crate) struct Monomorphizer<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    decls: NodeMap<&'t FunDecl>,
+    /// The functions each body uses
+    uses: NodeMap<Vec<&'a Instantiation>>,
+    /// The bodies with their drops, see drops.rs
+    bodies: NodeMap<&'a ElaboratedBody>,
+    items: MonoItems,
+    found: HashMap<InstanceKey, usize>,
+    found_packs: HashMap<(NodeId, Vec<Type>), usize>,
+    errs: Vec<MonoErr>
+}
+
+impl<'a, 't> Monomorphizer<'a, 't> {
+    pub fn collect(cx: &'a TypeCx<'t>, results: &'a TypeckResults, bodies: &'a [ElaboratedBody]) -> (MonoItems, Vec<MonoErr>) {
+        let mut mono = Monomorphizer {
+            cx, results, decls: NodeMap::new(), uses: NodeMap::new(), bodies: NodeMap::new(), items: MonoItems::default(),
+            found: HashMap::new(), found_packs: HashMap::new(), errs: Vec::new()
+        };
+        for (_, module) in cx.tree.modules() {
+            for item in &module.items {
+                match item {
+                    Item::Fun(fun) => { mono.decls.insert(fun.id, fun); }
+                    Item::Impls(impls) => impls.funs.iter().for_each(|fun| { mono.decls.insert(fun.id, fun); }),
+                    Item::Def(def) => {
+                        for member in &def.members {
+                            if let DefMember::Fun(fun) = member {
+                                mono.decls.insert(fun.id, fun);
+                            }
+                        }
+                    }
+                    Item::Interface(interface) => {
+                        for member in &interface.members {
+                            if let InterfaceMember::Fun(fun) = member {
+                                mono.decls.insert(fun.id, fun);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for instantiation in &results.instantiations {
+            mono.uses.get_or_default(instantiation.owner).push(instantiation);
+        }
+        for body in bodies {
+            mono.bodies.insert(body.fun, body);
+        }
+        let root = cx.tree.module(cx.tree.root());
+        let main = root.items.iter().find_map(|item| match item {
+            Item::Fun(fun) if fun.name == "main" && fun.generics.is_empty() => Some(fun.id),
+            _ => None
+        });
+        if let Some(main) = main {
+            mono.instance(main, Substitution::new(), None);
+            mono.run();
+        }
+        (mono.items, mono.errs)
+    }
+
+    /// Goes through the instances in the order they are found, finding those they use
+    fn run(&mut self) {
+        let mut next = 0;
+        while next < self.items.funs.len() {
+            let (fun, args) = (self.items.funs[next].fun, self.items.funs[next].args.clone());
+            for instantiation in self.uses.get(fun).cloned().unwrap_or_default() {
+                let used_args: Substitution = instantiation.args.iter().map(|(param, ty)| (*param, self.cx.normalize(&ty.substitute(&args)))).collect();
+                if !used_args.values().all(concrete) {
+                    continue;
+                }
+                let (used, used_args) = dispatch(self.cx, instantiation.fun, used_args);
+                if let Some(chain) = self.repeats(next, used, &used_args) {
+                    let name = self.cx.funs.get(used).map_or(String::new(), |sig| sig.name.clone());
+                    let mut chain: Vec<String> = chain.iter().map(|instance| self.items.funs[*instance].symbol.clone()).collect();
+                    chain.push(self.symbol(used, &used_args));
+                    let file = self.cx.tree.module(instantiation.module).file.clone();
+                    self.errs.push(MonoErr { kind: MonoErrKind::Infinite { name, chain: chain.into_iter().take(3).collect(), pack: false }, file, span: instantiation.span });
+                    return;
+                }
+                let callee = self.instance(used, used_args, Some(next));
+                self.items.funs[next].callees.push((instantiation.span, callee));
+            }
+            let instance = &self.items.funs[next];
+            let mut types: Vec<Type> = instance.receiver.iter().chain(&instance.params).cloned().collect();
+            types.push(instance.ret.clone());
+            if let Some(decl) = self.decls.get(fun) {
+                let mut ids = BodyIds::default();
+                decl.params.iter().for_each(|param| ids.0.push(param.id));
+                if let Some(body) = &decl.body {
+                    ids.visit_block(body);
+                }
+                types.extend(ids.0.into_iter().filter_map(|id| self.items.type_of(self.cx, self.results, next, id)));
+            }
+            for ty in types {
+                if self.packs_in(&ty, None).is_err() {
+                    return;
+                }
+            }
+            // What the body destroys calls the drops of the defs of Destroy. Those of
+            // generic values and arrays depend on what they turn out to be
+            let destroyed: Vec<(Type, bool, Span)> = self.bodies.get(fun).map(|body| body.cfg.blocks.iter().flat_map(|block| &block.events).filter_map(|event| match event {
+                Event::Drop { ty, fun, span, .. } => Some((ty.clone(), fun.is_none(), *span)),
+                _ => None
+            }).collect()).unwrap_or_default();
+            for (ty, whole, span) in destroyed {
+                let ty = self.cx.normalize(&ty.substitute(&args));
+                if !concrete(&ty) {
+                    continue;
+                }
+                let mut destroys = Vec::new();
+                self.destroys(&ty, whole, &mut destroys);
+                for (drop, drop_args) in destroys {
+                    let callee = self.instance(drop, drop_args, Some(next));
+                    self.items.funs[next].callees.push((span, callee));
+                }
+            }
+            next += 1;
+        }
+    }
+
+    /// The drops destroying a TY calls: that of its def of Destroy, then if WHOLE those
+    /// of its fields or elements, which drop elaboration only spells out for packs
+    fn destroys(&self, ty: &Type, whole: bool, destroys: &mut Vec<(NodeId, Substitution)>) {
+        if let Type::NoDrop(_) = ty {
+            return;
+        }
+        destroys.extend(drops::destroy_def(self.cx, ty));
+        if !whole {
+            return;
+        }
+        match ty {
+            Type::Pack { id, args, .. } => {
+                if let Some(index) = self.found_packs.get(&(*id, args.clone())) {
+                    for (_, field) in self.items.packs[*index].fields.iter().rev() {
+                        self.destroys(field, true, destroys);
+                    }
+                }
+            }
+            Type::Array(elem, _) => self.destroys(elem, true, destroys),
+            Type::Tuple(elems) => elems.iter().rev().for_each(|elem| self.destroys(elem, true, destroys)),
+            _ => {}
+        }
+    }
+
+    /// The instances of FUN from PARENT back to main, oldest first, if using FUN with
+    /// ARGS there would never end: if an instance of FUN leads to FUN with arguments
+    /// containing its own, going on from there leads to larger ones again, and so on.
+    /// Growth of any other kind stops at INSTANCE_LIMIT instances in a chain
+    fn repeats(&self, parent: usize, fun: NodeId, args: &Substitution) -> Option<Vec<usize>> {
+        let mut chain = Vec::new();
+        let mut current = Some(parent);
+        while let Some(instance) = current {
+            if self.items.funs[instance].fun == fun {
+                chain.push(instance);
+            }
+            current = self.items.funs[instance].parent;
+        }
+        chain.reverse();
+        let grows = |instance: &usize| {
+            let earlier = &self.items.funs[*instance].args;
+            earlier != args && earlier.iter().all(|(param, ty)| args.get(param).is_some_and(|arg| arg.any(&|inner| inner == ty)))
+        };
+        match chain.iter().position(grows) {
+            Some(start) => Some(chain.split_off(start)),
+            None if chain.len() >= INSTANCE_LIMIT => Some(chain),
+            None => None
+        }
+    }
+
+    /// The index of the instance of FUN with ARGS, added if it is new
+    fn instance(&mut self, fun: NodeId, args: Substitution, parent: Option<usize>) -> usize {
+        let key = key(fun, &args);
+        if let Some(index) = self.found.get(&key) {
+            return *index;
+        }
+        let sig = self.cx.funs.get(fun).cloned();
+        let lower = |ty: &Type| self.cx.normalize(&ty.substitute(&args));
+        let (receiver, params, ret) = match &sig {
+            Some(sig) => (sig.receiver.as_ref().map(lower), sig.params.iter().map(lower).collect(), lower(&sig.ret)),
+            None => (None, Vec::new(), Type::unit())
+        };
+        let symbol = self.symbol(fun, &args);
+        if let Some(container) = sig.and_then(|sig| sig.container) {
+            self.impls_instance(container, &args);
+        }
+        self.items.funs.push(FunInstance { fun, args, symbol, receiver, params, ret, callees: Vec::new(), parent });
+        self.found.insert(key, self.items.funs.len() - 1);
+        self.items.funs.len() - 1
+    }
+
+    fn impls_instance(&mut self, container: NodeId, args: &Substitution) {
+        let info = match self.cx.impls.iter().find(|info| info.id == container) {
+            Some(info) => info,
+            None => return
+        };
+        let instance = ImplsInstance {
+            id: container,
+            args: (0..info.generics).filter_map(|index| args.get(&(container, index)).cloned()).collect(),
+            target: self.cx.normalize(&info.target.substitute(args))
+        };
+        if !self.items.impls.contains(&instance) {
+            self.items.impls.push(instance);
+        }
+    }
+
+    /// The name of FUN with ARGS: its type (for the functions of impls, defs and
+    /// interfaces) and its own generic arguments, Box<i32>::new or execute<Job>
+    fn symbol(&self, fun: NodeId, args: &Substitution) -> String {
+        let sig = match self.cx.funs.get(fun) {
+            Some(sig) => sig,
+            None => return String::new()
+        };
+        let mut symbol = String::new();
+        if let Some(container) = sig.container {
+            let self_ty = match self.cx.interfaces.get(container) {
+                Some(info) => args.get(&(container, info.generics)).cloned(),
+                None => self.cx.self_types.get(container).map(|ty| self.cx.normalize(&ty.substitute(args)))
+            };
+            if let Some(self_ty) = self_ty {
+                symbol.push_str(&format!("{}::", self_ty));
+            }
+        }
+        symbol.push_str(&sig.name);
+        if sig.generics > 0 {
+            let own: Vec<String> = (0..sig.generics).map(|index| args.get(&(fun, index)).map_or("_".to_string(), Type::to_string)).collect();
+            symbol.push_str(&format!("<{}>", own.join(", ")));
+        }
+        symbol
+    }
+
+    /// Adds the instances of the packs in TY, and of those in their fields. PARENT is
+    /// the pack instance TY is a field of. Fails if a pack contains itself endlessly
+    fn packs_in(&mut self, ty: &Type, parent: Option<usize>) -> Result<(), ()> {
+        let (id, args) = match ty {
+            Type::Pack { id, args, .. } => (*id, args.clone()),
+            Type::Ref(inner) | Type::Ptr(inner) | Type::NoDrop(inner) | Type::Array(inner, _) => return self.packs_in(inner, parent),
+            Type::Tuple(elems) => return elems.iter().try_for_each(|elem| self.packs_in(elem, parent)),
+            Type::Fun(params, ret) => return params.iter().chain(std::iter::once(&**ret)).try_for_each(|ty| self.packs_in(ty, parent)),
+            _ => return Ok(())
+        };
+        for arg in &args {
+            self.packs_in(arg, parent)?;
+        }
+        if !concrete(ty) || self.found_packs.contains_key(&(id, args.clone())) {
+            return Ok(());
+        }
+        let mut chain = Vec::new();
+        let mut current = parent;
+        while let Some(instance) = current {
+            if self.items.packs[instance].id == id {
+                chain.push(instance);
+            }
+            current = self.items.packs[instance].parent;
+        }
+        chain.reverse();
+        // Like for functions, a pack containing itself with larger arguments never ends
+        let grows = |instance: &usize| {
+            let earlier = &self.items.packs[*instance].args;
+            *earlier != args && earlier.iter().zip(&args).all(|(earlier, arg)| arg.any(&|inner| inner == earlier))
+        };
+        let start = chain.iter().position(grows).or(if chain.len() >= INSTANCE_LIMIT { Some(0) } else { None });
+        if let Some(start) = start {
+            let mut chain: Vec<String> = chain[start..].iter().take(2).map(|instance| self.pack_name(&self.items.packs[*instance])).collect();
+            chain.push(ty.to_string());
+            let name = self.cx.packs.get(id).map_or(String::new(), |info| info.name.clone());
+            let (file, span) = self.pack_decl(id);
+            self.errs.push(MonoErr { kind: MonoErrKind::Infinite { name, chain, pack: true }, file, span });
+            return Err(());
+        }
+        let info = match self.cx.packs.get(id) {
+            Some(info) => info.clone(),
+            None => return Ok(())
+        };
+        let subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((id, index), arg)).collect();
+        let fields: Vec<(String, Type)> = info.fields.iter().map(|(name, ty)| (name.clone(), self.cx.normalize(&ty.substitute(&subst)))).collect();
+        self.items.packs.push(PackInstance { id, args: args.clone(), fields: fields.clone(), parent });
+        let index = self.items.packs.len() - 1;
+        self.found_packs.insert((id, args), index);
+        fields.iter().try_for_each(|(_, ty)| self.packs_in(ty, Some(index)))
+    }
+
+    fn pack_name(&self, pack: &PackInstance) -> String {
+        let name = self.cx.packs.get(pack.id).map_or(String::new(), |info| info.name.clone());
+        Type::Pack { id: pack.id, name, args: pack.args.clone() }.to_string()
+    }
+
+    /// Where the pack ID is declared
+    fn pack_decl(&self, id: NodeId) -> (PathBuf, Span) {
+        for (_, module) in self.cx.tree.modules() {
+            for item in &module.items {
+                if let Item::Pack(pack) = item {
+                    if pack.id == id {
+                        return (module.file.clone(), pack.span);
+                    }
+                }
+            }
+        }
+        (PathBuf::new(), Span::default())
+    }
+}
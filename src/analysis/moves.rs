@@ -0,0 +1,213 @@
+//! The move checker: values are moved by default, so once a place has been used by
+//! value (passed, returned, bound or assigned somewhere else) it can't be used again
+//! until it is given a new value. A use copies instead of moving when the type of the
+//! place is Copy.
+//!
+//! The checker follows the control-flow graph of each body, knowing at every point
+//! which places may have been moved out, and by which use: a place moved on only one
+//! side of an if counts as moved after it. Moving a field of a pack moves only that
+//! field, which leaves the rest usable but the pack as a whole partially moved. Values
+//! behind a reference, a pointer or an index can't be moved out at all, only copied.
+
+use std::path::PathBuf;
+
+use crate::analysis::cfg::{self, BlockId, Cfg, Event, Place};
+use crate::analysis::resolve::Builtin;
+use crate::analysis::solver::Proof;
+use crate::analysis::typeck::{BoundInfo, InterfaceRef, TypeCx, TypeckResults};
+use crate::analysis::types::Type;
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MoveErrKind {
+    /// PLACE is used (or borrowed) after MOVED, which is it or a part of it (PARTIAL)
+    /// or contains it, was moved at MOVED_AT
+    UseAfterMove { place: String, moved: String, ty: Type, moved_at: Span, partial: bool, borrow: bool },
+    /// PLACE is used before anything was assigned to the binding declared at DECLARED
+    Uninitialized { place: String, declared: Span },
+    /// PLACE, which isn't Copy, is moved out from behind a reference, pointer or index
+    MoveOutOf { place: String, ty: Type }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MoveErr {
+    pub kind: MoveErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl MoveErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match &self.kind {
+            MoveErrKind::UseAfterMove { place, moved, ty, moved_at, partial, borrow } => {
+                let (what, label) = if *borrow { ("borrow", "borrowed here after the move") } else { ("use", "used here after the move") };
+                let partially = if *partial { "partially " } else { "" };
+                // A use before the move it follows can only come back around a loop
+                let moved_label = if moved_at.start >= self.span.start {
+                    format!("{} moved here, in the previous iteration of the loop", moved)
+                } else {
+                    format!("{} moved here", moved)
+                };
+                Diagnostic::error(&format!("{} of {}moved {}", what, partially, place), self.span, label)
+                    .with_label(*moved_at, &moved_label)
+                    .with_note(&format!("{} is moved because its type {} is not Copy", moved, ty))
+            }
+            MoveErrKind::Uninitialized { place, declared } => {
+                Diagnostic::error(&format!("use of possibly uninitialized {}", place), self.span, "used here")
+                    .with_label(*declared, "declared here without a value")
+            }
+            MoveErrKind::MoveOutOf { place, ty } => {
+                Diagnostic::error(&format!("cannot move out of {}", place), self.span, "moved here")
+                    .with_note(&format!("{} is not Copy, and only Copy values can be taken from behind a reference, a pointer or an index", ty))
+            }
+        }
+    }
+}
+
+/// Whether a value of type TY is moved when it is used, rather than copied, where the
+/// bounds ENV hold. Types which aren't known well enough, after an error, are taken to be Copy
+pub(crate) fn moves(cx: &TypeCx, env: &[BoundInfo], ty: &Type) -> bool {
+    cx.prove(env, ty, &InterfaceRef::Builtin(Builtin::Copy), false) == Proof::Fails
+}
+
+/// A place which may have been moved out (or never given a value) at some point of a body
+#[derive(Debug, Clone, PartialEq)]
+struct Moved {
+    place: Place,
+    span: Span,
+    ty: Type,
+    uninit: bool
+}
+
+pub(crate) struct MoveChecker<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    env: &'a [BoundInfo],
+    file: PathBuf,
+    errs: Vec<MoveErr>
+}
+
+impl<'a, 't> MoveChecker<'a, 't> {
+    pub fn check(cx: &'a TypeCx<'t>, results: &'a TypeckResults) -> Vec<MoveErr> {
+        let mut errs = Vec::new();
+        for (fun, file) in cfg::bodies(cx.tree) {
+            let env = cx.bounds.get(fun.id).map_or(&[][..], |bounds| &bounds[..]);
+            let mut checker = MoveChecker { cx, results, env, file: file.clone(), errs: Vec::new() };
+            checker.check_body(&Cfg::build(cx, results, fun));
+            errs.append(&mut checker.errs);
+        }
+        errs
+    }
+
+    fn check_body(&mut self, cfg: &Cfg) {
+        // What may be moved when each block starts, None for the blocks not reached yet
+        let mut entries: Vec<Option<Vec<Moved>>> = vec![None; cfg.blocks.len()];
+        entries[0] = Some(Vec::new());
+        let mut work: Vec<BlockId> = vec![0];
+        while let Some(block) = work.pop() {
+            let mut state = entries[block].clone().unwrap_or_default();
+            for event in &cfg.blocks[block].events {
+                self.apply(cfg, &mut state, event, false);
+            }
+            for next in cfg.successors(block) {
+                let changed = match &mut entries[next] {
+                    Some(entry) => {
+                        let before = entry.len();
+                        for moved in &state {
+                            if !entry.contains(moved) {
+                                entry.push(moved.clone());
+                            }
+                        }
+                        entry.len() > before
+                    }
+                    entry => {
+                        *entry = Some(state.clone());
+                        true
+                    }
+                };
+                if changed && !work.contains(&next) {
+                    work.push(next);
+                }
+            }
+        }
+        for (block, entry) in entries.into_iter().enumerate() {
+            if let Some(mut state) = entry {
+                for event in &cfg.blocks[block].events {
+                    self.apply(cfg, &mut state, event, true);
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, cfg: &Cfg, state: &mut Vec<Moved>, event: &Event, report: bool) {
+        match event {
+            Event::Bind { local, name, init, span, .. } => {
+                state.retain(|moved| moved.place.local != *local);
+                if !init {
+                    let place = Place::local(*local, name);
+                    state.push(Moved { place, span: *span, ty: Type::Error, uninit: true });
+                }
+            }
+            Event::Use { place, expr, span } => {
+                if self.check_use(cfg, state, place, *span, false, report) {
+                    return;
+                }
+                let ty = match self.results.expr_types.get(*expr) {
+                    Some(ty) if moves(self.cx, self.env, ty) => ty.clone(),
+                    _ => return
+                };
+                if place.is_indirect() {
+                    if report {
+                        self.error(MoveErrKind::MoveOutOf { place: place.to_string(), ty }, *span);
+                    }
+                } else {
+                    state.push(Moved { place: place.clone(), span: *span, ty, uninit: false });
+                }
+            }
+            Event::Borrow { place, span, .. } => {
+                self.check_use(cfg, state, place, *span, true, report);
+            }
+            Event::Assign { place, .. } => {
+                if !place.is_indirect() {
+                    state.retain(|moved| !place.contains(&moved.place));
+                }
+            }
+            Event::End { local, .. } => state.retain(|moved| moved.place.local != *local),
+            Event::Return { .. } | Event::Drop { .. } | Event::SetFlag { .. } => {}
+        }
+    }
+
+    /// Checks that nothing of PLACE has been moved, reporting the first move found if
+    /// REPORT. Whether a move was found
+    fn check_use(&mut self, cfg: &Cfg, state: &[Moved], place: &Place, span: Span, borrow: bool, report: bool) -> bool {
+        let found = state.iter().find(|moved| moved.place.contains(place))
+            .map(|moved| (moved, false))
+            .or_else(|| state.iter().find(|moved| place.contains(&moved.place)).map(|moved| (moved, true)));
+        let (moved, partial) = match found {
+            Some(found) => found,
+            None => return false
+        };
+        if report {
+            let kind = if moved.uninit {
+                let declared = cfg.local(place.local).map_or(moved.span, |(_, _, declared)| *declared);
+                MoveErrKind::Uninitialized { place: place.to_string(), declared }
+            } else {
+                MoveErrKind::UseAfterMove {
+                    place: place.to_string(), moved: moved.place.to_string(), ty: moved.ty.clone(),
+                    moved_at: moved.span, partial, borrow
+                }
+            };
+            self.error(kind, span);
+        }
+        true
+    }
+
+    fn error(&mut self, kind: MoveErrKind, span: Span) {
+        let err = MoveErr { kind, file: self.file.clone(), span };
+        // The expressions a scope defers are checked on every way out of it
+        if !self.errs.contains(&err) {
+            self.errs.push(err);
+        }
+    }
+}
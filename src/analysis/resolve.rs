@@ -0,0 +1,655 @@
+//! Name resolution: connects every name in the code of a module tree to what it
+//! names. Module-level names (items, imports, globs) are looked up in the module tree,
+//! and the resolver adds the scopes inside the items: the generic parameters of an
+//! item (and of the impls, def or interface around a function), Self and self inside
+//! them, and the locals of the blocks.
+//!
+//! A let binding is in scope from the statement after it to the end of its block,
+//! so its initializer still sees what the name meant before: let alloc: *T = { alloc as _ };
+//! shadows alloc with a cast of the old alloc. Shadowing a binding which was never
+//! used in the same block is allowed but warned about, since the first value is lost.
+//! Generic parameters can't shadow each other.
+//!
+//! An impls or def without generic parameters declares them implicitly by using
+//! unknown names as the arguments of its types: impls Box<T> is generic over T.
+//!
+//! The result goes in side tables keyed by node id. Types have no ids, so the
+//! names used in the types of an item are recorded per item (functions being their
+//! own item, with the generics of the impls around them).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::analysis::modules::{Def, ModuleErrKind, ModuleId, ModuleTree};
+use crate::ast::declarations::{AssocDecl, Bound, ConstDecl, DefDecl, DefMember, FieldDecl, FunDecl, GenericParam, ImplsDecl, InterfaceDecl, InterfaceMember, Item, PackDecl, Param, WherePredicate};
+use crate::ast::expressions::{Expr, ExprKind, Path};
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::statements::{Block, LetStmt, Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::types::{Ty, TyKind};
+use crate::ast::visit::{self, Visitor};
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+/// What the compiler provides without it being declared anywhere. A declared item
+/// with the same name hides it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Builtin {
+    Sizeof,
+    Drop,
+    Alloc,
+    Dealloc,
+    Read, // ptr::read
+    Write, // ptr::write
+    Transmute,
+    Copy, // Interface of the types which are copied instead of moved
+    Destroy, // Interface of the types with a destructor
+    StrSlice // The type of string slices, "..." is a &StrSlice
+}
+
+impl Builtin {
+    const ALL: [Builtin; 10] = [
+        Builtin::Sizeof, Builtin::Drop, Builtin::Alloc, Builtin::Dealloc, Builtin::Read, Builtin::Write, Builtin::Transmute,
+        Builtin::Copy, Builtin::Destroy, Builtin::StrSlice
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|builtin| builtin.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Sizeof => "sizeof",
+            Builtin::Drop => "drop",
+            Builtin::Alloc => "alloc",
+            Builtin::Dealloc => "dealloc",
+            Builtin::Read => "ptr::read",
+            Builtin::Write => "ptr::write",
+            Builtin::Transmute => "transmute",
+            Builtin::Copy => "Copy",
+            Builtin::Destroy => "Destroy",
+            Builtin::StrSlice => "StrSlice"
+        }
+    }
+
+    /// Whether calling the builtin is only allowed in unsafe code
+    pub fn is_unsafe(&self) -> bool {
+        matches!(self, Builtin::Dealloc | Builtin::Read | Builtin::Write | Builtin::Transmute)
+    }
+
+    /// What the builtin is, as in "x is a function, not a type"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Builtin::Sizeof | Builtin::Drop | Builtin::Alloc | Builtin::Dealloc | Builtin::Read | Builtin::Write | Builtin::Transmute => "a function",
+            Builtin::Copy | Builtin::Destroy => "an interface",
+            Builtin::StrSlice => "a type"
+        }
+    }
+}
+
+/// What a name resolves to. Definitions are identified by the id of their node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Res {
+    Module(ModuleId),
+    Item(NodeId),
+    /// The INDEX-th generic parameter of OWNER (an item or function). The implicit
+    /// parameters of an impls or def come after the declared ones
+    Generic { owner: NodeId, index: usize },
+    SelfTy(NodeId), // Self in an impls, def or interface
+    SelfValue(NodeId), // self in a method
+    Local(NodeId), // A parameter, or a binding of a pattern
+    Builtin(Builtin)
+}
+
+/// What a path in an expression resolves to: RES for its first segments, with the
+/// last UNRESOLVED ones (from in String::from) left to the type checker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PartialRes {
+    pub res: Res,
+    pub unresolved: usize
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Resolutions {
+    /// The paths of the path expressions and pack literals, by expression id
+    pub paths: NodeMap<PartialRes>,
+    /// The names used in the types of each item, by item id (functions included)
+    pub types: NodeMap<HashMap<String, Res>>,
+    /// The names of the generic parameters impls and defs declare by using them
+    pub implicit_generics: NodeMap<Vec<String>>
+}
+
+impl Resolutions {
+    pub fn path(&self, expr: NodeId) -> Option<PartialRes> {
+        self.paths.get(expr).copied()
+    }
+
+    /// What NAME means in the types of the item OWNER
+    pub fn ty_name(&self, owner: NodeId, name: &str) -> Option<Res> {
+        self.types.get(owner).and_then(|names| names.get(name)).copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ResolveErrKind {
+    /// Resolving a path through the modules failed
+    Path(ModuleErrKind),
+    /// The name is in none of the scopes around it
+    Unresolved(String),
+    /// The name resolves to FOUND where EXPECTED was needed
+    Expected { name: String, found: &'static str, expected: &'static str },
+    /// Self or self used where there is none
+    NoSelf(&'static str),
+    /// A generic parameter with the name of one of the item around it
+    ShadowedGeneric { name: String, outer: Span },
+    /// Two parameters, or two bindings of a pattern, with the same name
+    Duplicate { name: String, first: Span },
+    /// A binding shadowed in its own block without being used (a warning)
+    ShadowedUnused { name: String, first: Span }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResolveErr {
+    pub kind: ResolveErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl ResolveErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        use ResolveErrKind::*;
+        match &self.kind {
+            Path(kind) => kind.to_diagnostic(self.span),
+            Unresolved(name) => Diagnostic::error(&format!("can't find {} in this scope", name), self.span, "not found"),
+            Expected { name, found, expected } => Diagnostic::error(&format!("{} is {}, not {}", name, found, expected), self.span, &format!("expected {}", expected)),
+            NoSelf("Self") => Diagnostic::error("Self is only defined in impls, defs and interfaces", self.span, "no Self here"),
+            NoSelf(what) => Diagnostic::error(&format!("{} is only defined in methods", what), self.span, &format!("no {} here", what))
+                .with_note("methods take self, &self or *self as their first parameter"),
+            ShadowedGeneric { name, outer } => Diagnostic::error(&format!("the generic parameter {} is already declared", name), self.span, "declared again here")
+                .with_label(*outer, &format!("{} is declared here", name)),
+            Duplicate { name, first } => Diagnostic::error(&format!("{} is bound more than once", name), self.span, "bound again here")
+                .with_label(*first, "first bound here"),
+            ShadowedUnused { name, first } => Diagnostic::warning(&format!("{} is shadowed before it is used", name), self.span, "shadowed here")
+                .with_label(*first, &format!("this value of {} is never used", name))
+                .with_note("bind it to _ if the value is not needed")
+        }
+    }
+}
+
+/// What a name is expected to be where it is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Type,
+    Interface,
+    Pack,
+    Value
+}
+
+impl Expect {
+    fn describe(&self) -> &'static str {
+        match self {
+            Expect::Type => "a type",
+            Expect::Interface => "an interface",
+            Expect::Pack => "a pack",
+            Expect::Value => "a value"
+        }
+    }
+}
+
+/// A local in scope, and whether it was used so far
+#[derive(Debug)]
+struct Local {
+    name: String,
+    id: NodeId,
+    span: Span,
+    used: bool
+}
+
+pub(crate) struct Resolver<'t> {
+    tree: &'t ModuleTree,
+    module: ModuleId,
+    /// What each item of the tree is, for the errors
+    kinds: NodeMap<&'static str>,
+    /// The generic parameters in scope, innermost last, with where they are declared
+    generics: Vec<(String, Res, Span)>,
+    self_ty: Option<NodeId>,
+    self_value: Option<NodeId>,
+    /// The item (or function) whose types are being resolved
+    owner: Option<NodeId>,
+    /// The locals of the blocks being resolved, innermost last
+    ribs: Vec<Vec<Local>>,
+    /// The names bound by the pattern being resolved
+    bound: Vec<(String, Span)>,
+    /// The span of the innermost node being resolved, for the errors about types
+    span: Span,
+    resolutions: Resolutions,
+    errs: Vec<ResolveErr>
+}
+
+impl<'t> Resolver<'t> {
+    /// Resolves the names of every module of TREE, its imports included
+    pub fn resolve(tree: &'t ModuleTree) -> (Resolutions, Vec<ResolveErr>) {
+        let mut kinds = NodeMap::new();
+        for (_, module) in tree.modules() {
+            for item in &module.items {
+                kinds.insert(item.id(), kind_of(item));
+            }
+        }
+        let errs = tree.check_imports().into_iter().map(|err| ResolveErr { kind: ResolveErrKind::Path(err.kind), file: err.file, span: err.span }).collect();
+        let mut resolver = Self {
+            tree, module: tree.root(), kinds, generics: Vec::new(), self_ty: None, self_value: None, owner: None,
+            ribs: Vec::new(), bound: Vec::new(), span: Span::default(), resolutions: Resolutions::default(), errs
+        };
+        for (id, module) in tree.modules() {
+            resolver.module = id;
+            for item in &module.items {
+                resolver.visit_item(item);
+            }
+        }
+        (resolver.resolutions, resolver.errs)
+    }
+
+    fn error(&mut self, kind: ResolveErrKind, span: Span) {
+        let file = self.tree.module(self.module).file.clone();
+        self.errs.push(ResolveErr { kind, file, span });
+    }
+
+    /// What RES is, as in "x is a function, not a type"
+    fn describe(&self, res: Res) -> &'static str {
+        match res {
+            Res::Module(_) => "a module",
+            Res::Item(id) => self.kinds.get(id).copied().unwrap_or("an item"),
+            Res::Generic { .. } => "a generic parameter",
+            Res::SelfTy(_) => "a type",
+            Res::SelfValue(_) | Res::Local(_) => "a local",
+            Res::Builtin(builtin) => builtin.kind()
+        }
+    }
+
+    /// Whether RES can be used where EXPECT is needed
+    fn fits(&self, res: Res, expect: Expect) -> bool {
+        let kind = self.describe(res);
+        match expect {
+            Expect::Type => matches!(res, Res::Generic { .. } | Res::SelfTy(_)) || kind == "a type" || kind == "a pack" || kind == "an interface",
+            Expect::Interface => kind == "an interface",
+            Expect::Pack => matches!(res, Res::SelfTy(_)) || kind == "a pack",
+            Expect::Value => matches!(res, Res::SelfValue(_) | Res::Local(_)) || kind == "a function" || kind == "a constant"
+        }
+    }
+
+    /// Reports NAME if it resolved to RES where EXPECT is needed but it isn't one
+    fn check_fits(&mut self, name: &str, res: Res, expect: Expect, span: Span) -> bool {
+        if self.fits(res, expect) {
+            return true;
+        }
+        let kind = ResolveErrKind::Expected { name: name.to_string(), found: self.describe(res), expected: expect.describe() };
+        self.error(kind, span);
+        false
+    }
+
+    /// Looks NAME up among the names which can start a type or a path: Self, the
+    /// generic parameters, the names of the module and the builtins
+    fn lookup_outer(&self, name: &str) -> Result<Res, ResolveErrKind> {
+        if name == "Self" {
+            return self.self_ty.map(Res::SelfTy).ok_or(ResolveErrKind::NoSelf("Self"));
+        }
+        if let Some((_, res, _)) = self.generics.iter().rev().find(|(generic, _, _)| generic == name) {
+            return Ok(*res);
+        }
+        match self.tree.resolve_path(self.module, &[name.to_string()]) {
+            Ok((def, _)) => Ok(self.def_res(def)),
+            Err(ModuleErrKind::Unresolved { .. }) => Builtin::from_name(name).map(Res::Builtin).ok_or_else(|| ResolveErrKind::Unresolved(name.to_string())),
+            Err(kind) => Err(ResolveErrKind::Path(kind))
+        }
+    }
+
+    fn def_res(&self, def: Def) -> Res {
+        match def {
+            Def::Module(id) => Res::Module(id),
+            Def::Item(..) => Res::Item(self.tree.item(def).map(Item::id).unwrap_or(NodeId::DUMMY))
+        }
+    }
+
+    /// Resolves the name of the type TY (if it has one), and the types inside it
+    fn resolve_ty(&mut self, ty: &Ty, expect: Expect) {
+        self.at(ty.span, |resolver| {
+            if let TyKind::Userdefined(name) | TyKind::Generic(name, _) = &ty.kind {
+                resolver.resolve_ty_name(name, expect);
+            }
+            visit::walk_ty(resolver, ty);
+        });
+    }
+
+    fn resolve_ty_name(&mut self, name: &str, expect: Expect) -> Option<Res> {
+        let span = self.span;
+        let res = match self.lookup_outer(name) {
+            Ok(res) => res,
+            Err(kind) => {
+                self.error(kind, span);
+                return None;
+            }
+        };
+        if let Some(owner) = self.owner {
+            self.resolutions.types.get_or_default(owner).insert(name.to_string(), res);
+        }
+        self.check_fits(name, res, expect, span);
+        Some(res)
+    }
+
+    /// Resolves the path of the expression ID as far as the modules go
+    fn resolve_path(&mut self, id: NodeId, path: &Path, expect: Expect) {
+        let span = path.span;
+        let names: Vec<String> = path.segments.iter().map(|segment| segment.name.clone()).collect();
+        let first = names[0].as_str();
+        let found = if names.len() == 1 && expect == Expect::Value {
+            self.lookup_value(first)
+        } else if names.len() == 1 || first == "Self" || self.generics.iter().any(|(generic, _, _)| generic == first) {
+            self.lookup_outer(first).map(|res| (res, 1))
+        } else {
+            match self.tree.resolve_path(self.module, &names) {
+                Ok((def, taken)) => Ok((self.def_res(def), taken)),
+                // The first segment isn't in the module, but it can still be a builtin,
+                // or the whole path one (ptr::read)
+                Err(ModuleErrKind::Unresolved { .. }) if matches!(self.tree.resolve_path(self.module, &names[..1]), Err(ModuleErrKind::Unresolved { .. })) => {
+                    match Builtin::from_name(&names.join("::")) {
+                        Some(builtin) => Ok((Res::Builtin(builtin), names.len())),
+                        None => self.lookup_outer(first).map(|res| (res, 1))
+                    }
+                }
+                Err(kind) => Err(ResolveErrKind::Path(kind))
+            }
+        };
+        let (res, taken) = match found {
+            Ok(found) => found,
+            Err(kind) => return self.error(kind, span)
+        };
+        let unresolved = names.len() - taken;
+        let name = names[..taken].join("::");
+        if unresolved == 0 {
+            if !self.check_fits(&name, res, expect, span) {
+                return;
+            }
+        } else if !self.fits(res, Expect::Type) {
+            // String::from goes on after a type, ptr::read after a module
+            let kind = ResolveErrKind::Expected { name, found: self.describe(res), expected: "a type or a module" };
+            return self.error(kind, span);
+        }
+        self.resolutions.paths.insert(id, PartialRes { res, unresolved });
+    }
+
+    /// Looks up a name used alone as a value: a local first, then self, then the outer names
+    fn lookup_value(&mut self, name: &str) -> Result<(Res, usize), ResolveErrKind> {
+        for rib in self.ribs.iter_mut().rev() {
+            if let Some(local) = rib.iter_mut().rev().find(|local| local.name == name) {
+                local.used = true;
+                return Ok((Res::Local(local.id), 1));
+            }
+        }
+        if name == "self" {
+            return self.self_value.map(|fun| (Res::SelfValue(fun), 1)).ok_or(ResolveErrKind::NoSelf("self"));
+        }
+        self.lookup_outer(name).map(|res| (res, 1))
+    }
+
+    /// Brings the generic parameters of OWNER in scope: the declared GENERICS,
+    /// then the IMPLICIT ones (declared at SPAN)
+    fn declare_generics(&mut self, owner: NodeId, generics: &[GenericParam], implicit: &[String], span: Span) {
+        let outer = self.generics.len();
+        let declared = generics.iter().map(|param| (&param.name, param.span)).chain(implicit.iter().map(|name| (name, span)));
+        for (index, (name, span)) in declared.enumerate() {
+            if let Some((_, _, first)) = self.generics[outer..].iter().find(|(generic, _, _)| generic == name) {
+                let kind = ResolveErrKind::Duplicate { name: name.clone(), first: *first };
+                self.error(kind, span);
+            } else if let Some((_, _, first)) = self.generics[..outer].iter().find(|(generic, _, _)| generic == name) {
+                let kind = ResolveErrKind::ShadowedGeneric { name: name.clone(), outer: *first };
+                self.error(kind, span);
+            }
+            self.generics.push((name.clone(), Res::Generic { owner, index }, span));
+        }
+    }
+
+    /// The names an impls or def without generics uses as the arguments of TYS,
+    /// which aren't defined anywhere: its implicit generic parameters
+    fn implicit_generics(&self, tys: &[&Ty]) -> Vec<String> {
+        fn collect(resolver: &Resolver, ty: &Ty, top: bool, names: &mut Vec<String>) {
+            match &ty.kind {
+                TyKind::Userdefined(name) if !top => {
+                    if let Err(ResolveErrKind::Unresolved(_)) = resolver.lookup_outer(name) {
+                        if !names.contains(name) {
+                            names.push(name.clone());
+                        }
+                    }
+                }
+                TyKind::Generic(_, args) => args.iter().for_each(|arg| collect(resolver, arg, false, names)),
+                TyKind::Ref(inner) | TyKind::Ptr(inner) | TyKind::NoDrop(inner) => collect(resolver, inner, top, names),
+                _ => {}
+            }
+        }
+        let mut names = Vec::new();
+        for ty in tys {
+            collect(self, ty, true, &mut names);
+        }
+        names
+    }
+
+    /// Runs F inside the item OWNER, which is generic over GENERICS (and IMPLICIT)
+    fn in_item(&mut self, owner: NodeId, generics: &[GenericParam], implicit: &[String], span: Span, f: impl FnOnce(&mut Self)) {
+        let outer_generics = self.generics.len();
+        let outer_owner = self.owner.replace(owner);
+        let outer_span = std::mem::replace(&mut self.span, span);
+        self.declare_generics(owner, generics, implicit, span);
+        f(self);
+        self.generics.truncate(outer_generics);
+        self.owner = outer_owner;
+        self.span = outer_span;
+    }
+
+    /// Runs F with SPAN as the span of the errors about types
+    fn at(&mut self, span: Span, f: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.span, span);
+        f(self);
+        self.span = outer;
+    }
+
+    /// Brings a local in scope in the innermost block
+    fn declare_local(&mut self, name: &str, id: NodeId, span: Span) {
+        if let Some((_, first)) = self.bound.iter().find(|(bound, _)| bound == name) {
+            let kind = ResolveErrKind::Duplicate { name: name.to_string(), first: *first };
+            return self.error(kind, span);
+        }
+        self.bound.push((name.to_string(), span));
+        let shadowed = self.ribs.last().and_then(|rib| rib.iter().rev().find(|local| local.name == name)).filter(|local| !local.used).map(|local| local.span);
+        if let Some(first) = shadowed {
+            self.error(ResolveErrKind::ShadowedUnused { name: name.to_string(), first }, span);
+        }
+        if let Some(rib) = self.ribs.last_mut() {
+            rib.push(Local { name: name.to_string(), id, span, used: false });
+        }
+    }
+
+    /// Resolves the bindings of PATTERN, which all go in the innermost block
+    fn bind(&mut self, pattern: &Pattern) {
+        self.bound.clear();
+        self.visit_pattern(pattern);
+        self.bound.clear();
+    }
+}
+
+fn kind_of(item: &Item) -> &'static str {
+    match item {
+        Item::Const(_) => "a constant",
+        Item::Pack(_) => "a pack",
+        Item::Fun(_) => "a function",
+        Item::Interface(_) => "an interface",
+        Item::Mod(_) => "a module",
+        Item::Impls(_) | Item::Def(_) | Item::Assoc(_) | Item::Use(_) => "an item"
+    }
+}
+
+impl<'ast, 't> Visitor<'ast> for Resolver<'t> {
+    fn visit_const(&mut self, decl: &'ast ConstDecl) {
+        self.in_item(decl.id, &[], &[], decl.span, |resolver| visit::walk_const(resolver, decl));
+    }
+
+    fn visit_pack(&mut self, pack: &'ast PackDecl) {
+        self.in_item(pack.id, &pack.generics, &[], pack.span, |resolver| visit::walk_pack(resolver, pack));
+    }
+
+    fn visit_field(&mut self, field: &'ast FieldDecl) {
+        self.at(field.span, |resolver| visit::walk_field(resolver, field));
+    }
+
+    fn visit_fun(&mut self, fun: &'ast FunDecl) {
+        let outer_self = std::mem::replace(&mut self.self_value, fun.receiver.map(|_| fun.id));
+        self.in_item(fun.id, &fun.generics, &[], fun.span, |resolver| {
+            resolver.ribs.push(Vec::new());
+            resolver.bound.clear();
+            visit::walk_fun(resolver, fun);
+            resolver.ribs.pop();
+        });
+        self.self_value = outer_self;
+    }
+
+    fn visit_param(&mut self, param: &'ast Param) {
+        self.at(param.span, |resolver| visit::walk_param(resolver, param));
+        self.declare_local(&param.name, param.id, param.span);
+    }
+
+    fn visit_generic_param(&mut self, param: &'ast GenericParam) {
+        self.at(param.span, |resolver| visit::walk_generic_param(resolver, param));
+    }
+
+    fn visit_bound(&mut self, bound: &'ast Bound) {
+        self.at(bound.span, |resolver| resolver.resolve_ty(&bound.interface, Expect::Interface));
+    }
+
+    fn visit_where_predicate(&mut self, predicate: &'ast WherePredicate) {
+        self.at(predicate.span, |resolver| visit::walk_where_predicate(resolver, predicate));
+    }
+
+    fn visit_impls(&mut self, impls: &'ast ImplsDecl) {
+        let implicit = if impls.generics.is_empty() { self.implicit_generics(&[&impls.target]) } else { Vec::new() };
+        self.in_item(impls.id, &impls.generics, &implicit, impls.span, |resolver| {
+            let outer_self = resolver.self_ty.replace(impls.id);
+            visit::walk_impls(resolver, impls);
+            resolver.self_ty = outer_self;
+        });
+        if !implicit.is_empty() {
+            self.resolutions.implicit_generics.insert(impls.id, implicit);
+        }
+    }
+
+    fn visit_def(&mut self, def: &'ast DefDecl) {
+        let implicit = if def.generics.is_empty() { self.implicit_generics(&[&def.interface, &def.target]) } else { Vec::new() };
+        self.in_item(def.id, &def.generics, &implicit, def.span, |resolver| {
+            let outer_self = resolver.self_ty.replace(def.id);
+            for param in &def.generics {
+                resolver.visit_generic_param(param);
+            }
+            resolver.resolve_ty(&def.interface, Expect::Interface);
+            resolver.visit_ty(&def.target);
+            for predicate in &def.where_clause {
+                resolver.visit_where_predicate(predicate);
+            }
+            for member in &def.members {
+                match member {
+                    DefMember::AssocType(def) => resolver.visit_assoc_type_def(def),
+                    DefMember::Field(def) => resolver.visit_assoc_field_def(def),
+                    DefMember::Fun(fun) => resolver.visit_fun(fun)
+                }
+            }
+            resolver.self_ty = outer_self;
+        });
+        if !implicit.is_empty() {
+            self.resolutions.implicit_generics.insert(def.id, implicit);
+        }
+    }
+
+    fn visit_interface(&mut self, interface: &'ast InterfaceDecl) {
+        self.in_item(interface.id, &interface.generics, &[], interface.span, |resolver| {
+            let outer_self = resolver.self_ty.replace(interface.id);
+            for param in &interface.generics {
+                resolver.visit_generic_param(param);
+            }
+            for ty in &interface.supers {
+                resolver.resolve_ty(ty, Expect::Interface);
+            }
+            for predicate in &interface.where_clause {
+                resolver.visit_where_predicate(predicate);
+            }
+            for member in &interface.members {
+                match member {
+                    InterfaceMember::AssocType(decl) => resolver.visit_assoc_type_decl(decl),
+                    InterfaceMember::Field(field) => resolver.visit_field(field),
+                    InterfaceMember::Fun(fun) => resolver.visit_fun(fun)
+                }
+            }
+            resolver.self_ty = outer_self;
+        });
+    }
+
+    fn visit_assoc(&mut self, assoc: &'ast AssocDecl) {
+        self.in_item(assoc.id, &[], &[], assoc.span, |resolver| {
+            resolver.resolve_ty_name(&assoc.interface, Expect::Interface);
+        });
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.ribs.push(Vec::new());
+        visit::walk_block(self, block);
+        self.ribs.pop();
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        self.at(stmt.span, |resolver| match &stmt.kind {
+            // The pattern of a for is only in scope in its body
+            StmtKind::For(pattern, iter, body) => {
+                resolver.visit_expr(iter);
+                resolver.ribs.push(Vec::new());
+                resolver.bind(pattern);
+                resolver.visit_block(body);
+                resolver.ribs.pop();
+            }
+            _ => visit::walk_stmt(resolver, stmt)
+        });
+    }
+
+    fn visit_let(&mut self, binding: &'ast LetStmt) {
+        // The initializer still sees the names the binding shadows
+        if let Some(ty) = &binding.ty {
+            self.visit_ty(ty);
+        }
+        if let Some(init) = &binding.init {
+            self.visit_expr(init);
+        }
+        self.bind(&binding.pattern);
+    }
+
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        match &pattern.kind {
+            PatternKind::Binding(name) => self.declare_local(name, pattern.id, pattern.span),
+            PatternKind::Pack(name, _) => self.at(pattern.span, |resolver| { resolver.resolve_ty_name(name, Expect::Pack); }),
+            PatternKind::Wildcard | PatternKind::Tuple(_) => {}
+        }
+        visit::walk_pattern(self, pattern);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match &expr.kind {
+            ExprKind::Path(path) => self.resolve_path(expr.id, path, Expect::Value),
+            ExprKind::PackLit(path, _) => self.resolve_path(expr.id, path, Expect::Pack),
+            ExprKind::Cast(..) => {
+                // A cast the language doesn't know calls Cast::as, if there is a Cast
+                let interface = &self.tree.operators().cast().interface;
+                if let (Ok(res @ Res::Item(_)), Some(owner)) = (self.lookup_outer(interface), self.owner) {
+                    self.resolutions.types.get_or_default(owner).insert(interface.clone(), res);
+                }
+            }
+            _ => {}
+        }
+        self.at(expr.span, |resolver| visit::walk_expr(resolver, expr));
+    }
+
+    fn visit_ty(&mut self, ty: &'ast Ty) {
+        self.resolve_ty(ty, Expect::Type);
+    }
+}
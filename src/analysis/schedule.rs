@@ -0,0 +1,218 @@
+//! The compile-time scheduler: each #compile_time function is a task, which the
+//! interpreter runs on its own, from a clean state. A task is pure when nothing it may
+//! call is external, and does IO otherwise. Pure tasks can't see each other, so they
+//! run on a pool of threads in whatever order; the IO ones share the sandbox, so they
+//! run one after the other, in the order they are declared. Either way the constants
+//! and the errors come out in the order of the tasks, so the result is the same
+//! whatever the number of threads.
+//!
+//! What a task may call is found from what each body uses, over-estimated: a use of a
+//! function of an interface may call the function of any def of it, a body reads the
+//! consts its paths name, and it may destroy any value of the types of its expressions
+//! and bindings, calling the drop of their def of Destroy and of that of their fields.
+//! A value of a generic type may be anything once monomorphized, so it may call the
+//! drop of any def of Destroy.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::analysis::cfg;
+use crate::analysis::interpret::{self, Constant, EvalErr, Interpreter, Limits, Sandbox};
+use crate::analysis::drops;
+use crate::analysis::resolve::{Builtin, Res};
+use crate::analysis::typeck::{InterfaceRef, TypeCx, TypeckResults};
+use crate::analysis::types::{Substitution, Type};
+use crate::ast::declarations::{FunDecl, Item};
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::visit::Visitor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Effect {
+    Pure,
+    /// It may call an external function
+    Io
+}
+
+/// A #compile_time function to run
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Task<'t> {
+    pub fun: &'t FunDecl,
+    pub file: &'t PathBuf,
+    pub effect: Effect
+}
+
+/// The ids of the nodes of an item
+#[derive(Default)]
+struct Ids(Vec<NodeId>);
+
+impl<'ast> Visitor<'ast> for Ids {
+    fn visit_id(&mut self, id: &NodeId) {
+        self.0.push(*id);
+    }
+}
+
+/// Adds to DROPS the drops destroying a value of type TY may call, ANY_DROP being the
+/// drop of every def of Destroy
+fn drops_of(cx: &TypeCx, ty: &Type, any_drop: &[NodeId], drops: &mut Vec<NodeId>) {
+    match ty {
+        Type::Pack { id, args, .. } => {
+            drops.extend(drops::destroy_def(cx, ty).map(|(fun, _)| fun));
+            let subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((*id, index), arg)).collect();
+            for (_, field) in cx.packs.get(*id).map_or(&[][..], |info| &info.fields[..]) {
+                drops_of(cx, &cx.normalize(&field.substitute(&subst)), any_drop, drops);
+            }
+        }
+        Type::Tuple(elems) => elems.iter().for_each(|elem| drops_of(cx, elem, any_drop, drops)),
+        Type::Array(elem, _) => drops_of(cx, elem, any_drop, drops),
+        Type::Param { .. } | Type::Assoc(..) => drops.extend_from_slice(any_drop),
+        _ => {}
+    }
+}
+
+/// The tasks of the program, in the order they are declared
+pub(crate) fn tasks<'t>(cx: &TypeCx<'t>, results: &TypeckResults) -> Vec<Task<'t>> {
+    let mut any_drop = Vec::new();
+    for info in &cx.impls {
+        if info.interface == Some(InterfaceRef::Builtin(Builtin::Destroy)) {
+            any_drop.extend(info.funs.get("drop"));
+        }
+    }
+    // What each body and each const uses: the functions it calls, the consts it reads
+    // and the drops of the values it may destroy
+    let mut bodies: NodeMap<()> = NodeMap::new();
+    let mut uses: NodeMap<Vec<NodeId>> = NodeMap::new();
+    let mut items: Vec<(NodeId, Ids)> = Vec::new();
+    for (fun, _) in cfg::bodies(cx.tree) {
+        bodies.insert(fun.id, ());
+        let mut ids = Ids::default();
+        ids.visit_fun(fun);
+        items.push((fun.id, ids));
+    }
+    for (_, module) in cx.tree.modules() {
+        for item in &module.items {
+            if let Item::Const(decl) = item {
+                let mut ids = Ids::default();
+                ids.visit_const(decl);
+                items.push((decl.id, ids));
+            }
+        }
+    }
+    for (owner, ids) in items {
+        let used = uses.get_or_default(owner);
+        for id in ids.0 {
+            if let Some(Res::Item(item)) = cx.resolutions.path(id).map(|partial| partial.res) {
+                if cx.consts.contains(item) {
+                    used.push(item);
+                }
+            }
+            for ty in results.expr_types.get(id).into_iter().chain(results.local_types.get(id)) {
+                drops_of(cx, ty, &any_drop, used);
+            }
+        }
+    }
+    for instantiation in &results.instantiations {
+        uses.get_or_default(instantiation.owner).push(instantiation.fun);
+    }
+    let callees = |fun: NodeId| -> Vec<NodeId> {
+        let mut callees = Vec::new();
+        for used in uses.get(fun).map_or(&[][..], |used| &used[..]) {
+            callees.push(*used);
+            if !interpret::in_interface(cx, *used) {
+                continue;
+            }
+            let (interface, name) = match cx.funs.get(*used) {
+                Some(sig) => (sig.container, &sig.name),
+                None => continue
+            };
+            for info in &cx.impls {
+                if matches!(&info.interface, Some(InterfaceRef::Declared(id, _)) if Some(*id) == interface) {
+                    callees.extend(info.funs.get(name));
+                }
+            }
+        }
+        callees
+    };
+    // Whether something reachable from ROOT is external
+    let does_io = |root: NodeId| {
+        let mut seen: NodeMap<()> = NodeMap::new();
+        let mut work = vec![root];
+        while let Some(fun) = work.pop() {
+            if seen.insert(fun, ()).is_some() {
+                continue;
+            }
+            if !bodies.contains(fun) && cx.funs.get(fun).is_some() && !interpret::in_interface(cx, fun) {
+                return true;
+            }
+            work.extend(callees(fun));
+        }
+        false
+    };
+    interpret::compile_time_funs(cx).into_iter().map(|(fun, file)| {
+        let effect = if does_io(fun.id) { Effect::Io } else { Effect::Pure };
+        Task { fun, file, effect }
+    }).collect()
+}
+
+/// How the tasks are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Scheduler {
+    /// The threads which run the pure tasks, as many as the machine can run at once if 0
+    pub threads: usize,
+    pub limits: Limits
+}
+
+impl Scheduler {
+    /// Runs the tasks of the program, with SANDBOX for their IO, giving what they
+    /// returned and the errors of those which failed
+    pub fn run(&self, cx: &TypeCx, results: &TypeckResults, sandbox: &mut Sandbox) -> (Vec<Constant>, Vec<EvalErr>) {
+        let tasks = tasks(cx, results);
+        let (pure, io): (Vec<usize>, Vec<usize>) = (0..tasks.len()).partition(|index| tasks[*index].effect == Effect::Pure);
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads
+        };
+        let next = AtomicUsize::new(0);
+        let mut outcomes: Vec<Option<Result<Constant, Box<EvalErr>>>> = vec![None; tasks.len()];
+        let (tasks, pure, next, limits) = (&tasks, &pure, &next, self.limits);
+        thread::scope(|scope| {
+            // Each call being run takes a good deal of the stack: the threads get enough
+            let builder = || thread::Builder::new().stack_size(interpret::STACK_SIZE);
+            let mut workers = Vec::new();
+            for _ in 0..threads.min(pure.len()) {
+                workers.push(builder().spawn_scoped(scope, move || {
+                    let mut interpreter = Interpreter::new(cx, results, limits);
+                    let mut done = Vec::new();
+                    while let Some(index) = pure.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let task = &tasks[*index];
+                        done.push((*index, interpreter.run_fun(task.fun, task.file)));
+                    }
+                    done
+                }));
+            }
+            if !io.is_empty() {
+                let sandbox = &mut *sandbox;
+                workers.push(builder().spawn_scoped(scope, move || {
+                    let mut interpreter = Interpreter::new(cx, results, limits).with_sandbox(std::mem::take(sandbox));
+                    let done = io.iter().map(|index| (*index, interpreter.run_fun(tasks[*index].fun, tasks[*index].file))).collect();
+                    *sandbox = interpreter.into_sandbox().unwrap_or_default();
+                    done
+                }));
+            }
+            for worker in workers {
+                for (index, outcome) in worker.expect("the interpreter can't be started").join().expect("the interpreter panicked") {
+                    outcomes[index] = Some(outcome);
+                }
+            }
+        });
+        let mut constants = Vec::new();
+        let mut errs = Vec::new();
+        for outcome in outcomes.into_iter().flatten() {
+            match outcome {
+                Ok(constant) => constants.push(constant),
+                Err(err) => errs.push(*err)
+            }
+        }
+        (constants, errs)
+    }
+}
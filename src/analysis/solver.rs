@@ -0,0 +1,621 @@
+//! The interface solver: proves that types implement interfaces and checks the defs
+//! themselves. A bound T: Interface holds when the bounds around it say so (with the
+//! interfaces those extend), or when a def for the interface matches the type and its
+//! own bounds hold in turn. Copy is also structural: the primitives, references,
+//! pointers and functions are Copy, and tuples and arrays are when their elements are.
+//!
+//! A negative bound T: !Copy holds for a generic parameter only if it is declared, and
+//! for any other type if no def could make it Copy. Two defs of an interface can't
+//! apply to the same type, unless their bounds keep them apart (T: Copy and T: !Copy).
+//! Associated types are normalised lazily: Box<i32>::Target is whatever the def of
+//! Box<T> says, while T::Target stays as it is until T is known.
+//!
+//! The solver runs after the type checker, on the functions it found each body using.
+
+use std::path::PathBuf;
+
+use crate::analysis::resolve::Builtin;
+use crate::analysis::typeck::{BoundInfo, ImplInfo, InterfaceRef, TypeCx, TypeckResults};
+use crate::analysis::types::{Substitution, Type};
+use crate::ast::declarations::{DefDecl, DefMember, Item};
+use crate::ast::ids::NodeId;
+use crate::ast::map::AstMap;
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+/// How deep defs may depend on the bounds of other defs before the solver gives up
+const MAX_DEPTH: usize = 32;
+
+/// Whether a bound holds. Unknown when the type is not inferred or failed to check,
+/// which the type checker already reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Proof {
+    Holds,
+    Fails,
+    Unknown
+}
+
+impl Proof {
+    /// Holds if both do, Fails if either does
+    fn and(self, other: Proof) -> Proof {
+        match (self, other) {
+            (Proof::Fails, _) | (_, Proof::Fails) => Proof::Fails,
+            (Proof::Unknown, _) | (_, Proof::Unknown) => Proof::Unknown,
+            _ => Proof::Holds
+        }
+    }
+
+    /// Holds if either does, Fails if both do
+    fn or(self, other: Proof) -> Proof {
+        match (self, other) {
+            (Proof::Holds, _) | (_, Proof::Holds) => Proof::Holds,
+            (Proof::Unknown, _) | (_, Proof::Unknown) => Proof::Unknown,
+            _ => Proof::Fails
+        }
+    }
+}
+
+/// The pairs of types inside A and B, if they are built the same way at the top
+fn children<'a>(a: &'a Type, b: &'a Type) -> Option<Vec<(&'a Type, &'a Type)>> {
+    let pairs = match (a, b) {
+        (Type::Prim(x), Type::Prim(y)) if x == y => Vec::new(),
+        (Type::Pack { id: x, args: x_args, .. }, Type::Pack { id: y, args: y_args, .. }) if x == y => x_args.iter().zip(y_args).collect(),
+        (Type::Param { owner: o, index: i, .. }, Type::Param { owner: p, index: j, .. }) if o == p && i == j => Vec::new(),
+        (Type::StrSlice, Type::StrSlice) | (Type::Never, Type::Never) => Vec::new(),
+        (Type::Var(x), Type::Var(y)) if x == y => Vec::new(),
+        (Type::Ref(x), Type::Ref(y)) | (Type::Ptr(x), Type::Ptr(y)) => vec![(&**x, &**y)],
+        (Type::Tuple(x), Type::Tuple(y)) if x.len() == y.len() => x.iter().zip(y).collect(),
+        (Type::Array(x, n), Type::Array(y, m)) if n == m => vec![(&**x, &**y)],
+        (Type::Fun(x, x_ret), Type::Fun(y, y_ret)) if x.len() == y.len() => x.iter().zip(y).chain(std::iter::once((&**x_ret, &**y_ret))).collect(),
+        (Type::Assoc(x, n), Type::Assoc(y, m)) if n == m => vec![(&**x, &**y)],
+        _ => return None
+    };
+    Some(pairs)
+}
+
+/// TY with the names of its generic parameters left out, to compare types which only
+/// differ by how their parameters are called
+fn anonymous(ty: &Type) -> Type {
+    ty.map(&mut |ty| match ty {
+        Type::Param { owner, index, .. } => Some(Type::Param { owner: *owner, index: *index, name: String::new() }),
+        _ => None
+    })
+}
+
+impl<'t> TypeCx<'t> {
+    /// Matches the target of the impls or def OWNER against TY, its generic parameters
+    /// standing for any type: Box<T> matches Box<i32> with T := i32
+    pub fn match_type(&self, pattern: &Type, ty: &Type, owner: NodeId, subst: &mut Substitution) -> bool {
+        match (pattern.without_nodrop(), ty.without_nodrop()) {
+            (Type::Param { owner: param_owner, index, .. }, ty) if *param_owner == owner => match subst.get(&(owner, *index)) {
+                Some(bound) => anonymous(bound) == anonymous(ty),
+                None => {
+                    subst.insert((owner, *index), ty.clone());
+                    true
+                }
+            },
+            (Type::Error, _) | (_, Type::Error) => true,
+            (pattern, ty) => match children(pattern, ty) {
+                Some(pairs) => pairs.into_iter().all(|(pattern, ty)| self.match_type(pattern, ty, owner, subst)),
+                None => false
+            }
+        }
+    }
+
+    /// What the associated type NAME of BASE is, according to the def matching BASE
+    pub fn normalize_assoc(&self, base: &Type, name: &str) -> Option<Type> {
+        self.impls.iter().find_map(|info| {
+            let ty = info.assoc_types.get(name)?;
+            let mut subst = Substitution::new();
+            if self.match_type(&info.target, base, info.id, &mut subst) {
+                Some(ty.substitute(&subst))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// TY with the associated types which a def gives replaced by what they are
+    pub fn normalize(&self, ty: &Type) -> Type {
+        ty.map(&mut |ty| match ty {
+            Type::Assoc(base, name) => {
+                let base = self.normalize(base);
+                Some(match self.normalize_assoc(&base, name) {
+                    Some(ty) => self.normalize(&ty),
+                    None => Type::Assoc(Box::new(base), name.clone())
+                })
+            }
+            _ => None
+        })
+    }
+
+    /// The interface as the source code spells it: Deref<i32> or Copy
+    pub fn interface_name(&self, interface: &InterfaceRef) -> String {
+        match interface {
+            InterfaceRef::Declared(id, args) => {
+                let name = self.interfaces.get(*id).map_or("{unknown}", |info| info.name.as_str());
+                if args.is_empty() {
+                    return name.to_string();
+                }
+                let args: Vec<String> = args.iter().map(Type::to_string).collect();
+                format!("{}<{}>", name, args.join(", "))
+            }
+            InterfaceRef::Builtin(builtin) => builtin.name().to_string()
+        }
+    }
+
+    /// What the interface ID says about SELF_TY, with ARGS for its generic parameters
+    fn interface_subst(&self, id: NodeId, args: &[Type], self_ty: &Type) -> Substitution {
+        let generics = self.interfaces.get(id).map_or(0, |info| info.generics);
+        let mut subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((id, index), arg)).collect();
+        subst.insert((id, generics), self_ty.clone());
+        subst
+    }
+
+    /// The bounds which hold where ENV does: with those of the interfaces they extend,
+    /// and the bounds of their associated types. T: Ord gives T: Eq if Ord extends Eq
+    pub fn elaborate(&self, env: &[BoundInfo]) -> Vec<BoundInfo> {
+        let mut bounds: Vec<BoundInfo> = env.iter().map(|bound| BoundInfo { ty: self.normalize(&bound.ty), ..bound.clone() }).collect();
+        let mut next = 0;
+        while next < bounds.len() {
+            let bound = bounds[next].clone();
+            next += 1;
+            let (id, args) = match (&bound.interface, bound.negative) {
+                (InterfaceRef::Declared(id, args), false) => (*id, args),
+                _ => continue
+            };
+            let info = match self.interfaces.get(id) {
+                Some(info) => info,
+                None => continue
+            };
+            let subst = self.interface_subst(id, args, &bound.ty);
+            let implied = info.supers.iter().map(|sup| BoundInfo { ty: bound.ty.clone(), interface: substitute_interface(sup, &subst), negative: false })
+                .chain(info.assoc_bounds.iter().map(|assoc| BoundInfo {
+                    ty: self.normalize(&assoc.ty.substitute(&subst)), interface: substitute_interface(&assoc.interface, &subst), negative: assoc.negative
+                }));
+            for implied in implied.collect::<Vec<BoundInfo>>() {
+                if !bounds.contains(&implied) {
+                    bounds.push(implied);
+                }
+            }
+        }
+        bounds
+    }
+
+    /// Whether TY: INTERFACE (or TY: !INTERFACE if NEGATIVE) holds where the bounds ENV do
+    pub fn prove(&self, env: &[BoundInfo], ty: &Type, interface: &InterfaceRef, negative: bool) -> Proof {
+        let env = self.elaborate(env);
+        let ty = self.normalize(ty);
+        self.prove_in(&env, &ty, interface, negative, 0)
+    }
+
+    fn prove_in(&self, env: &[BoundInfo], ty: &Type, interface: &InterfaceRef, negative: bool, depth: usize) -> Proof {
+        if !negative {
+            return self.implements(env, ty, interface, false, depth);
+        }
+        if env.iter().any(|bound| bound.negative && bound.ty == *ty && bound.interface == *interface) {
+            return Proof::Holds;
+        }
+        // The negative bound holds if the type doesn't implement the interface even when
+        // its generic parameters are assumed to implement whatever they could
+        match self.implements(env, ty, interface, true, depth) {
+            Proof::Fails => Proof::Holds,
+            Proof::Unknown => Proof::Unknown,
+            Proof::Holds => Proof::Fails
+        }
+    }
+
+    /// Whether TY implements INTERFACE. If OPTIMISTIC, a generic parameter implements
+    /// every interface that no bound in ENV rules out
+    fn implements(&self, env: &[BoundInfo], ty: &Type, interface: &InterfaceRef, optimistic: bool, depth: usize) -> Proof {
+        if depth > MAX_DEPTH {
+            return Proof::Unknown;
+        }
+        let ty = ty.without_nodrop();
+        if let Type::Var(_) | Type::Error | Type::Never = ty {
+            return Proof::Unknown;
+        }
+        if env.iter().any(|bound| !bound.negative && bound.ty == *ty && bound.interface == *interface) {
+            return Proof::Holds;
+        }
+        let structural = match (interface, ty) {
+            (InterfaceRef::Builtin(Builtin::Copy), Type::Prim(_) | Type::Ref(_) | Type::Ptr(_) | Type::Fun(..)) => Proof::Holds,
+            (InterfaceRef::Builtin(Builtin::Copy), Type::StrSlice) => Proof::Fails,
+            (InterfaceRef::Builtin(Builtin::Copy), Type::Tuple(elems)) => {
+                elems.iter().fold(Proof::Holds, |proof, elem| proof.and(self.implements(env, elem, interface, optimistic, depth + 1)))
+            }
+            (InterfaceRef::Builtin(Builtin::Copy), Type::Array(elem, _)) => self.implements(env, elem, interface, optimistic, depth + 1),
+            _ => self.by_def(env, ty, interface, optimistic, depth)
+        };
+        match ty {
+            Type::Param { .. } | Type::Assoc(..) if structural == Proof::Fails && optimistic => {
+                let ruled_out = env.iter().any(|bound| bound.negative && bound.ty == *ty && bound.interface == *interface);
+                if ruled_out { Proof::Fails } else { Proof::Holds }
+            }
+            _ => structural
+        }
+    }
+
+    /// Whether a def of INTERFACE matches TY and has its bounds hold
+    fn by_def(&self, env: &[BoundInfo], ty: &Type, interface: &InterfaceRef, optimistic: bool, depth: usize) -> Proof {
+        let mut proof = Proof::Fails;
+        for info in &self.impls {
+            let mut subst = Substitution::new();
+            let matches = match (&info.interface, interface) {
+                (Some(InterfaceRef::Builtin(a)), InterfaceRef::Builtin(b)) => a == b,
+                (Some(InterfaceRef::Declared(a, a_args)), InterfaceRef::Declared(b, b_args)) => {
+                    a == b && a_args.len() == b_args.len() && a_args.iter().zip(b_args).all(|(pattern, arg)| self.match_type(pattern, &self.normalize(arg), info.id, &mut subst))
+                }
+                _ => false
+            };
+            if !matches || !self.match_type(&info.target, ty, info.id, &mut subst) {
+                continue;
+            }
+            let bounds = self.bounds.get(info.id).cloned().unwrap_or_default();
+            let holds = bounds.iter().fold(Proof::Holds, |holds, bound| {
+                let bound_ty = self.normalize(&bound.ty.substitute(&subst));
+                let bound_interface = substitute_interface(&bound.interface, &subst);
+                let proof = if bound.negative {
+                    self.prove_in(env, &bound_ty, &bound_interface, true, depth + 1)
+                } else {
+                    self.implements(env, &bound_ty, &bound_interface, optimistic, depth + 1)
+                };
+                holds.and(proof)
+            });
+            proof = proof.or(holds);
+        }
+        proof
+    }
+}
+
+fn substitute_interface(interface: &InterfaceRef, subst: &Substitution) -> InterfaceRef {
+    match interface {
+        InterfaceRef::Declared(id, args) => InterfaceRef::Declared(*id, args.iter().map(|arg| arg.substitute(subst)).collect()),
+        InterfaceRef::Builtin(builtin) => InterfaceRef::Builtin(*builtin)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SolverErrKind {
+    /// TY: BOUND doesn't hold, where BOUND is Copy or !Copy. REASON says what needs it
+    Unsatisfied { ty: Type, bound: String, reason: String },
+    /// Two defs of INTERFACE apply to the same types
+    Overlap { interface: String, first: Type, second: Type, other: Span },
+    /// Two impls of overlapping types both define the function NAME
+    DuplicateFun { name: String, ty: Type, other: Span },
+    /// The functions, fields or associated types (WHAT) that a def leaves out
+    Missing { interface: String, ty: Type, what: &'static str, names: Vec<String> },
+    NotAMember { interface: String, what: &'static str, name: String },
+    SignatureMismatch { name: String, interface: String, expected: Type, found: Type },
+    FieldMismatch { name: String, expected: Type, found: Type },
+    /// The field a def maps a field of the interface to doesn't exist
+    NoSourceField { ty: Type, field: String },
+    CopyAndDestroy { ty: Type, other: Span }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SolverErr {
+    pub kind: SolverErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl SolverErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        use SolverErrKind::*;
+        let span = self.span;
+        match &self.kind {
+            Unsatisfied { ty, bound, reason } => Diagnostic::error(&format!("the bound {}: {} is not satisfied", ty, bound), span, reason),
+            Overlap { interface, first, second, other } => {
+                Diagnostic::error(&format!("conflicting defs of {}", interface), span, &format!("def for {}", second))
+                    .with_label(*other, &format!("overlaps with the def for {}", first))
+                    .with_note("opposite bounds on the generic parameters, like T: Copy and T: !Copy, keep defs apart")
+            }
+            DuplicateFun { name, ty, other } => Diagnostic::error(&format!("duplicate function {} for {}", name, ty), span, "defined again here")
+                .with_label(*other, "first defined here"),
+            Missing { interface, ty, what, names } => {
+                Diagnostic::error(&format!("missing {} {} in the def of {} for {}", what, names.join(", "), interface, ty), span, &format!("{} missing", what))
+            }
+            NotAMember { interface, what, name } => Diagnostic::error(&format!("{} is not a {} of {}", name, what, interface), span, "not in the interface"),
+            SignatureMismatch { name, interface, expected, found } => {
+                Diagnostic::error(&format!("{} doesn't have the signature {} requires", name, interface), span, &format!("expected {}, found {}", expected, found))
+            }
+            FieldMismatch { name, expected, found } => Diagnostic::error(&format!("the field {} has the wrong type", name), span, &format!("expected {}, found {}", expected, found)),
+            NoSourceField { ty, field } => Diagnostic::error(&format!("{} has no field {}", ty, field), span, "unknown field"),
+            CopyAndDestroy { ty, other } => Diagnostic::error(&format!("{} is both Copy and Destroy", ty), span, "def of Destroy")
+                .with_label(*other, "def of Copy")
+                .with_note("a copy would be destroyed along with the original")
+        }
+    }
+}
+
+/// Proves the bounds of the functions the bodies use, and checks the impls and defs
+pub(crate) struct Solver<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    /// The nodes of the program, to find where the functions of the impls and defs are declared
+    map: AstMap<'t>,
+    errs: Vec<SolverErr>
+}
+
+impl<'a, 't> Solver<'a, 't> {
+    pub fn check(cx: &'a TypeCx<'t>, results: &TypeckResults) -> Vec<SolverErr> {
+        let mut map = AstMap::new();
+        for (_, module) in cx.tree.modules() {
+            map.add_module(module);
+        }
+        let mut solver = Solver { cx, map, errs: Vec::new() };
+        for instantiation in &results.instantiations {
+            let env = cx.bounds.get(instantiation.owner).cloned().unwrap_or_default();
+            let name = cx.funs.get(instantiation.fun).map_or(String::new(), |sig| sig.name.clone());
+            for bound in cx.bounds.get(instantiation.fun).cloned().unwrap_or_default() {
+                let ty = bound.ty.substitute(&instantiation.args);
+                let interface = substitute_interface(&bound.interface, &instantiation.args);
+                let file = cx.tree.module(instantiation.module).file.clone();
+                solver.require(&env, ty, &interface, bound.negative, format!("required by this call to {}", name), file, instantiation.span);
+            }
+        }
+        solver.coherence();
+        for (module, m) in cx.tree.modules() {
+            for item in &m.items {
+                if let Item::Def(def) = item {
+                    let file = cx.tree.module(module).file.clone();
+                    solver.check_def(def, file);
+                }
+            }
+        }
+        solver.errs
+    }
+
+    fn error(&mut self, kind: SolverErrKind, file: PathBuf, span: Span) {
+        self.errs.push(SolverErr { kind, file, span });
+    }
+
+    fn file_of(&self, info: &ImplInfo) -> PathBuf {
+        self.cx.tree.module(info.module).file.clone()
+    }
+
+    /// Reports that REASON needs TY: INTERFACE if it fails
+    #[allow(clippy::too_many_arguments)]
+    fn require(&mut self, env: &[BoundInfo], ty: Type, interface: &InterfaceRef, negative: bool, reason: String, file: PathBuf, span: Span) {
+        if self.cx.prove(env, &ty, interface, negative) == Proof::Fails {
+            let bound = format!("{}{}", if negative { "!" } else { "" }, self.cx.interface_name(interface));
+            self.error(SolverErrKind::Unsatisfied { ty: self.cx.normalize(&ty), bound, reason }, file, span);
+        }
+    }
+
+    /// Whether the targets (and interface arguments) of the impls or defs A and B can be
+    /// the same type, with their bounds not ruling it out
+    fn overlap(&self, a: &ImplInfo, b: &ImplInfo) -> bool {
+        let owners = [a.id, b.id];
+        let mut subst = Substitution::new();
+        let args = match (&a.interface, &b.interface) {
+            (Some(InterfaceRef::Declared(_, a_args)), Some(InterfaceRef::Declared(_, b_args))) => a_args.iter().zip(b_args).collect(),
+            _ => Vec::new()
+        };
+        let unifies = args.into_iter().chain(std::iter::once((&a.target, &b.target))).all(|(x, y)| unify(x, y, &owners, &mut subst));
+        if !unifies {
+            return false;
+        }
+        let bounds: Vec<BoundInfo> = owners.iter().flat_map(|owner| self.cx.bounds.get(*owner).cloned().unwrap_or_default())
+            .map(|bound| BoundInfo {
+                ty: self.cx.normalize(&apply(&bound.ty, &owners, &subst)),
+                interface: match &bound.interface {
+                    InterfaceRef::Declared(id, args) => InterfaceRef::Declared(*id, args.iter().map(|arg| apply(arg, &owners, &subst)).collect()),
+                    InterfaceRef::Builtin(builtin) => InterfaceRef::Builtin(*builtin)
+                },
+                negative: bound.negative
+            })
+            .collect();
+        let contradiction = bounds.iter().any(|bound| bounds.iter().any(|other| other.ty == bound.ty && other.interface == bound.interface && other.negative != bound.negative));
+        // A bound on a type without generic parameters can be decided on its own
+        let fails = bounds.iter().any(|bound| {
+            !bound.ty.any(&|ty| matches!(ty, Type::Param { .. })) && self.cx.prove(&[], &bound.ty, &bound.interface, bound.negative) == Proof::Fails
+        });
+        !contradiction && !fails
+    }
+
+    /// Reports the defs of an interface applying to the same types, the types which are
+    /// both Copy and Destroy and the functions two impls of a type both define
+    fn coherence(&mut self) {
+        let impls = &self.cx.impls;
+        for (j, second) in impls.iter().enumerate() {
+            for first in &impls[..j] {
+                match (&first.interface, &second.interface) {
+                    (Some(a), Some(b)) if same_interface(a, b) && self.overlap(first, second) => {
+                        let kind = SolverErrKind::Overlap { interface: self.cx.interface_name(a), first: first.target.clone(), second: second.target.clone(), other: first.span };
+                        self.error(kind, self.file_of(second), second.span);
+                    }
+                    (Some(InterfaceRef::Builtin(a)), Some(InterfaceRef::Builtin(b))) if a != b && self.overlap(first, second) => {
+                        let (copy, destroy) = if *a == Builtin::Copy { (first, second) } else { (second, first) };
+                        let kind = SolverErrKind::CopyAndDestroy { ty: destroy.target.clone(), other: copy.span };
+                        self.error(kind, self.file_of(destroy), destroy.span);
+                    }
+                    (None, None) if !first.implicit && !second.implicit && self.overlap(first, second) => {
+                        let mut names: Vec<&String> = second.funs.keys().filter(|name| first.funs.contains_key(*name)).collect();
+                        names.sort();
+                        for name in names {
+                            let span = self.map.get(second.funs[name]).map_or(second.span, |node| node.span());
+                            let other = self.map.get(first.funs[name]).map_or(first.span, |node| node.span());
+                            let kind = SolverErrKind::DuplicateFun { name: name.clone(), ty: second.target.clone(), other };
+                            self.error(kind, self.file_of(second), span);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Checks that the def gives everything its interface requires, with the right types
+    fn check_def(&mut self, def: &DefDecl, file: PathBuf) {
+        let cx = self.cx;
+        let info = match cx.impls.iter().find(|info| info.id == def.id) {
+            Some(info) => info,
+            None => return
+        };
+        let env = cx.bounds.get(def.id).cloned().unwrap_or_default();
+        let (id, args) = match &info.interface {
+            Some(InterfaceRef::Declared(id, args)) => (*id, args),
+            Some(InterfaceRef::Builtin(Builtin::Destroy)) => {
+                if !info.funs.contains_key("drop") {
+                    let kind = SolverErrKind::Missing { interface: "Destroy".to_string(), ty: info.target.clone(), what: "functions", names: vec!["drop".to_string()] };
+                    self.error(kind, file, def.span);
+                }
+                return;
+            }
+            Some(InterfaceRef::Builtin(Builtin::Copy)) => {
+                // Copying a pack copies its fields
+                if let Type::Pack { id, name, args } = &info.target {
+                    let fields = cx.packs.get(*id).map(|pack| pack.fields.clone()).unwrap_or_default();
+                    let subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((*id, index), arg)).collect();
+                    for (field, ty) in fields {
+                        let reason = format!("required because the field {} of {} is copied", field, name);
+                        self.require(&env, ty.substitute(&subst), &InterfaceRef::Builtin(Builtin::Copy), false, reason, file.clone(), def.span);
+                    }
+                }
+                return;
+            }
+            _ => return
+        };
+        let interface = match cx.interfaces.get(id) {
+            Some(interface) => interface,
+            None => return
+        };
+        let name = cx.interface_name(info.interface.as_ref().unwrap_or(&InterfaceRef::Declared(id, Vec::new())));
+        let subst = cx.interface_subst(id, args, &info.target);
+        for sup in &interface.supers {
+            let sup = substitute_interface(sup, &subst);
+            let reason = format!("required because {} extends {}", name, cx.interface_name(&sup));
+            self.require(&env, info.target.clone(), &sup, false, reason, file.clone(), def.span);
+        }
+        let mut given_types = Vec::new();
+        let mut given_fields = Vec::new();
+        for member in &def.members {
+            match member {
+                DefMember::Fun(fun) => match interface.funs.get(&fun.name) {
+                    Some(required) => self.check_signature(*required, fun.id, &name, &subst, file.clone(), fun.span),
+                    None => self.error(SolverErrKind::NotAMember { interface: name.clone(), what: "function", name: fun.name.clone() }, file.clone(), fun.span)
+                },
+                DefMember::AssocType(assoc) => {
+                    if !interface.assoc_types.contains(&assoc.name) {
+                        self.error(SolverErrKind::NotAMember { interface: name.clone(), what: "associated type", name: assoc.name.clone() }, file.clone(), assoc.span);
+                        continue;
+                    }
+                    given_types.push(assoc.name.clone());
+                    for bound in interface.assoc_bounds.iter().filter(|bound| matches!(&bound.ty, Type::Assoc(_, bound_name) if *bound_name == assoc.name)) {
+                        let ty = bound.ty.substitute(&subst);
+                        let reason = format!("required by the associated type {} of {}", assoc.name, name);
+                        self.require(&env, ty, &substitute_interface(&bound.interface, &subst), bound.negative, reason, file.clone(), assoc.span);
+                    }
+                }
+                DefMember::Field(field) => {
+                    let expected = match interface.fields.iter().find(|(required, _)| *required == field.name) {
+                        Some((_, ty)) => cx.normalize(&ty.substitute(&subst)),
+                        None => {
+                            self.error(SolverErrKind::NotAMember { interface: name.clone(), what: "field", name: field.name.clone() }, file.clone(), field.span);
+                            continue;
+                        }
+                    };
+                    given_fields.push(field.name.clone());
+                    if let Ok(found) = cx.lower(&field.ty, def.id, &mut || None) {
+                        let found = cx.normalize(&found);
+                        if anonymous(&found) != anonymous(&expected) {
+                            self.error(SolverErrKind::FieldMismatch { name: field.name.clone(), expected: expected.clone(), found }, file.clone(), field.span);
+                            continue;
+                        }
+                    }
+                    let source = field.source.as_ref().unwrap_or(&field.name);
+                    match self.pack_field(&info.target, source) {
+                        Some(found) if anonymous(&found) != anonymous(&expected) => {
+                            self.error(SolverErrKind::FieldMismatch { name: source.clone(), expected, found }, file.clone(), field.span);
+                        }
+                        Some(_) => {}
+                        None => self.error(SolverErrKind::NoSourceField { ty: info.target.clone(), field: source.clone() }, file.clone(), field.span)
+                    }
+                }
+            }
+        }
+        let mut missing_funs: Vec<String> = interface.funs.keys().filter(|fun| !info.funs.contains_key(*fun) && !interface.provided.contains(fun)).cloned().collect();
+        missing_funs.sort();
+        // A field of the interface is taken from the pack field with the same name if the
+        // def doesn't map it
+        let missing_fields = interface.fields.iter().filter(|(field, ty)| {
+            !given_fields.contains(field) && !matches!(self.pack_field(&info.target, field), Some(found) if anonymous(&found) == anonymous(&cx.normalize(&ty.substitute(&subst))))
+        }).map(|(field, _)| field.clone()).collect();
+        let missing_types = interface.assoc_types.iter().filter(|assoc| !given_types.contains(assoc)).cloned().collect();
+        for (what, names) in [("functions", missing_funs), ("fields", missing_fields), ("associated types", missing_types)] {
+            if !names.is_empty() {
+                self.error(SolverErrKind::Missing { interface: name.clone(), ty: info.target.clone(), what, names }, file.clone(), def.span);
+            }
+        }
+    }
+
+    /// The type of the field NAME of TY, if it is a pack with such a field
+    fn pack_field(&self, ty: &Type, name: &str) -> Option<Type> {
+        let (id, args) = match ty {
+            Type::Pack { id, args, .. } => (*id, args),
+            _ => return None
+        };
+        let subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((id, index), arg)).collect();
+        let (_, ty) = self.cx.packs.get(id)?.fields.iter().find(|(field, _)| field == name)?;
+        Some(self.cx.normalize(&ty.substitute(&subst)))
+    }
+
+    /// Checks that the function FUN of a def has the signature of the function REQUIRED
+    /// of its interface, whose generic parameters SUBST gives
+    fn check_signature(&mut self, required: NodeId, fun: NodeId, interface: &str, subst: &Substitution, file: PathBuf, span: Span) {
+        let (required_sig, sig) = match (self.cx.funs.get(required), self.cx.funs.get(fun)) {
+            (Some(required_sig), Some(sig)) => (required_sig, sig),
+            _ => return
+        };
+        let as_type = |receiver: &Option<Type>, params: &[Type], ret: &Type| Type::Fun(receiver.iter().chain(params).cloned().collect(), Box::new(ret.clone()));
+        // The generic parameters of the required function are those of the def's
+        let expected = as_type(&required_sig.receiver, &required_sig.params, &required_sig.ret).substitute(subst).map(&mut |ty| match ty {
+            Type::Param { owner, index, name } if *owner == required => Some(Type::Param { owner: fun, index: *index, name: name.clone() }),
+            _ => None
+        });
+        let expected = self.cx.normalize(&expected);
+        let found = self.cx.normalize(&as_type(&sig.receiver, &sig.params, &sig.ret));
+        if required_sig.generics != sig.generics || anonymous(&expected) != anonymous(&found) {
+            let kind = SolverErrKind::SignatureMismatch { name: sig.name.clone(), interface: interface.to_string(), expected, found };
+            self.error(kind, file, span);
+        }
+    }
+}
+
+fn same_interface(a: &InterfaceRef, b: &InterfaceRef) -> bool {
+    match (a, b) {
+        (InterfaceRef::Declared(a, _), InterfaceRef::Declared(b, _)) => a == b,
+        (InterfaceRef::Builtin(a), InterfaceRef::Builtin(b)) => a == b,
+        _ => false
+    }
+}
+
+/// TY with the generic parameters of OWNERS replaced by what SUBST makes them
+fn apply(ty: &Type, owners: &[NodeId], subst: &Substitution) -> Type {
+    ty.map(&mut |ty| match ty {
+        Type::Param { owner, index, .. } if owners.contains(owner) => subst.get(&(*owner, *index)).map(|ty| apply(ty, owners, subst)),
+        _ => None
+    })
+}
+
+/// Makes A and B the same type, the generic parameters of OWNERS standing for any type
+fn unify(a: &Type, b: &Type, owners: &[NodeId], subst: &mut Substitution) -> bool {
+    let (a, b) = (apply(a, owners, subst), apply(b, owners, subst));
+    match (a.without_nodrop(), b.without_nodrop()) {
+        (Type::Param { owner: o, index: i, .. }, Type::Param { owner: p, index: j, .. }) if o == p && i == j => true,
+        (Type::Param { owner, index, .. }, ty) | (ty, Type::Param { owner, index, .. }) if owners.contains(owner) => {
+            let param = (*owner, *index);
+            if ty.any(&|inner| matches!(inner, Type::Param { owner, index, .. } if (*owner, *index) == param)) {
+                return false;
+            }
+            subst.insert(param, ty.clone());
+            true
+        }
+        (Type::Error, _) | (_, Type::Error) => true,
+        (a, b) => match children(a, b) {
+            Some(pairs) => pairs.into_iter().all(|(a, b)| unify(a, b, owners, subst)),
+            None => false
+        }
+    }
+}
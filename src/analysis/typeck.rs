@@ -0,0 +1,1865 @@
+//! Type checking with local type inference. The signatures of the items are written
+//! out in full, and the checker infers what the bodies leave out: the types of the
+//! locals (let s = String::from(imm);), the generic arguments of the calls and the
+//! types of the integer literals. Inference works by unification: every unknown type
+//! is a variable, and each expression unifies the types it relates. Expressions are
+//! checked against the type their context expects when there is one, so that an error
+//! points at the expression which doesn't fit instead of where the types finally clash.
+//!
+//! Where a value doesn't fit, the implicit functions in scope (those whose name resolves
+//! to them) are searched for the one converting it, so that with
+//! implicit fun itos(implicit n: i32): String, val str: String = 123; is checked as
+//! itos(123). The implicit arguments a call leaves out are found the same way, among the
+//! implicit parameters of the function and the implicit functions without parameters.
+//! Either has to be unique: the candidates are reported if several fit. The functions of
+//! an implicit impls are extension methods, found after those of the impls and defs of a
+//! type, and only where the module declaring them is (or is imported with a glob).
+//!
+//! Associated types like T::Target are normalised through the defs once their base is
+//! known. The functions each body uses are recorded with their generic arguments, so
+//! that the solver (analysis/solver.rs) can prove their bounds.
+//!
+//! Integer literals which nothing gives a type to are i32, like constants. The checker
+//! only works out what each expression is: whether a value is moved, dropped or
+//! borrowed correctly is checked by the later passes.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::analysis::consteval::ConstInt;
+use crate::analysis::modules::{Def, ModuleId, ModuleTree};
+use crate::analysis::resolve::{Builtin, Res, Resolutions};
+use crate::analysis::types::{Substitution, Type};
+use crate::ast::declarations::{Bound, DefMember, FunDecl, GenericParam, InterfaceMember, Item, Receiver, WherePredicate};
+use crate::ast::expressions::{Expr, ExprKind, FieldInit, Literal, Path, PathSegment, UnaryOp};
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::statements::{BindingKind, Block, LetStmt, Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::types::{PrimitiveType, Ty, TyKind};
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+/// The type of an integer literal which nothing gives a type to
+const DEFAULT_INT: PrimitiveType = PrimitiveType::SInt32;
+
+const ARITHMETIC: [&str; 10] = ["+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>"];
+const COMPARISONS: [&str; 6] = ["==", "!=", "<", ">", "<=", ">="];
+
+#[derive(Debug, Clone)]
+pub(crate) struct PackInfo {
+    pub name: String,
+    pub generics: Vec<String>,
+    pub fields: Vec<(String, Type)>
+}
+
+/// The signature of a function. Its generic parameters, and those of the impls, def
+/// or interface around it, are Params
+#[derive(Debug, Clone)]
+pub(crate) struct FunSig {
+    pub name: String,
+    /// The impls, def or interface the function is in
+    pub container: Option<NodeId>,
+    pub generics: usize,
+    /// The type of self (&Self for &self), if the function is a method
+    pub receiver: Option<Type>,
+    pub params: Vec<Type>,
+    /// How many of the parameters, at the end, are implicit
+    pub implicit_params: usize,
+    pub ret: Type,
+    pub is_unsafe: bool
+}
+
+/// A function declared implicit. One with a parameter converts a value of its type,
+/// one without gives an implicit value of its return type
+#[derive(Debug, Clone)]
+pub(crate) struct ImplicitFun {
+    pub id: NodeId,
+    pub name: String,
+    /// Where it is declared: it is used where its name resolves to it
+    pub module: ModuleId,
+    pub index: usize,
+    pub span: Span
+}
+
+/// Where the value of a left out implicit argument comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ImplicitArg {
+    /// An implicit parameter of the function around the call
+    Param(NodeId),
+    /// A call to an implicit function without parameters
+    Fun(NodeId)
+}
+
+/// An impls or a def: functions for the types matching its target
+#[derive(Debug, Clone)]
+pub(crate) struct ImplInfo {
+    pub id: NodeId,
+    /// The interface a def implements, None for impls
+    pub interface: Option<InterfaceRef>,
+    /// How many generic parameters it has, the implicit ones included
+    pub generics: usize,
+    pub target: Type,
+    pub funs: HashMap<String, NodeId>,
+    pub assoc_types: HashMap<String, Type>,
+    /// Whether it is an implicit impls, whose functions are extension methods
+    pub implicit: bool,
+    pub module: ModuleId,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct InterfaceInfo {
+    pub name: String,
+    /// How many generic parameters it declares. Self is the one after them
+    pub generics: usize,
+    pub supers: Vec<InterfaceRef>,
+    pub funs: HashMap<String, NodeId>,
+    pub fields: Vec<(String, Type)>,
+    pub assoc_types: Vec<String>,
+    /// The bounds of the associated types, on Self::Name: type Target: Copy;
+    pub assoc_bounds: Vec<BoundInfo>,
+    /// The functions with a body, which the defs don't have to give
+    pub provided: Vec<String>
+}
+
+/// An interface, with its generic arguments
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum InterfaceRef {
+    Declared(NodeId, Vec<Type>),
+    Builtin(Builtin)
+}
+
+/// T: Interface, or T: !Interface if NEGATIVE
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BoundInfo {
+    pub ty: Type,
+    pub interface: InterfaceRef,
+    pub negative: bool
+}
+
+/// What the type checker knows about the items of a module tree
+#[derive(Debug)]
+pub(crate) struct TypeCx<'t> {
+    pub tree: &'t ModuleTree,
+    pub resolutions: &'t Resolutions,
+    pub packs: NodeMap<PackInfo>,
+    pub funs: NodeMap<FunSig>,
+    /// The impls and defs, in the order of the modules
+    pub impls: Vec<ImplInfo>,
+    pub interfaces: NodeMap<InterfaceInfo>,
+    pub consts: NodeMap<Type>,
+    /// What Self is in each impls, def and interface
+    pub self_types: NodeMap<Type>,
+    /// The bounds holding inside each item: those of a function include the bounds
+    /// of the impls, def or interface around it
+    pub bounds: NodeMap<Vec<BoundInfo>>,
+    pub implicit_funs: Vec<ImplicitFun>
+}
+
+/// A use of the function FUN in the body of OWNER, with what the generic parameters
+/// of the function and of its container stand for there. The solver proves the bounds
+/// of the function for them
+#[derive(Debug, Clone)]
+pub(crate) struct Instantiation {
+    pub fun: NodeId,
+    pub args: Substitution,
+    pub owner: NodeId,
+    pub module: ModuleId,
+    pub span: Span
+}
+
+/// What the type checker found out about the bodies
+#[derive(Debug, Default)]
+pub(crate) struct TypeckResults {
+    pub expr_types: NodeMap<Type>,
+    /// The types of the parameters and of the patterns
+    pub local_types: NodeMap<Type>,
+    /// The function each method call (or path to a function) calls
+    pub calls: NodeMap<NodeId>,
+    /// The implicit function converting the value of an expression to the type its
+    /// context expects. The type of the expression is the one before the conversion
+    pub conversions: NodeMap<NodeId>,
+    /// The values of the implicit arguments a call leaves out
+    pub implicit_args: NodeMap<Vec<ImplicitArg>>,
+    pub instantiations: Vec<Instantiation>,
+    /// The generic argument of each path to a builtin function: T in sizeof<T>()
+    pub builtin_args: NodeMap<Type>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TypeErrKind {
+    Mismatch { expected: Type, found: Type },
+    /// More than one implicit fits: a conversion from a type to another (with what
+    /// each candidate does), an implicit argument of a type or an extension method
+    AmbiguousImplicit { what: String, candidates: Vec<(String, Span)>, explanations: Vec<String> },
+    NoImplicit(Type),
+    /// An implicit function with more than one parameter, or an implicit parameter
+    /// followed by an explicit one
+    ImplicitShape(String),
+    ArgCount { expected: usize, found: usize },
+    GenericCount { name: String, expected: usize, found: usize },
+    NoField { ty: Type, field: String },
+    NoFun { ty: Type, name: String },
+    NotCallable(Type),
+    InvalidOperand { op: String, ty: Type },
+    InvalidCast { from: Type, to: Type },
+    /// An integer literal (with its minus, if negated) which doesn't fit in its type
+    LiteralOutOfRange { literal: String, ty: PrimitiveType },
+    CantDeref(Type),
+    NotIndexable(Type),
+    NotIterable(Type),
+    MissingFields { pack: String, fields: Vec<String> },
+    DuplicateField(String),
+    NotAPlace,
+    /// The val binding (declared at the span given) or a part of it is assigned to
+    AssignToVal { name: String, declared: Span },
+    /// break or continue outside of a loop
+    OutsideLoop(&'static str),
+    /// The type of the local (or of the expression if None) is never found out
+    AnnotationsNeeded(Option<String>),
+    InterfaceAsType(String),
+    /// T: Interface and T: !Interface on the same item
+    ContradictoryBounds { ty: Type, interface: String },
+    InferInSignature,
+    UnsupportedPath(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TypeErr {
+    pub kind: TypeErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl TypeErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        use TypeErrKind::*;
+        let span = self.span;
+        match &self.kind {
+            Mismatch { expected, found } => Diagnostic::error("mismatched types", span, &format!("expected {}, found {}", expected, found)),
+            AmbiguousImplicit { what, candidates, explanations } => {
+                let diag = Diagnostic::error(&format!("ambiguous implicits for {}", what), span, "needed here");
+                let diag = candidates.iter().fold(diag, |diag, (name, span)| diag.with_label(*span, &format!("{} fits", name)));
+                explanations.iter().fold(diag, |diag, explanation| diag.with_note(explanation))
+                    .with_note("only one of them can be in scope where it is used")
+            }
+            NoImplicit(ty) => Diagnostic::error(&format!("no implicit value of type {} in scope", ty), span, "an implicit argument is left out here")
+                .with_note("implicit values are the implicit parameters of the function and the implicit functions without parameters"),
+            ImplicitShape(problem) => Diagnostic::error(problem, span, "declared here"),
+            ArgCount { expected, found } => {
+                Diagnostic::error(&format!("this takes {} but {} given", count(*expected, "argument"), were(*found)), span, &format!("expected {}", count(*expected, "argument")))
+            }
+            GenericCount { name, expected, found } => {
+                let message = format!("{} takes {} but {} given", name, count(*expected, "generic argument"), were(*found));
+                Diagnostic::error(&message, span, &format!("expected {}", count(*expected, "generic argument")))
+            }
+            NoField { ty, field } => Diagnostic::error(&format!("{} has no field {}", ty, field), span, "unknown field"),
+            NoFun { ty, name } => Diagnostic::error(&format!("{} has no function {}", ty, name), span, "not found")
+                .with_note("the functions of a type come from its impls, its defs and the bounds of a generic parameter"),
+            NotCallable(ty) => Diagnostic::error(&format!("{} is not a function", ty), span, "called here"),
+            InvalidOperand { op, ty } => Diagnostic::error(&format!("{} can't be applied to {}", op, ty), span, &format!("this is {}", ty)),
+            InvalidCast { from, to } => Diagnostic::error(&format!("{} can't be cast to {}", from, to), span, "invalid cast"),
+            LiteralOutOfRange { literal, ty } => {
+                let bits = ty.int_bits().unwrap_or(128);
+                let (min, max) = if ty.is_signed() {
+                    (format!("-{}", 1u128 << (bits - 1)), (u128::MAX >> (129 - bits)).to_string())
+                } else {
+                    ("0".to_string(), (u128::MAX >> (128 - bits)).to_string())
+                };
+                Diagnostic::error(&format!("literal out of range for {}", ty), span, &format!("{} doesn't fit in {}", literal, ty))
+                    .with_note(&format!("{} goes from {} to {}", ty, min, max))
+            }
+            CantDeref(ty) => Diagnostic::error(&format!("{} can't be dereferenced", ty), span, "not a reference or a pointer"),
+            NotIndexable(ty) => Diagnostic::error(&format!("{} can't be indexed", ty), span, "not an array"),
+            NotIterable(ty) => Diagnostic::error(&format!("{} can't be iterated over", ty), span, "not an array"),
+            MissingFields { pack, fields } => Diagnostic::error(&format!("missing fields {} in {}", fields.join(", "), pack), span, "every field needs a value"),
+            DuplicateField(field) => Diagnostic::error(&format!("{} is given more than once", field), span, "given again here"),
+            NotAPlace => Diagnostic::error("can't assign to this", span, "not a local, a field, an element or a dereference"),
+            AssignToVal { name, declared } => Diagnostic::error(&format!("{} is a val and can't be assigned again", name), span, "assigned here")
+                .with_label(*declared, &format!("{} is declared here", name))
+                .with_note(&format!("declare {} with let to assign it again", name)),
+            OutsideLoop(what) => Diagnostic::error(&format!("{} outside of a loop", what), span, &format!("can't {} here", what)),
+            AnnotationsNeeded(Some(name)) => Diagnostic::error(&format!("type annotations needed for {}", name), span, "its type can't be inferred")
+                .with_note(&format!("give {} a type: let {}: Type", name, name)),
+            AnnotationsNeeded(None) => Diagnostic::error("type annotations needed", span, "the type of this can't be inferred"),
+            InterfaceAsType(name) => Diagnostic::error(&format!("the interface {} is used as a type", name), span, "not a type")
+                .with_note("use a generic parameter bounded by the interface instead"),
+            ContradictoryBounds { ty, interface } => {
+                Diagnostic::error(&format!("{} is bounded by both {} and !{}", ty, interface, interface), span, "contradicts an earlier bound")
+                    .with_note("no type could ever be used here")
+            }
+            InferInSignature => Diagnostic::error("_ is not allowed in signatures", span, "write the type out"),
+            UnsupportedPath(path) => Diagnostic::error(&format!("{} can't be used as a value", path), span, "unsupported path")
+        }
+    }
+}
+
+/// N WHATs, with WHAT in the plural unless N is 1
+fn count(n: usize, what: &str) -> String {
+    if n == 1 { format!("1 {}", what) } else { format!("{} {}s", n, what) }
+}
+
+/// "N was" or "N were"
+fn were(n: usize) -> String {
+    if n == 1 { "1 was".to_string() } else { format!("{} were", n) }
+}
+
+/// Checks the items and the bodies of a module tree
+pub(crate) struct TypeChecker;
+
+impl TypeChecker {
+    pub fn check<'t>(tree: &'t ModuleTree, resolutions: &'t Resolutions) -> (TypeCx<'t>, TypeckResults, Vec<TypeErr>) {
+        let mut errs = Vec::new();
+        let cx = TypeCx::new(tree, resolutions, &mut errs);
+        let mut results = TypeckResults::default();
+        for (module, m) in tree.modules() {
+            for item in &m.items {
+                match item {
+                    Item::Fun(fun) => cx.check_fun(fun, module, &mut results, &mut errs),
+                    Item::Impls(impls) => impls.funs.iter().for_each(|fun| cx.check_fun(fun, module, &mut results, &mut errs)),
+                    Item::Def(def) => {
+                        for member in &def.members {
+                            if let DefMember::Fun(fun) = member {
+                                cx.check_fun(fun, module, &mut results, &mut errs);
+                            }
+                        }
+                    }
+                    Item::Interface(interface) => {
+                        for member in &interface.members {
+                            if let InterfaceMember::Fun(fun) = member {
+                                cx.check_fun(fun, module, &mut results, &mut errs);
+                            }
+                        }
+                    }
+                    Item::Const(decl) => {
+                        let ty = cx.consts.get(decl.id).cloned().unwrap_or(Type::Error);
+                        let mut fcx = FnCx::new(&cx, decl.id, module, ty.clone(), None, &mut results, &mut errs);
+                        fcx.check_expr(&decl.value, Some(&ty));
+                        fcx.finish();
+                    }
+                    Item::Pack(_) | Item::Assoc(_) | Item::Mod(_) | Item::Use(_) => {}
+                }
+            }
+        }
+        (cx, results, errs)
+    }
+}
+
+/// Collects what the items of a module tree declare, in three steps: the names of
+/// the packs and interfaces, then what Self is in each impls, def and interface,
+/// then the types of everything
+struct Collector<'a, 't> {
+    cx: TypeCx<'t>,
+    module: ModuleId,
+    errs: &'a mut Vec<TypeErr>
+}
+
+impl<'a, 't> Collector<'a, 't> {
+    fn error(&mut self, kind: TypeErrKind, span: Span) {
+        self.errs.push(TypeErr { kind, file: self.cx.tree.module(self.module).file.clone(), span });
+    }
+
+    /// Lowers a type of a signature, where _ is not allowed
+    fn lower(&mut self, ty: &Ty, owner: NodeId, span: Span) -> Type {
+        match self.cx.lower(ty, owner, &mut || None) {
+            Ok(ty) => ty,
+            Err(kind) => {
+                self.error(kind, span);
+                Type::Error
+            }
+        }
+    }
+
+    fn generics_of(&self, owner: NodeId, generics: &[GenericParam]) -> Vec<String> {
+        let mut names: Vec<String> = generics.iter().map(|param| param.name.clone()).collect();
+        names.extend(self.cx.resolutions.implicit_generics.get(owner).cloned().unwrap_or_default());
+        names
+    }
+
+    fn interface_ref(&mut self, ty: &Ty, owner: NodeId, span: Span) -> Option<InterfaceRef> {
+        let (name, args) = match &ty.kind {
+            TyKind::Userdefined(name) => (name, &[][..]),
+            TyKind::Generic(name, args) => (name, &args[..]),
+            _ => return None
+        };
+        match self.cx.resolutions.ty_name(owner, name)? {
+            Res::Item(id) if self.cx.interfaces.contains(id) => {
+                let args = args.iter().map(|arg| self.lower(arg, owner, span)).collect();
+                Some(InterfaceRef::Declared(id, args))
+            }
+            Res::Builtin(builtin) => Some(InterfaceRef::Builtin(builtin)),
+            _ => None
+        }
+    }
+
+    /// The bounds of GENERICS (the parameters of OWNER) and of its where clause
+    fn bounds(&mut self, owner: NodeId, generics: &[GenericParam], where_clause: &[WherePredicate]) -> Vec<BoundInfo> {
+        let mut bounds = Vec::new();
+        for (index, param) in generics.iter().enumerate() {
+            let ty = Type::Param { owner, index, name: param.name.clone() };
+            for bound in &param.bounds {
+                self.add_bound(&mut bounds, ty.clone(), bound, owner);
+            }
+        }
+        for predicate in where_clause {
+            let ty = self.lower(&predicate.ty, owner, predicate.span);
+            for bound in &predicate.bounds {
+                self.add_bound(&mut bounds, ty.clone(), bound, owner);
+            }
+        }
+        bounds
+    }
+
+    fn add_bound(&mut self, bounds: &mut Vec<BoundInfo>, ty: Type, bound: &Bound, owner: NodeId) {
+        let interface = match self.interface_ref(&bound.interface, owner, bound.span) {
+            Some(interface) => interface,
+            None => return
+        };
+        if bounds.iter().any(|other| other.ty == ty && other.interface == interface && other.negative != bound.negative) {
+            let interface = bound.interface.to_string();
+            self.error(TypeErrKind::ContradictoryBounds { ty: ty.clone(), interface }, bound.span);
+        }
+        bounds.push(BoundInfo { ty, interface, negative: bound.negative });
+    }
+
+    fn collect_fun(&mut self, fun: &FunDecl, container: Option<NodeId>) {
+        let self_ty = container.and_then(|container| self.cx.self_types.get(container).cloned()).unwrap_or(Type::Error);
+        let receiver = fun.receiver.map(|receiver| match receiver {
+            Receiver::Value => self_ty.clone(),
+            Receiver::Ref => Type::Ref(Box::new(self_ty.clone())),
+            Receiver::Ptr => Type::Ptr(Box::new(self_ty.clone()))
+        });
+        let params = fun.params.iter().map(|param| self.lower(&param.ty, fun.id, param.span)).collect();
+        let ret = fun.ret.as_ref().map(|ret| self.lower(ret, fun.id, fun.span)).unwrap_or_else(Type::unit);
+        let implicit_params = fun.params.iter().rev().take_while(|param| param.implicit).count();
+        if fun.params.iter().filter(|param| param.implicit).count() > implicit_params {
+            self.error(TypeErrKind::ImplicitShape("implicit parameters must come after the others".to_string()), fun.span);
+        }
+        let sig = FunSig { name: fun.name.clone(), container, generics: fun.generics.len(), receiver, params, implicit_params, ret, is_unsafe: fun.is_unsafe };
+        self.cx.funs.insert(fun.id, sig);
+        let mut bounds = container.and_then(|container| self.cx.bounds.get(container).cloned()).unwrap_or_default();
+        bounds.extend(self.bounds(fun.id, &fun.generics, &fun.where_clause));
+        self.cx.bounds.insert(fun.id, bounds);
+    }
+}
+
+impl<'t> TypeCx<'t> {
+    /// Explains the implicit function FUN used as a conversion from FROM: itos converts
+    /// i32 to String, declared as implicit fun itos(implicit n: i32): String
+    pub fn explain_implicit(&self, fun: NodeId, from: &Type) -> Option<String> {
+        let sig = self.funs.get(fun)?;
+        let implicit = self.implicit_funs.iter().find(|implicit| implicit.id == fun)?;
+        let decl = match &self.tree.module(implicit.module).items[implicit.index] {
+            Item::Fun(decl) => decl,
+            _ => return None
+        };
+        let header = FunDecl { body: None, attrs: Vec::new(), ..decl.clone() };
+        Some(format!("{} converts {} to {}, declared as {}", sig.name, from, sig.ret, header.to_string().trim_end_matches(';')))
+    }
+
+    fn new(tree: &'t ModuleTree, resolutions: &'t Resolutions, errs: &mut Vec<TypeErr>) -> Self {
+        let cx = Self {
+            tree, resolutions, packs: NodeMap::new(), funs: NodeMap::new(), impls: Vec::new(), interfaces: NodeMap::new(),
+            consts: NodeMap::new(), self_types: NodeMap::new(), bounds: NodeMap::new(), implicit_funs: Vec::new()
+        };
+        let mut collector = Collector { cx, module: tree.root(), errs };
+        // The names, so that the types can refer to any of them
+        for (module, m) in tree.modules() {
+            collector.module = module;
+            for item in &m.items {
+                match item {
+                    Item::Pack(pack) => {
+                        let info = PackInfo { name: pack.name.clone(), generics: collector.generics_of(pack.id, &pack.generics), fields: Vec::new() };
+                        collector.cx.packs.insert(pack.id, info);
+                    }
+                    Item::Interface(interface) => {
+                        let info = InterfaceInfo {
+                            name: interface.name.clone(), generics: interface.generics.len(), supers: Vec::new(),
+                            funs: HashMap::new(), fields: Vec::new(), assoc_types: Vec::new(), assoc_bounds: Vec::new(), provided: Vec::new()
+                        };
+                        collector.cx.interfaces.insert(interface.id, info);
+                        let self_ty = Type::Param { owner: interface.id, index: interface.generics.len(), name: "Self".to_string() };
+                        collector.cx.self_types.insert(interface.id, self_ty);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // What Self is
+        for (module, m) in tree.modules() {
+            collector.module = module;
+            for item in &m.items {
+                let (id, target, span) = match item {
+                    Item::Impls(impls) => (impls.id, &impls.target, impls.span),
+                    Item::Def(def) => (def.id, &def.target, def.span),
+                    _ => continue
+                };
+                let target = collector.lower(target, id, span);
+                collector.cx.self_types.insert(id, target);
+            }
+        }
+        // Everything else
+        for (module, m) in tree.modules() {
+            collector.module = module;
+            for (index, item) in m.items.iter().enumerate() {
+                match item {
+                    Item::Pack(pack) => {
+                        let fields = pack.fields.iter().map(|field| (field.name.clone(), collector.lower(&field.ty, pack.id, field.span))).collect();
+                        if let Some(info) = collector.cx.packs.get_mut(pack.id) {
+                            info.fields = fields;
+                        }
+                        let bounds = collector.bounds(pack.id, &pack.generics, &[]);
+                        collector.cx.bounds.insert(pack.id, bounds);
+                    }
+                    Item::Fun(fun) => {
+                        collector.collect_fun(fun, None);
+                        if fun.implicit {
+                            if fun.params.len() > 1 {
+                                let problem = format!("the implicit function {} has more than one parameter", fun.name);
+                                collector.error(TypeErrKind::ImplicitShape(problem), fun.span);
+                            }
+                            let implicit = ImplicitFun { id: fun.id, name: fun.name.clone(), module, index, span: fun.span };
+                            collector.cx.implicit_funs.push(implicit);
+                        }
+                    }
+                    Item::Const(decl) => {
+                        let ty = collector.lower(&decl.ty, decl.id, decl.ty.span);
+                        collector.cx.consts.insert(decl.id, ty);
+                    }
+                    Item::Impls(impls) => {
+                        let bounds = collector.bounds(impls.id, &impls.generics, &impls.where_clause);
+                        collector.cx.bounds.insert(impls.id, bounds);
+                        for fun in &impls.funs {
+                            collector.collect_fun(fun, Some(impls.id));
+                        }
+                        let info = ImplInfo {
+                            id: impls.id, interface: None, generics: collector.generics_of(impls.id, &impls.generics).len(),
+                            target: collector.cx.self_types.get(impls.id).cloned().unwrap_or(Type::Error),
+                            funs: impls.funs.iter().map(|fun| (fun.name.clone(), fun.id)).collect(), assoc_types: HashMap::new(),
+                            implicit: impls.implicit, module, span: impls.span
+                        };
+                        collector.cx.impls.push(info);
+                    }
+                    Item::Def(def) => {
+                        let bounds = collector.bounds(def.id, &def.generics, &def.where_clause);
+                        collector.cx.bounds.insert(def.id, bounds);
+                        let interface = collector.interface_ref(&def.interface, def.id, def.span);
+                        let mut funs = HashMap::new();
+                        let mut assoc_types = HashMap::new();
+                        for member in &def.members {
+                            match member {
+                                DefMember::Fun(fun) => {
+                                    collector.collect_fun(fun, Some(def.id));
+                                    funs.insert(fun.name.clone(), fun.id);
+                                }
+                                DefMember::AssocType(assoc) => {
+                                    let ty = collector.lower(&assoc.ty, def.id, assoc.span);
+                                    assoc_types.insert(assoc.name.clone(), ty);
+                                }
+                                DefMember::Field(_) => {}
+                            }
+                        }
+                        let info = ImplInfo {
+                            id: def.id, interface, generics: collector.generics_of(def.id, &def.generics).len(),
+                            target: collector.cx.self_types.get(def.id).cloned().unwrap_or(Type::Error), funs, assoc_types,
+                            implicit: false, module, span: def.span
+                        };
+                        collector.cx.impls.push(info);
+                    }
+                    Item::Interface(interface) => {
+                        let supers = interface.supers.iter().filter_map(|sup| collector.interface_ref(sup, interface.id, interface.span)).collect();
+                        let mut bounds = collector.bounds(interface.id, &interface.generics, &interface.where_clause);
+                        // Self implements the interface inside it
+                        let self_ty = collector.cx.self_types.get(interface.id).cloned().unwrap_or(Type::Error);
+                        let args = interface.generics.iter().enumerate().map(|(index, param)| Type::Param { owner: interface.id, index, name: param.name.clone() }).collect();
+                        bounds.push(BoundInfo { ty: self_ty, interface: InterfaceRef::Declared(interface.id, args), negative: false });
+                        collector.cx.bounds.insert(interface.id, bounds);
+                        let mut funs = HashMap::new();
+                        let mut fields = Vec::new();
+                        let mut assoc_types = Vec::new();
+                        let mut assoc_bounds = Vec::new();
+                        let mut provided = Vec::new();
+                        for member in &interface.members {
+                            match member {
+                                InterfaceMember::Fun(fun) => {
+                                    collector.collect_fun(fun, Some(interface.id));
+                                    funs.insert(fun.name.clone(), fun.id);
+                                    if fun.body.is_some() {
+                                        provided.push(fun.name.clone());
+                                    }
+                                }
+                                InterfaceMember::Field(field) => fields.push((field.name.clone(), collector.lower(&field.ty, interface.id, field.span))),
+                                InterfaceMember::AssocType(assoc) => {
+                                    assoc_types.push(assoc.name.clone());
+                                    let self_ty = collector.cx.self_types.get(interface.id).cloned().unwrap_or(Type::Error);
+                                    let ty = Type::Assoc(Box::new(self_ty), assoc.name.clone());
+                                    for bound in &assoc.bounds {
+                                        collector.add_bound(&mut assoc_bounds, ty.clone(), bound, interface.id);
+                                    }
+                                }
+                            }
+                        }
+                        if let Some(info) = collector.cx.interfaces.get_mut(interface.id) {
+                            info.supers = supers;
+                            info.funs = funs;
+                            info.fields = fields;
+                            info.assoc_types = assoc_types;
+                            info.assoc_bounds = assoc_bounds;
+                            info.provided = provided;
+                        }
+                    }
+                    Item::Mod(_) | Item::Assoc(_) | Item::Use(_) => {}
+                }
+            }
+        }
+        collector.cx
+    }
+
+    /// The interface and the name of the method OP desugars to when it is used in the
+    /// body of OWNER: + to Add::plus. The interface of an assoc is the one its name
+    /// resolves to where it is declared, Cast is looked up where the cast is
+    pub fn operator(&self, op: &str, owner: NodeId) -> Option<(NodeId, &'t str)> {
+        let assoc = self.tree.operators().get(op)?;
+        match self.resolutions.ty_name(assoc.decl.unwrap_or(owner), &assoc.interface) {
+            Some(Res::Item(interface)) if self.interfaces.get(interface).is_some() => Some((interface, assoc.method.as_str())),
+            _ => None
+        }
+    }
+
+    /// The type TY spells in the item OWNER. INFER gives the types of the _ in it,
+    /// None where _ is not allowed
+    pub fn lower(&self, ty: &Ty, owner: NodeId, infer: &mut dyn FnMut() -> Option<Type>) -> Result<Type, TypeErrKind> {
+        let lowered = match &ty.kind {
+            TyKind::Primitive(prim) => Type::Prim(*prim),
+            TyKind::Ref(inner) => Type::Ref(Box::new(self.lower(inner, owner, infer)?)),
+            TyKind::Ptr(inner) => Type::Ptr(Box::new(self.lower(inner, owner, infer)?)),
+            TyKind::NoDrop(inner) => Type::NoDrop(Box::new(self.lower(inner, owner, infer)?)),
+            TyKind::Infer => infer().ok_or(TypeErrKind::InferInSignature)?,
+            TyKind::Userdefined(name) => self.lower_named(name, &[], owner, infer)?,
+            TyKind::Generic(name, args) => self.lower_named(name, args, owner, infer)?,
+            TyKind::Assoc(base, name) => Type::Assoc(Box::new(self.lower(base, owner, infer)?), name.clone())
+        };
+        Ok(lowered)
+    }
+
+    fn lower_named(&self, name: &str, args: &[Ty], owner: NodeId, infer: &mut dyn FnMut() -> Option<Type>) -> Result<Type, TypeErrKind> {
+        // The resolver reported the names it couldn't resolve
+        let res = match self.resolutions.ty_name(owner, name) {
+            Some(res) => res,
+            None => return Ok(Type::Error)
+        };
+        let args = args.iter().map(|arg| self.lower(arg, owner, infer)).collect::<Result<Vec<Type>, TypeErrKind>>()?;
+        let no_args = |ty: Type| if args.is_empty() { Ok(ty) } else { Err(TypeErrKind::GenericCount { name: name.to_string(), expected: 0, found: args.len() }) };
+        match res {
+            Res::Item(id) => match (self.packs.get(id), self.interfaces.get(id)) {
+                (Some(pack), _) if pack.generics.len() != args.len() => {
+                    Err(TypeErrKind::GenericCount { name: name.to_string(), expected: pack.generics.len(), found: args.len() })
+                }
+                (Some(_), _) => Ok(Type::Pack { id, name: name.to_string(), args }),
+                (None, Some(_)) => Err(TypeErrKind::InterfaceAsType(name.to_string())),
+                (None, None) => Ok(Type::Error)
+            },
+            Res::Generic { owner, index } => no_args(Type::Param { owner, index, name: name.to_string() }),
+            Res::SelfTy(container) => no_args(self.self_types.get(container).cloned().unwrap_or(Type::Error)),
+            Res::Builtin(Builtin::StrSlice) => no_args(Type::StrSlice),
+            Res::Builtin(builtin) => Err(TypeErrKind::InterfaceAsType(builtin.name().to_string())),
+            Res::Module(_) | Res::SelfValue(_) | Res::Local(_) => Ok(Type::Error)
+        }
+    }
+
+    fn check_fun(&self, fun: &FunDecl, module: ModuleId, results: &mut TypeckResults, errs: &mut Vec<TypeErr>) {
+        let sig = match self.funs.get(fun.id) {
+            Some(sig) => sig,
+            None => return
+        };
+        for (param, ty) in fun.params.iter().zip(&sig.params) {
+            results.local_types.insert(param.id, ty.clone());
+        }
+        let body = match &fun.body {
+            Some(body) => body,
+            None => return
+        };
+        let mut fcx = FnCx::new(self, fun.id, module, sig.ret.clone(), sig.receiver.clone(), results, errs);
+        fcx.implicit_params = fun.params.iter().filter(|param| param.implicit).map(|param| (param.id, param.name.clone(), param.span)).collect();
+        let ret = sig.ret.clone();
+        fcx.check_block(body, Some(&ret));
+        fcx.finish();
+    }
+}
+
+/// What an inference variable stands for so far
+#[derive(Debug, Clone)]
+struct Var {
+    value: Option<Type>,
+    /// Whether it is the type of an integer literal, which can only be an integer
+    integral: bool
+}
+
+/// Checks one body: a function, or the value of a constant
+struct FnCx<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    /// The item whose names the types of the body use
+    owner: NodeId,
+    module: ModuleId,
+    ret: Type,
+    self_value: Option<Type>,
+    bounds: Vec<BoundInfo>,
+    vars: Vec<Var>,
+    /// How many loops are around the code being checked
+    loops: usize,
+    /// The casts, checked once the types are known
+    casts: Vec<(Type, Type, Span)>,
+    /// The integer literals, whether they are negated and the span of the literal
+    /// (with its minus), checked against their type once it is known
+    int_literals: Vec<(NodeId, u128, bool, Span)>,
+    /// The expressions and locals of the body, whose types are resolved at the end
+    exprs: Vec<NodeId>,
+    locals: Vec<(NodeId, String, Span)>,
+    /// The locals bound by val, which can't be assigned again
+    vals: NodeMap<(String, Span)>,
+    /// The implicit parameters of the function, which are implicit values in its body
+    implicit_params: Vec<(NodeId, String, Span)>,
+    /// The functions used by the body, whose generic arguments are resolved at the end
+    instantiations: Vec<(NodeId, Substitution, Span)>,
+    /// The generic arguments of the paths to builtins, resolved at the end too
+    builtin_args: Vec<(NodeId, Type)>,
+    results: &'a mut TypeckResults,
+    errs: &'a mut Vec<TypeErr>
+}
+
+impl<'a, 't> FnCx<'a, 't> {
+    fn new(cx: &'a TypeCx<'t>, owner: NodeId, module: ModuleId, ret: Type, self_value: Option<Type>, results: &'a mut TypeckResults, errs: &'a mut Vec<TypeErr>) -> Self {
+        let bounds = cx.bounds.get(owner).cloned().unwrap_or_default();
+        Self { cx, owner, module, ret, self_value, bounds, vars: Vec::new(), loops: 0, casts: Vec::new(), int_literals: Vec::new(), exprs: Vec::new(), locals: Vec::new(), vals: NodeMap::new(), implicit_params: Vec::new(), instantiations: Vec::new(), builtin_args: Vec::new(), results, errs }
+    }
+
+    fn error(&mut self, kind: TypeErrKind, span: Span) {
+        self.errs.push(TypeErr { kind, file: self.cx.tree.module(self.module).file.clone(), span });
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.vars.push(Var { value: None, integral: false });
+        Type::Var(self.vars.len() as u32 - 1)
+    }
+
+    fn fresh_int(&mut self) -> Type {
+        self.vars.push(Var { value: None, integral: true });
+        Type::Var(self.vars.len() as u32 - 1)
+    }
+
+    /// TY with the variables which are known replaced, at the top only
+    fn shallow(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(var) => match &self.vars[*var as usize].value {
+                Some(value) => self.shallow(value),
+                None => ty.clone()
+            },
+            Type::NoDrop(inner) => self.shallow(inner),
+            Type::Assoc(base, name) => match self.normalize(base, name) {
+                Ok(ty) => self.shallow(&ty),
+                Err(assoc) => assoc
+            },
+            ty => ty.clone()
+        }
+    }
+
+    /// TY with every variable which is known replaced, and the associated types which
+    /// are known normalised
+    fn resolve(&self, ty: &Type) -> Type {
+        ty.map(&mut |ty| match ty {
+            Type::Var(var) => self.vars[*var as usize].value.as_ref().map(|value| self.resolve(value)),
+            Type::Assoc(base, name) => Some(match self.normalize(base, name) {
+                Ok(ty) => self.resolve(&ty),
+                Err(assoc) => assoc
+            }),
+            _ => None
+        })
+    }
+
+    /// What BASE::NAME stands for, or the associated type with its base resolved if it
+    /// is not known yet
+    fn normalize(&self, base: &Type, name: &str) -> Result<Type, Type> {
+        let base = self.resolve(base);
+        self.cx.normalize_assoc(&base, name).ok_or_else(|| Type::Assoc(Box::new(base), name.to_string()))
+    }
+
+    fn occurs(&self, var: u32, ty: &Type) -> bool {
+        self.resolve(ty).any(&|ty| *ty == Type::Var(var))
+    }
+
+    fn bind(&mut self, var: u32, ty: Type) -> bool {
+        let integral = self.vars[var as usize].integral;
+        match &ty {
+            Type::Var(other) if integral => self.vars[*other as usize].integral = true,
+            ty if integral && !ty.is_integer() && *ty != Type::Error => return false,
+            ty if self.occurs(var, ty) => return false,
+            _ => {}
+        }
+        self.vars[var as usize].value = Some(ty);
+        true
+    }
+
+    /// Makes A and B the same type, giving the variables in them the types it takes.
+    /// -T is the same type as T: the - only changes how a value is dropped
+    fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+        match (a, b) {
+            (Type::Error, _) | (_, Type::Error) | (Type::Never, _) | (_, Type::Never) => true,
+            (Type::Var(x), Type::Var(y)) if x == y => true,
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => self.bind(var, ty),
+            (Type::Prim(a), Type::Prim(b)) => a == b,
+            (Type::Pack { id: a, args: a_args, .. }, Type::Pack { id: b, args: b_args, .. }) => a == b && self.unify_all(&a_args, &b_args),
+            (Type::Param { owner: a, index: i, .. }, Type::Param { owner: b, index: j, .. }) => a == b && i == j,
+            (Type::StrSlice, Type::StrSlice) => true,
+            (Type::Ref(a), Type::Ref(b)) | (Type::Ptr(a), Type::Ptr(b)) => self.unify(&a, &b),
+            (Type::Tuple(a), Type::Tuple(b)) => self.unify_all(&a, &b),
+            (Type::Array(a, n), Type::Array(b, m)) => n == m && self.unify(&a, &b),
+            (Type::Fun(a, a_ret), Type::Fun(b, b_ret)) => self.unify_all(&a, &b) && self.unify(&a_ret, &b_ret),
+            (Type::Assoc(a, a_name), Type::Assoc(b, b_name)) => a_name == b_name && self.unify(&a, &b),
+            _ => false
+        }
+    }
+
+    fn unify_all(&mut self, a: &[Type], b: &[Type]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.unify(a, b))
+    }
+
+    /// Unifies FOUND with EXPECTED, reporting a mismatch at SPAN if they differ
+    fn expect(&mut self, found: &Type, expected: &Type, span: Span) -> bool {
+        if self.unify(found, expected) {
+            return true;
+        }
+        let kind = TypeErrKind::Mismatch { expected: self.describe(expected), found: self.describe(found) };
+        self.error(kind, span);
+        false
+    }
+
+    /// TY as an error shows it: resolved, with the integer literals as their default type
+    fn describe(&self, ty: &Type) -> Type {
+        self.resolve(ty).map(&mut |ty| match ty {
+            Type::Var(var) if self.vars[*var as usize].integral => Some(Type::Prim(DEFAULT_INT)),
+            _ => None
+        })
+    }
+
+    /// Lowers a type written in the body, where _ is a new variable
+    fn lower(&mut self, ty: &Ty, span: Span) -> Type {
+        let mut infer_count = 0;
+        let placeholder = self.cx.lower(ty, self.owner, &mut || { infer_count += 1; Some(Type::Error) });
+        match placeholder {
+            Ok(_) if infer_count == 0 => placeholder.unwrap_or(Type::Error),
+            Ok(_) => {
+                let vars: Vec<Type> = (0..infer_count).map(|_| self.fresh()).collect();
+                let mut vars = vars.into_iter();
+                self.cx.lower(ty, self.owner, &mut || vars.next()).unwrap_or(Type::Error)
+            }
+            Err(kind) => {
+                self.error(kind, span);
+                Type::Error
+            }
+        }
+    }
+
+    fn record_local(&mut self, id: NodeId, name: &str, ty: Type, span: Span) {
+        self.results.local_types.insert(id, ty);
+        self.locals.push((id, name.to_string(), span));
+    }
+
+    /// Gives the integer literals their default type, checks the casts and resolves
+    /// the types of the body, reporting those which couldn't be inferred
+    fn finish(mut self) {
+        for var in 0..self.vars.len() {
+            if self.vars[var].value.is_none() && self.vars[var].integral {
+                self.vars[var].value = Some(Type::Prim(DEFAULT_INT));
+            }
+        }
+        for (from, to, span) in std::mem::take(&mut self.casts) {
+            let (from, to) = (self.resolve(&from), self.resolve(&to));
+            if !self.valid_cast(&from, &to) && !self.cast_fun(&from, &to, span) {
+                self.error(TypeErrKind::InvalidCast { from, to }, span);
+            }
+        }
+        for (id, n, negated, span) in std::mem::take(&mut self.int_literals) {
+            let ty = self.results.expr_types.get(id).map(|ty| self.resolve(ty));
+            if let Some(&Type::Prim(prim)) = ty.as_ref().map(Type::without_nodrop).filter(|ty| ty.is_integer()) {
+                let fits = if negated { ConstInt::from_negated(n, prim) } else { ConstInt::from_u128(n, prim) };
+                if fits.is_none() {
+                    let literal = if negated { format!("-{}", n) } else { n.to_string() };
+                    self.error(TypeErrKind::LiteralOutOfRange { literal, ty: prim }, span);
+                }
+            }
+        }
+        for (id, name, span) in std::mem::take(&mut self.locals) {
+            let ty = self.results.local_types.get(id).map(|ty| self.resolve(ty)).unwrap_or(Type::Error);
+            if ty.any(&|ty| matches!(ty, Type::Var(_))) {
+                self.error(TypeErrKind::AnnotationsNeeded(Some(name)), span);
+            }
+            self.results.local_types.insert(id, ty);
+        }
+        for id in std::mem::take(&mut self.exprs) {
+            if let Some(ty) = self.results.expr_types.get(id).map(|ty| self.resolve(ty)) {
+                self.results.expr_types.insert(id, ty);
+            }
+        }
+        for (fun, args, span) in std::mem::take(&mut self.instantiations) {
+            let args = args.into_iter().map(|(param, ty)| (param, self.resolve(&ty))).collect();
+            self.results.instantiations.push(Instantiation { fun, args, owner: self.owner, module: self.module, span });
+        }
+        for (id, ty) in std::mem::take(&mut self.builtin_args) {
+            let ty = self.resolve(&ty);
+            self.results.builtin_args.insert(id, ty);
+        }
+    }
+
+    fn valid_cast(&self, from: &Type, to: &Type) -> bool {
+        let unknown = |ty: &Type| ty.any(&|ty| matches!(ty, Type::Var(_) | Type::Error));
+        if unknown(from) || unknown(to) || from == to {
+            return true;
+        }
+        let int_like = |ty: &Type| ty.is_integer() || matches!(ty, Type::Prim(PrimitiveType::Bool | PrimitiveType::Char));
+        match (from.without_nodrop(), to.without_nodrop()) {
+            (from, to) if from.is_numeric() && to.is_numeric() => true,
+            (from, to) if int_like(from) && to.is_integer() => true,
+            (Type::Prim(PrimitiveType::UInt8), Type::Prim(PrimitiveType::Char)) => true,
+            // Pointers can be made from references and other pointers, and go back to
+            // references (which is unsafe): &t as *T, p as *u8, self.ptr as &T
+            (Type::Ref(_) | Type::Ptr(_), Type::Ptr(_)) | (Type::Ptr(_), Type::Ref(_)) => true,
+            (Type::Ptr(_), to) | (to, Type::Ptr(_)) if to.is_integer() => true,
+            (from, to) => from == to
+        }
+    }
+
+    /// Desugars a cast the language doesn't know to Cast::as, if FROM implements
+    /// Cast<TO>. Returns whether it does
+    fn cast_fun(&mut self, from: &Type, to: &Type, span: Span) -> bool {
+        let cx = self.cx;
+        let (interface, method) = match cx.operator("as", self.owner) {
+            Some(found) => found,
+            None => return false
+        };
+        for bound in self.bounds.clone() {
+            if let (false, true, InterfaceRef::Declared(id, args)) = (bound.negative, bound.ty == *from, &bound.interface) {
+                if *id == interface && args.len() == 1 && args[0] == *to {
+                    if let Some((fun, subst)) = self.interface_fun(interface, args, from, method) {
+                        self.instantiate(fun, subst, &[], span);
+                        return true;
+                    }
+                }
+            }
+        }
+        for info in &cx.impls {
+            let args = match &info.interface {
+                Some(InterfaceRef::Declared(id, args)) if *id == interface && args.len() == 1 => args,
+                _ => continue
+            };
+            let snapshot = self.vars.clone();
+            let subst: Substitution = (0..info.generics).map(|index| ((info.id, index), self.fresh())).collect();
+            if self.unify(&info.target.substitute(&subst), from) && self.unify(&args[0].substitute(&subst), to) {
+                if let Some(fun) = info.funs.get(method) {
+                    self.instantiate(*fun, subst, &[], span);
+                    return true;
+                }
+            }
+            self.vars = snapshot;
+        }
+        false
+    }
+
+    fn check_block(&mut self, block: &Block, expected: Option<&Type>) -> Type {
+        let tail = block.tail().map(|tail| tail as *const Expr);
+        let mut diverges = false;
+        for stmt in &block.stmts {
+            if let StmtKind::Expr(expr) = &stmt.kind {
+                if Some(expr as *const Expr) == tail {
+                    return self.check_expr(expr, expected);
+                }
+            }
+            diverges |= self.check_stmt(stmt);
+        }
+        if diverges {
+            return Type::Never;
+        }
+        if let Some(expected) = expected {
+            if !self.unify(&Type::unit(), expected) {
+                let kind = TypeErrKind::Mismatch { expected: self.describe(expected), found: Type::unit() };
+                self.error(kind, block.span);
+                return Type::Error;
+            }
+        }
+        Type::unit()
+    }
+
+    /// Checks STMT, giving whether the code after it is never reached
+    fn check_stmt(&mut self, stmt: &Stmt) -> bool {
+        match &stmt.kind {
+            StmtKind::Let(binding) => self.check_let(binding),
+            StmtKind::Defer(expr) => { self.check_expr(expr, None); }
+            StmtKind::Return(value) => {
+                let ret = self.ret.clone();
+                match value {
+                    Some(value) => { self.check_expr(value, Some(&ret)); }
+                    None => {
+                        if !self.unify(&Type::unit(), &ret) {
+                            let kind = TypeErrKind::Mismatch { expected: self.describe(&ret), found: Type::unit() };
+                            self.error(kind, stmt.span);
+                        }
+                    }
+                }
+                return true;
+            }
+            StmtKind::While(cond, body) => {
+                self.check_expr(cond, Some(&Type::bool()));
+                self.loops += 1;
+                self.check_block(body, Some(&Type::unit()));
+                self.loops -= 1;
+            }
+            StmtKind::For(pattern, iter, body) => {
+                let iter_ty = self.check_expr(iter, None);
+                let elem = match self.shallow(&iter_ty) {
+                    Type::Array(elem, _) => *elem,
+                    Type::Ref(inner) => match self.shallow(&inner) {
+                        Type::Array(elem, _) => Type::Ref(elem),
+                        _ => self.not_iterable(&iter_ty, iter.span)
+                    },
+                    Type::Error => Type::Error,
+                    _ => self.not_iterable(&iter_ty, iter.span)
+                };
+                self.bind_pattern(pattern, elem);
+                self.loops += 1;
+                self.check_block(body, Some(&Type::unit()));
+                self.loops -= 1;
+            }
+            StmtKind::Break | StmtKind::Continue => {
+                if self.loops == 0 {
+                    let what = if let StmtKind::Break = stmt.kind { "break" } else { "continue" };
+                    self.error(TypeErrKind::OutsideLoop(what), stmt.span);
+                }
+                return true;
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => {
+                let ty = self.check_expr(expr, None);
+                return self.shallow(&ty) == Type::Never;
+            }
+        }
+        false
+    }
+
+    fn not_iterable(&mut self, ty: &Type, span: Span) -> Type {
+        let ty = self.describe(ty);
+        self.error(TypeErrKind::NotIterable(ty), span);
+        Type::Error
+    }
+
+    fn check_let(&mut self, binding: &LetStmt) {
+        let declared = binding.ty.as_ref().map(|ty| self.lower(ty, binding.pattern.span));
+        let ty = match (&binding.init, declared) {
+            (Some(init), Some(declared)) => {
+                self.check_expr(init, Some(&declared));
+                declared
+            }
+            (Some(init), None) => self.check_expr(init, None),
+            (None, Some(declared)) => declared,
+            (None, None) => self.fresh()
+        };
+        let bound = self.locals.len();
+        self.bind_pattern(&binding.pattern, ty);
+        if binding.kind == BindingKind::Val {
+            for (id, name, span) in &self.locals[bound..] {
+                self.vals.insert(*id, (name.clone(), *span));
+            }
+        }
+    }
+
+    /// Gives the bindings of PATTERN their types, for a value of type TY
+    fn bind_pattern(&mut self, pattern: &Pattern, ty: Type) {
+        match &pattern.kind {
+            PatternKind::Binding(name) => return self.record_local(pattern.id, name, ty, pattern.span),
+            PatternKind::Wildcard => {}
+            PatternKind::Tuple(patterns) => {
+                let elems: Vec<Type> = match self.shallow(&ty) {
+                    Type::Tuple(elems) if elems.len() == patterns.len() => elems,
+                    Type::Error => vec![Type::Error; patterns.len()],
+                    _ => {
+                        let elems: Vec<Type> = patterns.iter().map(|_| self.fresh()).collect();
+                        self.expect(&Type::Tuple(elems.clone()), &ty, pattern.span);
+                        elems
+                    }
+                };
+                for (pattern, elem) in patterns.iter().zip(elems) {
+                    self.bind_pattern(pattern, elem);
+                }
+            }
+            PatternKind::Pack(name, fields) => {
+                let pack_ty = match self.cx.resolutions.ty_name(self.owner, name) {
+                    Some(Res::Item(id)) if self.cx.packs.contains(id) => {
+                        let args = (0..self.cx.packs.get(id).map_or(0, |pack| pack.generics.len())).map(|_| self.fresh()).collect();
+                        Type::Pack { id, name: name.clone(), args }
+                    }
+                    Some(Res::SelfTy(container)) => self.cx.self_types.get(container).cloned().unwrap_or(Type::Error),
+                    _ => Type::Error
+                };
+                self.expect(&pack_ty, &ty, pattern.span);
+                for (field, pattern) in fields {
+                    let field_ty = self.field_of(&pack_ty, field, pattern.span);
+                    self.bind_pattern(pattern, field_ty);
+                }
+            }
+        }
+        self.results.local_types.insert(pattern.id, ty);
+    }
+
+    /// The type of FIELD in a value of type TY, which may be behind references and pointers
+    fn field_of(&mut self, ty: &Type, field: &str, span: Span) -> Type {
+        let mut current = self.shallow(ty);
+        while let Type::Ref(inner) | Type::Ptr(inner) = current {
+            current = self.shallow(&inner);
+        }
+        let found = match &current {
+            Type::Error => return Type::Error,
+            Type::Pack { id, args, .. } => self.cx.packs.get(*id).and_then(|pack| {
+                let subst: Substitution = (0..pack.generics.len()).map(|index| (*id, index)).zip(args.iter().cloned()).collect();
+                pack.fields.iter().find(|(name, _)| name == field).map(|(_, ty)| ty.substitute(&subst))
+            }),
+            Type::Tuple(elems) => field.parse::<usize>().ok().and_then(|index| elems.get(index).cloned()),
+            Type::Param { .. } => self.interface_field(&current, field),
+            _ => None
+        };
+        found.unwrap_or_else(|| {
+            let ty = self.describe(&current);
+            self.error(TypeErrKind::NoField { ty, field: field.to_string() }, span);
+            Type::Error
+        })
+    }
+
+    /// A field which one of the interfaces bounding the parameter TY requires
+    fn interface_field(&self, ty: &Type, field: &str) -> Option<Type> {
+        for bound in self.bounds.iter().filter(|bound| !bound.negative && bound.ty == *ty) {
+            if let InterfaceRef::Declared(id, args) = &bound.interface {
+                let info = self.cx.interfaces.get(*id)?;
+                let mut subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((*id, index), arg)).collect();
+                subst.insert((*id, info.generics), ty.clone());
+                if let Some((_, field_ty)) = info.fields.iter().find(|(name, _)| name == field) {
+                    return Some(field_ty.substitute(&subst));
+                }
+            }
+        }
+        None
+    }
+
+    /// Checks EXPR, against EXPECTED if its context expects a type. Gives the type of
+    /// the value the context gets, which is Error if it doesn't fit, so that the mismatch
+    /// is only reported once
+    fn check_expr(&mut self, expr: &Expr, expected: Option<&Type>) -> Type {
+        let ty = self.infer_expr(expr, expected);
+        self.results.expr_types.insert(expr.id, ty.clone());
+        self.exprs.push(expr.id);
+        match expected {
+            Some(expected) => self.coerce(expr, &ty, expected),
+            None => ty
+        }
+    }
+
+    /// Makes the value of EXPR, of type TY, fit EXPECTED: as it is, or converted by the
+    /// implicit function in scope which takes a TY and gives an EXPECTED
+    fn coerce(&mut self, expr: &Expr, ty: &Type, expected: &Type) -> Type {
+        let snapshot = self.vars.clone();
+        if self.unify(ty, expected) {
+            return ty.clone();
+        }
+        self.vars = snapshot;
+        let conversions: Vec<ImplicitFun> = self.implicits_in_scope().filter(|implicit| self.cx.funs.get(implicit.id).is_some_and(|sig| sig.params.len() == 1)).collect();
+        let fitting: Vec<ImplicitFun> = conversions.into_iter().filter(|implicit| self.try_implicit(implicit.id, std::slice::from_ref(ty), expected, expr.span)).collect();
+        match &fitting[..] {
+            [] => {
+                self.expect(ty, expected, expr.span);
+                Type::Error
+            }
+            [conversion] => {
+                self.apply_implicit(conversion.id, std::slice::from_ref(ty), expected, expr.span);
+                self.results.conversions.insert(expr.id, conversion.id);
+                expected.clone()
+            }
+            _ => {
+                let from = self.describe(ty);
+                let what = format!("a conversion from {} to {}", from, self.describe(expected));
+                let candidates = fitting.iter().map(|implicit| (implicit.name.clone(), implicit.span)).collect();
+                let explanations = fitting.iter().filter_map(|implicit| self.cx.explain_implicit(implicit.id, &from)).collect();
+                self.error(TypeErrKind::AmbiguousImplicit { what, candidates, explanations }, expr.span);
+                Type::Error
+            }
+        }
+    }
+
+    /// The implicit functions which can be used here: those whose name resolves to them,
+    /// apart from the function being checked
+    fn implicits_in_scope(&self) -> impl Iterator<Item = ImplicitFun> + '_ {
+        self.cx.implicit_funs.iter().filter(move |implicit| {
+            let path = [implicit.name.clone()];
+            implicit.id != self.owner && self.cx.tree.resolve_path(self.module, &path) == Ok((Def::Item(implicit.module, implicit.index), 1))
+        }).cloned()
+    }
+
+    /// Whether calling the implicit function FUN with ARGS gives a RET, without
+    /// inferring anything
+    fn try_implicit(&mut self, fun: NodeId, args: &[Type], ret: &Type, span: Span) -> bool {
+        let snapshot = self.vars.clone();
+        let instantiations = self.instantiations.len();
+        let fits = self.apply_implicit(fun, args, ret, span);
+        self.vars = snapshot;
+        self.instantiations.truncate(instantiations);
+        fits
+    }
+
+    fn apply_implicit(&mut self, fun: NodeId, args: &[Type], ret: &Type, span: Span) -> bool {
+        let (_, params, fun_ret) = self.instantiate(fun, Substitution::new(), &[], span);
+        self.unify_all(&params, args) && self.unify(&fun_ret, ret)
+    }
+
+    /// Finds the value of a left out implicit argument of type TY: an implicit parameter
+    /// of the function, or an implicit function without parameters
+    fn implicit_arg(&mut self, ty: &Type, span: Span) -> Option<ImplicitArg> {
+        let mut fitting = Vec::new();
+        for (id, name, param_span) in self.implicit_params.clone() {
+            let param_ty = self.results.local_types.get(id).cloned().unwrap_or(Type::Error);
+            let snapshot = self.vars.clone();
+            if self.unify(&param_ty, ty) {
+                fitting.push((ImplicitArg::Param(id), name, param_span));
+            }
+            self.vars = snapshot;
+        }
+        let providers: Vec<ImplicitFun> = self.implicits_in_scope().filter(|implicit| self.cx.funs.get(implicit.id).is_some_and(|sig| sig.params.is_empty())).collect();
+        for implicit in providers {
+            if self.try_implicit(implicit.id, &[], ty, span) {
+                fitting.push((ImplicitArg::Fun(implicit.id), implicit.name, implicit.span));
+            }
+        }
+        match &fitting[..] {
+            [] => {
+                let ty = self.describe(ty);
+                self.error(TypeErrKind::NoImplicit(ty), span);
+                None
+            }
+            [(arg, ..)] => {
+                match arg {
+                    ImplicitArg::Param(id) => {
+                        let param_ty = self.results.local_types.get(*id).cloned().unwrap_or(Type::Error);
+                        self.unify(&param_ty, ty);
+                    }
+                    ImplicitArg::Fun(fun) => { self.apply_implicit(*fun, &[], ty, span); }
+                }
+                Some(*arg)
+            }
+            _ => {
+                let what = format!("an implicit {}", self.describe(ty));
+                let candidates = fitting.into_iter().map(|(_, name, span)| (name, span)).collect();
+                self.error(TypeErrKind::AmbiguousImplicit { what, candidates, explanations: Vec::new() }, span);
+                None
+            }
+        }
+    }
+
+    fn infer_expr(&mut self, expr: &Expr, expected: Option<&Type>) -> Type {
+        let expected_shallow = expected.map(|ty| self.shallow(ty));
+        match &expr.kind {
+            ExprKind::Literal(literal) => match literal {
+                Literal::Int(n) => {
+                    self.int_literals.push((expr.id, *n, false, expr.span));
+                    match expected_shallow {
+                        Some(ty) if ty.is_integer() || ty.is_numeric() => ty,
+                        _ => self.fresh_int()
+                    }
+                }
+                Literal::Bool(_) => Type::bool(),
+                Literal::Char(_) => Type::Prim(PrimitiveType::Char),
+                Literal::Str(_) => match expected_shallow {
+                    Some(Type::Prim(PrimitiveType::RefStaticStr)) => Type::Prim(PrimitiveType::RefStaticStr),
+                    _ => Type::Ref(Box::new(Type::StrSlice))
+                },
+                Literal::Unit => Type::unit()
+            },
+            ExprKind::Path(path) => self.check_path(expr, path),
+            ExprKind::Call(callee, args) => {
+                let callee_ty = self.check_expr(callee, None);
+                match self.shallow(&callee_ty) {
+                    Type::Fun(params, ret) => {
+                        self.hint(&ret, expected);
+                        let fun = self.results.calls.get(callee.id).copied();
+                        self.check_call_args(expr, fun, &params, args);
+                        *ret
+                    }
+                    Type::Error => {
+                        args.iter().for_each(|arg| { self.check_expr(arg, None); });
+                        Type::Error
+                    }
+                    ty => {
+                        let ty = self.describe(&ty);
+                        self.error(TypeErrKind::NotCallable(ty), callee.span);
+                        Type::Error
+                    }
+                }
+            }
+            ExprKind::MethodCall(receiver, method, args) => self.check_method_call(expr, receiver, method, args, expected),
+            ExprKind::Field(base, field) => {
+                let base_ty = self.check_expr(base, None);
+                self.field_of(&base_ty, field, expr.span)
+            }
+            ExprKind::Index(base, index) => {
+                let base_ty = self.check_expr(base, None);
+                let mut current = self.shallow(&base_ty);
+                while let Type::Ref(inner) | Type::Ptr(inner) = current {
+                    current = self.shallow(&inner);
+                }
+                match current {
+                    Type::Array(elem, _) => {
+                        let int = self.fresh_int();
+                        self.check_expr(index, Some(&int));
+                        *elem
+                    }
+                    Type::Error => {
+                        self.check_expr(index, None);
+                        Type::Error
+                    }
+                    current => match self.operator_fun("[]", &current, expr.span) {
+                        Some((params, ret)) => {
+                            self.check_args(&params, std::slice::from_ref(index), expr.span);
+                            ret
+                        }
+                        None => {
+                            let ty = self.describe(&current);
+                            self.error(TypeErrKind::NotIndexable(ty), base.span);
+                            Type::Error
+                        }
+                    }
+                }
+            }
+            ExprKind::PackLit(_, fields) => self.check_pack_lit(expr, fields),
+            ExprKind::Tuple(elems) => {
+                let expected: Vec<Option<Type>> = match expected_shallow {
+                    Some(Type::Tuple(tys)) if tys.len() == elems.len() => tys.into_iter().map(Some).collect(),
+                    _ => vec![None; elems.len()]
+                };
+                Type::Tuple(elems.iter().zip(expected).map(|(elem, expected)| self.check_expr(elem, expected.as_ref())).collect())
+            }
+            ExprKind::Array(elems) => {
+                let elem_ty = match expected_shallow {
+                    Some(Type::Array(elem, _)) => *elem,
+                    _ => self.fresh()
+                };
+                for elem in elems {
+                    self.check_expr(elem, Some(&elem_ty));
+                }
+                Type::Array(Box::new(elem_ty), elems.len() as u64)
+            }
+            ExprKind::Unary(op, operand) => self.check_unary(*op, operand, expected_shallow.as_ref(), expr.span),
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs_expected = if ARITHMETIC.contains(&op.as_str()) { expected } else { None };
+                let lhs_ty = self.check_expr(lhs, lhs_expected);
+                self.check_binary(op, &lhs_ty, rhs, expr.span)
+            }
+            ExprKind::Cast(operand, ty) => {
+                let from = self.check_expr(operand, None);
+                let to = self.lower(ty, expr.span);
+                self.casts.push((from, to.clone(), expr.span));
+                to
+            }
+            ExprKind::Assign(place, value) => {
+                let place_ty = self.check_place(place);
+                self.check_expr(value, Some(&place_ty));
+                Type::unit()
+            }
+            ExprKind::AssignOp(op, place, value) => {
+                let place_ty = self.check_place(place);
+                let result = self.check_binary(op, &place_ty, value, expr.span);
+                self.expect(&result, &place_ty, expr.span);
+                Type::unit()
+            }
+            ExprKind::Block(block) | ExprKind::Unsafe(block) => self.check_block(block, expected),
+            ExprKind::If(cond, then, otherwise) => {
+                self.check_expr(cond, Some(&Type::bool()));
+                match otherwise {
+                    Some(otherwise) => {
+                        let then_ty = self.check_block(then, expected);
+                        let then_diverges = self.shallow(&then_ty) == Type::Never;
+                        let expected = if then_diverges || then_ty == Type::Error { expected.cloned() } else { Some(then_ty.clone()) };
+                        let otherwise_ty = self.check_expr(otherwise, expected.as_ref());
+                        if then_diverges { otherwise_ty } else { then_ty }
+                    }
+                    None => {
+                        self.check_block(then, Some(&Type::unit()));
+                        Type::unit()
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gives the generic arguments of a call what its context expects of the result,
+    /// if it fits, so that the arguments are checked against them
+    fn hint(&mut self, ret: &Type, expected: Option<&Type>) {
+        if let Some(expected) = expected {
+            let snapshot = self.vars.clone();
+            if !self.unify(ret, expected) {
+                self.vars = snapshot;
+            }
+        }
+    }
+
+    /// Checks the arguments of a call to FUN (if it is known), whose implicit arguments
+    /// may all be left out
+    fn check_call_args(&mut self, call: &Expr, fun: Option<NodeId>, params: &[Type], args: &[Expr]) {
+        let implicit = fun.and_then(|fun| self.cx.funs.get(fun)).map_or(0, |sig| sig.implicit_params);
+        let explicit = params.len().saturating_sub(implicit);
+        if implicit == 0 || args.len() != explicit {
+            return self.check_args(params, args, call.span);
+        }
+        self.check_args(&params[..explicit], args, call.span);
+        let filled = params[explicit..].iter().filter_map(|param| self.implicit_arg(param, call.span)).collect();
+        self.results.implicit_args.insert(call.id, filled);
+    }
+
+    fn check_args(&mut self, params: &[Type], args: &[Expr], span: Span) {
+        if params.len() != args.len() {
+            self.error(TypeErrKind::ArgCount { expected: params.len(), found: args.len() }, span);
+        }
+        for (index, arg) in args.iter().enumerate() {
+            self.check_expr(arg, params.get(index));
+        }
+    }
+
+    /// Checks an expression which is assigned to, giving its type
+    fn check_place(&mut self, place: &Expr) -> Type {
+        let ty = self.check_expr(place, None);
+        match &place.kind {
+            ExprKind::Path(_) | ExprKind::Field(..) | ExprKind::Index(..) | ExprKind::Unary(UnaryOp::Deref, _) => {
+                if let Some((name, declared)) = self.assigned_local(place).and_then(|local| self.vals.get(local)).cloned() {
+                    self.error(TypeErrKind::AssignToVal { name, declared }, place.span);
+                }
+                ty
+            }
+            _ => {
+                self.error(TypeErrKind::NotAPlace, place.span);
+                Type::Error
+            }
+        }
+    }
+
+    /// The local whose own memory PLACE is: the local itself, or one of its fields or
+    /// elements. Going through a reference or a pointer leaves the local
+    fn assigned_local(&mut self, place: &Expr) -> Option<NodeId> {
+        match &place.kind {
+            ExprKind::Path(_) => match self.cx.resolutions.path(place.id) {
+                Some(partial) if partial.unresolved == 0 => match partial.res {
+                    Res::Local(id) => Some(id),
+                    _ => None
+                },
+                _ => None
+            },
+            ExprKind::Field(base, _) | ExprKind::Index(base, _) => {
+                let base_ty = self.results.expr_types.get(base.id).cloned()?;
+                match self.shallow(&base_ty) {
+                    Type::Ref(_) | Type::Ptr(_) => None,
+                    _ => self.assigned_local(base)
+                }
+            }
+            _ => None
+        }
+    }
+
+    fn check_unary(&mut self, op: UnaryOp, operand: &Expr, expected: Option<&Type>, span: Span) -> Type {
+        match op {
+            UnaryOp::Ref => {
+                let inner = match expected {
+                    Some(Type::Ref(inner)) => Some(*inner.clone()),
+                    _ => None
+                };
+                Type::Ref(Box::new(self.check_expr(operand, inner.as_ref())))
+            }
+            UnaryOp::Deref => {
+                let ty = self.check_expr(operand, None);
+                match self.shallow(&ty) {
+                    Type::Ref(inner) | Type::Ptr(inner) => *inner,
+                    Type::Error => Type::Error,
+                    Type::Var(_) => {
+                        self.error(TypeErrKind::AnnotationsNeeded(None), operand.span);
+                        Type::Error
+                    }
+                    ty => {
+                        let ty = self.describe(&ty);
+                        self.error(TypeErrKind::CantDeref(ty), span);
+                        Type::Error
+                    }
+                }
+            }
+            UnaryOp::Neg | UnaryOp::Not => {
+                let ty = self.check_expr(operand, expected.filter(|ty| ty.is_numeric() || **ty == Type::bool()));
+                // -128 fits in an i8 although 128 doesn't
+                if let (UnaryOp::Neg, ExprKind::Literal(Literal::Int(_)), Some(literal)) = (op, &operand.kind, self.int_literals.last_mut()) {
+                    literal.2 = true;
+                    literal.3 = span;
+                }
+                let fits = match self.shallow(&ty) {
+                    Type::Var(var) => self.vars[var as usize].integral,
+                    Type::Error => true,
+                    ty if op == UnaryOp::Neg => ty.is_numeric() && !matches!(ty, Type::Prim(prim) if prim.int_bits().is_some() && !prim.is_signed()),
+                    ty => ty.is_integer() || ty == Type::bool()
+                };
+                if !fits {
+                    let symbol = if op == UnaryOp::Neg { "-" } else { "!" };
+                    let ty = self.describe(&ty);
+                    self.error(TypeErrKind::InvalidOperand { op: symbol.to_string(), ty }, span);
+                    return Type::Error;
+                }
+                ty
+            }
+        }
+    }
+
+    /// The type of LHS OP RHS, where LHS has the type LHS_TY
+    fn check_binary(&mut self, op: &str, lhs_ty: &Type, rhs: &Expr, span: Span) -> Type {
+        let lhs = self.shallow(lhs_ty);
+        let builtin_operand = |cx: &Self, ty: &Type| match ty {
+            Type::Var(var) => cx.vars[*var as usize].integral,
+            Type::Error | Type::Never => true,
+            ty => ty.is_numeric()
+        };
+        if op == "&&" || op == "||" {
+            self.expect(&lhs, &Type::bool(), span);
+            self.check_expr(rhs, Some(&Type::bool()));
+            return Type::bool();
+        }
+        if ARITHMETIC.contains(&op) && builtin_operand(self, &lhs) {
+            // The amount of a shift can be any integer
+            let rhs_ty = if op == "<<" || op == ">>" { self.fresh_int() } else { lhs.clone() };
+            self.check_expr(rhs, Some(&rhs_ty));
+            return lhs;
+        }
+        let comparable = |ty: &Type| builtin_operand(self, ty) || matches!(ty, Type::Prim(_) | Type::Ptr(_) | Type::Var(_));
+        if COMPARISONS.contains(&op) && comparable(&lhs) {
+            self.check_expr(rhs, Some(&lhs));
+            return Type::bool();
+        }
+        match self.operator_fun(op, &lhs, span) {
+            Some((params, ret)) => {
+                self.check_args(&params, std::slice::from_ref(rhs), span);
+                ret
+            }
+            None => {
+                self.check_expr(rhs, None);
+                let ty = self.describe(&lhs);
+                self.error(TypeErrKind::InvalidOperand { op: op.to_string(), ty }, span);
+                Type::Error
+            }
+        }
+    }
+
+    /// The parameters and return type of the function associated with OP for TY
+    fn operator_fun(&mut self, op: &str, ty: &Type, span: Span) -> Option<(Vec<Type>, Type)> {
+        let (_, method) = self.cx.operator(op, self.owner)?;
+        let (fun, subst) = self.lookup_fun(ty, method, span)?;
+        let (_, params, ret) = self.instantiate(fun, subst, &[], span);
+        Some((params, ret))
+    }
+
+    fn check_method_call(&mut self, expr: &Expr, receiver: &Expr, method: &PathSegment, args: &[Expr], expected: Option<&Type>) -> Type {
+        let receiver_ty = self.check_expr(receiver, None);
+        let mut current = self.shallow(&receiver_ty);
+        let found = loop {
+            match current {
+                Type::Error => break None,
+                // The methods of an integer literal are those of its default type
+                Type::Var(var) if self.vars[var as usize].integral => {
+                    current = Type::Prim(DEFAULT_INT);
+                    self.unify(&receiver_ty, &current);
+                }
+                Type::Var(_) => {
+                    self.error(TypeErrKind::AnnotationsNeeded(None), receiver.span);
+                    break None;
+                }
+                _ => {}
+            }
+            if let Some(found) = self.lookup_fun(&current, &method.name, expr.span) {
+                break Some(found);
+            }
+            // Methods are looked up through references and pointers too
+            match current {
+                Type::Ref(inner) | Type::Ptr(inner) => current = self.shallow(&inner),
+                ty => {
+                    let ty = self.describe(&ty);
+                    self.error(TypeErrKind::NoFun { ty, name: method.name.clone() }, expr.span);
+                    break None;
+                }
+            }
+        };
+        match found {
+            Some((fun, subst)) => {
+                self.results.calls.insert(expr.id, fun);
+                let (_, params, ret) = self.instantiate(fun, subst, &method.generics, expr.span);
+                self.hint(&ret, expected);
+                self.check_call_args(expr, Some(fun), &params, args);
+                ret
+            }
+            None => {
+                args.iter().for_each(|arg| { self.check_expr(arg, None); });
+                Type::Error
+            }
+        }
+    }
+
+    /// Finds the function NAME of the type TY: in the impls and defs whose target TY
+    /// matches, or in the interfaces bounding TY if it is a generic parameter. Gives
+    /// the function with what the generic parameters of its impls, def or interface are
+    fn lookup_fun(&mut self, ty: &Type, name: &str, span: Span) -> Option<(NodeId, Substitution)> {
+        let ty = self.resolve(ty);
+        if let Type::Param { .. } = ty {
+            for bound in self.bounds.clone() {
+                if let (false, true, InterfaceRef::Declared(interface, args)) = (bound.negative, bound.ty == ty, &bound.interface) {
+                    if let Some(found) = self.interface_fun(*interface, args, &ty, name) {
+                        return Some(found);
+                    }
+                }
+            }
+        }
+        for info in self.cx.impls.iter().filter(|info| !info.implicit) {
+            let snapshot = self.vars.clone();
+            let subst: Substitution = (0..info.generics).map(|index| ((info.id, index), self.fresh())).collect();
+            if self.unify(&info.target.substitute(&subst), &ty) {
+                if let Some(fun) = info.funs.get(name) {
+                    return Some((*fun, subst));
+                }
+                // The functions the interface provides
+                if let Some(InterfaceRef::Declared(interface, args)) = &info.interface {
+                    let args: Vec<Type> = args.iter().map(|arg| arg.substitute(&subst)).collect();
+                    if let Some(found) = self.interface_fun(*interface, &args, &ty, name) {
+                        return Some(found);
+                    }
+                }
+            }
+            self.vars = snapshot;
+        }
+        self.lookup_extension(&ty, name, span)
+    }
+
+    /// Finds the extension method NAME of TY, in the implicit impls in scope. Reports
+    /// the ambiguity if several supply it, going on with the first one
+    fn lookup_extension(&mut self, ty: &Type, name: &str, span: Span) -> Option<(NodeId, Substitution)> {
+        let cx = self.cx;
+        let mut fitting = Vec::new();
+        let in_scope: Vec<&ImplInfo> = cx.impls.iter().filter(|info| info.implicit && info.funs.contains_key(name) && self.extension_in_scope(info)).collect();
+        for info in in_scope {
+            let snapshot = self.vars.clone();
+            let subst: Substitution = (0..info.generics).map(|index| ((info.id, index), self.fresh())).collect();
+            if self.unify(&info.target.substitute(&subst), ty) {
+                fitting.push(info);
+            }
+            self.vars = snapshot;
+        }
+        if fitting.len() > 1 {
+            let what = format!("the method {} of {}", name, self.describe(ty));
+            let candidates = fitting.iter().map(|info| (format!("implicit impls {}", info.target), info.span)).collect();
+            self.error(TypeErrKind::AmbiguousImplicit { what, candidates, explanations: Vec::new() }, span);
+        }
+        let info = fitting.first()?;
+        let subst: Substitution = (0..info.generics).map(|index| ((info.id, index), self.fresh())).collect();
+        self.unify(&info.target.substitute(&subst), ty);
+        Some((info.funs[name], subst))
+    }
+
+    /// Whether the implicit impls INFO is in scope: in the module declaring it (and
+    /// those inside it) and where that module is imported with a glob
+    fn extension_in_scope(&self, info: &ImplInfo) -> bool {
+        let tree = self.cx.tree;
+        tree.is_inside(self.module, info.module) || tree.module(self.module).imports.iter().any(|import| {
+            import.name.is_none() && tree.resolve_import(self.module, &import.path) == Ok(Def::Module(info.module))
+        })
+    }
+
+    /// The function NAME of the interface (or of the ones it extends), for SELF_TY
+    fn interface_fun(&self, interface: NodeId, args: &[Type], self_ty: &Type, name: &str) -> Option<(NodeId, Substitution)> {
+        let info = self.cx.interfaces.get(interface)?;
+        let mut subst: Substitution = args.iter().cloned().enumerate().map(|(index, arg)| ((interface, index), arg)).collect();
+        subst.insert((interface, info.generics), self_ty.clone());
+        if let Some(fun) = info.funs.get(name) {
+            return Some((*fun, subst));
+        }
+        info.supers.iter().find_map(|sup| match sup {
+            InterfaceRef::Declared(sup, sup_args) => {
+                let sup_args: Vec<Type> = sup_args.iter().map(|arg| arg.substitute(&subst)).collect();
+                self.interface_fun(*sup, &sup_args, self_ty, name)
+            }
+            InterfaceRef::Builtin(_) => None
+        })
+    }
+
+    /// The receiver, parameters and return type of the function FUN, whose container's
+    /// parameters are SUBST and whose own ones are GENERICS (or inferred if none are given)
+    fn instantiate(&mut self, fun: NodeId, mut subst: Substitution, generics: &[Ty], span: Span) -> (Option<Type>, Vec<Type>, Type) {
+        let sig = match self.cx.funs.get(fun) {
+            Some(sig) => sig.clone(),
+            None => return (None, Vec::new(), Type::Error)
+        };
+        if !generics.is_empty() && generics.len() != sig.generics {
+            self.error(TypeErrKind::GenericCount { name: sig.name.clone(), expected: sig.generics, found: generics.len() }, span);
+        }
+        for index in 0..sig.generics {
+            let ty = match generics.get(index) {
+                Some(ty) if generics.len() == sig.generics => self.lower(ty, span),
+                _ => self.fresh()
+            };
+            subst.insert((fun, index), ty);
+        }
+        self.instantiations.push((fun, subst.clone(), span));
+        let params = sig.params.iter().map(|param| param.substitute(&subst)).collect();
+        (sig.receiver.map(|receiver| receiver.substitute(&subst)), params, sig.ret.substitute(&subst))
+    }
+
+    fn check_path(&mut self, expr: &Expr, path: &Path) -> Type {
+        let partial = match self.cx.resolutions.path(expr.id) {
+            Some(partial) => partial,
+            None => return Type::Error
+        };
+        let last = &path.segments[path.segments.len() - 1];
+        match (partial.res, partial.unresolved) {
+            (Res::Local(id), 0) => self.results.local_types.get(id).cloned().unwrap_or(Type::Error),
+            (Res::SelfValue(_), 0) => self.self_value.clone().unwrap_or(Type::Error),
+            (Res::Item(id), 0) if self.cx.consts.contains(id) => self.cx.consts.get(id).cloned().unwrap_or(Type::Error),
+            (Res::Item(id), 0) => self.fun_value(expr, id, Substitution::new(), &last.generics),
+            (Res::Builtin(builtin), 0) => self.builtin(expr.id, builtin, &last.generics, expr.span),
+            (res, 1) => {
+                let first = &path.segments[0];
+                let ty = match res {
+                    Res::Item(id) if self.cx.packs.contains(id) => self.pack_type(id, &first.generics, expr.span),
+                    Res::Item(id) if self.cx.interfaces.contains(id) => {
+                        // Interface::fun, for whichever type implements it
+                        let self_ty = self.fresh();
+                        let args: Vec<Type> = (0..self.cx.interfaces.get(id).map_or(0, |info| info.generics)).map(|_| self.fresh()).collect();
+                        return match self.interface_fun(id, &args, &self_ty, &last.name) {
+                            Some((fun, subst)) => self.fun_value(expr, fun, subst, &last.generics),
+                            None => self.no_fun(self_ty, &last.name, expr.span)
+                        };
+                    }
+                    Res::SelfTy(container) => self.cx.self_types.get(container).cloned().unwrap_or(Type::Error),
+                    Res::Generic { owner, index } => Type::Param { owner, index, name: first.name.clone() },
+                    Res::Builtin(Builtin::StrSlice) => Type::StrSlice,
+                    _ => return Type::Error
+                };
+                match self.lookup_fun(&ty, &last.name, expr.span) {
+                    Some((fun, subst)) => self.fun_value(expr, fun, subst, &last.generics),
+                    None => self.no_fun(ty, &last.name, expr.span)
+                }
+            }
+            _ => {
+                let names: Vec<&str> = path.segments.iter().map(|segment| segment.name.as_str()).collect();
+                self.error(TypeErrKind::UnsupportedPath(names.join("::")), expr.span);
+                Type::Error
+            }
+        }
+    }
+
+    fn no_fun(&mut self, ty: Type, name: &str, span: Span) -> Type {
+        let ty = self.describe(&ty);
+        if ty != Type::Error {
+            self.error(TypeErrKind::NoFun { ty, name: name.to_string() }, span);
+        }
+        Type::Error
+    }
+
+    /// The type of a path to the function FUN, which can be one of a type: String::from.
+    /// The receiver of a method is its first parameter
+    fn fun_value(&mut self, expr: &Expr, fun: NodeId, subst: Substitution, generics: &[Ty]) -> Type {
+        self.results.calls.insert(expr.id, fun);
+        let (receiver, params, ret) = self.instantiate(fun, subst, generics, expr.span);
+        Type::Fun(receiver.into_iter().chain(params).collect(), Box::new(ret))
+    }
+
+    /// The pack ID with the generic arguments GENERICS, inferred if none are given
+    fn pack_type(&mut self, id: NodeId, generics: &[Ty], span: Span) -> Type {
+        let info = match self.cx.packs.get(id) {
+            Some(info) => info.clone(),
+            None => return Type::Error
+        };
+        if !generics.is_empty() && generics.len() != info.generics.len() {
+            self.error(TypeErrKind::GenericCount { name: info.name.clone(), expected: info.generics.len(), found: generics.len() }, span);
+        }
+        let args = (0..info.generics.len()).map(|index| match generics.get(index) {
+            Some(ty) if generics.len() == info.generics.len() => self.lower(ty, span),
+            _ => self.fresh()
+        }).collect();
+        Type::Pack { id, name: info.name, args }
+    }
+
+    /// The type of the path ID to a builtin function
+    fn builtin(&mut self, id: NodeId, builtin: Builtin, generics: &[Ty], span: Span) -> Type {
+        // transmute<T, U> turns a T into a U, the others take one type
+        let expected = if builtin == Builtin::Transmute { 2 } else { 1 };
+        let mut generics: Vec<Type> = if generics.len() == expected {
+            generics.iter().map(|ty| self.lower(ty, span)).collect()
+        } else if generics.is_empty() {
+            (0..expected).map(|_| self.fresh()).collect()
+        } else {
+            self.error(TypeErrKind::GenericCount { name: builtin.name().to_string(), expected, found: generics.len() }, span);
+            vec![Type::Error; expected]
+        };
+        let generic = generics.remove(0);
+        self.builtin_args.push((id, generic.clone()));
+        let u64 = Type::Prim(PrimitiveType::UInt64);
+        let (params, ret) = match builtin {
+            Builtin::Sizeof => (vec![], u64),
+            Builtin::Drop => (vec![generic], Type::unit()),
+            Builtin::Alloc => (vec![u64.clone(), u64], Type::Ptr(Box::new(Type::Prim(PrimitiveType::UInt8)))),
+            Builtin::Dealloc => (vec![Type::Ptr(Box::new(generic))], Type::unit()),
+            Builtin::Read => (vec![Type::Ptr(Box::new(generic.clone()))], generic),
+            Builtin::Write => (vec![Type::Ptr(Box::new(generic.clone())), generic], Type::unit()),
+            Builtin::Transmute => (vec![generic], generics.remove(0)),
+            Builtin::Copy | Builtin::Destroy | Builtin::StrSlice => return Type::Error
+        };
+        Type::Fun(params, Box::new(ret))
+    }
+
+    fn check_pack_lit(&mut self, expr: &Expr, fields: &[FieldInit]) -> Type {
+        let ty = match self.cx.resolutions.path(expr.id) {
+            Some(partial) if partial.unresolved == 0 => match partial.res {
+                Res::Item(id) => {
+                    let generics = match &expr.kind {
+                        ExprKind::PackLit(path, _) => path.segments[path.segments.len() - 1].generics.clone(),
+                        _ => Vec::new()
+                    };
+                    self.pack_type(id, &generics, expr.span)
+                }
+                Res::SelfTy(container) => self.cx.self_types.get(container).cloned().unwrap_or(Type::Error),
+                _ => Type::Error
+            },
+            _ => Type::Error
+        };
+        let (id, args) = match &ty {
+            Type::Pack { id, args, .. } => (*id, args.clone()),
+            _ => {
+                fields.iter().for_each(|field| { self.check_expr(&field.value, None); });
+                return Type::Error;
+            }
+        };
+        let info = match self.cx.packs.get(id) {
+            Some(info) => info.clone(),
+            None => return Type::Error
+        };
+        let subst: Substitution = (0..info.generics.len()).map(|index| (id, index)).zip(args).collect();
+        let mut given: Vec<&str> = Vec::new();
+        for field in fields {
+            if given.contains(&field.name.as_str()) {
+                self.error(TypeErrKind::DuplicateField(field.name.clone()), field.span);
+            }
+            given.push(&field.name);
+            match info.fields.iter().find(|(name, _)| *name == field.name) {
+                Some((_, field_ty)) => { self.check_expr(&field.value, Some(&field_ty.substitute(&subst))); }
+                None => {
+                    self.check_expr(&field.value, None);
+                    let ty = self.describe(&ty);
+                    self.error(TypeErrKind::NoField { ty, field: field.name.clone() }, field.span);
+                }
+            }
+        }
+        let missing: Vec<String> = info.fields.iter().filter(|(name, _)| !given.contains(&name.as_str())).map(|(name, _)| name.clone()).collect();
+        if !missing.is_empty() {
+            self.error(TypeErrKind::MissingFields { pack: info.name.clone(), fields: missing }, expr.span);
+        }
+        ty
+    }
+}
@@ -0,0 +1,147 @@
+//! The types the type checker works with. Unlike Ty, which is what the source code
+//! spells, a Type knows what its names refer to (a pack by the id of its declaration,
+//! a generic parameter by the item declaring it) and can hold inference variables.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::ast::ids::NodeId;
+use crate::ast::types::PrimitiveType;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Type {
+    Prim(PrimitiveType),
+    /// A pack, with its generic arguments: Box<i32>
+    Pack { id: NodeId, name: String, args: Vec<Type> },
+    /// The INDEX-th generic parameter of the item OWNER. Self in an interface is
+    /// the parameter after the declared ones
+    Param { owner: NodeId, index: usize, name: String },
+    StrSlice,
+    Ref(Box<Type>),
+    Ptr(Box<Type>),
+    NoDrop(Box<Type>),
+    Tuple(Vec<Type>),
+    Array(Box<Type>, u64),
+    Fun(Vec<Type>, Box<Type>),
+    /// The associated type NAME of the interface the base implements: T::Target. It
+    /// stays as it is while the base is a generic parameter, see TypeCx::normalize
+    Assoc(Box<Type>, String),
+    /// An inference variable, only found while a body is being checked
+    Var(u32),
+    /// The type of what never produces a value, like a block which returns
+    Never,
+    /// The type of what failed to check, which fits anywhere so errors don't pile up
+    Error
+}
+
+/// What the generic parameters of some items stand for
+pub(crate) type Substitution = HashMap<(NodeId, usize), Type>;
+
+impl Type {
+    pub fn unit() -> Self {
+        Type::Prim(PrimitiveType::Unit)
+    }
+
+    pub fn bool() -> Self {
+        Type::Prim(PrimitiveType::Bool)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Prim(prim) if prim.int_bits().is_some())
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Type::Prim(PrimitiveType::Float32 | PrimitiveType::Float64)) || self.is_integer()
+    }
+
+    /// The type without the - of -T, which changes how a value is dropped, not what it is
+    pub fn without_nodrop(&self) -> &Type {
+        match self {
+            Type::NoDrop(inner) => inner.without_nodrop(),
+            ty => ty
+        }
+    }
+
+    /// Replaces the generic parameters which SUBST gives a type to
+    pub fn substitute(&self, subst: &Substitution) -> Type {
+        self.map(&mut |ty| match ty {
+            Type::Param { owner, index, .. } => subst.get(&(*owner, *index)).cloned(),
+            _ => None
+        })
+    }
+
+    /// Rebuilds the type, replacing the parts for which F gives a replacement
+    pub fn map(&self, f: &mut impl FnMut(&Type) -> Option<Type>) -> Type {
+        if let Some(replaced) = f(self) {
+            return replaced;
+        }
+        match self {
+            Type::Pack { id, name, args } => Type::Pack { id: *id, name: name.clone(), args: args.iter().map(|arg| arg.map(f)).collect() },
+            Type::Ref(inner) => Type::Ref(Box::new(inner.map(f))),
+            Type::Ptr(inner) => Type::Ptr(Box::new(inner.map(f))),
+            Type::NoDrop(inner) => Type::NoDrop(Box::new(inner.map(f))),
+            Type::Tuple(elems) => Type::Tuple(elems.iter().map(|elem| elem.map(f)).collect()),
+            Type::Array(elem, len) => Type::Array(Box::new(elem.map(f)), *len),
+            Type::Fun(params, ret) => Type::Fun(params.iter().map(|param| param.map(f)).collect(), Box::new(ret.map(f))),
+            Type::Assoc(base, name) => Type::Assoc(Box::new(base.map(f)), name.clone()),
+            Type::Prim(_) | Type::Param { .. } | Type::StrSlice | Type::Var(_) | Type::Never | Type::Error => self.clone()
+        }
+    }
+
+    /// Whether F holds for the type or any type inside it
+    pub fn any(&self, f: &impl Fn(&Type) -> bool) -> bool {
+        if f(self) {
+            return true;
+        }
+        match self {
+            Type::Pack { args: elems, .. } | Type::Tuple(elems) => elems.iter().any(|elem| elem.any(f)),
+            Type::Ref(inner) | Type::Ptr(inner) | Type::NoDrop(inner) | Type::Array(inner, _) | Type::Assoc(inner, _) => inner.any(f),
+            Type::Fun(params, ret) => params.iter().any(|param| param.any(f)) || ret.any(f),
+            Type::Prim(_) | Type::Param { .. } | Type::StrSlice | Type::Var(_) | Type::Never | Type::Error => false
+        }
+    }
+}
+
+fn write_list(f: &mut Formatter<'_>, tys: &[Type]) -> fmt::Result {
+    for (i, ty) in tys.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", ty)?;
+    }
+    Ok(())
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Prim(prim) => write!(f, "{}", prim),
+            Type::Pack { name, args, .. } if args.is_empty() => f.write_str(name),
+            Type::Pack { name, args, .. } => {
+                write!(f, "{}<", name)?;
+                write_list(f, args)?;
+                f.write_str(">")
+            }
+            Type::Param { name, .. } => f.write_str(name),
+            Type::StrSlice => f.write_str("StrSlice"),
+            Type::Ref(inner) => write!(f, "&{}", inner),
+            Type::Ptr(inner) => write!(f, "*{}", inner),
+            Type::NoDrop(inner) => write!(f, "-{}", inner),
+            Type::Tuple(elems) => {
+                f.write_str("(")?;
+                write_list(f, elems)?;
+                if elems.len() == 1 { f.write_str(",)") } else { f.write_str(")") }
+            }
+            Type::Array(elem, len) => write!(f, "[{}; {}]", elem, len),
+            Type::Fun(params, ret) => {
+                f.write_str("fun(")?;
+                write_list(f, params)?;
+                write!(f, "): {}", ret)
+            }
+            Type::Assoc(base, name) => write!(f, "{}::{}", base, name),
+            Type::Var(_) => f.write_str("_"),
+            Type::Never => f.write_str("!"),
+            Type::Error => f.write_str("{error}")
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! The unsafety checker: the operations the compiler can't vouch for are only allowed
+//! in unsafe code, that is in an unsafe { } block or in the body of an unsafe fun. They
+//! are dereferencing a raw pointer (explicitly, or to reach a field or call a method),
+//! the casts which turn a reference into a pointer, a pointer into a reference or one
+//! pointer type into another, and calling an unsafe fun or one of the unsafe builtins
+//! (dealloc, ptr::read, ptr::write and transmute).
+//!
+//! An unsafe block which doesn't need to be one, because nothing in it is unsafe or
+//! because it is already inside unsafe code, gets a warning.
+
+use std::path::PathBuf;
+
+use crate::analysis::cfg;
+use crate::analysis::resolve::Res;
+use crate::analysis::typeck::{TypeCx, TypeckResults};
+use crate::analysis::types::Type;
+use crate::ast::expressions::{Expr, ExprKind, UnaryOp};
+use crate::ast::ids::NodeId;
+use crate::ast::visit::{self, Visitor};
+use crate::ast::Span;
+use crate::diagnostics::Diagnostic;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum UnsafeErrKind {
+    /// OPERATION is done outside unsafe code
+    Unsafe { operation: String },
+    /// An unsafe block with nothing unsafe in it, or inside the unsafe code at ENCLOSING
+    /// (an unsafe function if FUN)
+    Unnecessary { enclosing: Option<(Span, bool)> }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UnsafeErr {
+    pub kind: UnsafeErrKind,
+    pub file: PathBuf,
+    pub span: Span
+}
+
+impl UnsafeErr {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match &self.kind {
+            UnsafeErrKind::Unsafe { operation } => {
+                Diagnostic::error(&format!("{} is unsafe", operation), self.span, "needs an unsafe block")
+                    .with_note("the compiler can't check that this is sound: wrap it in unsafe { } to take that on")
+            }
+            UnsafeErrKind::Unnecessary { enclosing: None } => {
+                Diagnostic::warning("unnecessary unsafe block", self.span, "nothing in it is unsafe")
+            }
+            UnsafeErrKind::Unnecessary { enclosing: Some((enclosing, fun)) } => {
+                let label = if *fun { "inside this unsafe function" } else { "inside this unsafe block" };
+                Diagnostic::warning("unnecessary unsafe block", self.span, "already in unsafe code")
+                    .with_label(*enclosing, label)
+            }
+        }
+    }
+}
+
+/// Unsafe code: an unsafe block, or the body of an unsafe function
+struct Context {
+    span: Span,
+    fun: bool,
+    /// Whether something unsafe relies on it
+    used: bool
+}
+
+pub(crate) struct UnsafetyChecker<'a, 't> {
+    cx: &'a TypeCx<'t>,
+    results: &'a TypeckResults,
+    contexts: Vec<Context>,
+    file: PathBuf,
+    errs: Vec<UnsafeErr>
+}
+
+impl<'a, 't> UnsafetyChecker<'a, 't> {
+    pub fn check(cx: &'a TypeCx<'t>, results: &'a TypeckResults) -> Vec<UnsafeErr> {
+        let mut checker = UnsafetyChecker { cx, results, contexts: Vec::new(), file: PathBuf::new(), errs: Vec::new() };
+        for (fun, file) in cfg::bodies(cx.tree) {
+            checker.file = file.clone();
+            if fun.is_unsafe {
+                checker.contexts.push(Context { span: fun.span, fun: true, used: true });
+            }
+            if let Some(body) = &fun.body {
+                checker.visit_block(body);
+            }
+            checker.contexts.clear();
+        }
+        checker.errs
+    }
+
+    fn is_ptr(&self, id: NodeId) -> bool {
+        matches!(self.results.expr_types.get(id).map(Type::without_nodrop), Some(Type::Ptr(_)))
+    }
+
+    /// Whether reaching through the value of the expression ID, following references
+    /// and pointers as a field access does, goes through a pointer
+    fn through_ptr(&self, id: NodeId) -> bool {
+        let mut ty = self.results.expr_types.get(id).map(Type::without_nodrop);
+        while let Some(Type::Ref(inner) | Type::Ptr(inner)) = ty {
+            if matches!(ty, Some(Type::Ptr(_))) {
+                return true;
+            }
+            ty = Some(inner.without_nodrop());
+        }
+        false
+    }
+
+    /// Reports OPERATION at SPAN unless it is in unsafe code, which it then relies on
+    fn unsafe_operation(&mut self, operation: String, span: Span) {
+        // The outermost unsafe code is what allows it: those inside are unnecessary
+        match self.contexts.first_mut() {
+            Some(context) => context.used = true,
+            None => self.errs.push(UnsafeErr { kind: UnsafeErrKind::Unsafe { operation }, file: self.file.clone(), span })
+        }
+    }
+
+    /// The unsafe function the expression ID calls, if it does
+    fn unsafe_callee(&self, id: NodeId) -> Option<String> {
+        if let Some(Res::Builtin(builtin)) = self.cx.resolutions.path(id).map(|partial| partial.res) {
+            return if builtin.is_unsafe() { Some(builtin.name().to_string()) } else { None };
+        }
+        let sig = self.cx.funs.get(*self.results.calls.get(id)?)?;
+        if sig.is_unsafe { Some(sig.name.clone()) } else { None }
+    }
+}
+
+impl<'ast, 'a, 't> Visitor<'ast> for UnsafetyChecker<'a, 't> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match &expr.kind {
+            ExprKind::Unsafe(block) => {
+                let enclosing = self.contexts.last().map(|context| (context.span, context.fun));
+                self.contexts.push(Context { span: expr.span, fun: false, used: false });
+                self.visit_block(block);
+                let context = self.contexts.pop();
+                if enclosing.is_some() || context.is_some_and(|context| !context.used) {
+                    let kind = UnsafeErrKind::Unnecessary { enclosing };
+                    self.errs.push(UnsafeErr { kind, file: self.file.clone(), span: expr.span });
+                }
+                return;
+            }
+            ExprKind::Unary(UnaryOp::Deref, inner) if self.is_ptr(inner.id) => {
+                self.unsafe_operation("dereferencing a raw pointer".to_string(), expr.span);
+            }
+            ExprKind::Field(base, field) if self.through_ptr(base.id) => {
+                self.unsafe_operation(format!("reaching the field {} through a raw pointer", field), expr.span);
+            }
+            ExprKind::Call(callee, _) => {
+                if let Some(name) = self.unsafe_callee(callee.id) {
+                    self.unsafe_operation(format!("calling the unsafe function {}", name), expr.span);
+                }
+            }
+            ExprKind::MethodCall(receiver, method, _) => {
+                if let Some(name) = self.unsafe_callee(expr.id) {
+                    self.unsafe_operation(format!("calling the unsafe function {}", name), expr.span);
+                }
+                let by_ptr = self.results.calls.get(expr.id).and_then(|fun| self.cx.funs.get(*fun))
+                    .is_some_and(|sig| matches!(sig.receiver, Some(Type::Ptr(_))));
+                if !by_ptr && self.through_ptr(receiver.id) {
+                    self.unsafe_operation(format!("calling {} through a raw pointer", method.name), expr.span);
+                }
+            }
+            ExprKind::Cast(inner, _) => {
+                let from = self.results.expr_types.get(inner.id).map(Type::without_nodrop);
+                let to = self.results.expr_types.get(expr.id).map(Type::without_nodrop);
+                let operation = match (from, to) {
+                    (Some(Type::Ref(_)), Some(Type::Ptr(_))) => Some("casting a reference to a pointer"),
+                    (Some(Type::Ptr(_)), Some(Type::Ref(_))) => Some("casting a pointer to a reference"),
+                    (Some(Type::Ptr(from)), Some(Type::Ptr(to))) if from != to => Some("casting between pointer types"),
+                    _ => None
+                };
+                if let Some(operation) = operation {
+                    self.unsafe_operation(operation.to_string(), expr.span);
+                }
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,26 @@
+use super::Span;
+use super::ids::NodeId;
+
+/// Whether an attribute is about the thing after it or the whole file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AttrStyle {
+    Outer, // #feature(minus_attrs) before an item, a field or a statement
+    Inner // #![feature(minus_attrs)] at the start of the file
+}
+
+/// An attribute attached to the item, field or statement after it (or to the
+/// whole file if it is inner), for example #feature(minus_attrs) which enables
+/// the -T fields of a pack
+#[derive(Debug, Clone)]
+pub(crate) struct Attribute {
+    pub id: NodeId,
+    pub style: AttrStyle,
+    pub name: String,
+    pub args: Vec<String>,
+    pub span: Span
+}
+
+/// The attributes named NAME among ATTRS
+pub(crate) fn named<'a>(attrs: &'a [Attribute], name: &'a str) -> impl Iterator<Item = &'a Attribute> + 'a {
+    attrs.iter().filter(move |attr| attr.name == name)
+}
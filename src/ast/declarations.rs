@@ -0,0 +1,353 @@
+use super::Span;
+use super::ids::NodeId;
+use super::attributes::Attribute;
+use super::expressions::Expr;
+use super::statements::Block;
+use super::types::Ty;
+
+/// Who can see an item or a field. Everything is private unless marked public
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Visibility {
+    Private,
+    Public
+}
+
+/// An interface which a type has to implement (or, if negative, must not implement).
+/// For example the Copy in where T: Copy, or the !Copy in where T: !Copy
+#[derive(Debug, Clone)]
+pub(crate) struct Bound {
+    pub id: NodeId,
+    pub negative: bool,
+    pub interface: Ty,
+    pub span: Span
+}
+
+/// A generic parameter, such as the T in pack Box<T>, possibly with inline bounds: <T: Copy>
+#[derive(Debug, Clone)]
+pub(crate) struct GenericParam {
+    pub id: NodeId,
+    pub name: String,
+    pub bounds: Vec<Bound>,
+    pub span: Span
+}
+
+/// One predicate of a where clause: Ty: Bound + Bound ...
+#[derive(Debug, Clone)]
+pub(crate) struct WherePredicate {
+    pub id: NodeId,
+    pub ty: Ty,
+    pub bounds: Vec<Bound>,
+    pub span: Span
+}
+
+/// A field of a pack: [attrs] [public] name: Ty
+#[derive(Debug, Clone)]
+pub(crate) struct FieldDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub name: String,
+    pub ty: Ty,
+    pub span: Span
+}
+
+/// pack Name<Generics> { field: Ty, ... }
+/// Packs are the structs of gold: plain data, the methods come from impls blocks
+#[derive(Debug, Clone)]
+pub(crate) struct PackDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub name: String,
+    pub generics: Vec<GenericParam>,
+    pub fields: Vec<FieldDecl>,
+    pub span: Span
+}
+
+/// How a method takes the value it is called on: self, &self or *self
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Receiver {
+    Value,
+    Ref,
+    Ptr
+}
+
+/// A parameter of a function: name: Ty
+#[derive(Debug, Clone)]
+pub(crate) struct Param {
+    pub id: NodeId,
+    pub implicit: bool, // Left out by the callers and filled in from the implicits in scope
+    pub name: String,
+    pub ty: Ty,
+    pub span: Span
+}
+
+/// [attrs] [public] [implicit] [unsafe] fun name<Generics>([receiver,] params): Ret where Predicates { body }
+/// The return type defaults to () and the body is missing for bare signatures (fun f();).
+/// A body written = expr; is parsed as the block { expr }. An implicit function with one
+/// parameter is a conversion, one without parameters gives an implicit value. An unsafe
+/// function can only be called from unsafe code
+#[derive(Debug, Clone)]
+pub(crate) struct FunDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub implicit: bool,
+    pub is_unsafe: bool,
+    pub name: String,
+    pub generics: Vec<GenericParam>,
+    pub receiver: Option<Receiver>,
+    pub params: Vec<Param>,
+    pub ret: Option<Ty>,
+    pub where_clause: Vec<WherePredicate>,
+    pub body: Option<Block>,
+    pub span: Span
+}
+
+/// [implicit] impls Ty { fun ... }
+/// The inherent methods of a type. The draft leaves the generic parameters
+/// implicit (impls Box<T>), they can also be spelled out (impls<T> Box<T>).
+/// implicit impls add extension methods to a type declared anywhere, like i32,
+/// where they are in scope
+#[derive(Debug, Clone)]
+pub(crate) struct ImplsDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub implicit: bool,
+    pub generics: Vec<GenericParam>,
+    pub target: Ty,
+    pub where_clause: Vec<WherePredicate>,
+    pub funs: Vec<FunDecl>,
+    pub span: Span
+}
+
+/// type Name = Ty; inside a def, giving the associated type of the interface
+#[derive(Debug, Clone)]
+pub(crate) struct AssocTypeDef {
+    pub id: NodeId,
+    pub name: String,
+    pub ty: Ty,
+    pub span: Span
+}
+
+/// name: Ty; inside a def, supplying a field that the interface requires.
+/// By default the field of the implementing pack with the same name is used,
+/// name: Ty = other; maps it to the field other instead
+#[derive(Debug, Clone)]
+pub(crate) struct AssocFieldDef {
+    pub id: NodeId,
+    pub name: String,
+    pub ty: Ty,
+    pub source: Option<String>,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum DefMember {
+    AssocType(AssocTypeDef),
+    Field(AssocFieldDef),
+    Fun(FunDecl)
+}
+
+/// def Interface for Ty { members }
+/// The implementation of an interface for a type
+#[derive(Debug, Clone)]
+pub(crate) struct DefDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub generics: Vec<GenericParam>,
+    pub interface: Ty,
+    pub target: Ty,
+    pub where_clause: Vec<WherePredicate>,
+    pub members: Vec<DefMember>,
+    pub span: Span
+}
+
+/// type Name: Bounds; inside an interface, an associated type that every def has to give
+#[derive(Debug, Clone)]
+pub(crate) struct AssocTypeDecl {
+    pub id: NodeId,
+    pub name: String,
+    pub bounds: Vec<Bound>,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum InterfaceMember {
+    AssocType(AssocTypeDecl),
+    Field(FieldDecl), // name: Ty; a field every implementor must have
+    Fun(FunDecl) // A required function, or a provided one if it has a body
+}
+
+/// interface Name<Generics>: Supers { members }
+/// Unlike Rust traits, interfaces can also require fields, not just functions and types
+#[derive(Debug, Clone)]
+pub(crate) struct InterfaceDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub name: String,
+    pub generics: Vec<GenericParam>,
+    pub supers: Vec<Ty>,
+    pub where_clause: Vec<WherePredicate>,
+    pub members: Vec<InterfaceMember>,
+    pub span: Span
+}
+
+/// The operator of an assoc declaration
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Operator {
+    Binary(String), // +, ==, or anything made of operator characters, like <=>
+    Index // [ ]
+}
+
+/// assoc Interface::method with op
+/// Makes the operator sugar for calling the interface method, so that with
+/// assoc Add::plus with +, a + b means Add::plus(a, b)
+#[derive(Debug, Clone)]
+pub(crate) struct AssocDecl {
+    pub id: NodeId,
+    pub interface: String,
+    pub method: String,
+    pub op: Operator,
+    pub span: Span
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConstKind {
+    Const, // Inlined wherever it is used
+    Static // Lives at a single address for the whole program
+}
+
+/// const NAME: Ty = value; or static NAME: Ty = value;
+/// The value is evaluated at compile time, see analysis/consteval.rs
+#[derive(Debug, Clone)]
+pub(crate) struct ConstDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub kind: ConstKind,
+    pub name: String,
+    pub ty: Ty,
+    pub value: Expr,
+    pub span: Span
+}
+
+/// [public] mod name; or [public] mod name { items }
+/// The first form loads the module from its own file, see analysis/modules.rs
+#[derive(Debug, Clone)]
+pub(crate) struct ModDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub name: String,
+    pub items: Option<Vec<Item>>, // None if the module is in another file, moved out to the module by ModuleTree::load
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum UseTreeKind {
+    Simple(Option<String>), // a::b or a::b as c
+    Glob, // a::*
+    Nested(Vec<UseTree>) // a::{b, c::d}
+}
+
+/// What a use declaration imports: a path, and then either nothing (or a new name),
+/// everything public in it or more trees inside braces. The path may start with
+/// self or super, or be empty in front of braces and globs
+#[derive(Debug, Clone)]
+pub(crate) struct UseTree {
+    pub id: NodeId,
+    pub prefix: Vec<String>,
+    pub kind: UseTreeKind,
+    pub span: Span
+}
+
+/// [public] use tree;
+/// A public use makes the imported names visible to the other modules too
+#[derive(Debug, Clone)]
+pub(crate) struct UseDecl {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub tree: UseTree,
+    pub span: Span
+}
+
+/// Anything which can appear at the top level of a file
+#[derive(Debug, Clone)]
+pub(crate) enum Item {
+    Const(ConstDecl),
+    Pack(PackDecl),
+    Fun(FunDecl),
+    Impls(ImplsDecl),
+    Def(DefDecl),
+    Interface(InterfaceDecl),
+    Assoc(AssocDecl),
+    Mod(ModDecl),
+    Use(UseDecl)
+}
+
+impl Item {
+    /// The name the item defines in its module, if it defines one
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Item::Const(decl) => Some(&decl.name),
+            Item::Pack(pack) => Some(&pack.name),
+            Item::Fun(fun) => Some(&fun.name),
+            Item::Interface(interface) => Some(&interface.name),
+            Item::Mod(module) => Some(&module.name),
+            Item::Impls(_) | Item::Def(_) | Item::Assoc(_) | Item::Use(_) => None
+        }
+    }
+
+    /// Who can see the name the item defines
+    pub fn vis(&self) -> Visibility {
+        match self {
+            Item::Const(decl) => decl.vis,
+            Item::Pack(pack) => pack.vis,
+            Item::Fun(fun) => fun.vis,
+            Item::Interface(interface) => interface.vis,
+            Item::Mod(module) => module.vis,
+            Item::Use(decl) => decl.vis,
+            Item::Impls(_) | Item::Def(_) | Item::Assoc(_) => Visibility::Private
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        match self {
+            Item::Const(decl) => decl.id,
+            Item::Pack(pack) => pack.id,
+            Item::Fun(fun) => fun.id,
+            Item::Impls(impls) => impls.id,
+            Item::Def(def) => def.id,
+            Item::Interface(interface) => interface.id,
+            Item::Assoc(assoc) => assoc.id,
+            Item::Mod(module) => module.id,
+            Item::Use(decl) => decl.id
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Item::Const(decl) => decl.span,
+            Item::Pack(pack) => pack.span,
+            Item::Fun(fun) => fun.span,
+            Item::Impls(impls) => impls.span,
+            Item::Def(def) => def.span,
+            Item::Interface(interface) => interface.span,
+            Item::Assoc(assoc) => assoc.span,
+            Item::Mod(module) => module.span,
+            Item::Use(decl) => decl.span
+        }
+    }
+}
+
+/// A whole source file: the inner attributes at its start, then its items in order
+#[derive(Debug, Clone)]
+pub(crate) struct Program {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub items: Vec<Item>,
+    pub span: Span
+}
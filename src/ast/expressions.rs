@@ -0,0 +1,109 @@
+use super::Span;
+use super::ids::NodeId;
+use super::operators::OperatorRegistry;
+use super::statements::Block;
+use super::types::Ty;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Expr {
+    pub id: NodeId,
+    pub kind: ExprKind,
+    pub span: Span
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Literal {
+    Int(u128), // Negative numbers are negated literals
+    Bool(bool),
+    Char(char),
+    Str(String), // "..." is a &StrSlice
+    Unit // ()
+}
+
+/// One segment of a path, with the generic arguments given to it: ptr::read<T>
+#[derive(Debug, Clone)]
+pub(crate) struct PathSegment {
+    pub id: NodeId,
+    pub name: String,
+    pub generics: Vec<Ty>,
+    pub span: Span
+}
+
+/// A name, possibly qualified: x, self, String::from, ptr::read<T>
+#[derive(Debug, Clone)]
+pub(crate) struct Path {
+    pub id: NodeId,
+    pub segments: Vec<PathSegment>,
+    pub span: Span
+}
+
+impl Path {
+    /// The path made of just NAME, written at SPAN
+    pub fn single(name: &str, span: Span) -> Self {
+        Self { id: NodeId::DUMMY, segments: vec![PathSegment { id: NodeId::DUMMY, name: name.to_string(), generics: Vec::new(), span }], span }
+    }
+
+    /// The name the path ends with
+    pub fn last(&self) -> &str {
+        &self.segments[self.segments.len() - 1].name
+    }
+}
+
+/// How tightly the expressions which aren't binary operators bind, compared to the
+/// precedences of the binary operators (1 to 10, see OperatorRegistry::precedence).
+/// The printer uses these to know where parentheses are needed
+pub(crate) const PREC_ASSIGN: u8 = 0;
+pub(crate) const PREC_CAST: u8 = 11;
+pub(crate) const PREC_UNARY: u8 = 12;
+pub(crate) const PREC_POSTFIX: u8 = 13;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    Neg, // -e
+    Not, // !e
+    Deref, // *e
+    Ref // &e
+}
+
+/// field: value in a pack literal, where Point { x } is short for Point { x: x }
+#[derive(Debug, Clone)]
+pub(crate) struct FieldInit {
+    pub id: NodeId,
+    pub name: String,
+    pub value: Expr,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ExprKind {
+    Literal(Literal),
+    Path(Path),
+    Call(Box<Expr>, Vec<Expr>), // callee(args), sizeof<T>() is a call too
+    MethodCall(Box<Expr>, PathSegment, Vec<Expr>), // receiver.method<T>(args)
+    Field(Box<Expr>, String), // e.field, or e.0 for tuples
+    Index(Box<Expr>, Box<Expr>), // e[i]
+    PackLit(Path, Vec<FieldInit>), // ProjectBuild { level: o_level, files }
+    Tuple(Vec<Expr>), // (a, b) or (a,)
+    Array(Vec<Expr>), // [a, b]
+    Unary(UnaryOp, Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>), // Any builtin or associated operator
+    Cast(Box<Expr>, Ty), // e as Ty, where the type may be (or contain) _ to be inferred
+    Assign(Box<Expr>, Box<Expr>), // place = value
+    AssignOp(String, Box<Expr>, Box<Expr>), // place += value, for any binary operator
+    Block(Block),
+    Unsafe(Block), // unsafe { }
+    If(Box<Expr>, Block, Option<Box<Expr>>) // if cond { } else ..., where else is a block or another if
+}
+
+impl Expr {
+    /// How tightly the expression binds, see PREC_ASSIGN and friends
+    pub fn precedence(&self) -> u8 {
+        match &self.kind {
+            ExprKind::Assign(..) | ExprKind::AssignOp(..) | ExprKind::If(..) => PREC_ASSIGN,
+            ExprKind::Binary(op, _, _) => OperatorRegistry::precedence(op),
+            ExprKind::Cast(..) => PREC_CAST,
+            ExprKind::Unary(..) => PREC_UNARY,
+            _ => PREC_POSTFIX
+        }
+    }
+}
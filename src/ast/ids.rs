@@ -0,0 +1,98 @@
+//! Identities for the nodes of the AST. The parser gives every node NodeId::DUMMY,
+//! and once a program (or a whole module tree) is parsed IdAssigner numbers its nodes
+//! in the order the visitors walk them. The passes then keep what they find out about
+//! the nodes in side tables keyed by id (types, resolutions) instead of in the tree.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::ast::mut_visit::MutVisitor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct NodeId(pub u32);
+
+impl NodeId {
+    /// The id of a node which wasn't numbered yet
+    pub const DUMMY: NodeId = NodeId(u32::MAX);
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A side table, holding a T for some of the nodes. Ids are dense, so it is a vector
+#[derive(Debug, Clone)]
+pub(crate) struct NodeMap<T> {
+    entries: Vec<Option<T>>
+}
+
+impl<T> Default for NodeMap<T> {
+    fn default() -> Self {
+        Self { entries: Vec::new() }
+    }
+}
+
+impl<T> NodeMap<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the entry of ID, giving back the one it replaces
+    pub fn insert(&mut self, id: NodeId, value: T) -> Option<T> {
+        assert!(id != NodeId::DUMMY, "the node has no id, were the ids assigned?");
+        if self.entries.len() <= id.index() {
+            self.entries.resize_with(id.index() + 1, || None);
+        }
+        self.entries[id.index()].replace(value)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.entries.get(id.index()).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.entries.get_mut(id.index()).and_then(Option::as_mut)
+    }
+
+    /// The entry of ID, set to the default first if there is none
+    pub fn get_or_default(&mut self, id: NodeId) -> &mut T where T: Default {
+        if !self.contains(id) {
+            self.insert(id, T::default());
+        }
+        self.get_mut(id).unwrap()
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// The entries in the order of their ids
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.entries.iter().enumerate().filter_map(|(i, entry)| entry.as_ref().map(|value| (NodeId(i as u32), value)))
+    }
+}
+
+/// Numbers the nodes it visits from where it was left off, so that the
+/// modules of a program, visited one after the other, get distinct ids
+#[derive(Debug, Default)]
+pub(crate) struct IdAssigner {
+    next: u32
+}
+
+impl IdAssigner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MutVisitor for IdAssigner {
+    fn visit_id(&mut self, id: &mut NodeId) {
+        *id = NodeId(self.next);
+        self.next += 1;
+    }
+}
@@ -0,0 +1,269 @@
+//! A view of a numbered AST as an arena: every node can be looked up by its id,
+//! along with the node holding it. The tree keeps owning the nodes, the map only
+//! borrows them, so it has to be built again after the tree is changed.
+
+use crate::analysis::modules::Module;
+use crate::ast::attributes::Attribute;
+use crate::ast::declarations::{AssocDecl, AssocFieldDef, AssocTypeDecl, AssocTypeDef, Bound, ConstDecl, DefDecl, FieldDecl, FunDecl, GenericParam, ImplsDecl, InterfaceDecl, ModDecl, PackDecl, Param, UseDecl, UseTree, WherePredicate};
+#[cfg(test)]
+use crate::ast::declarations::Program;
+use crate::ast::expressions::{Expr, FieldInit, Path, PathSegment};
+use crate::ast::ids::{NodeId, NodeMap};
+use crate::ast::statements::{Block, LetStmt, Pattern, Stmt};
+use crate::ast::types::Ty;
+use crate::ast::visit::{self, Visitor};
+use crate::ast::Span;
+
+/// A reference to any node which has an id
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Node<'ast> {
+    #[cfg(test)]
+    Program(&'ast Program),
+    Module(&'ast Module),
+    Attribute(&'ast Attribute),
+    Const(&'ast ConstDecl),
+    Pack(&'ast PackDecl),
+    Field(&'ast FieldDecl),
+    Fun(&'ast FunDecl),
+    Param(&'ast Param),
+    GenericParam(&'ast GenericParam),
+    Bound(&'ast Bound),
+    WherePredicate(&'ast WherePredicate),
+    Impls(&'ast ImplsDecl),
+    Def(&'ast DefDecl),
+    AssocTypeDef(&'ast AssocTypeDef),
+    AssocFieldDef(&'ast AssocFieldDef),
+    Interface(&'ast InterfaceDecl),
+    AssocTypeDecl(&'ast AssocTypeDecl),
+    Assoc(&'ast AssocDecl),
+    Mod(&'ast ModDecl),
+    Use(&'ast UseDecl),
+    UseTree(&'ast UseTree),
+    Block(&'ast Block),
+    Stmt(&'ast Stmt),
+    Let(&'ast LetStmt),
+    Pattern(&'ast Pattern),
+    Expr(&'ast Expr),
+    FieldInit(&'ast FieldInit),
+    Path(&'ast Path),
+    PathSegment(&'ast PathSegment),
+    Ty(&'ast Ty)
+}
+
+impl<'ast> Node<'ast> {
+    pub fn span(&self) -> Span {
+        match self {
+            #[cfg(test)]
+            Node::Program(node) => node.span,
+            Node::Module(node) => node.span,
+            Node::Attribute(node) => node.span,
+            Node::Const(node) => node.span,
+            Node::Pack(node) => node.span,
+            Node::Field(node) => node.span,
+            Node::Fun(node) => node.span,
+            Node::Param(node) => node.span,
+            Node::GenericParam(node) => node.span,
+            Node::Bound(node) => node.span,
+            Node::WherePredicate(node) => node.span,
+            Node::Impls(node) => node.span,
+            Node::Def(node) => node.span,
+            Node::AssocTypeDef(node) => node.span,
+            Node::AssocFieldDef(node) => node.span,
+            Node::Interface(node) => node.span,
+            Node::AssocTypeDecl(node) => node.span,
+            Node::Assoc(node) => node.span,
+            Node::Mod(node) => node.span,
+            Node::Use(node) => node.span,
+            Node::UseTree(node) => node.span,
+            Node::Block(node) => node.span,
+            Node::Stmt(node) => node.span,
+            Node::Let(node) => node.span,
+            Node::Pattern(node) => node.span,
+            Node::Expr(node) => node.span,
+            Node::FieldInit(node) => node.span,
+            Node::Path(node) => node.span,
+            Node::PathSegment(node) => node.span,
+            Node::Ty(node) => node.span
+        }
+    }
+}
+
+/// Every node of one or more programs by id, and the parent of each of them
+/// (the closest enclosing node with an id, None for programs and modules)
+#[derive(Debug, Default)]
+pub(crate) struct AstMap<'ast> {
+    nodes: NodeMap<Node<'ast>>,
+    parents: NodeMap<NodeId>,
+    /// The nodes being walked, innermost last
+    stack: Vec<NodeId>
+}
+
+impl<'ast> AstMap<'ast> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds MODULE and the nodes of its items, which must be numbered already. The
+    /// items of the modules inside it are added with those modules
+    pub fn add_module(&mut self, module: &'ast Module) {
+        self.enter(module.id, Node::Module(module), |map| {
+            for attr in &module.attrs {
+                map.visit_attribute(attr);
+            }
+            for item in &module.items {
+                map.visit_item(item);
+            }
+        });
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<Node<'ast>> {
+        self.nodes.get(id).copied()
+    }
+
+    /// The node of ID, which must be in the map
+    #[cfg(test)]
+    pub fn node(&self, id: NodeId) -> Node<'ast> {
+        self.get(id).unwrap_or_else(|| panic!("no node {} in the map", id))
+    }
+
+    #[cfg(test)]
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents.get(id).copied()
+    }
+
+    /// The parents of ID, innermost first
+    #[cfg(test)]
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |id| self.parent(*id))
+    }
+
+    /// Records NODE and walks its children with it as their parent
+    fn enter(&mut self, id: NodeId, node: Node<'ast>, walk: impl FnOnce(&mut Self)) {
+        assert!(self.nodes.insert(id, node).is_none(), "node {} is in the map twice, were the ids assigned?", id);
+        if let Some(parent) = self.stack.last() {
+            self.parents.insert(id, *parent);
+        }
+        self.stack.push(id);
+        walk(self);
+        self.stack.pop();
+    }
+}
+
+impl<'ast> Visitor<'ast> for AstMap<'ast> {
+    #[cfg(test)]
+    fn visit_program(&mut self, program: &'ast Program) {
+        self.enter(program.id, Node::Program(program), |map| visit::walk_program(map, program));
+    }
+
+    fn visit_attribute(&mut self, attr: &'ast Attribute) {
+        self.enter(attr.id, Node::Attribute(attr), |map| visit::walk_attribute(map, attr));
+    }
+
+    fn visit_const(&mut self, decl: &'ast ConstDecl) {
+        self.enter(decl.id, Node::Const(decl), |map| visit::walk_const(map, decl));
+    }
+
+    fn visit_pack(&mut self, pack: &'ast PackDecl) {
+        self.enter(pack.id, Node::Pack(pack), |map| visit::walk_pack(map, pack));
+    }
+
+    fn visit_field(&mut self, field: &'ast FieldDecl) {
+        self.enter(field.id, Node::Field(field), |map| visit::walk_field(map, field));
+    }
+
+    fn visit_fun(&mut self, fun: &'ast FunDecl) {
+        self.enter(fun.id, Node::Fun(fun), |map| visit::walk_fun(map, fun));
+    }
+
+    fn visit_param(&mut self, param: &'ast Param) {
+        self.enter(param.id, Node::Param(param), |map| visit::walk_param(map, param));
+    }
+
+    fn visit_generic_param(&mut self, param: &'ast GenericParam) {
+        self.enter(param.id, Node::GenericParam(param), |map| visit::walk_generic_param(map, param));
+    }
+
+    fn visit_bound(&mut self, bound: &'ast Bound) {
+        self.enter(bound.id, Node::Bound(bound), |map| visit::walk_bound(map, bound));
+    }
+
+    fn visit_where_predicate(&mut self, predicate: &'ast WherePredicate) {
+        self.enter(predicate.id, Node::WherePredicate(predicate), |map| visit::walk_where_predicate(map, predicate));
+    }
+
+    fn visit_impls(&mut self, impls: &'ast ImplsDecl) {
+        self.enter(impls.id, Node::Impls(impls), |map| visit::walk_impls(map, impls));
+    }
+
+    fn visit_def(&mut self, def: &'ast DefDecl) {
+        self.enter(def.id, Node::Def(def), |map| visit::walk_def(map, def));
+    }
+
+    fn visit_assoc_type_def(&mut self, def: &'ast AssocTypeDef) {
+        self.enter(def.id, Node::AssocTypeDef(def), |map| visit::walk_assoc_type_def(map, def));
+    }
+
+    fn visit_assoc_field_def(&mut self, def: &'ast AssocFieldDef) {
+        self.enter(def.id, Node::AssocFieldDef(def), |map| visit::walk_assoc_field_def(map, def));
+    }
+
+    fn visit_interface(&mut self, interface: &'ast InterfaceDecl) {
+        self.enter(interface.id, Node::Interface(interface), |map| visit::walk_interface(map, interface));
+    }
+
+    fn visit_assoc_type_decl(&mut self, decl: &'ast AssocTypeDecl) {
+        self.enter(decl.id, Node::AssocTypeDecl(decl), |map| visit::walk_assoc_type_decl(map, decl));
+    }
+
+    fn visit_assoc(&mut self, assoc: &'ast AssocDecl) {
+        self.enter(assoc.id, Node::Assoc(assoc), |map| visit::walk_assoc(map, assoc));
+    }
+
+    fn visit_mod(&mut self, module: &'ast ModDecl) {
+        self.enter(module.id, Node::Mod(module), |map| visit::walk_mod(map, module));
+    }
+
+    fn visit_use(&mut self, decl: &'ast UseDecl) {
+        self.enter(decl.id, Node::Use(decl), |map| visit::walk_use(map, decl));
+    }
+
+    fn visit_use_tree(&mut self, tree: &'ast UseTree) {
+        self.enter(tree.id, Node::UseTree(tree), |map| visit::walk_use_tree(map, tree));
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.enter(block.id, Node::Block(block), |map| visit::walk_block(map, block));
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        self.enter(stmt.id, Node::Stmt(stmt), |map| visit::walk_stmt(map, stmt));
+    }
+
+    fn visit_let(&mut self, binding: &'ast LetStmt) {
+        self.enter(binding.id, Node::Let(binding), |map| visit::walk_let(map, binding));
+    }
+
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        self.enter(pattern.id, Node::Pattern(pattern), |map| visit::walk_pattern(map, pattern));
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        self.enter(expr.id, Node::Expr(expr), |map| visit::walk_expr(map, expr));
+    }
+
+    fn visit_field_init(&mut self, init: &'ast FieldInit) {
+        self.enter(init.id, Node::FieldInit(init), |map| visit::walk_field_init(map, init));
+    }
+
+    fn visit_path(&mut self, path: &'ast Path) {
+        self.enter(path.id, Node::Path(path), |map| visit::walk_path(map, path));
+    }
+
+    fn visit_path_segment(&mut self, segment: &'ast PathSegment) {
+        self.enter(segment.id, Node::PathSegment(segment), |map| visit::walk_path_segment(map, segment));
+    }
+
+    fn visit_ty(&mut self, ty: &'ast Ty) {
+        self.enter(ty.id, Node::Ty(ty), |map| visit::walk_ty(map, ty));
+    }
+}
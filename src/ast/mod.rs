@@ -1,4 +1,24 @@
-
 pub mod types;
+pub mod attributes;
+pub mod declarations;
+pub mod operators;
+pub mod statements;
+pub mod expressions;
+pub mod pretty;
+pub mod ids;
+#[macro_use]
+pub mod visit;
+pub mod mut_visit;
+pub mod map;
+
+/// Where a node was found in the input: the byte range it covers, plus
+/// the row and column it starts at (both counted from 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub row: usize,
+    pub col: usize
+}
 
 
@@ -0,0 +1,14 @@
+//! The mutable twin of visit.rs, for the passes which rewrite the tree in place
+//! (numbering the nodes, desugaring). It has the same methods and walk_ functions,
+//! taking &mut nodes
+
+use crate::ast::attributes::Attribute;
+use crate::ast::declarations::{AssocDecl, AssocFieldDef, AssocTypeDecl, AssocTypeDef, Bound, ConstDecl, DefDecl, DefMember, FieldDecl, FunDecl, GenericParam, ImplsDecl, InterfaceDecl, InterfaceMember, Item, ModDecl, PackDecl, Param, UseDecl, UseTree, UseTreeKind, WherePredicate};
+#[cfg(test)]
+use crate::ast::declarations::Program;
+use crate::ast::expressions::{Expr, ExprKind, FieldInit, Path, PathSegment};
+use crate::ast::ids::NodeId;
+use crate::ast::statements::{Block, LetStmt, Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::types::{Ty, TyKind};
+
+make_ast_visitor!(MutVisitor, mut);
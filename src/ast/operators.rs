@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Span;
+use super::ids::NodeId;
+use super::declarations::{AssocDecl, Operator};
+
+/// Which interface method an operator stands for
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OpAssoc {
+    pub interface: String,
+    pub method: String,
+    /// Where the association was declared, None for the builtin ones
+    pub declared_at: Option<Span>,
+    /// The assoc declaration, once the nodes are numbered. The interface is
+    /// resolved in its module
+    pub decl: Option<NodeId>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum OpRegistryErr {
+    /// The operator is already bound to a method (the previous binding is given)
+    AlreadyAssociated(Operator, OpAssoc),
+    /// The symbol is part of the syntax of the language, for example = or ::
+    Reserved(String)
+}
+
+/// Symbols which already mean something else, so they can't become operators
+const RESERVED: [&str; 9] = ["=", ".", ":", "::", ",", ";", "->", "=>", "#"];
+
+/// Keeps track of which operators are associated to which interface methods,
+/// so that a + b can be desugared to Add::plus(a, b). The expression parser asks it
+/// which symbols are operators (and how tightly they bind) and the type checker
+/// asks it which method to call.
+/// 'as' is always associated to Cast::as, everything else comes from assoc declarations
+#[derive(Debug, Clone)]
+pub(crate) struct OperatorRegistry {
+    binary: HashMap<String, OpAssoc>,
+    index: Option<OpAssoc>,
+    cast: OpAssoc,
+    /// The symbols associated anywhere in the program, which can be used before
+    /// their assoc declaration is parsed
+    declared: HashSet<String>,
+    /// Whether the program is only being scanned for its assoc declarations, in
+    /// which case any symbol which isn't reserved is an operator
+    scanning: bool
+}
+
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OperatorRegistry {
+    pub fn new() -> Self {
+        let cast = OpAssoc { interface: "Cast".to_string(), method: "as".to_string(), declared_at: None, decl: None };
+        Self { binary: HashMap::new(), index: None, cast, declared: HashSet::new(), scanning: false }
+    }
+
+    /// A registry for scanning a program for its assoc declarations before they are
+    /// known: any symbol which isn't reserved parses as an operator
+    pub fn scanning() -> Self {
+        Self { scanning: true, ..Self::new() }
+    }
+
+    /// A registry for parsing a program whose assoc declarations associate SYMBOLS,
+    /// so that they can be used in the files parsed before the ones declaring them
+    pub fn declaring(symbols: HashSet<String>) -> Self {
+        Self { declared: symbols, ..Self::new() }
+    }
+
+    /// Registers the association of an assoc declaration. Every operator can be
+    /// associated only once
+    pub fn declare(&mut self, decl: &AssocDecl) -> Result<(), OpRegistryErr> {
+        let assoc = OpAssoc { interface: decl.interface.clone(), method: decl.method.clone(), declared_at: Some(decl.span), decl: None };
+        let previous = match &decl.op {
+            Operator::Binary(symbol) => {
+                if Self::is_reserved(symbol) {
+                    return Err(OpRegistryErr::Reserved(symbol.clone()));
+                }
+                self.binary.get(symbol)
+            }
+            Operator::Index => self.index.as_ref()
+        };
+        if let Some(previous) = previous {
+            return Err(OpRegistryErr::AlreadyAssociated(decl.op.clone(), previous.clone()));
+        }
+        match &decl.op {
+            Operator::Binary(symbol) => { self.binary.insert(symbol.clone(), assoc); }
+            Operator::Index => { self.index = Some(assoc); }
+        }
+        Ok(())
+    }
+
+    /// Remembers that the association of DECL, which was registered before its nodes
+    /// were numbered, is the declaration DECL.id
+    pub fn bind(&mut self, decl: &AssocDecl) {
+        let assoc = match &decl.op {
+            Operator::Binary(symbol) => self.binary.get_mut(symbol),
+            Operator::Index => self.index.as_mut()
+        };
+        // An assoc which failed to register declares nothing
+        if let Some(assoc) = assoc.filter(|assoc| assoc.declared_at == Some(decl.span) && assoc.decl.is_none()) {
+            assoc.decl = Some(decl.id);
+        }
+    }
+
+    pub fn is_reserved(symbol: &str) -> bool {
+        RESERVED.contains(&symbol) || symbol.contains("//") || symbol.contains("/*")
+    }
+
+    /// The method a binary operator desugars to, if it was associated
+    pub fn binary(&self, symbol: &str) -> Option<&OpAssoc> {
+        self.binary.get(symbol)
+    }
+
+    /// The method a[i] desugars to, if it was associated
+    pub fn index(&self) -> Option<&OpAssoc> {
+        self.index.as_ref()
+    }
+
+    /// The method 'as' desugars to
+    pub fn cast(&self) -> &OpAssoc {
+        &self.cast
+    }
+
+    /// The method OP desugars to, whichever kind of operator it is: [] for indexing,
+    /// as for casts and the symbol of a binary operator
+    pub fn get(&self, op: &str) -> Option<&OpAssoc> {
+        match op {
+            "[]" => self.index(),
+            "as" => Some(self.cast()),
+            symbol => self.binary(symbol)
+        }
+    }
+
+    /// Whether SYMBOL can be used as a binary operator: either the builtin
+    /// operators on primitives or anything that is associated in the program
+    pub fn is_binary(&self, symbol: &str) -> bool {
+        BUILTIN_BINARY.contains(&symbol) || self.binary.contains_key(symbol) || self.declared.contains(symbol)
+            || (self.scanning && !Self::is_reserved(symbol))
+    }
+
+    /// How tightly SYMBOL binds, higher binds tighter. The builtin operators bind
+    /// like they do in C, user declared operators bind by their first character
+    /// (like in Scala), so that <=> binds like < and ++ binds like +
+    pub fn precedence(symbol: &str) -> u8 {
+        match symbol {
+            "||" => 1,
+            "&&" => 2,
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => 3,
+            "|" => 4,
+            "^" => 5,
+            "&" => 6,
+            "<<" | ">>" => 7,
+            "+" | "-" => 8,
+            "*" | "/" | "%" => 9,
+            _ => match symbol.chars().next() {
+                Some('|') => 4,
+                Some('^') => 5,
+                Some('&') => 6,
+                Some('=') | Some('!') | Some('<') | Some('>') => 3,
+                Some('+') | Some('-') => 8,
+                Some('*') | Some('/') | Some('%') => 9,
+                _ => 10
+            }
+        }
+    }
+}
+
+/// Binary operators that work on primitives without any association
+pub(crate) const BUILTIN_BINARY: [&str; 18] = [
+    "||", "&&", "==", "!=", "<", ">", "<=", ">=", "|", "^", "&", "<<", ">>", "+", "-", "*", "/", "%"
+];
+
+/// The characters operator symbols are made of
+pub(crate) fn is_operator_char(c: char) -> bool {
+    "+-*/%<>=!&|^~@$?".contains(c)
+}
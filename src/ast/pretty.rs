@@ -0,0 +1,711 @@
+//! Pretty-printing of the AST back to canonical gold syntax.
+//! Every node implements Display here (and not next to its definition), so that
+//! the whole canonical syntax can be read in one place. Whatever gets printed
+//! here must parse back to the very same node, which is checked by the
+//! round-trip tests in tests/pretty_tests.rs
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::ast::attributes::{AttrStyle, Attribute};
+use crate::ast::declarations::{AssocDecl, AssocFieldDef, AssocTypeDecl, AssocTypeDef, Bound, ConstDecl, ConstKind, DefDecl, DefMember, FieldDecl, FunDecl, GenericParam, ImplsDecl, InterfaceDecl, InterfaceMember, Item, ModDecl, Operator, PackDecl, Param, Program, Receiver, UseDecl, UseTree, UseTreeKind, Visibility, WherePredicate};
+use crate::ast::expressions::{Expr, ExprKind, FieldInit, Literal, Path, PathSegment, UnaryOp, PREC_ASSIGN, PREC_CAST, PREC_POSTFIX, PREC_UNARY};
+use crate::ast::statements::{BindingKind, Block, LetStmt, Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::types::{PrimitiveType, Ty, TyKind};
+
+const INDENT: &str = "    ";
+
+/// Writes the items separated by SEP, without a trailing separator
+pub(crate) fn write_sep<T: Display>(f: &mut Formatter<'_>, items: &[T], sep: &str) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 {
+            f.write_str(sep)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Writes NODE indented by one level, for the bodies of packs, functions and blocks
+pub(crate) fn write_indented<T: Display>(f: &mut Formatter<'_>, node: &T) -> fmt::Result {
+    for line in node.to_string().lines() {
+        if line.is_empty() {
+            writeln!(f)?;
+        } else {
+            writeln!(f, "{}{}", INDENT, line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes <T, U> if there are any generic parameters, nothing otherwise
+fn write_generics(f: &mut Formatter<'_>, generics: &[GenericParam]) -> fmt::Result {
+    if generics.is_empty() {
+        return Ok(());
+    }
+    f.write_str("<")?;
+    write_sep(f, generics, ", ")?;
+    f.write_str(">")
+}
+
+/// Writes where P1, P2 if there are any predicates, nothing otherwise
+fn write_where(f: &mut Formatter<'_>, where_clause: &[WherePredicate]) -> fmt::Result {
+    if where_clause.is_empty() {
+        return Ok(());
+    }
+    f.write_str(" where ")?;
+    write_sep(f, where_clause, ", ")
+}
+
+/// Writes the members of an impls or def block, separated by empty lines
+fn write_members<T: Display>(f: &mut Formatter<'_>, members: &[T]) -> fmt::Result {
+    if members.is_empty() {
+        return f.write_str(" {}");
+    }
+    f.write_str(" {\n")?;
+    let members: Vec<String> = members.iter().map(|member| member.to_string()).collect();
+    write_indented(f, &members.join("\n\n"))?;
+    f.write_str("}")
+}
+
+/// Writes every attribute on its own line, as they go in front of items, fields and statements
+fn write_attrs(f: &mut Formatter<'_>, attrs: &[Attribute]) -> fmt::Result {
+    for attr in attrs {
+        writeln!(f, "{}", attr)?;
+    }
+    Ok(())
+}
+
+impl Display for PrimitiveType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TyKind::Primitive(prim) => write!(f, "{}", prim),
+            TyKind::Userdefined(name) => f.write_str(name),
+            TyKind::Generic(name, args) => {
+                write!(f, "{}<", name)?;
+                write_sep(f, args, ", ")?;
+                f.write_str(">")
+            }
+            TyKind::Ref(inner) => write!(f, "&{}", inner),
+            TyKind::Ptr(inner) => write!(f, "*{}", inner),
+            TyKind::NoDrop(inner) => write!(f, "-{}", inner),
+            TyKind::Infer => f.write_str("_"),
+            TyKind::Assoc(base, name) => write!(f, "{}::{}", base, name),
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.style == AttrStyle::Inner {
+            f.write_str("#![")?;
+        } else {
+            f.write_str("#")?;
+        }
+        f.write_str(&self.name)?;
+        if !self.args.is_empty() {
+            f.write_str("(")?;
+            write_sep(f, &self.args, ", ")?;
+            f.write_str(")")?;
+        }
+        if self.style == AttrStyle::Inner {
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Visibility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Visibility::Public => f.write_str("public "),
+            Visibility::Private => Ok(())
+        }
+    }
+}
+
+impl Display for Bound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            f.write_str("!")?;
+        }
+        write!(f, "{}", self.interface)
+    }
+}
+
+impl Display for GenericParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.bounds.is_empty() {
+            f.write_str(": ")?;
+            write_sep(f, &self.bounds, " + ")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for WherePredicate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.ty)?;
+        write_sep(f, &self.bounds, " + ")
+    }
+}
+
+impl Display for FieldDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Field attributes stay on the line of the field
+        for attr in &self.attrs {
+            write!(f, "{} ", attr)?;
+        }
+        write!(f, "{}{}: {}", self.vis, self.name, self.ty)
+    }
+}
+
+impl Display for ConstDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        let keyword = match self.kind {
+            ConstKind::Const => "const",
+            ConstKind::Static => "static"
+        };
+        write!(f, "{}{} {}: {} = {};", self.vis, keyword, self.name, self.ty, self.value)
+    }
+}
+
+impl Display for PackDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        write!(f, "{}pack {}", self.vis, self.name)?;
+        write_generics(f, &self.generics)?;
+        if self.fields.is_empty() {
+            return f.write_str(" {}");
+        }
+        f.write_str(" {\n")?;
+        let fields: Vec<String> = self.fields.iter().map(|field| field.to_string()).collect();
+        write_indented(f, &fields.join(",\n"))?;
+        f.write_str("}")
+    }
+}
+
+impl Display for Receiver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Receiver::Value => f.write_str("self"),
+            Receiver::Ref => f.write_str("&self"),
+            Receiver::Ptr => f.write_str("*self")
+        }
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.implicit {
+            f.write_str("implicit ")?;
+        }
+        write!(f, "{}: {}", self.name, self.ty)
+    }
+}
+
+impl Display for FunDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        write!(f, "{}{}{}fun {}", self.vis, if self.implicit { "implicit " } else { "" }, if self.is_unsafe { "unsafe " } else { "" }, self.name)?;
+        write_generics(f, &self.generics)?;
+        f.write_str("(")?;
+        if let Some(receiver) = &self.receiver {
+            write!(f, "{}", receiver)?;
+            if !self.params.is_empty() {
+                f.write_str(", ")?;
+            }
+        }
+        write_sep(f, &self.params, ", ")?;
+        f.write_str(")")?;
+        if let Some(ret) = &self.ret {
+            write!(f, ": {}", ret)?;
+        }
+        write_where(f, &self.where_clause)?;
+        match &self.body {
+            Some(body) => write!(f, " {}", body),
+            None => f.write_str(";")
+        }
+    }
+}
+
+impl Display for ImplsDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        f.write_str(if self.implicit { "implicit impls" } else { "impls" })?;
+        write_generics(f, &self.generics)?;
+        write!(f, " {}", self.target)?;
+        write_where(f, &self.where_clause)?;
+        write_members(f, &self.funs)
+    }
+}
+
+impl Display for AssocTypeDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "type {} = {};", self.name, self.ty)
+    }
+}
+
+impl Display for AssocFieldDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.ty)?;
+        if let Some(source) = &self.source {
+            write!(f, " = {}", source)?;
+        }
+        f.write_str(";")
+    }
+}
+
+impl Display for DefMember {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DefMember::AssocType(assoc) => write!(f, "{}", assoc),
+            DefMember::Field(field) => write!(f, "{}", field),
+            DefMember::Fun(fun) => write!(f, "{}", fun)
+        }
+    }
+}
+
+impl Display for DefDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        f.write_str("def")?;
+        write_generics(f, &self.generics)?;
+        write!(f, " {} for {}", self.interface, self.target)?;
+        write_where(f, &self.where_clause)?;
+        write_members(f, &self.members)
+    }
+}
+
+impl Display for AssocTypeDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "type {}", self.name)?;
+        if !self.bounds.is_empty() {
+            f.write_str(": ")?;
+            write_sep(f, &self.bounds, " + ")?;
+        }
+        f.write_str(";")
+    }
+}
+
+impl Display for InterfaceMember {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InterfaceMember::AssocType(assoc) => write!(f, "{}", assoc),
+            InterfaceMember::Field(field) => write!(f, "{};", field),
+            InterfaceMember::Fun(fun) => write!(f, "{}", fun)
+        }
+    }
+}
+
+impl Display for InterfaceDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        write!(f, "{}interface {}", self.vis, self.name)?;
+        write_generics(f, &self.generics)?;
+        if !self.supers.is_empty() {
+            f.write_str(": ")?;
+            write_sep(f, &self.supers, " + ")?;
+        }
+        write_where(f, &self.where_clause)?;
+        write_members(f, &self.members)
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Operator::Binary(symbol) => f.write_str(symbol),
+            Operator::Index => f.write_str("[]")
+        }
+    }
+}
+
+impl Display for AssocDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "assoc {}::{} with {}", self.interface, self.method, self.op)
+    }
+}
+
+impl Display for ModDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        write!(f, "{}mod {}", self.vis, self.name)?;
+        match &self.items {
+            Some(items) => write_members(f, items),
+            None => f.write_str(";")
+        }
+    }
+}
+
+impl Display for UseTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_sep(f, &self.prefix, "::")?;
+        // a::* and a::{b}, but just * and {b} when there is no prefix
+        let sep = if self.prefix.is_empty() { "" } else { "::" };
+        match &self.kind {
+            UseTreeKind::Simple(None) => Ok(()),
+            UseTreeKind::Simple(Some(rename)) => write!(f, " as {}", rename),
+            UseTreeKind::Glob => write!(f, "{}*", sep),
+            UseTreeKind::Nested(trees) => {
+                write!(f, "{}{{", sep)?;
+                write_sep(f, trees, ", ")?;
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl Display for UseDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        write!(f, "{}use {};", self.vis, self.tree)
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Const(decl) => write!(f, "{}", decl),
+            Item::Pack(pack) => write!(f, "{}", pack),
+            Item::Fun(fun) => write!(f, "{}", fun),
+            Item::Impls(impls) => write!(f, "{}", impls),
+            Item::Def(def) => write!(f, "{}", def),
+            Item::Interface(interface) => write!(f, "{}", interface),
+            Item::Assoc(assoc) => write!(f, "{}", assoc),
+            Item::Mod(module) => write!(f, "{}", module),
+            Item::Use(decl) => write!(f, "{}", decl)
+        }
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The inner attributes and the items are separated by an empty line
+        write_attrs(f, &self.attrs)?;
+        if !self.attrs.is_empty() && !self.items.is_empty() {
+            writeln!(f)?;
+        }
+        write_sep(f, &self.items, "\n\n")?;
+        if !self.items.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Block {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.stmts.is_empty() {
+            return f.write_str("{}");
+        }
+        f.write_str("{\n")?;
+        for stmt in &self.stmts {
+            write_indented(f, stmt)?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl Display for BindingKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingKind::Let => f.write_str("let"),
+            BindingKind::Val => f.write_str("val")
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            PatternKind::Binding(name) => f.write_str(name),
+            PatternKind::Wildcard => f.write_str("_"),
+            PatternKind::Tuple(patterns) => {
+                f.write_str("(")?;
+                write_sep(f, patterns, ", ")?;
+                f.write_str(")")
+            }
+            PatternKind::Pack(name, fields) => {
+                write!(f, "{} {{ ", name)?;
+                for (i, (field, pattern)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    match &pattern.kind {
+                        // Point { x: x } is printed as Point { x }
+                        PatternKind::Binding(binding) if binding == field => f.write_str(field)?,
+                        _ => write!(f, "{}: {}", field, pattern)?
+                    }
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+}
+
+impl Display for LetStmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.pattern)?;
+        if let Some(ty) = &self.ty {
+            write!(f, ": {}", ty)?;
+        }
+        if let Some(init) = &self.init {
+            write!(f, " = {}", init)?;
+        }
+        f.write_str(";")
+    }
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_attrs(f, &self.attrs)?;
+        match &self.kind {
+            StmtKind::Let(binding) => write!(f, "{}", binding),
+            StmtKind::Defer(deferred) => write!(f, "defer({});", deferred),
+            StmtKind::Return(None) => f.write_str("return;"),
+            StmtKind::Return(Some(value)) => write!(f, "return {};", value),
+            StmtKind::While(cond, body) => {
+                f.write_str("while ")?;
+                write_cond(f, cond)?;
+                write!(f, " {}", body)
+            }
+            StmtKind::For(pattern, iter, body) => {
+                write!(f, "for {} in ", pattern)?;
+                write_cond(f, iter)?;
+                write!(f, " {}", body)
+            }
+            StmtKind::Break => f.write_str("break;"),
+            StmtKind::Continue => f.write_str("continue;"),
+            StmtKind::Expr(expr) => write_stmt_expr(f, expr),
+            StmtKind::Semi(expr) => {
+                write_stmt_expr(f, expr)?;
+                f.write_str(";")
+            }
+        }
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Int(n) => write!(f, "{}", n),
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Char(c) => {
+                f.write_str("'")?;
+                write_escaped(f, *c, '\'')?;
+                f.write_str("'")
+            }
+            Literal::Str(s) => {
+                f.write_str("\"")?;
+                for c in s.chars() {
+                    write_escaped(f, c, '"')?;
+                }
+                f.write_str("\"")
+            }
+            Literal::Unit => f.write_str("()")
+        }
+    }
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.generics.is_empty() {
+            f.write_str("<")?;
+            write_sep(f, &self.generics, ", ")?;
+            f.write_str(">")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_sep(f, &self.segments, "::")
+    }
+}
+
+/// Writes C as it goes between QUOTEs: with one of the escapes the lexer knows, as
+/// \u{...} if it is another control character, and as itself otherwise
+fn write_escaped(f: &mut Formatter<'_>, c: char, quote: char) -> fmt::Result {
+    match c {
+        '\n' => f.write_str("\\n"),
+        '\t' => f.write_str("\\t"),
+        '\r' => f.write_str("\\r"),
+        '\0' => f.write_str("\\0"),
+        '\\' => f.write_str("\\\\"),
+        c if c == quote => write!(f, "\\{}", c),
+        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32),
+        c => write!(f, "{}", c)
+    }
+}
+
+/// Writes EXPR, in parentheses if it binds looser than MIN_PREC
+fn write_operand(f: &mut Formatter<'_>, expr: &Expr, min_prec: u8) -> fmt::Result {
+    if expr.precedence() < min_prec {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+/// The expression EXPR starts with, when printed without parentheses: a in a.b + c
+fn leftmost(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Call(inner, _) | ExprKind::MethodCall(inner, _, _) | ExprKind::Field(inner, _)
+        | ExprKind::Index(inner, _) | ExprKind::Cast(inner, _) | ExprKind::Binary(_, inner, _)
+        | ExprKind::Assign(inner, _) | ExprKind::AssignOp(_, inner, _) => leftmost(inner),
+        _ => expr
+    }
+}
+
+/// Whether EXPR has a pack literal which isn't inside brackets, which would be
+/// taken for the block of an if, while or for if printed as a condition
+fn has_bare_pack_lit(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::PackLit(..) => true,
+        ExprKind::Call(inner, _) | ExprKind::MethodCall(inner, _, _) | ExprKind::Field(inner, _)
+        | ExprKind::Index(inner, _) | ExprKind::Cast(inner, _) | ExprKind::Unary(_, inner) => has_bare_pack_lit(inner),
+        ExprKind::Binary(_, lhs, rhs) | ExprKind::Assign(lhs, rhs) | ExprKind::AssignOp(_, lhs, rhs) => {
+            has_bare_pack_lit(lhs) || has_bare_pack_lit(rhs)
+        }
+        _ => false
+    }
+}
+
+/// Writes the condition of an if or a while (or what a for iterates over)
+fn write_cond(f: &mut Formatter<'_>, cond: &Expr) -> fmt::Result {
+    if has_bare_pack_lit(cond) {
+        write!(f, "({})", cond)
+    } else {
+        write!(f, "{}", cond)
+    }
+}
+
+/// Writes an expression statement. If it starts with a block without being one, it
+/// goes in parentheses, since a statement which starts with a block ends with it
+fn write_stmt_expr(f: &mut Formatter<'_>, expr: &Expr) -> fmt::Result {
+    let starts_with_block = matches!(leftmost(expr).kind, ExprKind::Block(_) | ExprKind::Unsafe(_) | ExprKind::If(..));
+    let is_block = matches!(expr.kind, ExprKind::Block(_) | ExprKind::Unsafe(_) | ExprKind::If(..));
+    if starts_with_block && !is_block {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Neg => f.write_str("-"),
+            UnaryOp::Not => f.write_str("!"),
+            UnaryOp::Deref => f.write_str("*"),
+            UnaryOp::Ref => f.write_str("&")
+        }
+    }
+}
+
+impl Display for FieldInit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.value.kind {
+            // Point { x: x } is printed as Point { x }
+            ExprKind::Path(path) if path.segments.len() == 1 && path.segments[0].generics.is_empty() && path.last() == self.name => {
+                f.write_str(&self.name)
+            }
+            _ => write!(f, "{}: {}", self.name, self.value)
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Literal(lit) => write!(f, "{}", lit),
+            ExprKind::Path(path) => write!(f, "{}", path),
+            ExprKind::Call(callee, args) => {
+                write_operand(f, callee, PREC_POSTFIX)?;
+                f.write_str("(")?;
+                write_sep(f, args, ", ")?;
+                f.write_str(")")
+            }
+            ExprKind::MethodCall(receiver, method, args) => {
+                write_operand(f, receiver, PREC_POSTFIX)?;
+                write!(f, ".{}(", method)?;
+                write_sep(f, args, ", ")?;
+                f.write_str(")")
+            }
+            ExprKind::Field(base, field) => {
+                write_operand(f, base, PREC_POSTFIX)?;
+                write!(f, ".{}", field)
+            }
+            ExprKind::Index(base, index) => {
+                write_operand(f, base, PREC_POSTFIX)?;
+                write!(f, "[{}]", index)
+            }
+            ExprKind::PackLit(path, fields) => {
+                if fields.is_empty() {
+                    return write!(f, "{} {{}}", path);
+                }
+                write!(f, "{} {{ ", path)?;
+                write_sep(f, fields, ", ")?;
+                f.write_str(" }")
+            }
+            ExprKind::Tuple(elements) => {
+                f.write_str("(")?;
+                write_sep(f, elements, ", ")?;
+                // (a) would just be a in parentheses
+                if elements.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            ExprKind::Array(elements) => {
+                f.write_str("[")?;
+                write_sep(f, elements, ", ")?;
+                f.write_str("]")
+            }
+            ExprKind::Unary(op, operand) => {
+                write!(f, "{}", op)?;
+                write_operand(f, operand, PREC_UNARY)
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                // Left associative, so a - (b - c) keeps its parentheses
+                let prec = self.precedence();
+                write_operand(f, lhs, prec)?;
+                write!(f, " {} ", op)?;
+                write_operand(f, rhs, prec + 1)
+            }
+            ExprKind::Cast(operand, ty) => {
+                write_operand(f, operand, PREC_CAST)?;
+                write!(f, " as {}", ty)
+            }
+            ExprKind::Assign(place, value) => {
+                write_operand(f, place, PREC_ASSIGN + 1)?;
+                write!(f, " = {}", value)
+            }
+            ExprKind::AssignOp(op, place, value) => {
+                write_operand(f, place, PREC_ASSIGN + 1)?;
+                write!(f, " {}= {}", op, value)
+            }
+            ExprKind::Block(block) => write!(f, "{}", block),
+            ExprKind::Unsafe(block) => write!(f, "unsafe {}", block),
+            ExprKind::If(cond, then, otherwise) => {
+                f.write_str("if ")?;
+                write_cond(f, cond)?;
+                write!(f, " {}", then)?;
+                if let Some(otherwise) = otherwise {
+                    write!(f, " else {}", otherwise)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
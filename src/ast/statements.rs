@@ -0,0 +1,80 @@
+use super::Span;
+use super::ids::NodeId;
+use super::attributes::Attribute;
+use super::expressions::Expr;
+use super::types::Ty;
+
+/// { stmt* }
+/// If the last statement is an expression without a semicolon, it is the value of the block
+#[derive(Debug, Clone)]
+pub(crate) struct Block {
+    pub id: NodeId,
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+    /// The closing }, where the scope of the block ends
+    pub end: Span
+}
+
+impl Block {
+    /// The expression giving the value of the block, if any
+    pub fn tail(&self) -> Option<&Expr> {
+        match self.stmts.last().map(|stmt| &stmt.kind) {
+            Some(StmtKind::Expr(expr)) => Some(expr),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Stmt {
+    pub id: NodeId,
+    pub attrs: Vec<Attribute>,
+    pub kind: StmtKind,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum StmtKind {
+    Let(LetStmt),
+    Defer(Expr), // defer(expr); runs expr when the scope is left
+    Return(Option<Expr>),
+    While(Expr, Block),
+    For(Pattern, Expr, Block), // for pattern in expr { }
+    Break,
+    Continue,
+    Expr(Expr), // An expression without a semicolon: the value of the block, or a block-like expression
+    Semi(Expr) // expr;
+}
+
+/// let binds a variable that can be assigned again, val one that can't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BindingKind {
+    Let,
+    Val
+}
+
+/// let pattern: Ty = init;
+#[derive(Debug, Clone)]
+pub(crate) struct LetStmt {
+    pub id: NodeId,
+    pub kind: BindingKind,
+    pub pattern: Pattern,
+    pub ty: Option<Ty>,
+    pub init: Option<Expr>,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern {
+    pub id: NodeId,
+    pub kind: PatternKind,
+    pub span: Span
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum PatternKind {
+    Binding(String), // x
+    Wildcard, // _
+    Tuple(Vec<Pattern>), // (a, b)
+    Pack(String, Vec<(String, Pattern)>) // Point { x, y: other }
+}
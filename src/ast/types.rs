@@ -1,7 +1,10 @@
-#[allow(dead_code)]
+use std::hash::{Hash, Hasher};
 
-// TODO(mike): impl debug manually
-#[derive(Debug)]
+use super::ids::NodeId;
+use super::Span;
+
+// Printing lives in ast/pretty.rs, which renders these back to gold syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum PrimitiveType {
     UInt8, // Unsigned ints
     UInt16,
@@ -13,6 +16,8 @@ pub(crate) enum PrimitiveType {
     SInt32,
     SInt64,
     SInt128,
+    Float32,
+    Float64,
     // ------ //
     Bool,
     Char,
@@ -20,12 +25,115 @@ pub(crate) enum PrimitiveType {
     RefStaticStr // Do we allow this?
 }
 
-#[derive(Debug)]
-pub(crate) enum Ty {
+impl PrimitiveType {
+    /// The primitive type which is spelled exactly NAME in the source code, if any.
+    /// &StaticString is not here since it is made of two tokens, the type parser
+    /// recognises it on its own.
+    pub fn from_name(name: &str) -> Option<Self> {
+        use PrimitiveType::*;
+        let prim = match name {
+            "u8" => UInt8,
+            "u16" => UInt16,
+            "u32" => UInt32,
+            "u64" => UInt64,
+            "u128" => UInt128,
+            "i8" => SInt8,
+            "i16" => SInt16,
+            "i32" => SInt32,
+            "i64" => SInt64,
+            "i128" => SInt128,
+            "f32" => Float32,
+            "f64" => Float64,
+            "bool" => Bool,
+            "char" => Char,
+            "()" => Unit,
+            _ => return None
+        };
+        Some(prim)
+    }
+
+    /// How many bits the integer type has, None if it's not an integer type
+    pub fn int_bits(&self) -> Option<u32> {
+        use PrimitiveType::*;
+        match self {
+            UInt8 | SInt8 => Some(8),
+            UInt16 | SInt16 => Some(16),
+            UInt32 | SInt32 => Some(32),
+            UInt64 | SInt64 => Some(64),
+            UInt128 | SInt128 => Some(128),
+            _ => None
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        use PrimitiveType::*;
+        matches!(self, SInt8 | SInt16 | SInt32 | SInt64 | SInt128)
+    }
+
+    /// How the primitive is spelled in gold source code
+    pub fn name(&self) -> &'static str {
+        use PrimitiveType::*;
+        match self {
+            UInt8 => "u8",
+            UInt16 => "u16",
+            UInt32 => "u32",
+            UInt64 => "u64",
+            UInt128 => "u128",
+            SInt8 => "i8",
+            SInt16 => "i16",
+            SInt32 => "i32",
+            SInt64 => "i64",
+            SInt128 => "i128",
+            Float32 => "f32",
+            Float64 => "f64",
+            Bool => "bool",
+            Char => "char",
+            Unit => "()",
+            RefStaticStr => "&StaticString"
+        }
+    }
+}
+
+/// A type as it is written in the source, with an id so that the passes can say
+/// what they found out about it (what it resolves to, what it lowers to)
+#[derive(Debug, Clone)]
+pub(crate) struct Ty {
+    pub id: NodeId,
+    pub kind: TyKind,
+    pub span: Span
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum TyKind {
     Primitive(PrimitiveType), // u8, bool, etc...
     Userdefined(String), // For example: "pack SomeType"
     Generic(String, Vec<Ty>), // For example: Hashmap<i32, Vec<Something, Allocator>>
     Ref(Box<Ty>), // &Ty
     Ptr(Box<Ty>), // *Ty or even ** Ty
+    NoDrop(Box<Ty>), // -Ty, owned but never dropped automatically at the end of its scope
+    Infer, // _, left for the type checker to fill in: x as _ or &t as *_
+    Assoc(Box<Ty>, String), // T::Target, a type the interfaces implemented by T give a name to
+}
+
+impl Ty {
+    /// A type which isn't in the source, so it has no id or span yet
+    #[cfg(test)]
+    pub fn new(kind: TyKind) -> Self {
+        Self { id: NodeId::DUMMY, kind, span: Span::default() }
+    }
 }
 
+/// Two types are the same if they are spelled the same, wherever they are written
+impl PartialEq for Ty {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Eq for Ty {}
+
+impl Hash for Ty {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+    }
+}
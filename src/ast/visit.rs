@@ -0,0 +1,423 @@
+//! Visitors over the AST, so that a pass only has to say what it does with the
+//! nodes it cares about. Every node kind has a visit_ method, which by default calls
+//! the walk_ function of the same name to visit the node's children in source order.
+//! A visitor overrides the methods it needs, and calls the walk_ function itself to
+//! keep going down (or doesn't, to skip the children).
+//!
+//! Visitor borrows the tree for 'ast, so a visitor can keep references to the nodes,
+//! and MutVisitor (in mut_visit.rs) can change them. Both are generated by
+//! make_ast_visitor! from the same definition, so they can't drift apart.
+
+use crate::ast::attributes::Attribute;
+use crate::ast::declarations::{AssocDecl, AssocFieldDef, AssocTypeDecl, AssocTypeDef, Bound, ConstDecl, DefDecl, DefMember, FieldDecl, FunDecl, GenericParam, ImplsDecl, InterfaceDecl, InterfaceMember, Item, ModDecl, PackDecl, Param, UseDecl, UseTree, UseTreeKind, WherePredicate};
+#[cfg(test)]
+use crate::ast::declarations::Program;
+use crate::ast::expressions::{Expr, ExprKind, FieldInit, Path, PathSegment};
+use crate::ast::ids::NodeId;
+use crate::ast::statements::{Block, LetStmt, Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::types::{Ty, TyKind};
+
+/// Generates a visitor trait and its walk_ functions. The references are
+/// &'lt Node if a lifetime is given and &mut Node if mut is
+macro_rules! make_ast_visitor {
+    ($visitor:ident $(<$lt:lifetime>)? $(, $mutability:ident)?) => {
+        pub(crate) trait $visitor$(<$lt>)?: Sized {
+            /// Called with the id of every node which has one, before its children
+            fn visit_id(&mut self, _id: &$($mutability)? NodeId) {}
+            // The passes go through the items of the module tree, only the tests visit whole programs
+            #[cfg(test)]
+            fn visit_program(&mut self, program: &$($lt)? $($mutability)? Program) { walk_program(self, program) }
+            fn visit_attribute(&mut self, attr: &$($lt)? $($mutability)? Attribute) { walk_attribute(self, attr) }
+            fn visit_item(&mut self, item: &$($lt)? $($mutability)? Item) { walk_item(self, item) }
+            fn visit_const(&mut self, decl: &$($lt)? $($mutability)? ConstDecl) { walk_const(self, decl) }
+            fn visit_pack(&mut self, pack: &$($lt)? $($mutability)? PackDecl) { walk_pack(self, pack) }
+            fn visit_field(&mut self, field: &$($lt)? $($mutability)? FieldDecl) { walk_field(self, field) }
+            fn visit_fun(&mut self, fun: &$($lt)? $($mutability)? FunDecl) { walk_fun(self, fun) }
+            fn visit_param(&mut self, param: &$($lt)? $($mutability)? Param) { walk_param(self, param) }
+            fn visit_generic_param(&mut self, param: &$($lt)? $($mutability)? GenericParam) { walk_generic_param(self, param) }
+            fn visit_bound(&mut self, bound: &$($lt)? $($mutability)? Bound) { walk_bound(self, bound) }
+            fn visit_where_predicate(&mut self, predicate: &$($lt)? $($mutability)? WherePredicate) { walk_where_predicate(self, predicate) }
+            fn visit_impls(&mut self, impls: &$($lt)? $($mutability)? ImplsDecl) { walk_impls(self, impls) }
+            fn visit_def(&mut self, def: &$($lt)? $($mutability)? DefDecl) { walk_def(self, def) }
+            fn visit_assoc_type_def(&mut self, def: &$($lt)? $($mutability)? AssocTypeDef) { walk_assoc_type_def(self, def) }
+            fn visit_assoc_field_def(&mut self, def: &$($lt)? $($mutability)? AssocFieldDef) { walk_assoc_field_def(self, def) }
+            fn visit_interface(&mut self, interface: &$($lt)? $($mutability)? InterfaceDecl) { walk_interface(self, interface) }
+            fn visit_assoc_type_decl(&mut self, decl: &$($lt)? $($mutability)? AssocTypeDecl) { walk_assoc_type_decl(self, decl) }
+            fn visit_assoc(&mut self, assoc: &$($lt)? $($mutability)? AssocDecl) { walk_assoc(self, assoc) }
+            fn visit_mod(&mut self, module: &$($lt)? $($mutability)? ModDecl) { walk_mod(self, module) }
+            fn visit_use(&mut self, decl: &$($lt)? $($mutability)? UseDecl) { walk_use(self, decl) }
+            fn visit_use_tree(&mut self, tree: &$($lt)? $($mutability)? UseTree) { walk_use_tree(self, tree) }
+            fn visit_block(&mut self, block: &$($lt)? $($mutability)? Block) { walk_block(self, block) }
+            fn visit_stmt(&mut self, stmt: &$($lt)? $($mutability)? Stmt) { walk_stmt(self, stmt) }
+            fn visit_let(&mut self, binding: &$($lt)? $($mutability)? LetStmt) { walk_let(self, binding) }
+            fn visit_pattern(&mut self, pattern: &$($lt)? $($mutability)? Pattern) { walk_pattern(self, pattern) }
+            fn visit_expr(&mut self, expr: &$($lt)? $($mutability)? Expr) { walk_expr(self, expr) }
+            fn visit_field_init(&mut self, init: &$($lt)? $($mutability)? FieldInit) { walk_field_init(self, init) }
+            fn visit_path(&mut self, path: &$($lt)? $($mutability)? Path) { walk_path(self, path) }
+            fn visit_path_segment(&mut self, segment: &$($lt)? $($mutability)? PathSegment) { walk_path_segment(self, segment) }
+            fn visit_ty(&mut self, ty: &$($lt)? $($mutability)? Ty) { walk_ty(self, ty) }
+        }
+
+        #[cfg(test)]
+        pub(crate) fn walk_program<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, program: &$($lt)? $($mutability)? Program) {
+            visitor.visit_id(&$($mutability)? program.id);
+            for attr in &$($mutability)? program.attrs {
+                visitor.visit_attribute(attr);
+            }
+            for item in &$($mutability)? program.items {
+                visitor.visit_item(item);
+            }
+        }
+
+        pub(crate) fn walk_attribute<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, attr: &$($lt)? $($mutability)? Attribute) {
+            visitor.visit_id(&$($mutability)? attr.id);
+        }
+
+        pub(crate) fn walk_item<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, item: &$($lt)? $($mutability)? Item) {
+            match item {
+                Item::Const(decl) => visitor.visit_const(decl),
+                Item::Pack(pack) => visitor.visit_pack(pack),
+                Item::Fun(fun) => visitor.visit_fun(fun),
+                Item::Impls(impls) => visitor.visit_impls(impls),
+                Item::Def(def) => visitor.visit_def(def),
+                Item::Interface(interface) => visitor.visit_interface(interface),
+                Item::Assoc(assoc) => visitor.visit_assoc(assoc),
+                Item::Mod(module) => visitor.visit_mod(module),
+                Item::Use(decl) => visitor.visit_use(decl)
+            }
+        }
+
+        pub(crate) fn walk_const<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, decl: &$($lt)? $($mutability)? ConstDecl) {
+            visitor.visit_id(&$($mutability)? decl.id);
+            for attr in &$($mutability)? decl.attrs {
+                visitor.visit_attribute(attr);
+            }
+            visitor.visit_ty(&$($mutability)? decl.ty);
+            visitor.visit_expr(&$($mutability)? decl.value);
+        }
+
+        pub(crate) fn walk_pack<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, pack: &$($lt)? $($mutability)? PackDecl) {
+            visitor.visit_id(&$($mutability)? pack.id);
+            for attr in &$($mutability)? pack.attrs {
+                visitor.visit_attribute(attr);
+            }
+            for param in &$($mutability)? pack.generics {
+                visitor.visit_generic_param(param);
+            }
+            for field in &$($mutability)? pack.fields {
+                visitor.visit_field(field);
+            }
+        }
+
+        pub(crate) fn walk_field<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, field: &$($lt)? $($mutability)? FieldDecl) {
+            visitor.visit_id(&$($mutability)? field.id);
+            for attr in &$($mutability)? field.attrs {
+                visitor.visit_attribute(attr);
+            }
+            visitor.visit_ty(&$($mutability)? field.ty);
+        }
+
+        pub(crate) fn walk_fun<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, fun: &$($lt)? $($mutability)? FunDecl) {
+            visitor.visit_id(&$($mutability)? fun.id);
+            for attr in &$($mutability)? fun.attrs {
+                visitor.visit_attribute(attr);
+            }
+            for param in &$($mutability)? fun.generics {
+                visitor.visit_generic_param(param);
+            }
+            for param in &$($mutability)? fun.params {
+                visitor.visit_param(param);
+            }
+            if let Some(ret) = &$($mutability)? fun.ret {
+                visitor.visit_ty(ret);
+            }
+            for predicate in &$($mutability)? fun.where_clause {
+                visitor.visit_where_predicate(predicate);
+            }
+            if let Some(body) = &$($mutability)? fun.body {
+                visitor.visit_block(body);
+            }
+        }
+
+        pub(crate) fn walk_param<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, param: &$($lt)? $($mutability)? Param) {
+            visitor.visit_id(&$($mutability)? param.id);
+            visitor.visit_ty(&$($mutability)? param.ty);
+        }
+
+        pub(crate) fn walk_generic_param<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, param: &$($lt)? $($mutability)? GenericParam) {
+            visitor.visit_id(&$($mutability)? param.id);
+            for bound in &$($mutability)? param.bounds {
+                visitor.visit_bound(bound);
+            }
+        }
+
+        pub(crate) fn walk_bound<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, bound: &$($lt)? $($mutability)? Bound) {
+            visitor.visit_id(&$($mutability)? bound.id);
+            visitor.visit_ty(&$($mutability)? bound.interface);
+        }
+
+        pub(crate) fn walk_where_predicate<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, predicate: &$($lt)? $($mutability)? WherePredicate) {
+            visitor.visit_id(&$($mutability)? predicate.id);
+            visitor.visit_ty(&$($mutability)? predicate.ty);
+            for bound in &$($mutability)? predicate.bounds {
+                visitor.visit_bound(bound);
+            }
+        }
+
+        pub(crate) fn walk_impls<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, impls: &$($lt)? $($mutability)? ImplsDecl) {
+            visitor.visit_id(&$($mutability)? impls.id);
+            for attr in &$($mutability)? impls.attrs {
+                visitor.visit_attribute(attr);
+            }
+            for param in &$($mutability)? impls.generics {
+                visitor.visit_generic_param(param);
+            }
+            visitor.visit_ty(&$($mutability)? impls.target);
+            for predicate in &$($mutability)? impls.where_clause {
+                visitor.visit_where_predicate(predicate);
+            }
+            for fun in &$($mutability)? impls.funs {
+                visitor.visit_fun(fun);
+            }
+        }
+
+        pub(crate) fn walk_def<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, def: &$($lt)? $($mutability)? DefDecl) {
+            visitor.visit_id(&$($mutability)? def.id);
+            for attr in &$($mutability)? def.attrs {
+                visitor.visit_attribute(attr);
+            }
+            for param in &$($mutability)? def.generics {
+                visitor.visit_generic_param(param);
+            }
+            visitor.visit_ty(&$($mutability)? def.interface);
+            visitor.visit_ty(&$($mutability)? def.target);
+            for predicate in &$($mutability)? def.where_clause {
+                visitor.visit_where_predicate(predicate);
+            }
+            for member in &$($mutability)? def.members {
+                match member {
+                    DefMember::AssocType(def) => visitor.visit_assoc_type_def(def),
+                    DefMember::Field(def) => visitor.visit_assoc_field_def(def),
+                    DefMember::Fun(fun) => visitor.visit_fun(fun)
+                }
+            }
+        }
+
+        pub(crate) fn walk_assoc_type_def<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, def: &$($lt)? $($mutability)? AssocTypeDef) {
+            visitor.visit_id(&$($mutability)? def.id);
+            visitor.visit_ty(&$($mutability)? def.ty);
+        }
+
+        pub(crate) fn walk_assoc_field_def<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, def: &$($lt)? $($mutability)? AssocFieldDef) {
+            visitor.visit_id(&$($mutability)? def.id);
+            visitor.visit_ty(&$($mutability)? def.ty);
+        }
+
+        pub(crate) fn walk_interface<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, interface: &$($lt)? $($mutability)? InterfaceDecl) {
+            visitor.visit_id(&$($mutability)? interface.id);
+            for attr in &$($mutability)? interface.attrs {
+                visitor.visit_attribute(attr);
+            }
+            for param in &$($mutability)? interface.generics {
+                visitor.visit_generic_param(param);
+            }
+            for ty in &$($mutability)? interface.supers {
+                visitor.visit_ty(ty);
+            }
+            for predicate in &$($mutability)? interface.where_clause {
+                visitor.visit_where_predicate(predicate);
+            }
+            for member in &$($mutability)? interface.members {
+                match member {
+                    InterfaceMember::AssocType(decl) => visitor.visit_assoc_type_decl(decl),
+                    InterfaceMember::Field(field) => visitor.visit_field(field),
+                    InterfaceMember::Fun(fun) => visitor.visit_fun(fun)
+                }
+            }
+        }
+
+        pub(crate) fn walk_assoc_type_decl<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, decl: &$($lt)? $($mutability)? AssocTypeDecl) {
+            visitor.visit_id(&$($mutability)? decl.id);
+            for bound in &$($mutability)? decl.bounds {
+                visitor.visit_bound(bound);
+            }
+        }
+
+        pub(crate) fn walk_assoc<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, assoc: &$($lt)? $($mutability)? AssocDecl) {
+            visitor.visit_id(&$($mutability)? assoc.id);
+        }
+
+        pub(crate) fn walk_mod<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, module: &$($lt)? $($mutability)? ModDecl) {
+            visitor.visit_id(&$($mutability)? module.id);
+            for attr in &$($mutability)? module.attrs {
+                visitor.visit_attribute(attr);
+            }
+            if let Some(items) = &$($mutability)? module.items {
+                for item in items {
+                    visitor.visit_item(item);
+                }
+            }
+        }
+
+        pub(crate) fn walk_use<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, decl: &$($lt)? $($mutability)? UseDecl) {
+            visitor.visit_id(&$($mutability)? decl.id);
+            for attr in &$($mutability)? decl.attrs {
+                visitor.visit_attribute(attr);
+            }
+            visitor.visit_use_tree(&$($mutability)? decl.tree);
+        }
+
+        pub(crate) fn walk_use_tree<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, tree: &$($lt)? $($mutability)? UseTree) {
+            visitor.visit_id(&$($mutability)? tree.id);
+            if let UseTreeKind::Nested(trees) = &$($mutability)? tree.kind {
+                for tree in trees {
+                    visitor.visit_use_tree(tree);
+                }
+            }
+        }
+
+        pub(crate) fn walk_block<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, block: &$($lt)? $($mutability)? Block) {
+            visitor.visit_id(&$($mutability)? block.id);
+            for stmt in &$($mutability)? block.stmts {
+                visitor.visit_stmt(stmt);
+            }
+        }
+
+        pub(crate) fn walk_stmt<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, stmt: &$($lt)? $($mutability)? Stmt) {
+            visitor.visit_id(&$($mutability)? stmt.id);
+            for attr in &$($mutability)? stmt.attrs {
+                visitor.visit_attribute(attr);
+            }
+            match &$($mutability)? stmt.kind {
+                StmtKind::Let(binding) => visitor.visit_let(binding),
+                StmtKind::Defer(expr) | StmtKind::Expr(expr) | StmtKind::Semi(expr) | StmtKind::Return(Some(expr)) => visitor.visit_expr(expr),
+                StmtKind::While(cond, body) => {
+                    visitor.visit_expr(cond);
+                    visitor.visit_block(body);
+                }
+                StmtKind::For(pattern, iter, body) => {
+                    visitor.visit_pattern(pattern);
+                    visitor.visit_expr(iter);
+                    visitor.visit_block(body);
+                }
+                StmtKind::Return(None) | StmtKind::Break | StmtKind::Continue => {}
+            }
+        }
+
+        pub(crate) fn walk_let<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, binding: &$($lt)? $($mutability)? LetStmt) {
+            visitor.visit_id(&$($mutability)? binding.id);
+            visitor.visit_pattern(&$($mutability)? binding.pattern);
+            if let Some(ty) = &$($mutability)? binding.ty {
+                visitor.visit_ty(ty);
+            }
+            if let Some(init) = &$($mutability)? binding.init {
+                visitor.visit_expr(init);
+            }
+        }
+
+        pub(crate) fn walk_pattern<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, pattern: &$($lt)? $($mutability)? Pattern) {
+            visitor.visit_id(&$($mutability)? pattern.id);
+            match &$($mutability)? pattern.kind {
+                PatternKind::Binding(_) | PatternKind::Wildcard => {}
+                PatternKind::Tuple(patterns) => {
+                    for pattern in patterns {
+                        visitor.visit_pattern(pattern);
+                    }
+                }
+                PatternKind::Pack(_, fields) => {
+                    for (_, pattern) in fields {
+                        visitor.visit_pattern(pattern);
+                    }
+                }
+            }
+        }
+
+        pub(crate) fn walk_expr<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, expr: &$($lt)? $($mutability)? Expr) {
+            visitor.visit_id(&$($mutability)? expr.id);
+            match &$($mutability)? expr.kind {
+                ExprKind::Literal(_) => {}
+                ExprKind::Path(path) => visitor.visit_path(path),
+                ExprKind::Call(callee, args) => {
+                    visitor.visit_expr(callee);
+                    for arg in args {
+                        visitor.visit_expr(arg);
+                    }
+                }
+                ExprKind::MethodCall(receiver, method, args) => {
+                    visitor.visit_expr(receiver);
+                    visitor.visit_path_segment(method);
+                    for arg in args {
+                        visitor.visit_expr(arg);
+                    }
+                }
+                ExprKind::Field(base, _) => visitor.visit_expr(base),
+                ExprKind::Index(base, index) => {
+                    visitor.visit_expr(base);
+                    visitor.visit_expr(index);
+                }
+                ExprKind::PackLit(path, fields) => {
+                    visitor.visit_path(path);
+                    for field in fields {
+                        visitor.visit_field_init(field);
+                    }
+                }
+                ExprKind::Tuple(elems) | ExprKind::Array(elems) => {
+                    for elem in elems {
+                        visitor.visit_expr(elem);
+                    }
+                }
+                ExprKind::Unary(_, operand) => visitor.visit_expr(operand),
+                ExprKind::Binary(_, lhs, rhs) | ExprKind::Assign(lhs, rhs) | ExprKind::AssignOp(_, lhs, rhs) => {
+                    visitor.visit_expr(lhs);
+                    visitor.visit_expr(rhs);
+                }
+                ExprKind::Cast(operand, ty) => {
+                    visitor.visit_expr(operand);
+                    visitor.visit_ty(ty);
+                }
+                ExprKind::Block(block) | ExprKind::Unsafe(block) => visitor.visit_block(block),
+                ExprKind::If(cond, then, otherwise) => {
+                    visitor.visit_expr(cond);
+                    visitor.visit_block(then);
+                    if let Some(otherwise) = otherwise {
+                        visitor.visit_expr(otherwise);
+                    }
+                }
+            }
+        }
+
+        pub(crate) fn walk_field_init<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, init: &$($lt)? $($mutability)? FieldInit) {
+            visitor.visit_id(&$($mutability)? init.id);
+            visitor.visit_expr(&$($mutability)? init.value);
+        }
+
+        pub(crate) fn walk_path<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, path: &$($lt)? $($mutability)? Path) {
+            visitor.visit_id(&$($mutability)? path.id);
+            for segment in &$($mutability)? path.segments {
+                visitor.visit_path_segment(segment);
+            }
+        }
+
+        pub(crate) fn walk_path_segment<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, segment: &$($lt)? $($mutability)? PathSegment) {
+            visitor.visit_id(&$($mutability)? segment.id);
+            for ty in &$($mutability)? segment.generics {
+                visitor.visit_ty(ty);
+            }
+        }
+
+        pub(crate) fn walk_ty<$($lt,)? V: $visitor$(<$lt>)?>(visitor: &mut V, ty: &$($lt)? $($mutability)? Ty) {
+            visitor.visit_id(&$($mutability)? ty.id);
+            match &$($mutability)? ty.kind {
+                TyKind::Primitive(_) | TyKind::Userdefined(_) | TyKind::Infer => {}
+                TyKind::Generic(_, args) => {
+                    for arg in args {
+                        visitor.visit_ty(arg);
+                    }
+                }
+                TyKind::Ref(inner) | TyKind::Ptr(inner) | TyKind::NoDrop(inner) | TyKind::Assoc(inner, _) => visitor.visit_ty(inner)
+            }
+        }
+    };
+}
+
+make_ast_visitor!(Visitor<'ast>);
@@ -0,0 +1,163 @@
+//! The errors and warnings of the passes which run after parsing. The parser has its own
+//! SyntaxErr since it stops at the first error, while the later passes keep going and
+//! report everything they find, each diagnostic pointing at the code it is about.
+//! A diagnostic has a primary label (where the problem is) and any number of secondary
+//! ones (what lead to it, for example where a value was moved), plus notes.
+
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+use crate::analysis::borrows::BorrowErr;
+use crate::analysis::consteval::ConstErr;
+use crate::analysis::drops::DropErr;
+use crate::analysis::interpret::EvalErr;
+use crate::analysis::leaks::LeakErr;
+use crate::analysis::modules::ModuleErr;
+use crate::analysis::mono::MonoErr;
+use crate::analysis::moves::MoveErr;
+use crate::analysis::resolve::ResolveErr;
+use crate::analysis::solver::SolverErr;
+use crate::analysis::typeck::TypeErr;
+use crate::analysis::unsafety::UnsafeErr;
+use crate::ast::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning
+}
+
+/// A message attached to a piece of the source code
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Label {
+    pub span: Span,
+    pub message: String
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: &str, span: Span, label: &str) -> Self {
+        let primary = Label { span, message: label.to_string() };
+        Self { severity, message: message.to_string(), primary, secondary: Vec::new(), notes: Vec::new() }
+    }
+
+    pub fn error(message: &str, span: Span, label: &str) -> Self {
+        Self::new(Severity::Error, message, span, label)
+    }
+
+    pub fn warning(message: &str, span: Span, label: &str) -> Self {
+        Self::new(Severity::Warning, message, span, label)
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.secondary.push(Label { span, message: message.to_string() });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic against SRC (the input the spans point into), with
+    /// the line of every label and the labelled code underlined: ^ for the primary
+    /// label and - for the secondary ones
+    #[cfg(test)]
+    pub fn render(&self, src: &str) -> String {
+        self.render_at(None, src)
+    }
+
+    /// Renders the diagnostic like render, pointing at the FILE SRC is the source of
+    pub fn render_file(&self, file: &Path, src: &str) -> String {
+        self.render_at(Some(file), src)
+    }
+
+    /// The errors about a whole file (like a missing one) have labels with the
+    /// default span, which points nowhere: those are rendered without a snippet
+    fn render_at(&self, file: Option<&Path>, src: &str) -> String {
+        let mut out = format!("{}: {}\n", self.severity, self.message);
+        let span = self.primary.span;
+        let position = Some(format!("{}:{}", span.row, span.col)).filter(|_| span != Span::default());
+        let location = match (file, position) {
+            (Some(file), Some(position)) => Some(format!("{}:{}", file.display(), position)),
+            (Some(file), None) => Some(file.display().to_string()),
+            (None, position) => position
+        };
+        if let Some(location) = location {
+            out.push_str(&format!(" --> {}\n", location));
+        }
+        let labels = std::iter::once((&self.primary, '^')).chain(self.secondary.iter().map(|label| (label, '-')));
+        let labels: Vec<_> = labels.filter(|(label, _)| label.span != Span::default()).collect();
+        // Every line gets the gutter of the longest row number, so that the bars line up
+        let gutter = " ".repeat(labels.iter().map(|(label, _)| label.span.row.to_string().len()).max().unwrap_or(0));
+        for (label, underline) in labels {
+            let line = src.lines().nth(label.span.row.saturating_sub(1)).unwrap_or("");
+            let row = format!("{:>1$}", label.span.row, gutter.len());
+            let indent = label.span.col.saturating_sub(1);
+            // Only the first line of a span which covers several gets underlined
+            let line_left = line.chars().count().saturating_sub(indent);
+            let width = src.get(label.span.start..label.span.end).map(|s| s.chars().count()).unwrap_or(1);
+            let width = width.min(line_left).max(1);
+            out.push_str(&format!("{} |\n{} | {}\n", gutter, row, line));
+            out.push_str(&format!("{} | {}{} {}\n", gutter, " ".repeat(indent), underline.to_string().repeat(width), label.message));
+        }
+        for note in &self.notes {
+            out.push_str(&format!(" = note: {}\n", note));
+        }
+        out
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning")
+        }
+    }
+}
+
+/// An error of a pass, which knows the file it was found in and its diagnostic
+pub(crate) trait Reported {
+    fn file(&self) -> &Path;
+    fn diagnostic(&self) -> Diagnostic;
+}
+
+macro_rules! reported {
+    ($($err:ty),*) => {
+        $(impl Reported for $err {
+            fn file(&self) -> &Path {
+                &self.file
+            }
+
+            fn diagnostic(&self) -> Diagnostic {
+                self.to_diagnostic()
+            }
+        })*
+    };
+}
+
+reported!(ModuleErr, ResolveErr, TypeErr, SolverErr, MoveErr, BorrowErr, UnsafeErr, LeakErr, DropErr, MonoErr, EvalErr);
+
+/// A const is reported where its evaluation failed
+impl Reported for ConstErr {
+    fn file(&self) -> &Path {
+        &self.err.file
+    }
+
+    fn diagnostic(&self) -> Diagnostic {
+        self.to_diagnostic()
+    }
+}
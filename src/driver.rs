@@ -0,0 +1,94 @@
+//! Compiles a program: loads its files, runs the passes over it and then its
+//! compile-time code. A stage only runs if the ones before it found no errors, since
+//! each pass relies on what the previous ones checked (the type checker needs the
+//! names resolved, the borrow checker needs the expressions typed, and so on). The
+//! checks which only need a typed program all run, so that every error they find is
+//! reported at once.
+
+use std::path::{Path, PathBuf};
+
+use crate::analysis::borrows::BorrowChecker;
+use crate::analysis::consteval::eval_consts;
+use crate::analysis::drops::{DropElaborator, ElaboratedBody};
+use crate::analysis::features::FeatureChecker;
+use crate::analysis::interpret::{Constant, Sandbox};
+use crate::analysis::leaks::LeakChecker;
+use crate::analysis::modules::{ModuleTree, SourceFiles};
+use crate::analysis::mono::Monomorphizer;
+use crate::analysis::moves::MoveChecker;
+use crate::analysis::resolve::Resolver;
+use crate::analysis::schedule::Scheduler;
+use crate::analysis::solver::Solver;
+use crate::analysis::typeck::TypeChecker;
+use crate::analysis::unsafety::UnsafetyChecker;
+use crate::diagnostics::{Diagnostic, Reported};
+
+/// What compiling a program gave
+#[derive(Debug, Default)]
+pub(crate) struct Compilation {
+    /// The diagnostics of the passes which ran, with the file each of them is about
+    pub diagnostics: Vec<(PathBuf, Diagnostic)>,
+    /// The values of the consts, then what the compile-time functions returned
+    pub constants: Vec<Constant>,
+    /// The bodies with their drops, by the name of their function
+    pub bodies: Vec<(String, ElaboratedBody)>,
+    /// What the compile-time functions printed
+    pub output: String
+}
+
+impl Compilation {
+    pub fn failed(&self) -> bool {
+        self.diagnostics.iter().any(|(_, diagnostic)| diagnostic.is_error())
+    }
+
+    fn report<E: Reported>(&mut self, errs: &[E]) {
+        self.diagnostics.extend(errs.iter().map(|err| (err.file().to_path_buf(), err.diagnostic())));
+    }
+}
+
+/// Compiles the program whose root module is the file at ROOT, reading the files
+/// which aren't in SOURCES from the disk
+pub(crate) fn compile(sources: &mut SourceFiles, root: &Path) -> Compilation {
+    let mut compilation = Compilation::default();
+    let (tree, errs) = ModuleTree::load(sources, root);
+    compilation.report(&errs);
+    compilation.diagnostics.extend(FeatureChecker::check_tree(&tree));
+    if compilation.failed() {
+        return compilation;
+    }
+    let (resolutions, errs) = Resolver::resolve(&tree);
+    compilation.report(&errs);
+    if compilation.failed() {
+        return compilation;
+    }
+    let (cx, results, errs) = TypeChecker::check(&tree, &resolutions);
+    compilation.report(&errs);
+    if compilation.failed() {
+        return compilation;
+    }
+    compilation.report(&Solver::check(&cx, &results));
+    compilation.report(&MoveChecker::check(&cx, &results));
+    compilation.report(&BorrowChecker::check(&cx, &results));
+    compilation.report(&UnsafetyChecker::check(&cx, &results));
+    compilation.report(&LeakChecker::check(&cx, &results));
+    let (bodies, errs) = DropElaborator::elaborate(&cx, &results);
+    compilation.report(&errs);
+    compilation.report(&Monomorphizer::collect(&cx, &results, &bodies).1);
+    let name = |body: &ElaboratedBody| cx.funs.get(body.fun).map_or(String::new(), |sig| sig.name.clone());
+    compilation.bodies = bodies.into_iter().map(|body| (name(&body), body)).collect();
+    if compilation.failed() {
+        return compilation;
+    }
+    let (constants, errs) = eval_consts(&cx, &results);
+    compilation.constants = constants;
+    compilation.report(&errs);
+    if compilation.failed() {
+        return compilation;
+    }
+    let mut sandbox = Sandbox::default();
+    let (constants, errs) = Scheduler::default().run(&cx, &results, &mut sandbox);
+    compilation.constants.extend(constants);
+    compilation.output = sandbox.output;
+    compilation.report(&errs);
+    compilation
+}
@@ -1,47 +1,58 @@
-#![feature(assoc_char_funcs)]
-#![feature(int_error_matching)]
 mod parsing;
+#[cfg(test)]
 mod tests;
 mod ast;
+mod analysis;
+mod diagnostics;
+mod driver;
 
-#[allow(unused_imports)]
-use parsing::combinators::*;
-use parsing::{Parser, ParsingBaggage, ParsingContext, combinators::CharParser};
-use parsing::literals::{IdentParser, NumberParser};
-use parsing::types::Type;
-use ast::types;
+use std::fs;
+use std::path::Path;
+use std::process;
 
+use analysis::modules::SourceFiles;
 
 
-#[allow(dead_code)]
+
+const COMPILE_ERROR_EXIT: i32 = 1;
 const NO_ARGS_EXIT: i32 = 2;
-#[allow(dead_code)]
 const FILE_NOT_FOUND_EXIT: i32 = 3;
-#[allow(dead_code)]
 const EXECUTABLE_AND_MORE: usize = 2;
+/// Prints the drops of every body after compiling
+const DROPS_FLAG: &str = "--drops";
 
 fn main() {
-    // let args: Vec<String> = std::env::args().collect();
-    // if args.len() < EXECUTABLE_AND_MORE {
-        // println!("I cannot dig any gold if you don't give me any arguments!");
-        // process::exit(NO_ARGS_EXIT);
-    // }
-    // let file = &args[1];
-
-    // @MAYBE(mike): Move this in another interface, so that we can deal with this gracefully 
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < EXECUTABLE_AND_MORE {
+        println!("I cannot dig any gold if you don't give me any arguments!");
+        process::exit(NO_ARGS_EXIT);
+    }
+    let file = &args[1];
+
+    // @MAYBE(mike): Move this in another interface, so that we can deal with this gracefully
     // when we have more files to compile
-    // let file = match fs::read_to_string(file) {
-        // Ok(contents) => contents,
-        // Err(ioe) => {
-          //   println!("ERROR {} found while opening file {}", ioe, file);
-            // process::exit(FILE_NOT_FOUND_EXIT);
-//         }
-    //};
-    let t = Type;
-
-    let mut ctx = ParsingContext::new(" \n\n Vec<HashMap<Hello<Hi>, Byebye>> abcdef");
-    println!("{:?}", t.parse(&ParsingBaggage::init(), &mut ctx));
-    println!("{:?}", &ctx);
-    println!("{:?}", &ctx.input[ctx.index..]);
-
+    let mut sources = SourceFiles::new();
+    match fs::read_to_string(file) {
+        Ok(contents) => sources.add(file, &contents),
+        Err(ioe) => {
+            println!("ERROR {} found while opening file {}", ioe, file);
+            process::exit(FILE_NOT_FOUND_EXIT);
+        }
+    };
+    let compilation = driver::compile(&mut sources, Path::new(file));
+    for (path, diagnostic) in &compilation.diagnostics {
+        eprintln!("{}", diagnostic.render_file(path, sources.get(path).unwrap_or("")));
+    }
+    print!("{}", compilation.output);
+    for constant in &compilation.constants {
+        println!("{}", constant);
+    }
+    if args[2..].iter().any(|arg| arg == DROPS_FLAG) {
+        for (name, body) in &compilation.bodies {
+            print!("fun {}\n{}", name, body);
+        }
+    }
+    if compilation.failed() {
+        process::exit(COMPILE_ERROR_EXIT);
+    }
 }
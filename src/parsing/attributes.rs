@@ -0,0 +1,85 @@
+use crate::ast::attributes::{AttrStyle, Attribute};
+use crate::ast::ids::NodeId;
+use crate::parsing::combinators::{CharParser, SepByParser};
+use crate::parsing::literals::IdentParser;
+use crate::parsing::{expect, optional, Parser, ParsingBaggage, ParsingContext, SyntaxErr};
+
+// Corresponding EBNF for attributes
+// Attribute -> '#' '!'? '[' Meta ']' | '#' Meta | '(' '#' Meta ')'
+// Meta -> Ident Args?
+// Args -> [':'] '(' Ident (',' Ident)* ')'
+// The colon is there so that the draft's #feature: (minus_attrs) is accepted, and so
+// are the parentheses around (#compile_time). The canonical form (the one we print)
+// is #feature(minus_attrs) for outer attributes and #![feature(minus_attrs)] for inner ones.
+// Inner attributes (#!) are about the whole file, so they can only be at its start
+
+/// Whether an attribute starts at the cursor
+pub(crate) fn at_attribute(ctx: &ParsingContext) -> bool {
+    ctx.cursor.starts_with('#') || (ctx.cursor.starts_with('(') && ctx.cursor[1..].trim_start().starts_with('#'))
+}
+
+/// Whether an inner attribute starts at the cursor
+pub(crate) fn at_inner_attribute(ctx: &ParsingContext) -> bool {
+    ctx.cursor.starts_with("#!")
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AttributeParser;
+
+impl Parser for AttributeParser {
+    type Output = Attribute;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let parenthesised = optional(CharParser('('), baggage, ctx).is_some();
+        expect(CharParser('#'), "an attribute", baggage, ctx)?;
+        let style = match (parenthesised, optional(CharParser('!'), baggage, ctx)) {
+            (false, Some(_)) => AttrStyle::Inner,
+            _ => AttrStyle::Outer
+        };
+        let bracketed = !parenthesised && optional(CharParser('['), baggage, ctx).is_some();
+        if style == AttrStyle::Inner && !bracketed {
+            return Err(SyntaxErr::new("'[' after #!", ctx));
+        }
+        let name = expect(IdentParser, "the name of the attribute", baggage, ctx)?;
+        let colon = optional(CharParser(':'), baggage, ctx).is_some();
+        let args = if colon || ctx.peek_char() == Some('(') {
+            expect(CharParser('('), "the arguments of the attribute", baggage, ctx)?;
+            let args = SepByParser::new(IdentParser, CharParser(','))
+                .parse(baggage, ctx)
+                .map_err(|_| SyntaxErr::new("an attribute argument", ctx))?;
+            expect(CharParser(')'), "')' after the attribute arguments", baggage, ctx)?;
+            args
+        } else {
+            Vec::new()
+        };
+        if bracketed {
+            expect(CharParser(']'), "']' after the attribute", baggage, ctx)?;
+        }
+        if parenthesised {
+            expect(CharParser(')'), "')' after the attribute", baggage, ctx)?;
+        }
+        Ok(Attribute { id: NodeId::DUMMY, style, name, args, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses the outer attributes in front of an item, a field or a statement
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AttributesParser;
+
+impl Parser for AttributesParser {
+    type Output = Vec<Attribute>;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let mut attrs = Vec::new();
+        while at_attribute(ctx) {
+            if at_inner_attribute(ctx) {
+                return Err(SyntaxErr::new("an outer attribute (#! attributes go at the start of the file)", ctx));
+            }
+            attrs.push(AttributeParser.parse(baggage, ctx)?);
+        }
+        Ok(attrs)
+    }
+}
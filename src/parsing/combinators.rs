@@ -4,12 +4,14 @@ use crate::parsing::{ParsingContext, ParserErr, Parser, ParsingBaggage};
 // can have its own label like this:
 // "Expected one of <label1>, <label2> ... "
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) struct SepBy1Parser<P, Del> {
     inside: P,
     delimiter: Del
 }
 
+#[allow(dead_code)]
 impl<P, Del> SepBy1Parser<P, Del> {
     pub fn new(inside: P, delimiter: Del) -> Self {
         Self { inside, delimiter }
@@ -92,11 +94,13 @@ impl<P: Parser, Del: Parser> Parser for SepByParser<P, Del> {
 /// It started eating the '1' and '2' since it was partially matching the very first parser.
 /// However, ManyParser<P> in this case will just return an empty vec, and then a "12". 
 /// It would find 0 instances of "123" but it will find afterwards the "12" we wanted it to find.
+#[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) struct ManyParser<P> {
     inside: P
 }
 
+#[allow(dead_code)]
 impl<P> ManyParser<P> {
     pub fn new(inside: P) -> Self {
         Self { inside }
@@ -155,9 +159,9 @@ impl<'ps, O: Debug, E: Debug + ParserErr> Parser for AlternativeParser<'ps, O, E
         // Perform all parsers until we succeed or we run out of things to do
         for p in &self.variants {
             let r = p.parse(baggage, ctx);
-            match r {
-                Ok(o) => { res = Some(o); break; }
-                _ => ()
+            if let Ok(o) = r {
+                res = Some(o);
+                break;
             }
         }
         if let Some(o) = res {
@@ -185,13 +189,15 @@ impl Parser for StringParser {
     type Output = &'static str;
     type PErr = StringParseErr;
     fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let before = ctx.current_state();
         let res = AttemptParser::new(RawStringParser::new(self.expected)).parse(baggage, ctx);
         if res.is_ok() {
             if ctx.keywords.contains(self.expected) {
                 if let Some(next) = ctx.peek_char() {
                     if next.is_alphanumeric() || next == '_' {
                         // We wanted an keyword, but we actually found an identifier (for example
-                        // bools instead of the bool keyword)
+                        // bools instead of the bool keyword). The identifier is not ours to eat
+                        ctx.roll_back_op(before);
                         let mut found = self.expected.to_string();
                         found.push(next);
                         return Err(StringParseErr::StringMismatch(self.expected, found));
@@ -231,6 +237,7 @@ pub(crate) struct RawCharParser(char);
 pub(crate) struct CharParser(pub char);
 
 impl CharParser {
+    #[allow(dead_code)]
     #[inline(always)]
     pub fn new(ch: char) -> Self {
         Self(ch)
@@ -254,12 +261,14 @@ impl Parser for CharParser {
 }
 
 impl RawCharParser {
+    #[allow(dead_code)]
     #[inline(always)]
     pub fn new(ch: char) -> Self {
         Self(ch)
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) enum CharParseErr {
     CharMismatch(char, char), // expected, found
@@ -271,7 +280,7 @@ impl Parser for RawCharParser {
     type Output = char;
     type PErr = CharParseErr;
 
-    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+    fn parse(&self, _baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
         match ctx.peek_char() {
             None => Err(CharParseErr::Empty),
             Some(ch) => {
@@ -299,6 +308,7 @@ impl RawStringParser {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) enum StringParseErr {
     StringMismatch(&'static str, String),
@@ -311,14 +321,13 @@ impl Parser for RawStringParser {
     type Output = &'static str;
     type PErr = StringParseErr; // Char mismatch err
 
-    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+    fn parse(&self, _baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
         let inp = ctx.eat_many(self.expected.len());
-        let res = match inp {
+        match inp {
             Some(i) if i == self.expected => Ok(self.expected),
             Some(i) => Err(StringParseErr::StringMismatch(self.expected, i.to_string())),
             None => Err(StringParseErr::StringMismatch(self.expected, ctx.cursor.to_string()))
-        };
-        res
+        }
     }
 }
 
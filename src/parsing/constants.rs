@@ -0,0 +1,40 @@
+use crate::ast::declarations::{ConstDecl, ConstKind, Visibility};
+use crate::ast::ids::NodeId;
+use crate::parsing::attributes::AttributesParser;
+use crate::parsing::combinators::{CharParser, StringParser};
+use crate::parsing::declarations::VisibilityParser;
+use crate::parsing::expressions::ExprParser;
+use crate::parsing::literals::IdentParser;
+use crate::parsing::types::Type;
+use crate::parsing::{expect, optional, Parser, ParsingBaggage, ParsingContext, SyntaxErr};
+
+// Corresponding EBNF for constants
+// Const -> Attribute* Vis ('const' | 'static') Ident ':' Ty '=' Expr ';'
+// The type can't be left out, since it decides how the value is evaluated
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConstParser;
+
+impl Parser for ConstParser {
+    type Output = ConstDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let vis = VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        let kind = if optional(StringParser::new("static"), baggage, ctx).is_some() {
+            ConstKind::Static
+        } else {
+            expect(StringParser::new("const"), "const or static", baggage, ctx)?;
+            ConstKind::Const
+        };
+        let name = expect(IdentParser, "the name of the constant", baggage, ctx)?;
+        expect(CharParser(':'), "':' and the type of the constant", baggage, ctx)?;
+        let ty = expect(Type, "the type of the constant", baggage, ctx)?;
+        expect(CharParser('='), "'=' and the value of the constant", baggage, ctx)?;
+        let value = ExprParser.parse(baggage, ctx)?;
+        expect(CharParser(';'), "';' after the constant", baggage, ctx)?;
+        Ok(ConstDecl { id: NodeId::DUMMY, attrs, vis, kind, name, ty, value, span: ctx.span_from(start) })
+    }
+}
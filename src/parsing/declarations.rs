@@ -0,0 +1,539 @@
+use crate::ast::declarations::{AssocDecl, AssocFieldDef, AssocTypeDecl, AssocTypeDef, Bound, DefDecl, DefMember, FieldDecl, FunDecl, GenericParam, ImplsDecl, InterfaceDecl, InterfaceMember, Item, Operator, PackDecl, Param, Program, Receiver, Visibility, WherePredicate};
+use crate::ast::ids::NodeId;
+use crate::ast::statements::{Block, Stmt, StmtKind};
+use crate::ast::operators::{is_operator_char, OpRegistryErr};
+use crate::parsing::attributes::{at_inner_attribute, AttributeParser, AttributesParser};
+use crate::parsing::combinators::{CharParser, SepByParser, StringParser};
+use crate::parsing::constants::ConstParser;
+use crate::parsing::expressions::ExprParser;
+use crate::parsing::literals::IdentParser;
+use crate::parsing::modules::{ModParser, UseParser};
+use crate::parsing::statements::BlockParser;
+use crate::parsing::types::Type;
+use crate::parsing::{expect, list_until, optional, Parser, ParsingBaggage, ParsingContext, SyntaxErr};
+
+// Corresponding EBNF for declarations
+// Program -> InnerAttribute* Item*
+// Attribute and InnerAttribute are in parsing/attributes.rs
+// Item -> Const | Pack | Fun | Impls | Def | Interface | Assoc | Mod | Use
+// Const is in parsing/constants.rs, Mod and Use in parsing/modules.rs
+// Pack -> Attribute* Vis 'pack' Ident Generics? '{' Fields? '}' ';'?
+// Fields -> Field (',' Field)* ','?
+// Field -> Attribute* Vis Ident ':' Ty
+// Fun -> Attribute* Vis 'implicit'? 'unsafe'? 'fun' Ident Generics? '(' Params ')' (':' Ty)? Where? (Block | '=' Expr ';' | ';')
+// Impls -> Attribute* 'implicit'? 'impls' Generics? Ty Where? '{' Fun* '}' ';'?
+// Def -> Attribute* 'def' Generics? Ty 'for' Ty Where? '{' DefMember* '}' ';'?
+// DefMember -> 'type' Ident '=' Ty ';' | Ident ':' Ty ('=' Ident)? ';' | Fun
+// Interface -> Attribute* Vis 'interface' Ident Generics? (':' Ty ('+' Ty)*)? Where? '{' InterfaceMember* '}' ';'?
+// InterfaceMember -> 'type' Ident (':' Bounds)? ';' | Field ';' | Fun
+// Assoc -> 'assoc' Ident '::' Ident 'with' ('[' ']' | OpChar+) ';'?
+// Params -> (Receiver ','?)? (Param (',' Param)*)?
+// Receiver -> 'self' | '&' 'self' | '*' 'self'
+// Param -> 'implicit'? (Ident ':' Ty | Ty Ident)
+// Where -> 'where' Predicate (',' Predicate)*
+// Predicate -> Ty ':' Bounds
+// Generics -> '<' GenericParam (',' GenericParam)* '>'
+// GenericParam -> Ident (':' Bounds)?
+// Bounds -> Bound ('+' Bound)*
+// Bound -> '!'? Ty
+// Vis -> 'public'?
+
+/// Parses the optional 'public' in front of items and fields. Never fails,
+/// since leaving it out just means the thing is private
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct VisibilityParser;
+
+impl Parser for VisibilityParser {
+    type Output = Visibility;
+    type PErr = ();
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        match optional(StringParser::new("public"), baggage, ctx) {
+            Some(_) => Ok(Visibility::Public),
+            None => Ok(Visibility::Private)
+        }
+    }
+}
+
+/// Parses the generic parameters of a declaration: <T, U>.
+/// Yields no parameters if there is no opening angle bracket
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GenericParamsParser;
+
+impl Parser for GenericParamsParser {
+    type Output = Vec<GenericParam>;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        if optional(CharParser('<'), baggage, ctx).is_none() {
+            return Ok(Vec::new());
+        }
+        list_until(',', '>', baggage, ctx, |baggage, ctx| {
+            let start = ctx.position();
+            let name = expect(IdentParser, "a generic parameter", baggage, ctx)?;
+            let bounds = if optional(CharParser(':'), baggage, ctx).is_some() {
+                BoundsParser.parse(baggage, ctx)?
+            } else {
+                Vec::new()
+            };
+            Ok(GenericParam { id: NodeId::DUMMY, name, bounds, span: ctx.span_from(start) })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BoundParser;
+
+impl Parser for BoundParser {
+    type Output = Bound;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let negative = optional(CharParser('!'), baggage, ctx).is_some();
+        let interface = expect(Type, "an interface", baggage, ctx)?;
+        Ok(Bound { id: NodeId::DUMMY, negative, interface, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses one or more bounds separated by '+'
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BoundsParser;
+
+impl Parser for BoundsParser {
+    type Output = Vec<Bound>;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let bounds = SepByParser::new(BoundParser, CharParser('+')).parse(baggage, ctx)?;
+        if bounds.is_empty() {
+            return Err(SyntaxErr::new("an interface", ctx));
+        }
+        Ok(bounds)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WherePredicateParser;
+
+impl Parser for WherePredicateParser {
+    type Output = WherePredicate;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let ty = expect(Type, "a type to bound", baggage, ctx)?;
+        expect(CharParser(':'), "':' before the bounds", baggage, ctx)?;
+        let bounds = BoundsParser.parse(baggage, ctx)?;
+        Ok(WherePredicate { id: NodeId::DUMMY, ty, bounds, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses an optional where clause. Yields no predicates if there is no 'where'
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WhereClauseParser;
+
+impl Parser for WhereClauseParser {
+    type Output = Vec<WherePredicate>;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        if optional(StringParser::new("where"), baggage, ctx).is_none() {
+            return Ok(Vec::new());
+        }
+        let predicates = SepByParser::new(WherePredicateParser, CharParser(',')).parse(baggage, ctx)?;
+        if predicates.is_empty() {
+            return Err(SyntaxErr::new("a where predicate", ctx));
+        }
+        Ok(predicates)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FieldParser;
+
+impl Parser for FieldParser {
+    type Output = FieldDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let vis = VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        let name = expect(IdentParser, "the name of a field", baggage, ctx)?;
+        expect(CharParser(':'), "':' after the field name", baggage, ctx)?;
+        let ty = expect(Type, "the type of the field", baggage, ctx)?;
+        Ok(FieldDecl { id: NodeId::DUMMY, attrs, vis, name, ty, span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PackParser;
+
+impl Parser for PackParser {
+    type Output = PackDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let vis = VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        expect(StringParser::new("pack"), "pack", baggage, ctx)?;
+        let name = expect(IdentParser, "the name of the pack", baggage, ctx)?;
+        let generics = GenericParamsParser.parse(baggage, ctx)?;
+        expect(CharParser('{'), "'{' before the fields of the pack", baggage, ctx)?;
+        let fields = list_until(',', '}', baggage, ctx, |baggage, ctx| FieldParser.parse(baggage, ctx))?;
+        // The draft ends packs with "};", so we allow the semicolon
+        optional(CharParser(';'), baggage, ctx);
+        Ok(PackDecl { id: NodeId::DUMMY, attrs, vis, name, generics, fields, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses the receiver of a method: self, &self or *self
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ReceiverParser;
+
+impl Parser for ReceiverParser {
+    type Output = Receiver;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let receiver = if optional(CharParser('&'), baggage, ctx).is_some() {
+            Receiver::Ref
+        } else if optional(CharParser('*'), baggage, ctx).is_some() {
+            Receiver::Ptr
+        } else {
+            Receiver::Value
+        };
+        expect(StringParser::new("self"), "self", baggage, ctx)?;
+        Ok(receiver)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ParamParser;
+
+impl Parser for ParamParser {
+    type Output = Param;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let implicit = optional(StringParser::new("implicit"), baggage, ctx).is_some();
+        let (name, ty) = match optional(IdentParser.then_discard(CharParser(':')), baggage, ctx) {
+            Some(name) => (name, expect(Type, "the type of the parameter", baggage, ctx)?),
+            None => {
+                // The draft also declares parameters C-style: fun other<T>(T t)
+                let ty = expect(Type, "a parameter", baggage, ctx)?;
+                (expect(IdentParser, "the name of the parameter", baggage, ctx)?, ty)
+            }
+        };
+        Ok(Param { id: NodeId::DUMMY, implicit, name, ty, span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FunParser;
+
+impl Parser for FunParser {
+    type Output = FunDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let vis = VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        let implicit = optional(StringParser::new("implicit"), baggage, ctx).is_some();
+        let is_unsafe = optional(StringParser::new("unsafe"), baggage, ctx).is_some();
+        expect(StringParser::new("fun"), "fun", baggage, ctx)?;
+        // as is a keyword, but it is also the method of Cast which casts desugar to
+        let name = match optional(StringParser::new("as"), baggage, ctx) {
+            Some(name) => name.to_string(),
+            None => expect(IdentParser, "the name of the function", baggage, ctx)?
+        };
+        let generics = GenericParamsParser.parse(baggage, ctx)?;
+        expect(CharParser('('), "'(' before the parameters", baggage, ctx)?;
+        let receiver = optional(ReceiverParser, baggage, ctx);
+        if receiver.is_some() && ctx.peek_char() != Some(')') {
+            expect(CharParser(','), "',' after the receiver", baggage, ctx)?;
+        }
+        let params = list_until(',', ')', baggage, ctx, |baggage, ctx| ParamParser.parse(baggage, ctx))?;
+        let ret = match optional(CharParser(':'), baggage, ctx) {
+            Some(_) => Some(expect(Type, "the return type", baggage, ctx)?),
+            None => None
+        };
+        let where_clause = WhereClauseParser.parse(baggage, ctx)?;
+        let body = if optional(CharParser(';'), baggage, ctx).is_some() {
+            None
+        } else if ctx.peek_char() == Some('{') {
+            Some(BlockParser.parse(baggage, ctx)?)
+        } else if optional(CharParser('='), baggage, ctx).is_some() {
+            // implicit fun itos(implicit n: i32): String = String::from(n);
+            let start = ctx.position();
+            let value = expect(ExprParser, "the value of the function", baggage, ctx)?;
+            let span = ctx.span_from(start);
+            let end = ctx.position();
+            expect(CharParser(';'), "';' after the value of the function", baggage, ctx)?;
+            let stmt = Stmt { id: NodeId::DUMMY, attrs: Vec::new(), kind: StmtKind::Expr(value), span };
+            Some(Block { id: NodeId::DUMMY, stmts: vec![stmt], span, end })
+        } else {
+            return Err(SyntaxErr::new("the body of the function or ';'", ctx));
+        };
+        Ok(FunDecl { id: NodeId::DUMMY, attrs, vis, implicit, is_unsafe, name, generics, receiver, params, ret, where_clause, body, span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ImplsParser;
+
+impl Parser for ImplsParser {
+    type Output = ImplsDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let implicit = optional(StringParser::new("implicit"), baggage, ctx).is_some();
+        expect(StringParser::new("impls"), "impls", baggage, ctx)?;
+        let generics = GenericParamsParser.parse(baggage, ctx)?;
+        let target = expect(Type, "the type to implement", baggage, ctx)?;
+        let where_clause = WhereClauseParser.parse(baggage, ctx)?;
+        expect(CharParser('{'), "'{' before the methods", baggage, ctx)?;
+        let mut funs = Vec::new();
+        while optional(CharParser('}'), baggage, ctx).is_none() {
+            funs.push(FunParser.parse(baggage, ctx)?);
+        }
+        optional(CharParser(';'), baggage, ctx);
+        Ok(ImplsDecl { id: NodeId::DUMMY, attrs, implicit, generics, target, where_clause, funs, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses one member of a def block: an associated type, a field or a function
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DefMemberParser;
+
+impl Parser for DefMemberParser {
+    type Output = DefMember;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        if optional(StringParser::new("type"), baggage, ctx).is_some() {
+            let name = expect(IdentParser, "the name of the associated type", baggage, ctx)?;
+            expect(CharParser('='), "'=' after the associated type", baggage, ctx)?;
+            let ty = expect(Type, "the associated type", baggage, ctx)?;
+            expect(CharParser(';'), "';' after the associated type", baggage, ctx)?;
+            return Ok(DefMember::AssocType(AssocTypeDef { id: NodeId::DUMMY, name, ty, span: ctx.span_from(start) }));
+        }
+
+        if at_fun(baggage, ctx) {
+            return FunParser.parse(baggage, ctx).map(DefMember::Fun);
+        }
+
+        let name = expect(IdentParser, "an associated type, field or function", baggage, ctx)?;
+        expect(CharParser(':'), "':' after the field name", baggage, ctx)?;
+        let ty = expect(Type, "the type of the field", baggage, ctx)?;
+        let source = match optional(CharParser('='), baggage, ctx) {
+            Some(_) => Some(expect(IdentParser, "the field of the pack to use", baggage, ctx)?),
+            None => None
+        };
+        expect(CharParser(';'), "';' after the field", baggage, ctx)?;
+        Ok(DefMember::Field(AssocFieldDef { id: NodeId::DUMMY, name, ty, source, span: ctx.span_from(start) }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DefParser;
+
+impl Parser for DefParser {
+    type Output = DefDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        expect(StringParser::new("def"), "def", baggage, ctx)?;
+        let generics = GenericParamsParser.parse(baggage, ctx)?;
+        let interface = expect(Type, "the interface to implement", baggage, ctx)?;
+        expect(StringParser::new("for"), "for", baggage, ctx)?;
+        let target = expect(Type, "the type implementing the interface", baggage, ctx)?;
+        let where_clause = WhereClauseParser.parse(baggage, ctx)?;
+        expect(CharParser('{'), "'{' before the definitions", baggage, ctx)?;
+        let mut members = Vec::new();
+        while optional(CharParser('}'), baggage, ctx).is_none() {
+            members.push(DefMemberParser.parse(baggage, ctx)?);
+        }
+        optional(CharParser(';'), baggage, ctx);
+        Ok(DefDecl { id: NodeId::DUMMY, attrs, generics, interface, target, where_clause, members, span: ctx.span_from(start) })
+    }
+}
+
+/// Whether the member at the cursor (after its attributes and visibility) is a function
+fn at_fun(baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> bool {
+    let before = ctx.current_state();
+    let _ = AttributesParser.parse(baggage, ctx);
+    VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+    optional(StringParser::new("implicit"), baggage, ctx);
+    optional(StringParser::new("unsafe"), baggage, ctx);
+    let is_fun = ctx.peek_word() == "fun";
+    ctx.roll_back_op(before);
+    is_fun
+}
+
+/// Parses one member of an interface: an associated type, a required field or a function
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InterfaceMemberParser;
+
+impl Parser for InterfaceMemberParser {
+    type Output = InterfaceMember;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        if optional(StringParser::new("type"), baggage, ctx).is_some() {
+            let name = expect(IdentParser, "the name of the associated type", baggage, ctx)?;
+            let bounds = match optional(CharParser(':'), baggage, ctx) {
+                Some(_) => BoundsParser.parse(baggage, ctx)?,
+                None => Vec::new()
+            };
+            expect(CharParser(';'), "';' after the associated type", baggage, ctx)?;
+            return Ok(InterfaceMember::AssocType(AssocTypeDecl { id: NodeId::DUMMY, name, bounds, span: ctx.span_from(start) }));
+        }
+        if at_fun(baggage, ctx) {
+            return FunParser.parse(baggage, ctx).map(InterfaceMember::Fun);
+        }
+        let field = FieldParser.parse(baggage, ctx)?;
+        expect(CharParser(';'), "';' after the field", baggage, ctx)?;
+        Ok(InterfaceMember::Field(field))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InterfaceParser;
+
+impl Parser for InterfaceParser {
+    type Output = InterfaceDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let vis = VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        expect(StringParser::new("interface"), "interface", baggage, ctx)?;
+        let name = expect(IdentParser, "the name of the interface", baggage, ctx)?;
+        let generics = GenericParamsParser.parse(baggage, ctx)?;
+        let mut supers = Vec::new();
+        if optional(CharParser(':'), baggage, ctx).is_some() {
+            loop {
+                supers.push(expect(Type, "a super interface", baggage, ctx)?);
+                if optional(CharParser('+'), baggage, ctx).is_none() {
+                    break;
+                }
+            }
+        }
+        let where_clause = WhereClauseParser.parse(baggage, ctx)?;
+        expect(CharParser('{'), "'{' before the members of the interface", baggage, ctx)?;
+        let mut members = Vec::new();
+        while optional(CharParser('}'), baggage, ctx).is_none() {
+            members.push(InterfaceMemberParser.parse(baggage, ctx)?);
+        }
+        optional(CharParser(';'), baggage, ctx);
+        Ok(InterfaceDecl { id: NodeId::DUMMY, attrs, vis, name, generics, supers, where_clause, members, span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AssocParser;
+
+impl Parser for AssocParser {
+    type Output = AssocDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        expect(StringParser::new("assoc"), "assoc", baggage, ctx)?;
+        let interface = expect(IdentParser, "an interface", baggage, ctx)?;
+        expect(StringParser::new("::"), "'::' between the interface and the method", baggage, ctx)?;
+        let method = expect(IdentParser, "the method to associate", baggage, ctx)?;
+        expect(StringParser::new("with"), "with", baggage, ctx)?;
+        let op = if optional(CharParser('['), baggage, ctx).is_some() {
+            expect(CharParser(']'), "']' closing the index operator", baggage, ctx)?;
+            Operator::Index
+        } else {
+            let len = ctx.cursor.find(|c| !is_operator_char(c)).unwrap_or(ctx.cursor.len());
+            if len == 0 {
+                return Err(SyntaxErr::new("an operator", ctx));
+            }
+            let symbol = ctx.eat_many(len).unwrap_or_default().to_string();
+            ctx.eat_ws();
+            Operator::Binary(symbol)
+        };
+        optional(CharParser(';'), baggage, ctx);
+        Ok(AssocDecl { id: NodeId::DUMMY, interface, method, op, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses any item, deciding which one by the keyword after its attributes and visibility.
+/// The operators of assoc declarations can be used from the next item on
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ItemParser;
+
+impl Parser for ItemParser {
+    type Output = Item;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let before = ctx.current_state();
+        AttributesParser.parse(baggage, ctx)?;
+        VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        optional(StringParser::new("implicit"), baggage, ctx);
+        optional(StringParser::new("unsafe"), baggage, ctx);
+        let keyword = ctx.peek_word();
+        let not_an_item = SyntaxErr::new("an item (const, static, pack, fun, impls, def, interface, assoc, mod or use)", ctx);
+        ctx.roll_back_op(before);
+        let item = match keyword {
+            "const" | "static" => ConstParser.parse(baggage, ctx).map(Item::Const),
+            "pack" => PackParser.parse(baggage, ctx).map(Item::Pack),
+            "fun" => FunParser.parse(baggage, ctx).map(Item::Fun),
+            "impls" => ImplsParser.parse(baggage, ctx).map(Item::Impls),
+            "def" => DefParser.parse(baggage, ctx).map(Item::Def),
+            "interface" => InterfaceParser.parse(baggage, ctx).map(Item::Interface),
+            "assoc" => AssocParser.parse(baggage, ctx).map(Item::Assoc),
+            "mod" => ModParser.parse(baggage, ctx).map(Item::Mod),
+            "use" => UseParser.parse(baggage, ctx).map(Item::Use),
+            _ => Err(not_an_item)
+        }?;
+        if let Item::Assoc(assoc) = &item {
+            baggage.operators.borrow_mut().declare(assoc).map_err(|err| {
+                let expected = match err {
+                    OpRegistryErr::AlreadyAssociated(..) => "an operator which is not associated yet",
+                    OpRegistryErr::Reserved(_) => "an operator which is not reserved"
+                };
+                SyntaxErr { expected: expected.to_string(), found: assoc.to_string(), row: assoc.span.row, col: assoc.span.col }
+            })?;
+        }
+        Ok(item)
+    }
+}
+
+/// Parses items until the end of the input
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ProgramParser;
+
+impl Parser for ProgramParser {
+    type Output = Program;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let mut attrs = Vec::new();
+        while at_inner_attribute(ctx) {
+            attrs.push(AttributeParser.parse(baggage, ctx)?);
+        }
+        let mut items = Vec::new();
+        while !ctx.cursor.is_empty() {
+            items.push(ItemParser.parse(baggage, ctx)?);
+        }
+        Ok(Program { id: NodeId::DUMMY, attrs, items, span: ctx.span_from(start) })
+    }
+}
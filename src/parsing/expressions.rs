@@ -0,0 +1,429 @@
+use crate::ast::expressions::{Expr, ExprKind, FieldInit, Literal, Path, PathSegment, UnaryOp};
+use crate::ast::ids::NodeId;
+use crate::ast::operators::{is_operator_char, OperatorRegistry};
+use crate::ast::types::Ty;
+use crate::parsing::combinators::{CharParser, StringParser};
+use crate::parsing::literals::{CharLiteralParser, IdentParser, IntLiteralParser, LiteralParseErr, StringLiteralParser};
+use crate::parsing::statements::BlockParser;
+use crate::parsing::types::Type;
+use crate::parsing::{expect, list_until, optional, Parser, ParsingBaggage, ParsingContext, SyntaxErr};
+
+// Corresponding EBNF for expressions
+// Expr -> Binary (AssignOp Expr)?
+// AssignOp -> '=' | BinOp '='
+// Binary -> Cast (BinOp Cast)*, where BinOp is any builtin or associated operator
+// Cast -> Unary ('as' Ty)*
+// Unary -> ('-' | '!' | '*' | '&') Unary | Postfix
+// Postfix -> Primary ('(' Args ')' | '.' Field | '.' Segment '(' Args ')' | '[' Expr ']')*
+// Args -> (Expr (',' Expr)* ','?)?
+// Field -> Ident | Int
+// Primary -> Literal | '(' ')' | '(' Expr ')' | '(' Expr ',' Args ')' | '[' Args ']'
+//          | Path | Path '{' FieldInits '}' | Block | 'unsafe' Block | If
+// Literal -> Int | Char | String | 'true' | 'false'
+// Path -> ('self' | 'super' ('::' 'super')* | Segment) ('::' Segment)*
+// Segment -> Ident ('<' Ty (',' Ty)* '>')?
+// FieldInits -> (Ident (':' Expr)? (',' Ident (':' Expr)?)* ','?)?
+// If -> 'if' Cond Block ('else' (If | Block))?
+// Cond is an Expr which can't be a pack literal outside of brackets, so that the
+// '{' of if x { } is not taken for a literal. Same for the conditions of while and for.
+// Generics need no turbofish: a < after a name starts generic arguments only if
+// they close and are followed by '(' or '::', otherwise it is a comparison. So
+// sizeof<T>() and ptr::read<T>(p) are calls, while a < b && c > d compares
+// (a < b > (c) is a call as well, write (a < b) > (c) to compare)
+
+/// Turns the error of a literal parser into a syntax error at the cursor
+fn literal_err(err: LiteralParseErr, ctx: &ParsingContext) -> SyntaxErr {
+    let expected = match err {
+        LiteralParseErr::NotALiteral => "a literal".to_string(),
+        LiteralParseErr::Unterminated => "the closing quote of the literal".to_string(),
+        LiteralParseErr::InvalidEscape(c) => format!("a valid escape instead of \\{}", c),
+        LiteralParseErr::Overflow(n) => format!("an integer smaller than {}", n),
+        LiteralParseErr::NotAnInt(n) => format!("a decimal integer instead of {}", n),
+        LiteralParseErr::EmptyChar => "a character between the quotes".to_string()
+    };
+    SyntaxErr::new(&expected, ctx)
+}
+
+/// An operator at the cursor, in a place where a binary operator can be
+#[derive(Debug, Clone, PartialEq)]
+enum OpToken {
+    Binary(String),
+    Assign,
+    AssignOp(String)
+}
+
+/// Reads the operator at the cursor without eating it, returning it and its length.
+/// Operator characters are eaten greedily, as long as they make up a known
+/// operator: with a associated to +-, a+-b is a +- b, otherwise it is a + -b
+fn peek_operator(registry: &OperatorRegistry, ctx: &ParsingContext) -> Option<(OpToken, usize)> {
+    let run = ctx.cursor.find(|c: char| !is_operator_char(c)).unwrap_or(ctx.cursor.len());
+    (1..=run).rev().find_map(|len| {
+        let symbol = &ctx.cursor[..len];
+        let token = if registry.is_binary(symbol) {
+            OpToken::Binary(symbol.to_string())
+        } else if symbol == "=" {
+            OpToken::Assign
+        } else if symbol.ends_with('=') && registry.is_binary(&symbol[..len - 1]) {
+            OpToken::AssignOp(symbol[..len - 1].to_string())
+        } else {
+            return None;
+        };
+        Some((token, len))
+    })
+}
+
+/// Tries to parse generic arguments <A, B> at the cursor. They are only taken if
+/// they are followed by '(' or '::', otherwise nothing is eaten and the < is left
+/// to be a comparison
+fn generic_args(baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Vec<Ty> {
+    if ctx.peek_char() != Some('<') {
+        return Vec::new();
+    }
+    let state = ctx.current_state();
+    ctx.advance_one().eat_ws();
+    let args = list_until(',', '>', baggage, ctx, |baggage, ctx| expect(Type, "a type", baggage, ctx));
+    match args {
+        Ok(args) if !args.is_empty() && (ctx.cursor.starts_with('(') || ctx.cursor.starts_with("::")) => args,
+        _ => {
+            ctx.roll_back_op(state);
+            Vec::new()
+        }
+    }
+}
+
+/// Parses a path segment: a name with optional generic arguments
+fn segment(baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<PathSegment, SyntaxErr> {
+    let start = ctx.position();
+    let name = expect(IdentParser, "a name", baggage, ctx)?;
+    let generics = generic_args(baggage, ctx);
+    Ok(PathSegment { id: NodeId::DUMMY, name, generics, span: ctx.span_from(start) })
+}
+
+/// Parses a full expression, including assignments
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ExprParser;
+
+impl Parser for ExprParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        AssignExprParser { pack_lits: true }.parse(baggage, ctx)
+    }
+}
+
+/// Parses the condition of an if or a while, or what a for loop iterates over,
+/// where a pack literal would be mistaken for the block which follows
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CondParser;
+
+impl Parser for CondParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        AssignExprParser { pack_lits: false }.parse(baggage, ctx)
+    }
+}
+
+/// Parses a = b, a += b and anything which binds tighter. Assignments are right
+/// associative: a = b = c is a = (b = c).
+/// PACK_LITS tells whether Path { } is a pack literal, which is the case everywhere
+/// except in conditions (until a bracket is opened)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AssignExprParser {
+    pub pack_lits: bool
+}
+
+impl Parser for AssignExprParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let place = BinaryExprParser { min_prec: 1, pack_lits: self.pack_lits }.parse(baggage, ctx)?;
+        let op = peek_operator(&baggage.operators.borrow(), ctx);
+        let (op, len) = match op {
+            Some((OpToken::Binary(_), _)) | None => return Ok(place),
+            Some(op) => op
+        };
+        ctx.advance_bytes(len);
+        ctx.eat_ws();
+        let value = Box::new(self.parse(baggage, ctx)?);
+        let kind = match op {
+            OpToken::AssignOp(op) => ExprKind::AssignOp(op, Box::new(place), value),
+            _ => ExprKind::Assign(Box::new(place), value)
+        };
+        Ok(Expr { id: NodeId::DUMMY, kind, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses binary operators binding at least as tight as MIN_PREC, by precedence
+/// climbing. Every binary operator is left associative
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BinaryExprParser {
+    pub min_prec: u8,
+    pub pack_lits: bool
+}
+
+impl Parser for BinaryExprParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let mut lhs = CastExprParser { pack_lits: self.pack_lits }.parse(baggage, ctx)?;
+        loop {
+            let op = peek_operator(&baggage.operators.borrow(), ctx);
+            let (op, len) = match op {
+                Some((OpToken::Binary(op), len)) if OperatorRegistry::precedence(&op) >= self.min_prec => (op, len),
+                _ => return Ok(lhs)
+            };
+            ctx.advance_bytes(len);
+            ctx.eat_ws();
+            let rhs_parser = BinaryExprParser { min_prec: OperatorRegistry::precedence(&op) + 1, pack_lits: self.pack_lits };
+            let rhs = rhs_parser.parse(baggage, ctx)?;
+            lhs = Expr { id: NodeId::DUMMY, kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), span: ctx.span_from(start) };
+        }
+    }
+}
+
+/// Parses casts, which bind tighter than binary operators and looser than unary
+/// ones: &t as *T as *u8 is ((&t) as *T) as *u8
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CastExprParser {
+    pub pack_lits: bool
+}
+
+impl Parser for CastExprParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let mut expr = UnaryExprParser { pack_lits: self.pack_lits }.parse(baggage, ctx)?;
+        while optional(StringParser::new("as"), baggage, ctx).is_some() {
+            let ty = expect(Type, "the type to cast to", baggage, ctx)?;
+            expr = Expr { id: NodeId::DUMMY, kind: ExprKind::Cast(Box::new(expr), ty), span: ctx.span_from(start) };
+        }
+        Ok(expr)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UnaryExprParser {
+    pub pack_lits: bool
+}
+
+impl Parser for UnaryExprParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let op = match ctx.peek_char() {
+            Some('-') => UnaryOp::Neg,
+            Some('!') => UnaryOp::Not,
+            Some('*') => UnaryOp::Deref,
+            Some('&') => UnaryOp::Ref,
+            _ => return PostfixExprParser { pack_lits: self.pack_lits }.parse(baggage, ctx)
+        };
+        // One character at a time, so &&x is & &x and --x is - -x
+        ctx.advance_one().eat_ws();
+        let operand = self.parse(baggage, ctx)?;
+        Ok(Expr { id: NodeId::DUMMY, kind: ExprKind::Unary(op, Box::new(operand)), span: ctx.span_from(start) })
+    }
+}
+
+/// Parses a primary expression followed by any number of calls, field accesses,
+/// method calls and indexing: a.b(c)[d](e)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PostfixExprParser {
+    pub pack_lits: bool
+}
+
+impl Parser for PostfixExprParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let mut expr = PrimaryExprParser { pack_lits: self.pack_lits }.parse(baggage, ctx)?;
+        loop {
+            let kind = match ctx.peek_char() {
+                Some('(') => {
+                    ctx.advance_one().eat_ws();
+                    let args = list_until(',', ')', baggage, ctx, |baggage, ctx| ExprParser.parse(baggage, ctx))?;
+                    ExprKind::Call(Box::new(expr), args)
+                }
+                Some('[') => {
+                    ctx.advance_one().eat_ws();
+                    let index = ExprParser.parse(baggage, ctx)?;
+                    expect(CharParser(']'), "']'", baggage, ctx)?;
+                    ExprKind::Index(Box::new(expr), Box::new(index))
+                }
+                Some('.') => {
+                    ctx.advance_one().eat_ws();
+                    let word = ctx.peek_word();
+                    if word.starts_with(|c: char| c.is_ascii_digit()) {
+                        // t.0, the fields of tuples
+                        if !word.chars().all(|c| c.is_ascii_digit()) {
+                            return Err(SyntaxErr::new("the number of a tuple field", ctx));
+                        }
+                        let len = word.len();
+                        ctx.advance_bytes(len);
+                        ctx.eat_ws();
+                        ExprKind::Field(Box::new(expr), word.to_string())
+                    } else {
+                        let segment = segment(baggage, ctx)?;
+                        if optional(CharParser('('), baggage, ctx).is_some() {
+                            let args = list_until(',', ')', baggage, ctx, |baggage, ctx| ExprParser.parse(baggage, ctx))?;
+                            ExprKind::MethodCall(Box::new(expr), segment, args)
+                        } else {
+                            ExprKind::Field(Box::new(expr), segment.name)
+                        }
+                    }
+                }
+                _ => return Ok(expr)
+            };
+            expr = Expr { id: NodeId::DUMMY, kind, span: ctx.span_from(start) };
+        }
+    }
+}
+
+/// Parses if cond { } else ..., where the else branch is a block or another if
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IfParser;
+
+impl Parser for IfParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        expect(StringParser::new("if"), "if", baggage, ctx)?;
+        let cond = CondParser.parse(baggage, ctx)?;
+        let then = BlockParser.parse(baggage, ctx)?;
+        let otherwise = if optional(StringParser::new("else"), baggage, ctx).is_some() {
+            let else_start = ctx.position();
+            if ctx.peek_word() == "if" {
+                Some(Box::new(IfParser.parse(baggage, ctx)?))
+            } else {
+                let block = BlockParser.parse(baggage, ctx)?;
+                Some(Box::new(Expr { id: NodeId::DUMMY, kind: ExprKind::Block(block), span: ctx.span_from(else_start) }))
+            }
+        } else {
+            None
+        };
+        Ok(Expr { id: NodeId::DUMMY, kind: ExprKind::If(Box::new(cond), then, otherwise), span: ctx.span_from(start) })
+    }
+}
+
+/// Parses a path, and the pack literal it starts if pack literals are allowed here
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PathExprParser {
+    pub pack_lits: bool
+}
+
+impl Parser for PathExprParser {
+    type Output = ExprKind;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let first = if optional(StringParser::new("self"), baggage, ctx).is_some() {
+            PathSegment { id: NodeId::DUMMY, name: "self".to_string(), generics: Vec::new(), span: ctx.span_from(start) }
+        } else if optional(StringParser::new("super"), baggage, ctx).is_some() {
+            PathSegment { id: NodeId::DUMMY, name: "super".to_string(), generics: Vec::new(), span: ctx.span_from(start) }
+        } else {
+            let name = expect(IdentParser, "an expression", baggage, ctx)?;
+            let generics = generic_args(baggage, ctx);
+            PathSegment { id: NodeId::DUMMY, name, generics, span: ctx.span_from(start) }
+        };
+        let mut segments = vec![first];
+        while ctx.cursor.starts_with("::") {
+            ctx.advance_bytes(2);
+            ctx.eat_ws();
+            // super::super::f goes up two modules
+            let only_supers = segments.iter().all(|segment| segment.name == "super");
+            let segment_start = ctx.position();
+            if only_supers && optional(StringParser::new("super"), baggage, ctx).is_some() {
+                segments.push(PathSegment { id: NodeId::DUMMY, name: "super".to_string(), generics: Vec::new(), span: ctx.span_from(segment_start) });
+            } else {
+                segments.push(segment(baggage, ctx)?);
+            }
+        }
+        let path = Path { id: NodeId::DUMMY, segments, span: ctx.span_from(start) };
+        if !self.pack_lits || ctx.peek_char() != Some('{') {
+            return Ok(ExprKind::Path(path));
+        }
+        ctx.advance_one().eat_ws();
+        let fields = list_until(',', '}', baggage, ctx, |baggage, ctx| {
+            let start = ctx.position();
+            let name = expect(IdentParser, "a field", baggage, ctx)?;
+            let value = match optional(CharParser(':'), baggage, ctx) {
+                Some(_) => ExprParser.parse(baggage, ctx)?,
+                None => Expr { id: NodeId::DUMMY, kind: ExprKind::Path(Path::single(&name, ctx.span_from(start))), span: ctx.span_from(start) }
+            };
+            Ok(FieldInit { id: NodeId::DUMMY, name, value, span: ctx.span_from(start) })
+        })?;
+        Ok(ExprKind::PackLit(path, fields))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PrimaryExprParser {
+    pub pack_lits: bool
+}
+
+impl Parser for PrimaryExprParser {
+    type Output = Expr;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let kind = match ctx.peek_char() {
+            Some('{') => ExprKind::Block(BlockParser.parse(baggage, ctx)?),
+            Some('(') => {
+                ctx.advance_one().eat_ws();
+                if optional(CharParser(')'), baggage, ctx).is_some() {
+                    ExprKind::Literal(Literal::Unit)
+                } else {
+                    let first = ExprParser.parse(baggage, ctx)?;
+                    if optional(CharParser(','), baggage, ctx).is_some() {
+                        let mut elements = vec![first];
+                        elements.extend(list_until(',', ')', baggage, ctx, |baggage, ctx| ExprParser.parse(baggage, ctx))?);
+                        ExprKind::Tuple(elements)
+                    } else {
+                        expect(CharParser(')'), "')'", baggage, ctx)?;
+                        // Parentheses only group, so they don't get a node of their own
+                        first.kind
+                    }
+                }
+            }
+            Some('[') => {
+                ctx.advance_one().eat_ws();
+                ExprKind::Array(list_until(',', ']', baggage, ctx, |baggage, ctx| ExprParser.parse(baggage, ctx))?)
+            }
+            Some('"') => ExprKind::Literal(Literal::Str(StringLiteralParser.parse(baggage, ctx).map_err(|err| literal_err(err, ctx))?)),
+            Some('\'') => ExprKind::Literal(Literal::Char(CharLiteralParser.parse(baggage, ctx).map_err(|err| literal_err(err, ctx))?)),
+            Some(c) if c.is_ascii_digit() => ExprKind::Literal(Literal::Int(IntLiteralParser.parse(baggage, ctx).map_err(|err| literal_err(err, ctx))?)),
+            _ => match ctx.peek_word() {
+                "if" => return IfParser.parse(baggage, ctx),
+                "unsafe" => {
+                    expect(StringParser::new("unsafe"), "unsafe", baggage, ctx)?;
+                    ExprKind::Unsafe(BlockParser.parse(baggage, ctx)?)
+                }
+                "true" | "false" => {
+                    let value = expect(StringParser::new("true"), "true", baggage, ctx).is_ok();
+                    if !value {
+                        expect(StringParser::new("false"), "false", baggage, ctx)?;
+                    }
+                    ExprKind::Literal(Literal::Bool(value))
+                }
+                _ => PathExprParser { pack_lits: self.pack_lits }.parse(baggage, ctx)?
+            }
+        };
+        Ok(Expr { id: NodeId::DUMMY, kind, span: ctx.span_from(start) })
+    }
+}
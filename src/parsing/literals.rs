@@ -3,9 +3,11 @@ use super::ParsingContext;
 use crate::parsing::{Parser, ParserErr, ParsingBaggage};
 
 
+#[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) struct ParseNumData(i32, i32, String);
 
+#[allow(dead_code)]
 #[derive(Debug, PartialEq)]
 pub(crate) enum NumberParseErr {
     InvalidNumber(ParseNumData),
@@ -16,12 +18,14 @@ pub(crate) enum NumberParseErr {
 
 impl ParserErr for NumberParseErr {}
 
+#[allow(dead_code)]
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct NumberParser {}
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct IdentParser;
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) enum IdentParserErrReason {
     IllicitChar(char),
@@ -34,7 +38,9 @@ pub(crate) enum IdentParserErrReason {
 pub(crate) struct IdentParserErr {
     pub found: String,
     pub reason: IdentParserErrReason,
+    #[allow(dead_code)]
     row: usize,
+    #[allow(dead_code)]
     col: usize
 }
 
@@ -43,7 +49,7 @@ impl IdentParserErr {
         Self { found: found.to_string(), reason, row: 0, col: 0 }
     }
 
-    fn _parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<String, IdentParserErr> {
+    fn _parse(&self, _baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<String, IdentParserErr> {
         let inp = ctx.eat_until_ws();
         let _inp = inp.to_string();
         let inp: &str = &_inp;
@@ -151,3 +157,142 @@ impl Parser for NumberParser {
 }
 
 
+#[derive(Debug, PartialEq)]
+pub(crate) enum LiteralParseErr {
+    NotALiteral,
+    Unterminated,
+    InvalidEscape(char),
+    Overflow(String),
+    /// A number which isn't a decimal integer, such as 1.5 or 0xff
+    NotAnInt(String),
+    EmptyChar
+}
+
+impl ParserErr for LiteralParseErr {}
+
+/// Parses an unsigned integer literal such as 123 or 1_000_000.
+/// Unlike NumberParser this stops at the first non-digit, so it works inside
+/// expressions (123; or f(1,2)). Negative numbers are negated literals. There
+/// are no float or hex literals, so 1.5 and 0xff are errors rather than 1 and 0
+/// followed by something else
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IntLiteralParser;
+
+impl Parser for IntLiteralParser {
+    type Output = u128;
+    type PErr = LiteralParseErr;
+
+    fn parse(&self, _baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        if !ctx.cursor.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(LiteralParseErr::NotALiteral);
+        }
+        let len = ctx.cursor.find(|c: char| !(c.is_ascii_digit() || c == '_')).unwrap_or(ctx.cursor.len());
+        let mut rest = ctx.cursor[len..].chars();
+        let fraction = rest.next() == Some('.') && rest.next().is_some_and(|c| c.is_ascii_digit());
+        if fraction || ctx.cursor[len..].starts_with(|c: char| c.is_alphabetic()) {
+            let end = ctx.cursor.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).unwrap_or(ctx.cursor.len());
+            return Err(LiteralParseErr::NotAnInt(ctx.cursor[..end].to_string()));
+        }
+        let digits: String = ctx.cursor[..len].chars().filter(|&c| c != '_').collect();
+        let n = digits.parse::<u128>().map_err(|_| LiteralParseErr::Overflow(digits.clone()))?;
+        ctx.advance_bytes(len);
+        ctx.eat_ws();
+        Ok(n)
+    }
+}
+
+/// Reads one (possibly escaped) character of a string or char literal from CHARS
+fn unescape(chars: &mut std::str::CharIndices, quote: char) -> Result<Option<(usize, char)>, LiteralParseErr> {
+    match chars.next() {
+        None => Err(LiteralParseErr::Unterminated),
+        Some((_, c)) if c == quote => Ok(None),
+        Some((idx, '\\')) => {
+            let escaped = match chars.next() {
+                Some((_, 'n')) => '\n',
+                Some((_, 't')) => '\t',
+                Some((_, 'r')) => '\r',
+                Some((_, '0')) => '\0',
+                Some((_, '\\')) => '\\',
+                Some((_, '\'')) => '\'',
+                Some((_, '"')) => '"',
+                Some((_, 'u')) => unescape_unicode(chars)?,
+                Some((_, other)) => return Err(LiteralParseErr::InvalidEscape(other)),
+                None => return Err(LiteralParseErr::Unterminated)
+            };
+            Ok(Some((idx, escaped)))
+        }
+        Some((idx, c)) => Ok(Some((idx, c)))
+    }
+}
+
+/// Reads the {1F600} of a \u{1F600} escape, up to 6 hex digits naming a char
+fn unescape_unicode(chars: &mut std::str::CharIndices) -> Result<char, LiteralParseErr> {
+    if !matches!(chars.next(), Some((_, '{'))) {
+        return Err(LiteralParseErr::InvalidEscape('u'));
+    }
+    let mut digits = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '}')) => break,
+            Some((_, c)) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+            Some(_) => return Err(LiteralParseErr::InvalidEscape('u')),
+            None => return Err(LiteralParseErr::Unterminated)
+        }
+    }
+    u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32).ok_or(LiteralParseErr::InvalidEscape('u'))
+}
+
+/// Parses "a string literal", with the usual escapes (\n, \t, \r, \0, \\, \', \")
+/// and \u{...} for any other char
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StringLiteralParser;
+
+impl Parser for StringLiteralParser {
+    type Output = String;
+    type PErr = LiteralParseErr;
+
+    fn parse(&self, _baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        if !ctx.cursor.starts_with('"') {
+            return Err(LiteralParseErr::NotALiteral);
+        }
+        let mut chars = ctx.cursor.char_indices();
+        chars.next();
+        let mut s = String::new();
+        while let Some((_, c)) = unescape(&mut chars, '"')? {
+            s.push(c);
+        }
+        let len = chars.next().map(|(idx, _)| idx).unwrap_or(ctx.cursor.len());
+        ctx.advance_bytes(len);
+        ctx.eat_ws();
+        Ok(s)
+    }
+}
+
+/// Parses 'c', a char literal with the same escapes as strings
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CharLiteralParser;
+
+impl Parser for CharLiteralParser {
+    type Output = char;
+    type PErr = LiteralParseErr;
+
+    fn parse(&self, _baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        if !ctx.cursor.starts_with('\'') {
+            return Err(LiteralParseErr::NotALiteral);
+        }
+        let mut chars = ctx.cursor.char_indices();
+        chars.next();
+        let c = match unescape(&mut chars, '\'')? {
+            Some((_, c)) => c,
+            None => return Err(LiteralParseErr::EmptyChar)
+        };
+        match chars.next() {
+            Some((idx, '\'')) => {
+                ctx.advance_bytes(idx + 1);
+                ctx.eat_ws();
+                Ok(c)
+            }
+            _ => Err(LiteralParseErr::Unterminated)
+        }
+    }
+}
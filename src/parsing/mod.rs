@@ -1,35 +1,92 @@
-/// This represents the whole parsing module, which contains the context, 
-/// and the parser trait and some parsing utilities. The parser combinator
-/// themselves are in different files from this module
-/// This parsing framework has most of its inspiration from Parsley Scala library
-/// (https://github.com/j-mie6/Parsley). While it is popular to create the parsing machine
-/// at compile-time via a state machine, we opt for a handwritten parser which operates at runtime
-/// (since realistically parsing is never the longest part of a compiler and it allows us
-/// to have perfect control over the erorr messages we give out).
-/// There are however a few distinctions between parsley and this parsing framework, which
-/// are stated in the combinators. Also, if you are familiar with parsing combinators from
-/// functional languages, this parsing framework will feel very at home, albeit more verbose.
-/// The parsing framework itself can be written as a proc macro with custom instructions 
-/// but that would be a library on its own. What we are interested in is precisely to parse
-/// correctly and give good errors, but exactly how we parse the source code.
-
-
-use std::fs;
+//! This represents the whole parsing module, which contains the context, 
+//! and the parser trait and some parsing utilities. The parser combinator
+//! themselves are in different files from this module
+//! This parsing framework has most of its inspiration from Parsley Scala library
+//! (https://github.com/j-mie6/Parsley). While it is popular to create the parsing machine
+//! at compile-time via a state machine, we opt for a handwritten parser which operates at runtime
+//! (since realistically parsing is never the longest part of a compiler and it allows us
+//! to have perfect control over the erorr messages we give out).
+//! There are however a few distinctions between parsley and this parsing framework, which
+//! are stated in the combinators. Also, if you are familiar with parsing combinators from
+//! functional languages, this parsing framework will feel very at home, albeit more verbose.
+//! The parsing framework itself can be written as a proc macro with custom instructions 
+//! but that would be a library on its own. What we are interested in is precisely to parse
+//! correctly and give good errors, but exactly how we parse the source code.
+
 use std::collections::HashSet;
-use std::cell::UnsafeCell;
+use std::cell::{RefCell, UnsafeCell};
 use std::collections::VecDeque;
 
 pub mod statements;
+pub mod expressions;
 pub mod literals;
 pub mod combinators;
 pub mod types;
+pub mod attributes;
+pub mod declarations;
+pub mod constants;
+pub mod modules;
 use combinators::{StringParseErr, StringParser};
 
-use self::combinators::AlternativeParser;
+use self::combinators::{AlternativeParser, AttemptParser, CharParser, OptionParser};
+use crate::ast::Span;
+use crate::ast::operators::OperatorRegistry;
 
 // Empty for now
 pub(crate) trait ParserErr: Debug {}
 
+/// The error of the item grammar (and everything which nests inside items).
+/// Those parsers call each other recursively, so instead of nesting error types
+/// they all report what they expected to find and where they were
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SyntaxErr {
+    pub expected: String,
+    pub found: String,
+    pub row: usize,
+    pub col: usize
+}
+
+impl ParserErr for SyntaxErr {}
+
+impl SyntaxErr {
+    /// We expected EXPECTED but found whatever is at the cursor
+    pub fn new(expected: &str, ctx: &ParsingContext) -> Self {
+        Self { expected: expected.to_string(), found: ctx.found(), row: ctx.row, col: ctx.col }
+    }
+}
+
+/// Runs PARSER, which has to succeed. If it doesn't, the input is rolled back and
+/// we report that EXPECTED was expected at the cursor. This is meant for the small
+/// parsers (chars, keywords, identifiers, types) whose own errors don't say where they failed
+pub(crate) fn expect<P: Parser>(parser: P, expected: &str, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<P::Output, SyntaxErr> {
+    AttemptParser::new(parser).parse(baggage, ctx).map_err(|_| SyntaxErr::new(expected, ctx))
+}
+
+/// Parses the elements of a bracketed list, separated by SEP and ended by CLOSE.
+/// The opening bracket must already be eaten, the closing one gets eaten here.
+/// A trailing SEP before CLOSE is allowed
+pub(crate) fn list_until<T, F>(sep: char, close: char, baggage: &ParsingBaggage, ctx: &mut ParsingContext, mut element: F) -> Result<Vec<T>, SyntaxErr>
+    where F: FnMut(&ParsingBaggage, &mut ParsingContext) -> Result<T, SyntaxErr>
+{
+    let mut elements = Vec::new();
+    loop {
+        if optional(CharParser(close), baggage, ctx).is_some() {
+            return Ok(elements);
+        }
+        elements.push(element(baggage, ctx)?);
+        if optional(CharParser(sep), baggage, ctx).is_none() {
+            let expected = format!("'{}' or '{}'", sep, close);
+            expect(CharParser(close), &expected, baggage, ctx)?;
+            return Ok(elements);
+        }
+    }
+}
+
+/// Runs PARSER if it can, without eating any input otherwise
+pub(crate) fn optional<P: Parser>(parser: P, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Option<P::Output> {
+    OptionParser::new(parser).parse_to_option(baggage, ctx)
+}
+
 
 /// This is a struct which represents the whole parsing context,
 /// which takes care of the positions in the input where we are currently at
@@ -39,9 +96,13 @@ pub(crate) struct ParsingContext<'inp> {
     pub row: usize,
     pub col: usize,
     pub index: usize, // The place where we are at in the input
+    #[allow(dead_code)]
     pub input: &'inp str, // The whole input
     pub cursor: &'inp str, // Where we are currently in the input
-    keywords: HashSet<&'static str>
+    keywords: HashSet<&'static str>,
+    /// The byte range of the last whitespace (and comments) eaten, so that spans
+    /// can end where the last token did instead of after the whitespace which follows
+    last_ws: (usize, usize)
 }
 
 // TODO(mike): Refactor so that ParsingBaggage is different from ParsingContext,
@@ -52,16 +113,23 @@ pub(crate) struct ParsingContext<'inp> {
 pub(crate) struct ParsingBaggage<'pctx> {
     // The order of the base types matter, since the base type parser will be
     // in the order of base_types.
+    #[allow(dead_code)]
     pub base_types: Vec<&'static str>,
+    #[allow(dead_code)]
     pub base_type_string_parers: Vec<StringParser>,
+    #[allow(dead_code)]
     pub base_type_parser: AlternativeParser<'pctx, &'static str, StringParseErr>,
+    // The operators associated so far. Assoc declarations are registered as soon as
+    // they are parsed, so the operators can be used by the code after them. The
+    // module tree starts it with the symbols associated anywhere in the program
+    pub operators: RefCell<OperatorRegistry>,
 }
 
 impl<'pctx> ParsingBaggage<'pctx> {
     pub fn init() -> Self {
 
         let base_types = vec!["i128", "i64", "i32", "i16", "i8", "u128", "u64", "u32", "u16", "u8", "bool", "()", "f64", "f32"]; 
-        let ps: Vec<_> = base_types.clone().into_iter().map(|t| StringParser::new(t)).collect();
+        let ps: Vec<_> = base_types.clone().into_iter().map(StringParser::new).collect();
         let ps: UnsafeCell<Vec<_>> = ps.into();
 
         // TODO(mike): Make an AlternativeParser which takes an owned type, 
@@ -75,7 +143,7 @@ impl<'pctx> ParsingBaggage<'pctx> {
         let ap = AlternativeParser::new(parsers);
 
         let ps = ps.into_inner();
-        Self { base_types, base_type_string_parers: ps, base_type_parser: ap }
+        Self { base_types, base_type_string_parers: ps, base_type_parser: ap, operators: RefCell::new(OperatorRegistry::new()) }
     }
 }
 
@@ -100,10 +168,9 @@ impl<F: Parser, S: Parser> Parser for DoubleParser<F, S> {
     type PErr = DoubleParserErr<F::PErr, S::PErr>;
 
     fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
-        let r1;
-        match self.first.parse(baggage, ctx) {
+        let r1 = match self.first.parse(baggage, ctx) {
             Err(e) => return Err(DoubleParserErr::FirstError(e)),
-            Ok(r) => { r1 = r; }
+            Ok(r) => r
         };
         match self.second.parse(baggage, ctx) {
             Ok(r2) => Ok((r1, r2)),
@@ -167,8 +234,8 @@ impl<F: Parser, S: Parser> Parser for DiscardThenParser<F, S> {
 
 }
 
-impl<'a, E: ParserErr> ParserErr for &'a E {}
-impl<'a, T: Parser> Parser for &'a T {
+impl<E: ParserErr> ParserErr for &E {}
+impl<T: Parser> Parser for &T {
     type Output = T::Output;
     type PErr = T::PErr;
     fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<T::Output, T::PErr> {
@@ -189,6 +256,7 @@ pub(crate) trait Parser: Debug {
     }
 
     /// Runs the parser on given input. Useful for small-scale testing
+    #[allow(dead_code)]
     fn run_parser(&self, inp: &str) -> Result<Self::Output, Self::PErr> {
         let mut ctx = ParsingContext::new(inp);
         let baggage = ParsingBaggage::init();
@@ -210,6 +278,29 @@ impl<'inp> ParsingContext<'inp> {
         self.cursor.chars().peekable().peek().copied()
     }
 
+    /// A zero-width span at the cursor, parsers take it to remember where a node started
+    pub fn position(&self) -> Span {
+        Span { start: self.index, end: self.index, row: self.row, col: self.col }
+    }
+
+    /// The span from START (taken with position()) up to the cursor
+    pub fn span_from(&self, start: Span) -> Span {
+        let end = if self.last_ws.1 == self.index { self.last_ws.0.max(start.start) } else { self.index };
+        Span { end, ..start }
+    }
+
+    /// A short description of what is at the cursor, for error messages
+    pub fn found(&self) -> String {
+        let word: String = self.cursor.chars().take_while(|c| !c.is_whitespace()).take(16).collect();
+        if word.is_empty() { "end of input".to_string() } else { word }
+    }
+
+    /// The word (identifier or keyword) at the cursor, without eating it
+    pub fn peek_word(&self) -> &'inp str {
+        let len = self.cursor.find(|c: char| !(c == '_' || (c.is_alphanumeric() && c.is_ascii()))).unwrap_or(self.cursor.len());
+        &self.cursor[..len]
+    }
+
     pub fn contains_keyword(&self, w: &str) -> bool {
         self.keywords.contains(w)
     }
@@ -240,16 +331,16 @@ impl<'inp> ParsingContext<'inp> {
     pub fn new<T>(input: &'inp T) -> Self
         where T: AsRef<str> + ?Sized
     {
-        let kw = vec!["let", "let", "for", "def", "if", "else", "bool", "()", "f32", "f64","i8", "i16", "i32", "i64", "i128", "u8", "u16", "u32", "u64", "u128", "&StaticString"];
+        let kw = vec!["let", "for", "def", "if", "else", "pack", "public", "fun", "where", "self", "impls", "type", "interface", "assoc", "with", "val", "defer", "return", "unsafe", "while", "in", "break", "continue", "true", "false", "as", "const", "static", "mod", "use", "super", "implicit", "bool", "()", "f32", "f64","i8", "i16", "i32", "i64", "i128", "u8", "u16", "u32", "u64", "u128", "&StaticString"];
         let keywords: HashSet<&'static str> = kw.into_iter().collect();
-        let mut s = Self { row: 1, col: 1, index: 0, input: input.as_ref(), cursor: input.as_ref(), keywords };
+        let mut s = Self { row: 1, col: 1, index: 0, input: input.as_ref(), cursor: input.as_ref(), keywords, last_ws: (0, 0) };
         s.eat_ws();
         s
     }
 
     /// Eat n characters from the input and spit them back, if there are enough in the input
     pub fn eat_many(&mut self, n: usize) -> Option<&str> {
-        assert_eq!(n != 0, true, "Cannot eat 0 chars");
+        assert!(n != 0, "Cannot eat 0 chars");
         if self.cursor.len() < n {
             return None;
         }         
@@ -271,6 +362,7 @@ impl<'inp> ParsingContext<'inp> {
     }
 
     /// Eat everything until whitespace (or end of input) and spit it back
+    #[allow(dead_code)]
     pub fn eat_until_ws(&mut self) -> &str {
         self.eat_until_cond(&|c| c.is_whitespace())
     }
@@ -282,6 +374,7 @@ impl<'inp> ParsingContext<'inp> {
     /// so the type eaten by the TypeParser is "Basic1", then "Basic2", then "Basic3"
     /// but if we want to parse Gen<Basic1, Basic2, Basic3> what we want the parser to eat
     /// is "Gen<Basic1, Basic2, Basic3>", since those commas are part of the type definition
+    #[allow(dead_code)]
     pub fn eat_type_definition(&mut self) -> Option<&str> {
         // TODO(mike): return errors instead of None, since there are multiple ways the input can be ill-formed
         
@@ -350,6 +443,7 @@ impl<'inp> ParsingContext<'inp> {
     }
 
 
+    #[allow(dead_code)]
     pub fn eat_until_cond(&mut self, cond: &dyn Fn(char) -> bool) -> &str {
         let mut advanced = 0;
         let mut found_last = false;
@@ -375,21 +469,40 @@ impl<'inp> ParsingContext<'inp> {
         until_ws
     }
 
-    /// Discard all whitespace. Returns self for chaining commodity
+    /// Moves the cursor over the first LEN bytes, keeping track of rows and columns
+    fn advance_bytes(&mut self, len: usize) {
+        for c in self.cursor[..len].chars() {
+            if c == '\n' {
+                self.row += 1; self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+        self.index += len;
+        self.cursor = &self.cursor[len..];
+    }
+
+    /// Discard all whitespace and comments (both // line and /* block */ ones).
+    /// Returns self for chaining commodity
     pub fn eat_ws(&mut self) -> &mut Self {
-        let mut non_ws = 0;
-        let mut found = false;
-        for (i, c) in self.cursor.chars().enumerate() {
-            non_ws = i;
-             match c {
-                '\n' => { self.row += 1; self.col = 1 }
-                ch if !ch.is_whitespace() => { found = true; break }
-                _ => {  self.col += 1 }
+        let ws_start = self.index;
+        loop {
+            let skipped = if self.cursor.starts_with("//") {
+                self.cursor.find('\n').unwrap_or(self.cursor.len())
+            } else if self.cursor.starts_with("/*") {
+                // An unclosed block comment swallows the rest of the input
+                self.cursor.find("*/").map(|end| end + 2).unwrap_or(self.cursor.len())
+            } else {
+                self.cursor.find(|c: char| !c.is_whitespace()).unwrap_or(self.cursor.len())
+            };
+            if skipped == 0 {
+                break;
             }
+            self.advance_bytes(skipped);
+        }
+        if self.index > ws_start {
+            self.last_ws = (ws_start, self.index);
         }
-        let non_ws = if found || non_ws == 0 { non_ws } else { non_ws + 1};
-        self.cursor = &self.cursor[non_ws..];
-        self.index = self.index + non_ws;
         self
     }
 
@@ -0,0 +1,110 @@
+use crate::ast::declarations::{ModDecl, UseDecl, UseTree, UseTreeKind, Visibility};
+use crate::ast::ids::NodeId;
+use crate::parsing::attributes::AttributesParser;
+use crate::parsing::combinators::{CharParser, StringParser};
+use crate::parsing::declarations::{ItemParser, VisibilityParser};
+use crate::parsing::literals::IdentParser;
+use crate::parsing::{expect, list_until, optional, Parser, ParsingBaggage, ParsingContext, SyntaxErr};
+
+// Corresponding EBNF for modules
+// Mod -> Attribute* Vis 'mod' Ident (';' | '{' Item* '}')
+// Use -> Attribute* Vis 'use' UseTree ';'
+// UseTree -> UsePath ('as' Ident)? | (UsePath '::')? ('*' | '{' UseTree (',' UseTree)* ','? '}')
+// UsePath -> PathStart ('::' Ident)*
+// PathStart -> 'self' | 'super' ('::' 'super')* | Ident
+// self and super can only start a path (super can be repeated to go up more levels),
+// how the paths are resolved is explained in analysis/modules.rs
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ModParser;
+
+impl Parser for ModParser {
+    type Output = ModDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let vis = VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        expect(StringParser::new("mod"), "mod", baggage, ctx)?;
+        let name = expect(IdentParser, "the name of the module", baggage, ctx)?;
+        let items = if optional(CharParser(';'), baggage, ctx).is_some() {
+            None
+        } else {
+            expect(CharParser('{'), "';' or '{' before the items of the module", baggage, ctx)?;
+            let mut items = Vec::new();
+            while optional(CharParser('}'), baggage, ctx).is_none() {
+                if ctx.cursor.is_empty() {
+                    return Err(SyntaxErr::new("'}' closing the module", ctx));
+                }
+                items.push(ItemParser.parse(baggage, ctx)?);
+            }
+            Some(items)
+        };
+        Ok(ModDecl { id: NodeId::DUMMY, attrs, vis, name, items, span: ctx.span_from(start) })
+    }
+}
+
+/// Parses the segment at the cursor if it can go after PREFIX: self and super only
+/// at the start, and super after other supers
+fn use_segment(prefix: &[String], baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<String, SyntaxErr> {
+    let only_supers = prefix.iter().all(|segment| segment == "super");
+    if prefix.is_empty() && optional(StringParser::new("self"), baggage, ctx).is_some() {
+        return Ok("self".to_string());
+    }
+    if only_supers && optional(StringParser::new("super"), baggage, ctx).is_some() {
+        return Ok("super".to_string());
+    }
+    expect(IdentParser, "a name", baggage, ctx)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UseTreeParser;
+
+impl Parser for UseTreeParser {
+    type Output = UseTree;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let mut prefix = Vec::new();
+        loop {
+            if optional(CharParser('*'), baggage, ctx).is_some() {
+                return Ok(UseTree { id: NodeId::DUMMY, prefix, kind: UseTreeKind::Glob, span: ctx.span_from(start) });
+            }
+            if optional(CharParser('{'), baggage, ctx).is_some() {
+                let trees = list_until(',', '}', baggage, ctx, |baggage, ctx| UseTreeParser.parse(baggage, ctx))?;
+                return Ok(UseTree { id: NodeId::DUMMY, prefix, kind: UseTreeKind::Nested(trees), span: ctx.span_from(start) });
+            }
+            prefix.push(use_segment(&prefix, baggage, ctx)?);
+            if !ctx.cursor.starts_with("::") {
+                break;
+            }
+            ctx.advance_bytes(2);
+            ctx.eat_ws();
+        }
+        let rename = match optional(StringParser::new("as"), baggage, ctx) {
+            Some(_) => Some(expect(IdentParser, "the name to import as", baggage, ctx)?),
+            None => None
+        };
+        Ok(UseTree { id: NodeId::DUMMY, prefix, kind: UseTreeKind::Simple(rename), span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UseParser;
+
+impl Parser for UseParser {
+    type Output = UseDecl;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let vis = VisibilityParser.parse(baggage, ctx).unwrap_or(Visibility::Private);
+        expect(StringParser::new("use"), "use", baggage, ctx)?;
+        let tree = UseTreeParser.parse(baggage, ctx)?;
+        expect(CharParser(';'), "';' after the use declaration", baggage, ctx)?;
+        Ok(UseDecl { id: NodeId::DUMMY, attrs, vis, tree, span: ctx.span_from(start) })
+    }
+}
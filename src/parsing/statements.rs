@@ -0,0 +1,196 @@
+use crate::ast::expressions::{Expr, ExprKind};
+use crate::ast::ids::NodeId;
+use crate::ast::statements::{BindingKind, Block, LetStmt, Pattern, PatternKind, Stmt, StmtKind};
+use crate::ast::Span;
+use crate::parsing::attributes::AttributesParser;
+use crate::parsing::combinators::{CharParser, StringParser};
+use crate::parsing::expressions::{CondParser, ExprParser, PrimaryExprParser};
+use crate::parsing::literals::IdentParser;
+use crate::parsing::types::Type;
+use crate::parsing::{expect, list_until, optional, Parser, ParsingBaggage, ParsingContext, SyntaxErr};
+
+// Corresponding EBNF for statements
+// Block -> '{' Stmt* '}'
+// Stmt -> Attribute* (Let | Defer | Return | While | For | 'break' ';' | 'continue' ';' | Expr ';'?) | ';'
+// Let -> ('let' | 'val') Pattern (':' Ty)? ('=' Expr)? ';'
+// Defer -> 'defer' Expr ';'
+// Return -> 'return' Expr? ';'
+// While -> 'while' Cond Block
+// For -> 'for' Pattern 'in' Cond Block
+// Pattern -> '_' | Ident | '(' Pattern (',' Pattern)* ')' | Ident '{' FieldPattern (',' FieldPattern)* '}'
+// FieldPattern -> Ident (':' Pattern)?
+// An expression can go without its semicolon only if it is block-like (a block, unsafe
+// or if) or if it is the last thing in the block, in which case it is the block's value.
+// An expression statement which starts with a block-like expression ends right after it
+// While and For take conditions, see Cond in parsing/expressions.rs
+
+/// Whether EXPR ends with a block, so it doesn't need a semicolon to be a statement
+fn is_block_like(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Block(_) | ExprKind::Unsafe(_) | ExprKind::If(..))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PatternParser;
+
+impl Parser for PatternParser {
+    type Output = Pattern;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let kind = if ctx.peek_word() == "_" {
+            ctx.advance_one();
+            ctx.eat_ws();
+            PatternKind::Wildcard
+        } else if optional(CharParser('('), baggage, ctx).is_some() {
+            PatternKind::Tuple(list_until(',', ')', baggage, ctx, |baggage, ctx| PatternParser.parse(baggage, ctx))?)
+        } else {
+            let name = expect(IdentParser, "a pattern", baggage, ctx)?;
+            if optional(CharParser('{'), baggage, ctx).is_some() {
+                let fields = list_until(',', '}', baggage, ctx, |baggage, ctx| {
+                    let field_start = ctx.position();
+                    let field = expect(IdentParser, "a field", baggage, ctx)?;
+                    // Point { x } is short for Point { x: x }
+                    let pattern = match optional(CharParser(':'), baggage, ctx) {
+                        Some(_) => PatternParser.parse(baggage, ctx)?,
+                        None => Pattern { id: NodeId::DUMMY, kind: PatternKind::Binding(field.clone()), span: ctx.span_from(field_start) }
+                    };
+                    Ok((field, pattern))
+                })?;
+                PatternKind::Pack(name, fields)
+            } else {
+                PatternKind::Binding(name)
+            }
+        };
+        Ok(Pattern { id: NodeId::DUMMY, kind, span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LetParser;
+
+impl Parser for LetParser {
+    type Output = LetStmt;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let kind = if optional(StringParser::new("val"), baggage, ctx).is_some() {
+            BindingKind::Val
+        } else {
+            expect(StringParser::new("let"), "let or val", baggage, ctx)?;
+            BindingKind::Let
+        };
+        let pattern = PatternParser.parse(baggage, ctx)?;
+        let ty = match optional(CharParser(':'), baggage, ctx) {
+            Some(_) => Some(expect(Type, "the type of the binding", baggage, ctx)?),
+            None => None
+        };
+        let init = match optional(CharParser('='), baggage, ctx) {
+            Some(_) => Some(ExprParser.parse(baggage, ctx)?),
+            None => None
+        };
+        expect(CharParser(';'), "';' after the binding", baggage, ctx)?;
+        Ok(LetStmt { id: NodeId::DUMMY, kind, pattern, ty, init, span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StmtParser;
+
+impl Parser for StmtParser {
+    type Output = Stmt;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        let attrs = AttributesParser.parse(baggage, ctx)?;
+        let kind = match ctx.peek_word() {
+            "let" | "val" => StmtKind::Let(LetParser.parse(baggage, ctx)?),
+            "defer" => {
+                expect(StringParser::new("defer"), "defer", baggage, ctx)?;
+                let deferred = ExprParser.parse(baggage, ctx)?;
+                expect(CharParser(';'), "';' after defer", baggage, ctx)?;
+                StmtKind::Defer(deferred)
+            }
+            "return" => {
+                expect(StringParser::new("return"), "return", baggage, ctx)?;
+                let value = match optional(CharParser(';'), baggage, ctx) {
+                    Some(_) => None,
+                    None => {
+                        let value = ExprParser.parse(baggage, ctx)?;
+                        expect(CharParser(';'), "';' after return", baggage, ctx)?;
+                        Some(value)
+                    }
+                };
+                StmtKind::Return(value)
+            }
+            "while" => {
+                expect(StringParser::new("while"), "while", baggage, ctx)?;
+                let cond = CondParser.parse(baggage, ctx)?;
+                StmtKind::While(cond, BlockParser.parse(baggage, ctx)?)
+            }
+            "for" => {
+                expect(StringParser::new("for"), "for", baggage, ctx)?;
+                let pattern = PatternParser.parse(baggage, ctx)?;
+                expect(StringParser::new("in"), "in", baggage, ctx)?;
+                let iter = CondParser.parse(baggage, ctx)?;
+                StmtKind::For(pattern, iter, BlockParser.parse(baggage, ctx)?)
+            }
+            "break" | "continue" => {
+                let is_break = optional(StringParser::new("break"), baggage, ctx).is_some();
+                if !is_break {
+                    expect(StringParser::new("continue"), "continue", baggage, ctx)?;
+                }
+                expect(CharParser(';'), "';'", baggage, ctx)?;
+                if is_break { StmtKind::Break } else { StmtKind::Continue }
+            }
+            word => {
+                // A statement which starts with a block ends with it: in
+                // unsafe { f(); } *p = 1; the * is a dereference, not a product
+                let expr = if word == "if" || word == "unsafe" || ctx.peek_char() == Some('{') {
+                    PrimaryExprParser { pack_lits: true }.parse(baggage, ctx)?
+                } else {
+                    ExprParser.parse(baggage, ctx)?
+                };
+                if optional(CharParser(';'), baggage, ctx).is_some() {
+                    StmtKind::Semi(expr)
+                } else if is_block_like(&expr) || ctx.peek_char() == Some('}') {
+                    StmtKind::Expr(expr)
+                } else {
+                    return Err(SyntaxErr::new("';' after the expression", ctx));
+                }
+            }
+        };
+        Ok(Stmt { id: NodeId::DUMMY, attrs, kind, span: ctx.span_from(start) })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BlockParser;
+
+impl Parser for BlockParser {
+    type Output = Block;
+    type PErr = SyntaxErr;
+
+    fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
+        let start = ctx.position();
+        expect(CharParser('{'), "'{'", baggage, ctx)?;
+        let mut stmts = Vec::new();
+        let end = loop {
+            let close = ctx.position();
+            if optional(CharParser('}'), baggage, ctx).is_some() {
+                break Span { end: close.start + 1, ..close };
+            }
+            if ctx.cursor.is_empty() {
+                return Err(SyntaxErr::new("'}' closing the block", ctx));
+            }
+            // Stray semicolons (a;; b;) are empty statements
+            if optional(CharParser(';'), baggage, ctx).is_some() {
+                continue;
+            }
+            stmts.push(StmtParser.parse(baggage, ctx)?);
+        };
+        Ok(Block { id: NodeId::DUMMY, stmts, span: ctx.span_from(start), end })
+    }
+}
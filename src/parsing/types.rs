@@ -1,10 +1,13 @@
-use crate::parsing::combinators::{OptionParser, CharParser, StringParser, StringParseErr, AlternativeParser, SepByParser};
+use crate::parsing::combinators::{OptionParser, CharParser, StringParser, AlternativeParser, SepByParser};
 use crate::parsing::{ParsingBaggage, ParserErr, Parser, ParsingContext};
-use crate::parsing::literals::{IdentParser, IdentParserErr, IdentParserErrReason};
+use crate::parsing::literals::{IdentParser, IdentParserErr};
 
-use crate::ast::types::Ty;
+use crate::ast::ids::NodeId;
+use crate::ast::types::{PrimitiveType, Ty, TyKind};
+use crate::ast::Span;
 use crate::parsing::literals::IdentParserErrReason::FoundKeyword;
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) enum TypeParserErr {
     UnclosedGeneric(String),
@@ -39,7 +42,8 @@ impl ParserErr for TypeParserErr {}
 pub(crate) struct Type;
 
 // Corresponding EBNF for types
-// Ty -> '&' Ty | '*' Ty | Ident | Ident '<' Generics '>'
+// Ty -> '&' Ty | '*' Ty | '-' Ty | '()' | '_' | Named ('::' Ident)*
+// Named -> Ident | Ident '<' Generics '>'
 // Generics -> Ty (',' Ty)*
 
 // Check the definition of Ty in ast/types.rs if confused
@@ -57,6 +61,11 @@ impl Parser for Type {
 }
 
 
+/// The type of kind KIND which started at START, up to the cursor
+fn ty(kind: TyKind, start: Span, ctx: &ParsingContext) -> Ty {
+    Ty { id: NodeId::DUMMY, kind, span: ctx.span_from(start) }
+}
+
 #[derive(Debug)]
 struct GenericOrSimpleTy;
 
@@ -66,15 +75,33 @@ impl Parser for GenericOrSimpleTy {
 
     fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
         use TypeParserErr::*;
+        let start = ctx.position();
+        if OptionParser::new(StringParser::new("()")).parse_to_option(baggage, ctx).is_some() {
+            return Ok(ty(TyKind::Primitive(PrimitiveType::Unit), start, ctx));
+        }
+        if ctx.peek_word() == "_" {
+            ctx.advance_one().eat_ws();
+            return Ok(ty(TyKind::Infer, start, ctx));
+        }
         match SimpleType.parse(baggage, ctx) {
             Ok(id) => {
                 let generics = CharParser('<').discard_then(SepByParser::new(Type, CharParser(','))).then_discard(CharParser('>'));
                 let mby_gens = OptionParser::new(generics).parse_to_option(baggage, ctx);
                 let id = id.to_string();
-                match mby_gens {
-                    Some(tys) => Ok(Ty::Generic(id, tys)),
-                    None => Ok(Ty::Userdefined(id))
+                let kind = match mby_gens {
+                    Some(tys) => TyKind::Generic(id, tys),
+                    None => match PrimitiveType::from_name(&id) {
+                        Some(prim) => TyKind::Primitive(prim),
+                        None => TyKind::Userdefined(id)
+                    }
+                };
+                let mut named = ty(kind, start, ctx);
+                // Associated types, T::Target or Box<T>::Target::Item
+                while OptionParser::new(StringParser::new("::")).parse_to_option(baggage, ctx).is_some() {
+                    let name = SimpleType.parse(baggage, ctx).map_err(|_| InvalidFormat("__associated type name__".to_string()))?;
+                    named = ty(TyKind::Assoc(Box::new(named), name), start, ctx);
                 }
+                Ok(named)
             }
             _ => Err(InvalidFormat("__invalid simple or generic ty format__".to_string()))
         }
@@ -91,20 +118,21 @@ impl Parser for RefTy {
     fn parse(&self, baggage: &ParsingBaggage, ctx: &mut ParsingContext) -> Result<Self::Output, Self::PErr> {
         use TypeParserErr::*;
 
+        let start = ctx.position();
         match ctx.peek_char() {
-            Some(c) if c == '&' || c == '*' => {
+            Some(c) if c == '&' || c == '*' || c == '-' => {
                 ctx.advance_one();
                 ctx.eat_ws();
-                let res = Type.parse(baggage, ctx);
-                if res.is_err() {
-                    res
-                } else {
-                    let ty = res.unwrap();
-                    let ty = if c == '&' { Ty::Ref(Box::new(ty)) } else { Ty::Ptr(Box::new(ty)) };
-                    Ok(ty)
-                }
+                let inner = Type.parse(baggage, ctx)?;
+                let kind = match (c, inner) {
+                    ('&', Ty { kind: TyKind::Userdefined(name), .. }) if name == "StaticString" => TyKind::Primitive(PrimitiveType::RefStaticStr),
+                    ('&', inner) => TyKind::Ref(Box::new(inner)),
+                    ('-', inner) => TyKind::NoDrop(Box::new(inner)),
+                    (_, inner) => TyKind::Ptr(Box::new(inner))
+                };
+                Ok(ty(kind, start, ctx))
             },
-            _ => Err(InvalidFormat("__not ptr or ref type__".to_string()))
+            _ => Err(InvalidFormat("__not ptr, ref or nodrop type__".to_string()))
         }
     }
 }
@@ -0,0 +1,139 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod attribute_tests {
+
+    use crate::analysis::features::{is_compile_time, FeatureChecker};
+    use crate::ast::attributes::AttrStyle;
+    use crate::ast::declarations::Item;
+    use crate::diagnostics::Diagnostic;
+    use crate::parsing::attributes::AttributeParser;
+    use crate::parsing::declarations::ProgramParser;
+    use crate::parsing::statements::BlockParser;
+    use crate::parsing::Parser;
+
+    fn check(src: &str) -> Vec<Diagnostic> {
+        FeatureChecker::check_program(&ProgramParser.run_parser(src).unwrap())
+    }
+
+    /// The messages of the errors the feature checker finds in SRC
+    fn errors(src: &str) -> Vec<String> {
+        check(src).into_iter().map(|diag| diag.message).collect()
+    }
+
+    #[test]
+    fn attribute_forms() {
+        let attr = AttributeParser.run_parser("#feature: (minus_attrs)").unwrap();
+        assert_eq!(attr.style, AttrStyle::Outer);
+        assert_eq!(attr.to_string(), "#feature(minus_attrs)");
+        let attr = AttributeParser.run_parser("#![feature(custom_operators, minus_attrs)]").unwrap();
+        assert_eq!(attr.style, AttrStyle::Inner);
+        assert_eq!(attr.args.len(), 2);
+        assert_eq!(attr.to_string(), "#![feature(custom_operators, minus_attrs)]");
+        let attr = AttributeParser.run_parser("( #compile_time )").unwrap();
+        assert_eq!(attr.name, "compile_time");
+        assert_eq!(attr.to_string(), "#compile_time");
+        assert_eq!(AttributeParser.run_parser("#[compile_time]").unwrap().to_string(), "#compile_time");
+        let err = AttributeParser.run_parser("#!feature(minus_attrs)").unwrap_err();
+        assert_eq!(err.expected, "'[' after #!");
+        let err = AttributeParser.run_parser("#![feature(minus_attrs)").unwrap_err();
+        assert_eq!(err.expected, "']' after the attribute");
+    }
+
+    #[test]
+    fn attributes_on_files_items_fields_and_statements() {
+        let src = "#![feature(minus_attrs)]
+                   #![feature(custom_operators)]
+                   pack RcBox<T> { #feature(minus_attrs) ptr: -Box<T>, count: u32 }
+                   (#compile_time) fun build_options() { #compile_time let x = 1; x }";
+        let program = ProgramParser.run_parser(src).unwrap();
+        assert_eq!(program.attrs.len(), 2);
+        match &program.items[0] {
+            Item::Pack(pack) => assert_eq!(pack.fields[0].attrs[0].name, "feature"),
+            _ => panic!("expected a pack")
+        }
+        match &program.items[1] {
+            Item::Fun(fun) => {
+                assert!(is_compile_time(&fun.attrs));
+                let body = fun.body.as_ref().unwrap();
+                assert_eq!(body.stmts[0].attrs[0].name, "compile_time");
+            }
+            _ => panic!("expected a function")
+        }
+        assert_eq!(program.to_string(), "#![feature(minus_attrs)]
+#![feature(custom_operators)]
+
+pack RcBox<T> {
+    #feature(minus_attrs) ptr: -Box<T>,
+    count: u32
+}
+
+#compile_time
+fun build_options() {
+    #compile_time
+    let x = 1;
+    x
+}
+");
+    }
+
+    #[test]
+    fn inner_attributes_only_at_the_start() {
+        let err = ProgramParser.run_parser("fun f() { }\n#![feature(minus_attrs)]").unwrap_err();
+        assert_eq!(err.expected, "an outer attribute (#! attributes go at the start of the file)");
+        assert_eq!(err.row, 2);
+        let err = BlockParser.run_parser("{ #![feature(minus_attrs)] f(); }").unwrap_err();
+        assert_eq!(err.expected, "an outer attribute (#! attributes go at the start of the file)");
+        // Malformed attributes are reported instead of being skipped
+        let err = ProgramParser.run_parser("#feature(minus_attrs pack P { }").unwrap_err();
+        assert_eq!(err.expected, "')' after the attribute arguments");
+    }
+
+    #[test]
+    fn minus_fields_are_gated() {
+        let src = "pack RcBox<T> {\n    ptr: -Box<T>,\n    count: u32\n}";
+        let diags = check(src);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].render(src), "error: fields which are never dropped (-T) are experimental
+ --> 2:5
+  |
+2 |     ptr: -Box<T>,
+  |     ^^^^^^^^^^^^ ptr has type -Box<T>
+ = note: add #feature(minus_attrs) to the field or the item holding it, or #![feature(minus_attrs)] at the start of the file to enable it
+");
+        assert_eq!(errors("interface Shared { value: -u8; }").len(), 1);
+        // Enabled on the item, on the field or for the whole file
+        assert_eq!(errors("#feature: (minus_attrs) pack RcBox<T> { ptr: -Box<T>, count: u32 }"), Vec::<String>::new());
+        assert_eq!(errors("pack RcBox<T> { #feature(minus_attrs) ptr: -Box<T>, count: u32 }"), Vec::<String>::new());
+        assert_eq!(errors("#![feature(minus_attrs)] interface Shared { value: -u8; }"), Vec::<String>::new());
+        // Enabling it on one pack doesn't enable it on the next
+        assert_eq!(errors("#feature(minus_attrs) pack A { a: -u8 } pack B { b: -u8 }").len(), 1);
+        // -T is not gated outside of fields
+        assert_eq!(errors("fun f(t: T) { let moved: -T = t; }"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unusual_operators_are_gated() {
+        assert_eq!(errors("assoc Add::plus with +; assoc Index::index with [];"), Vec::<String>::new());
+        assert_eq!(errors("assoc Ord::cmp with <=>;"), vec!["associating the symbol <=> is experimental".to_string()]);
+        assert_eq!(errors("#![feature(custom_operators)] assoc Ord::cmp with <=>;"), Vec::<String>::new());
+        let diags = check("assoc Concat::concat with ++;");
+        assert_eq!(diags[0].notes, vec!["add #![feature(custom_operators)] at the start of the file to enable it".to_string()]);
+    }
+
+    #[test]
+    fn unknown_and_misplaced_attributes() {
+        assert_eq!(errors("#inline fun f() { }"), vec!["unknown attribute #inline".to_string()]);
+        assert_eq!(errors("#![feature(gc)]"), vec!["unknown feature gc".to_string()]);
+        assert_eq!(errors("#feature fun f() { }"), vec!["#feature needs the features to enable".to_string()]);
+        assert_eq!(errors("#compile_time(fast) fun f() { }"), vec!["#compile_time takes no arguments".to_string()]);
+        assert_eq!(errors("#compile_time pack P { }"), vec!["#compile_time can't be attached to an item".to_string()]);
+        assert_eq!(errors("#![compile_time]"), vec!["#compile_time can't be attached to the file".to_string()]);
+        assert_eq!(errors("#allow(leaks) fun f() { #allow(leaks) let j = g(); }"), Vec::<String>::new());
+        assert_eq!(errors("#allow fun f() { }"), vec!["#allow needs the lints to silence".to_string()]);
+        assert_eq!(errors("fun f() { #allow(leaks, races) let j = g(); }"), vec!["unknown lint races".to_string()]);
+        assert_eq!(errors("#allow(leaks) pack P { }"), vec!["#allow can't be attached to an item".to_string()]);
+        // Statements nested in expressions are checked too
+        assert_eq!(errors("fun f() { if a { #feature(minus_attrs) g(); } }"), vec!["#feature can't be attached to a statement".to_string()]);
+    }
+}
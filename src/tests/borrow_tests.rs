@@ -0,0 +1,102 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod borrow_tests {
+
+    use crate::analysis::borrows::{BorrowChecker, BorrowErr};
+    use crate::tests::{check_source, messages, BOX};
+
+    const MAP: &str = "pack Map<K, V> { keys: *K, values: *V }
+                       impls Map<K, V> {
+                           fun from(k1: K, v1: V, k2: K, v2: V): Map<K, V> { Map { keys: 0 as *K, values: 0 as *V } }
+                           fun len(&self): i32 { 0 }
+                       }\n";
+
+    fn check(src: &str) -> Vec<BorrowErr> {
+        check_source(src, |cx, results| BorrowChecker::check(cx, results))
+    }
+
+    #[test]
+    fn the_draft_references_to_moved_data() {
+        let src = format!("{}{}fun main() {{
+    let i: Box<i32> = Box::new(123);
+    let j: Box<i32> = Box::new(123);
+    let hmap2: Map<i32, &Box<i32>> = Map::from(1, &i, 2, &j);
+    let hmap = Map::from(1, i, 2, j);
+    let size = hmap2.len();
+}}", BOX, MAP);
+        let errs = check(&src);
+        assert_eq!(errs.len(), 2);
+        assert_eq!(errs[0].to_diagnostic().render(&src), "\
+error: cannot move out of i while it is borrowed
+ --> 29:29
+   |
+29 |     let hmap = Map::from(1, i, 2, j);
+   |                             ^ moved here
+   |
+28 |     let hmap2: Map<i32, &Box<i32>> = Map::from(1, &i, 2, &j);
+   |                                                    - i is borrowed here
+   |
+30 |     let size = hmap2.len();
+   |                ----- the borrow is used here, later
+");
+        assert_eq!(errs[1].to_diagnostic().message, "cannot move out of j while it is borrowed");
+
+        // Without a later use of the references, the moves are fine
+        let src = format!("{}{}fun main() {{
+    let i: Box<i32> = Box::new(123);
+    let hmap2: Map<i32, &Box<i32>> = Map::from(1, &i, 2, &i);
+    let moved = i;
+}}", BOX, MAP);
+        assert_eq!(messages(&check(&src)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn references_outliving_what_they_borrow() {
+        let src = "fun dangling(): i32 {
+    let r: &i32;
+    {
+        let x = 1;
+        r = &x;
+    }
+    *r
+}";
+        let errs = check(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: x does not live long enough
+ --> 5:14
+  |
+5 |         r = &x;
+  |              ^ borrowed here
+  |
+6 |     }
+  |     - x goes out of scope here, while still borrowed
+  |
+7 |     *r
+  |     -- the borrow is used here, later
+");
+
+        let src = "fun looped(c: bool) {
+                       let y = 0;
+                       let r = &y;
+                       while c {
+                           let x = 1;
+                           r = &x;
+                           let v = *r;
+                       }
+                       let copied = &r;
+                   }";
+        assert_eq!(messages(&check(src)), vec!["x does not live long enough"]);
+    }
+
+    #[test]
+    fn returned_references() {
+        let src = format!("{}{}
+            pack Pair {{ first: Box<i32>, second: Box<i32> }}
+            fun local(): &i32 {{ let x = 1; &x }}
+            fun owned(b: Box<i32>): &Box<i32> {{ return &b; }}
+            fun through(p: &Pair): &Box<i32> {{ &p.first }}
+            fun passed(p: &Pair): &Pair {{ let q = p; q }}", BOX, MAP);
+        assert_eq!(messages(&check(&src)), vec!["cannot return a reference to x", "cannot return a reference to b"]);
+    }
+}
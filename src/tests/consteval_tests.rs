@@ -0,0 +1,156 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod consteval_tests {
+
+    use crate::analysis::consteval::{eval_consts, ConstErr};
+    use crate::analysis::interpret::{Constant, EvalErrKind};
+    use crate::analysis::layout::LayoutErr;
+    use crate::analysis::types::Type;
+    use crate::ast::types::PrimitiveType;
+    use crate::parsing::constants::ConstParser;
+    use crate::parsing::Parser;
+    use crate::tests::check_source;
+
+    /// The values of the constants of SRC, which must type check, and the errors
+    fn eval(src: &str) -> (Vec<Constant>, Vec<ConstErr>) {
+        check_source(src, eval_consts)
+    }
+
+    /// The value of the constant X in SRC, printed
+    fn value_of_x(src: &str) -> String {
+        let (constants, errs) = eval(src);
+        assert_eq!(errs, vec![]);
+        constants.iter().find(|constant| constant.name == "X").unwrap().value.to_string()
+    }
+
+    /// The error of the only constant in SRC which fails
+    fn err_of(src: &str) -> ConstErr {
+        let (_, mut errs) = eval(src);
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        errs.remove(0)
+    }
+
+    #[test]
+    fn parse_constants() {
+        let decl = ConstParser.run_parser("public const NO_ARGS_EXIT: i32 = 2;").unwrap();
+        assert_eq!(decl.name, "NO_ARGS_EXIT");
+        assert_eq!(decl.to_string(), "public const NO_ARGS_EXIT: i32 = 2;");
+        let decl = ConstParser.run_parser("static  GREETING : &StaticString=\"hi\" ;").unwrap();
+        assert_eq!(decl.to_string(), "static GREETING: &StaticString = \"hi\";");
+        let err = ConstParser.run_parser("const X = 3;").unwrap_err();
+        assert_eq!(err.expected, "':' and the type of the constant");
+        let err = ConstParser.run_parser("const X: u8 = 3").unwrap_err();
+        assert_eq!(err.expected, "';' after the constant");
+    }
+
+    #[test]
+    fn driver_exit_codes() {
+        let (constants, errs) = eval("const NO_ARGS_EXIT: i32 = 2; const FILE_NOT_FOUND_EXIT: i32 = NO_ARGS_EXIT + 1;");
+        assert_eq!(errs, vec![]);
+        assert_eq!(constants[1].name, "FILE_NOT_FOUND_EXIT");
+        assert_eq!(constants[1].value.to_string(), "3");
+        assert_eq!(constants[1].ty, Type::Prim(PrimitiveType::SInt32));
+    }
+
+    #[test]
+    fn integer_arithmetic() {
+        assert_eq!(value_of_x("const X: i64 = (1 + 2) * 3 - 10 / 4 % 3;"), "7");
+        assert_eq!(value_of_x("const X: i8 = -128;"), "-128");
+        assert_eq!(value_of_x("const X: u128 = 340282366920938463463374607431768211455;"), "340282366920938463463374607431768211455");
+        assert_eq!(value_of_x("const X: i128 = -170141183460469231731687303715884105728;"), "-170141183460469231731687303715884105728");
+        assert_eq!(value_of_x("const X: u32 = 1 << 31 | 15;"), "2147483663");
+        assert_eq!(value_of_x("const X: u16 = !0 >> 4 ^ 1;"), "4094");
+        assert_eq!(value_of_x("const X: bool = A < B && !(A == 0); const A: u8 = 3; const B: u8 = 200;"), "true");
+        assert_eq!(value_of_x("const X: i32 = -7 / 2;"), "-3");
+    }
+
+    #[test]
+    fn casts_truncate_and_extend() {
+        assert_eq!(value_of_x("const X: u8 = 300 as u8;"), "44");
+        assert_eq!(value_of_x("const X: u32 = -1 as i8 as u32;"), "4294967295");
+        assert_eq!(value_of_x("const X: i64 = 255 as u8 as i8 as i64;"), "-1");
+        assert_eq!(value_of_x("const X: u8 = true as u8 + 'a' as u8;"), "98");
+        assert_eq!(value_of_x("const X: char = 65 as u8 as char;"), "'A'");
+    }
+
+    #[test]
+    fn sizes() {
+        let src = "pack Box<T> { ptr: *T }
+                   pack Rc<T> { count: u32, flag: bool, value: -T }
+                   const X: u64 = sizeof<Rc<u64>>() + sizeof<Box<u8>>();";
+        // Rc<u64> is 4 (count) + 1 (flag) + 3 (padding) + 8 (value)
+        assert_eq!(value_of_x(src), "24");
+        assert_eq!(value_of_x("const X: u64 = sizeof<&StaticString>();"), "16");
+        assert_eq!(value_of_x("const X: u8 = sizeof<&StaticString>() as u8 * 15;"), "240");
+        // The product is computed as the u8 X is declared as
+        let err = err_of("const X: u8 = sizeof<&StaticString>() as u8 * 16;");
+        assert_eq!(err.err.kind, EvalErrKind::Overflow { operation: "16 * 16".to_string(), ty: PrimitiveType::UInt8 });
+        let err = err_of("pack List { next: List } const X: u64 = sizeof<List>();");
+        assert_eq!(err.err.kind, EvalErrKind::Layout(LayoutErr::Recursive("List".to_string())));
+    }
+
+    #[test]
+    fn overflow_is_reported_against_the_declared_type() {
+        let src = "const LIMIT: u8 = 200;\nconst X: u8 = LIMIT + 100;";
+        let err = err_of(src);
+        assert_eq!(err.err.kind, EvalErrKind::Overflow { operation: "200 + 100".to_string(), ty: PrimitiveType::UInt8 });
+        let rendered = err.to_diagnostic().render(src);
+        assert_eq!(rendered, "error: evaluating 200 + 100 overflows u8
+ --> 2:15
+  |
+2 | const X: u8 = LIMIT + 100;
+  |               ^^^^^^^^^^^ this overflows u8
+  |
+2 | const X: u8 = LIMIT + 100;
+  |          -- because X is declared as u8
+ = note: while evaluating X at compile time
+");
+        // A literal which doesn't fit is a type error before it is evaluated
+        let err = err_of("const X: i8 = 127 + 1;");
+        assert_eq!(err.err.kind, EvalErrKind::Overflow { operation: "127 + 1".to_string(), ty: PrimitiveType::SInt8 });
+        let err = err_of("const X: u32 = 1 << 32;");
+        assert_eq!(err.err.kind, EvalErrKind::Overflow { operation: "1 << 32".to_string(), ty: PrimitiveType::UInt32 });
+        let err = err_of("const X: i16 = -(-32768);");
+        assert!(matches!(err.err.kind, EvalErrKind::Overflow { .. }));
+        let err = err_of("const X: u8 = 3 - 4;");
+        assert!(matches!(err.err.kind, EvalErrKind::Overflow { .. }));
+    }
+
+    #[test]
+    fn constants_run_checked_code() {
+        let src = "pack Point { x: i32, y: i32 }
+                   fun double(n: i32): i32 { let doubled = n * 2; doubled }
+                   const X: i32 = double(A) + 1;
+                   const A: i32 = 20;
+                   const ORIGIN: Point = Point { x: 0, y: A };";
+        let (constants, errs) = eval(src);
+        assert_eq!(errs, vec![]);
+        let constants: Vec<String> = constants.iter().map(Constant::to_string).collect();
+        assert_eq!(constants, vec!["const X: i32 = 41;", "const A: i32 = 20;", "const ORIGIN: Point = Point { x: 0, y: 20 };"]);
+    }
+
+    #[test]
+    fn invalid_constants() {
+        assert_eq!(err_of("const X: u8 = 1 / (2 - 2);").err.kind, EvalErrKind::DivisionByZero);
+        assert_eq!(err_of("static S: i32 = 1; const X: i32 = S;").err.kind, EvalErrKind::StaticInConst("S".to_string()));
+        assert_eq!(value_of_x("const A: i32 = 1; static X: i32 = A + 1;"), "2");
+        assert_eq!(value_of_x("static A: i32 = 1; static X: i32 = A + 1;"), "2");
+        let err = err_of("const X: *u8 = 0 as *u8;");
+        assert!(matches!(err.err.kind, EvalErrKind::Transient { .. }));
+    }
+
+    #[test]
+    fn cycles_and_dependencies_are_reported_once() {
+        let (_, errs) = eval("const A: i32 = B; const B: i32 = A + 1;");
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].err.kind, EvalErrKind::Cycle("A".to_string()));
+        let (_, errs) = eval("const A: i32 = B; const B: i32 = C; const C: i32 = B * 2;");
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].err.kind, EvalErrKind::Cycle("B".to_string()));
+        // B overflows, and A and C, which use it, are not reported again
+        let (constants, errs) = eval("const A: u8 = B; const B: u8 = 255 + 1; const C: u8 = B; const D: u8 = 1;");
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].err.fun, "B");
+        assert_eq!(constants.len(), 1);
+    }
+}
@@ -0,0 +1,321 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod declaration_tests {
+
+    use crate::ast::declarations::{DefMember, InterfaceMember, Item, Receiver, Visibility};
+    use crate::ast::types::{PrimitiveType, Ty, TyKind};
+    use crate::parsing::declarations::{DefParser, FunParser, ImplsParser, InterfaceParser, PackParser, ProgramParser};
+    use crate::parsing::Parser;
+
+    #[test]
+    fn draft_pack() {
+        let src = "
+            pack Struct {
+                name: i32,
+                op: OtherPack, // Owned OtherPack, can free it
+                os: &OtherStruct,  // borrow of OtherStruct
+                ptr_otherstruct: *OtherStruct, /* raw pointer */
+                allocation: Box<OtherStruct>,
+            };";
+        let pack = PackParser.run_parser(src).unwrap();
+        assert_eq!(pack.name, "Struct");
+        assert_eq!(pack.vis, Visibility::Private);
+        let names: Vec<&str> = pack.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["name", "op", "os", "ptr_otherstruct", "allocation"]);
+        assert_eq!(pack.fields[0].ty, Ty::new(TyKind::Primitive(PrimitiveType::SInt32)));
+        assert_eq!(pack.fields[2].ty, Ty::new(TyKind::Ref(Box::new(Ty::new(TyKind::Userdefined("OtherStruct".to_string()))))));
+        assert_eq!(pack.fields[4].ty.to_string(), "Box<OtherStruct>");
+    }
+
+    #[test]
+    fn generic_pack_with_attributes() {
+        let src = "#feature: (minus_attrs)
+            public pack RcBox<T> {
+                ptr: -Box<T>,
+                public count: u32
+            };";
+        let pack = PackParser.run_parser(src).unwrap();
+        assert_eq!(pack.vis, Visibility::Public);
+        assert_eq!(pack.attrs.len(), 1);
+        assert_eq!(pack.attrs[0].name, "feature");
+        assert_eq!(pack.attrs[0].args, vec!["minus_attrs".to_string()]);
+        assert_eq!(pack.generics.len(), 1);
+        assert_eq!(pack.generics[0].name, "T");
+        assert_eq!(pack.fields[0].ty.to_string(), "-Box<T>");
+        assert_eq!(pack.fields[0].vis, Visibility::Private);
+        assert_eq!(pack.fields[1].vis, Visibility::Public);
+    }
+
+    #[test]
+    fn empty_pack() {
+        let pack = PackParser.run_parser("pack Nothing {}").unwrap();
+        assert_eq!(pack.fields.len(), 0);
+    }
+
+    #[test]
+    fn pack_spans() {
+        let pack = PackParser.run_parser("\n  pack Box<T> {\n    ptr: *T\n}").unwrap();
+        assert_eq!((pack.span.row, pack.span.col), (2, 3));
+        assert_eq!((pack.fields[0].span.row, pack.fields[0].span.col), (3, 5));
+    }
+
+    #[test]
+    fn malformed_packs() {
+        // Missing the type of the field
+        let err = PackParser.run_parser("pack P { a: , b: i32 }").unwrap_err();
+        assert_eq!(err.expected, "the type of the field");
+        assert_eq!((err.row, err.col), (1, 13));
+        // Missing a comma between fields
+        let err = PackParser.run_parser("pack P { a: i32 b: i32 }").unwrap_err();
+        assert_eq!(err.expected, "',' or '}'");
+        assert_eq!(err.found, "b:");
+        // Keywords are not names
+        assert!(PackParser.run_parser("pack pack { }").is_err());
+        assert!(PackParser.run_parser("package P { }").is_err());
+    }
+
+    #[test]
+    fn draft_generic_function() {
+        let src = "fun execute<T>(owned_something: T) where T: Execute {
+            t.execute();
+            // drop(t) called at the end.
+        }";
+        let fun = FunParser.run_parser(src).unwrap();
+        assert_eq!(fun.name, "execute");
+        assert_eq!(fun.generics[0].name, "T");
+        assert_eq!(fun.params.len(), 1);
+        assert_eq!(fun.params[0].name, "owned_something");
+        assert_eq!(fun.params[0].ty, Ty::new(TyKind::Userdefined("T".to_string())));
+        assert!(fun.ret.is_none());
+        assert_eq!(fun.where_clause.len(), 1);
+        assert!(!fun.where_clause[0].bounds[0].negative);
+        assert_eq!(fun.where_clause[0].bounds[0].interface.to_string(), "Execute");
+        assert_eq!(fun.body.unwrap().stmts.len(), 1);
+    }
+
+    #[test]
+    fn negative_bounds_and_c_style_params() {
+        let fun = FunParser.run_parser("fun other<T>(T t) where T: !Copy + Send, U: Copy { let moved = t; }").unwrap();
+        assert_eq!(fun.params[0].name, "t");
+        assert_eq!(fun.params[0].ty.to_string(), "T");
+        let bounds = &fun.where_clause[0].bounds;
+        assert_eq!(bounds.len(), 2);
+        assert!(bounds[0].negative);
+        assert!(!bounds[1].negative);
+        assert_eq!(fun.where_clause[1].ty.to_string(), "U");
+
+        let fun = FunParser.run_parser("fun pick<T: Copy + !Drop, U>(a: T, b: U): T;").unwrap();
+        assert_eq!(fun.generics[0].bounds.len(), 2);
+        assert_eq!(fun.generics[1].bounds.len(), 0);
+        assert!(fun.body.is_none());
+    }
+
+    #[test]
+    fn methods_and_attributes() {
+        let fun = FunParser.run_parser("public fun deref(&self): &T {\n unsafe { self.ptr as &T };\n }").unwrap();
+        assert_eq!(fun.vis, Visibility::Public);
+        assert_eq!(fun.receiver, Some(Receiver::Ref));
+        assert_eq!(fun.params.len(), 0);
+        assert_eq!(fun.ret.unwrap().to_string(), "&T");
+
+        let fun = FunParser.run_parser("(#compile_time) fun build_options(self, level: u8): ProjectBuild { }").unwrap();
+        assert_eq!(fun.attrs[0].name, "compile_time");
+        assert_eq!(fun.receiver, Some(Receiver::Value));
+        assert_eq!(fun.params[0].ty, Ty::new(TyKind::Primitive(PrimitiveType::UInt8)));
+        assert_eq!(fun.body.unwrap().stmts.len(), 0);
+    }
+
+    #[test]
+    fn draft_implicit_function() {
+        let fun = FunParser.run_parser("implicit fun itos(implicit n: i32): String = String::from(n);").unwrap();
+        assert!(fun.implicit);
+        assert!(fun.params[0].implicit);
+        assert_eq!(fun.body.as_ref().unwrap().tail().unwrap().to_string(), "String::from(n)");
+        assert_eq!(fun.to_string(), "implicit fun itos(implicit n: i32): String {\n    String::from(n)\n}");
+        // It is an item like any function
+        let program = ProgramParser.run_parser("public implicit fun zero(): i32 = 0; fun f(a: i32, implicit b: i32) { }").unwrap();
+        assert_eq!(program.items.len(), 2);
+        let err = FunParser.run_parser("implicit fun f(): i32 = 0").unwrap_err();
+        assert_eq!(err.expected, "';' after the value of the function");
+    }
+
+    #[test]
+    fn function_bodies_skip_braces_in_strings_and_comments() {
+        let src = "fun main(argc: i32, argv: **char) { let s = \"}\"; /* } */ if x { y(); } // }\n } fun after() {}";
+        let program = ProgramParser.run_parser(src).unwrap();
+        assert_eq!(program.items.len(), 2);
+        match &program.items[0] {
+            Item::Fun(fun) => assert_eq!(fun.params[1].ty.to_string(), "**char"),
+            _ => panic!("expected a function")
+        }
+    }
+
+    #[test]
+    fn malformed_functions() {
+        let err = FunParser.run_parser("fun f(a: i32").unwrap_err();
+        assert_eq!(err.expected, "',' or ')'");
+        let err = FunParser.run_parser("fun f() where { }").unwrap_err();
+        assert_eq!(err.expected, "a where predicate");
+        let err = FunParser.run_parser("fun f() { let x = 1;").unwrap_err();
+        assert_eq!(err.expected, "'}' closing the block");
+        let err = FunParser.run_parser("fun f() -> i32").unwrap_err();
+        assert_eq!(err.expected, "the body of the function or ';'");
+        let err = ProgramParser.run_parser("pack A {}\nstruct B {}").unwrap_err();
+        assert_eq!((err.row, err.col, err.found.as_str()), (2, 1, "struct"));
+    }
+
+    #[test]
+    fn draft_impls() {
+        let src = "impls Box<T> {
+            public fun new(t: T): Box<T> {
+                let leaking: -T = t;
+            }
+            fun get(&self): &T { self.ptr }
+        };";
+        let impls = ImplsParser.run_parser(src).unwrap();
+        assert_eq!(impls.target.to_string(), "Box<T>");
+        assert_eq!(impls.generics.len(), 0);
+        assert_eq!(impls.funs.len(), 2);
+        assert_eq!(impls.funs[0].vis, Visibility::Public);
+        assert_eq!(impls.funs[1].receiver, Some(Receiver::Ref));
+
+        let impls = ImplsParser.run_parser("impls<T> Wrapper<T> where T: Copy { }").unwrap();
+        assert_eq!(impls.generics[0].name, "T");
+        assert_eq!(impls.where_clause.len(), 1);
+    }
+
+    #[test]
+    fn extension_impls() {
+        let impls = ImplsParser.run_parser("implicit impls &StrSlice { fun shout(self): String; }").unwrap();
+        assert!(impls.implicit);
+        assert_eq!(impls.target.to_string(), "&StrSlice");
+        assert!(impls.to_string().starts_with("implicit impls &StrSlice {"));
+        let program = ProgramParser.run_parser("implicit impls i32 { fun double(self): i32 = self * 2; }").unwrap();
+        assert!(matches!(&program.items[0], Item::Impls(impls) if impls.implicit));
+    }
+
+    #[test]
+    fn draft_defs() {
+        let src = "def Deref for Box<T> {
+            type Target = &T;
+            len: u32 = count;
+            public fun deref(&self): &T {
+                unsafe { self.ptr as &T };
+            }
+        }";
+        let def = DefParser.run_parser(src).unwrap();
+        assert_eq!(def.interface.to_string(), "Deref");
+        assert_eq!(def.target.to_string(), "Box<T>");
+        assert_eq!(def.members.len(), 3);
+        match &def.members[0] {
+            DefMember::AssocType(assoc) => {
+                assert_eq!(assoc.name, "Target");
+                assert_eq!(assoc.ty.to_string(), "&T");
+            }
+            _ => panic!("expected an associated type")
+        }
+        match &def.members[1] {
+            DefMember::Field(field) => {
+                assert_eq!(field.name, "len");
+                assert_eq!(field.source.as_deref(), Some("count"));
+            }
+            _ => panic!("expected an associated field")
+        }
+        match &def.members[2] {
+            DefMember::Fun(fun) => assert_eq!(fun.name, "deref"),
+            _ => panic!("expected a function")
+        }
+
+        let def = DefParser.run_parser("def<T> Add<i32> for Num<T> where T: Add<i32> { fun plus(self, other: i32): Num<T>; }").unwrap();
+        assert_eq!(def.interface.to_string(), "Add<i32>");
+        assert_eq!(def.generics.len(), 1);
+    }
+
+    #[test]
+    fn malformed_impls_and_defs() {
+        let err = DefParser.run_parser("def Deref Box<T> { }").unwrap_err();
+        assert_eq!(err.expected, "for");
+        let err = DefParser.run_parser("def Deref for Box<T> { type Target = &T }").unwrap_err();
+        assert_eq!(err.expected, "';' after the associated type");
+        let err = ImplsParser.run_parser("impls Box<T> { len: u32; }").unwrap_err();
+        assert_eq!(err.expected, "fun");
+    }
+
+    #[test]
+    fn program_with_every_item() {
+        let src = "pack Box<T> { ptr: *T }
+            impls Box<T> { public fun new(t: T) { } }
+            def Destroy for Box<T> { public fun drop() { unsafe { dealloc(self.ptr); }; } }
+            interface Destroy { fun drop(&self); }
+            const NO_ARGS_EXIT: i32 = 2;
+            mod ptr;
+            use ptr::read;
+            fun main() { }";
+        let program = ProgramParser.run_parser(src).unwrap();
+        let kinds: Vec<&str> = program.items.iter().map(|item| match item {
+            Item::Const(_) => "const",
+            Item::Pack(_) => "pack",
+            Item::Fun(_) => "fun",
+            Item::Impls(_) => "impls",
+            Item::Def(_) => "def",
+            Item::Interface(_) => "interface",
+            Item::Assoc(_) => "assoc",
+            Item::Mod(_) => "mod",
+            Item::Use(_) => "use",
+        }).collect();
+        assert_eq!(kinds, vec!["pack", "impls", "def", "interface", "const", "mod", "use", "fun"]);
+    }
+
+    #[test]
+    fn interfaces() {
+        let src = "public interface Container<K>: Sized + Iterable<K> where K: Copy {
+            type Item: Clone + !Copy;
+            type Plain;
+            public len: u32;
+            fun get(&self, key: K): &Item;
+            fun is_empty(&self): bool {
+                self.len == 0
+            }
+        }";
+        let interface = InterfaceParser.run_parser(src).unwrap();
+        assert_eq!(interface.name, "Container");
+        assert_eq!(interface.vis, Visibility::Public);
+        assert_eq!(interface.generics[0].name, "K");
+        let supers: Vec<String> = interface.supers.iter().map(|ty| ty.to_string()).collect();
+        assert_eq!(supers, vec!["Sized", "Iterable<K>"]);
+        assert_eq!(interface.where_clause.len(), 1);
+        assert_eq!(interface.members.len(), 5);
+        match &interface.members[0] {
+            InterfaceMember::AssocType(assoc) => {
+                assert_eq!(assoc.name, "Item");
+                assert_eq!(assoc.bounds.len(), 2);
+                assert!(assoc.bounds[1].negative);
+            }
+            _ => panic!("expected an associated type")
+        }
+        match &interface.members[2] {
+            InterfaceMember::Field(field) => {
+                assert_eq!(field.name, "len");
+                assert_eq!(field.vis, Visibility::Public);
+                assert_eq!(field.ty.to_string(), "u32");
+            }
+            _ => panic!("expected a required field")
+        }
+        match (&interface.members[3], &interface.members[4]) {
+            (InterfaceMember::Fun(required), InterfaceMember::Fun(provided)) => {
+                assert!(required.body.is_none());
+                assert!(provided.body.is_some());
+            }
+            _ => panic!("expected two functions")
+        }
+    }
+
+    #[test]
+    fn malformed_interfaces() {
+        let err = InterfaceParser.run_parser("interface A { len: u32 }").unwrap_err();
+        assert_eq!(err.expected, "';' after the field");
+        let err = InterfaceParser.run_parser("interface A: { }").unwrap_err();
+        assert_eq!(err.expected, "a super interface");
+    }
+}
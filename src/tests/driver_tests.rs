@@ -0,0 +1,125 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod driver_tests {
+
+    use std::path::Path;
+
+    use crate::analysis::interpret::Constant;
+    use crate::analysis::modules::SourceFiles;
+    use crate::ast::Span;
+    use crate::diagnostics::Diagnostic;
+    use crate::driver::{compile, Compilation};
+
+    fn compiled(files: &[(&str, &str)]) -> (Compilation, SourceFiles) {
+        let mut sources = SourceFiles::new();
+        for (path, src) in files {
+            sources.add(*path, src);
+        }
+        (compile(&mut sources, Path::new("src/main.tx")), sources)
+    }
+
+    /// The diagnostics of the compilation, rendered against the file each of them is in
+    fn rendered(compilation: &Compilation, sources: &SourceFiles) -> Vec<String> {
+        compilation.diagnostics.iter().map(|(file, diagnostic)| diagnostic.render_file(file, sources.get(file).unwrap_or(""))).collect()
+    }
+
+    #[test]
+    fn programs_are_checked_then_their_compile_time_code_runs() {
+        let (compilation, _) = compiled(&[
+            ("src/main.tx", "mod util;
+                             fun print<T>(t: T);
+                             const LIMIT: i32 = util::twice(21);
+                             #compile_time fun greet(): i32 { print(\"hello\"); LIMIT }"),
+            ("src/util.tx", "public fun twice(n: i32): i32 { n * 2 }")
+        ]);
+        assert_eq!(compilation.diagnostics.len(), 0);
+        assert!(!compilation.failed());
+        assert_eq!(compilation.constants.iter().map(Constant::to_string).collect::<Vec<_>>(), vec![
+            "const LIMIT: i32 = 42;",
+            "const greet: i32 = 42;"
+        ]);
+        assert_eq!(compilation.output, "hello");
+    }
+
+    #[test]
+    fn elaborated_bodies_are_kept() {
+        let (compilation, _) = compiled(&[("src/main.tx", "pack Box { n: i32 }
+                                                          def Destroy for Box { fun drop(&self) { } }
+                                                          fun consume(b: Box) { }
+                                                          fun main(c: bool) { let b = Box { n: 1 }; if c { consume(b); } }")]);
+        assert!(!compilation.failed());
+        let bodies: Vec<String> = compilation.bodies.iter().map(|(name, body)| format!("fun {}\n{}", name, body)).collect();
+        assert_eq!(bodies, vec![
+            "fun drop\n",
+            "fun consume\nblock0: drop b: Box\n",
+            "fun main\nflag0: b\nblock0: flag0 = true\nblock1: flag0 = false\nblock3: drop b: Box if flag0\n"
+        ]);
+    }
+
+    #[test]
+    fn errors_stop_the_stages_after_them() {
+        // The borrow checker would find nothing to say about a body which doesn't resolve
+        let (compilation, sources) = compiled(&[
+            ("src/main.tx", "mod util;\nfun main() { util::missing(); }"),
+            ("src/util.tx", "public fun twice(n: i32): i32 {\n    true\n}")
+        ]);
+        assert!(compilation.failed());
+        assert_eq!(rendered(&compilation, &sources), vec!["error: can't find missing in module util
+ --> src/main.tx:2:14
+  |
+2 | fun main() { util::missing(); }
+  |              ^^^^^^^^^^^^^ not found
+"]);
+        // Once it resolves, the type errors are reported in the file they are in
+        let (compilation, sources) = compiled(&[
+            ("src/main.tx", "mod util;\nfun main() { util::twice(1); }"),
+            ("src/util.tx", "public fun twice(n: i32): i32 {\n    true\n}")
+        ]);
+        assert_eq!(rendered(&compilation, &sources), vec!["error: mismatched types
+ --> src/util.tx:2:5
+  |
+2 |     true
+  |     ^^^^ expected i32, found bool
+"]);
+        assert_eq!(compilation.constants.len(), 0);
+    }
+
+    #[test]
+    fn inline_modules_have_the_features_of_their_file() {
+        let (compilation, _) = compiled(&[("src/main.tx", "#![feature(minus_attrs)]\nmod inner { pack Raw { t: -i32 } }")]);
+        assert_eq!(compilation.diagnostics.len(), 0);
+        let (compilation, _) = compiled(&[("src/main.tx", "mod inner { pack Raw { t: -i32 } }")]);
+        let messages: Vec<(&Path, &str)> = compilation.diagnostics.iter().map(|(file, diagnostic)| (file.as_path(), diagnostic.message.as_str())).collect();
+        assert_eq!(messages, vec![(Path::new("src/main.tx"), "fields which are never dropped (-T) are experimental")]);
+    }
+
+    #[test]
+    fn missing_root_files_are_reported_without_a_snippet() {
+        let mut sources = SourceFiles::new();
+        let compilation = compile(&mut sources, Path::new("src/missing.tx"));
+        assert!(compilation.failed());
+        assert_eq!(rendered(&compilation, &sources), vec!["error: file not found for module the root module
+ --> src/missing.tx
+ = note: looked for src/missing.tx
+"]);
+    }
+
+    #[test]
+    fn labels_on_rows_of_different_widths_line_up() {
+        let src = (1..=10).map(|row| format!("val x{} = {};", row, row)).collect::<Vec<_>>().join("\n");
+        let at = |row: usize| {
+            let start = src.match_indices('\n').nth(row - 2).map_or(0, |(i, _)| i + 1);
+            Span { start, end: start + 3, row, col: 1 }
+        };
+        let diagnostic = Diagnostic::error("x10 shadows x8", at(10), "declared again here").with_label(at(8), "first declared here");
+        assert_eq!(diagnostic.render_file(Path::new("src/main.tx"), &src), "error: x10 shadows x8
+ --> src/main.tx:10:1
+   |
+10 | val x10 = 10;
+   | ^^^ declared again here
+   |
+ 8 | val x8 = 8;
+   | --- first declared here
+");
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod drop_tests {
+
+    use crate::analysis::cfg::Event;
+    use crate::analysis::drops::{DropElaborator, DropErr, ElaboratedBody};
+    use crate::tests::{check_source, BOX};
+
+    const DESTROY: &str = "def Destroy for Box<T> { fun drop(&self) { } }
+                           fun consume(b: Box<i32>) { }\n";
+
+    /// The elaborated body of the function NAME, with the errors
+    fn elaborate(src: &str, name: &str) -> (ElaboratedBody, Vec<DropErr>) {
+        check_source(src, |cx, results| {
+            let (bodies, errs) = DropElaborator::elaborate(cx, results);
+            let body = bodies.into_iter().find(|body| cx.funs.get(body.fun).is_some_and(|sig| sig.name == name)).unwrap();
+            (body, errs)
+        })
+    }
+
+    /// The drops, drop flags and uses of the body, block by block
+    fn events(body: &ElaboratedBody) -> Vec<String> {
+        body.cfg.blocks.iter().flat_map(|block| block.events.iter()).filter_map(|event| match event {
+            Event::Drop { place, ty, flag: Some(flag), .. } => Some(format!("drop {}: {} if flag{}", place, ty, flag)),
+            Event::Drop { place, ty, .. } => Some(format!("drop {}: {}", place, ty)),
+            Event::SetFlag { flag, value } => Some(format!("flag{} = {}", flag, value)),
+            Event::Use { place, .. } => Some(format!("use {}", place)),
+            _ => None
+        }).collect()
+    }
+
+    #[test]
+    fn the_draft_drop_order() {
+        let src = format!("{}{}
+            fun main() {{
+                let i = Box::new(1);
+                let j: -Box<i32> = Box::new(2);
+                defer(drop(j));
+                let b = Box::new(3);
+            }}", BOX, DESTROY);
+        let (body, errs) = elaborate(&src, "main");
+        assert_eq!(errs, vec![]);
+        assert_eq!(events(&body), vec!["drop b: Box<i32>", "use j", "drop i: Box<i32>"]);
+        let calls_destroy = body.cfg.blocks.iter().flat_map(|block| &block.events).all(|event| match event {
+            Event::Drop { fun, .. } => fun.is_some(),
+            _ => true
+        });
+        assert!(calls_destroy);
+    }
+
+    #[test]
+    fn packs_are_destroyed_then_their_fields_in_reverse() {
+        let src = format!("{}{}
+            pack Pair {{ first: Box<i32>, second: Box<i32>, n: i32 }}
+            def Destroy for Pair {{ fun drop(&self) {{ }} }}
+            pack Inner {{ b: Box<bool>, n: i32 }}
+            pack Plain {{ first: Box<i32>, second: -Box<i32>, third: Inner }}
+            fun pair(p: Pair) {{ }}
+            fun plain(p: Plain) {{ }}
+            fun generic<T>(t: T, u: T, b: -Box<i32>) {{ }}", BOX, DESTROY);
+        let (body, _) = elaborate(&src, "pair");
+        assert_eq!(events(&body), vec!["drop p: Pair", "drop p.second: Box<i32>", "drop p.first: Box<i32>"]);
+        let (body, _) = elaborate(&src, "plain");
+        assert_eq!(events(&body), vec!["drop p.third.b: Box<bool>", "drop p.first: Box<i32>"]);
+        // Generic values are destroyed by what they turn out to be
+        let (body, _) = elaborate(&src, "generic");
+        assert_eq!(events(&body), vec!["drop u: T", "drop t: T"]);
+        assert!(matches!(body.cfg.blocks[0].events.iter().find(|event| matches!(event, Event::Drop { .. })), Some(Event::Drop { fun: None, .. })));
+    }
+
+    #[test]
+    fn drops_on_every_exit() {
+        let src = format!("{}{}
+            fun exits(c: bool) {{
+                let a = Box::new(1);
+                while c {{
+                    let b = Box::new(2);
+                    defer(consume(Box::new(3)));
+                    if c {{ break; }}
+                    if c {{ continue; }}
+                    if c {{ return; }}
+                }}
+            }}", BOX, DESTROY);
+        let (body, _) = elaborate(&src, "exits");
+        let events = events(&body);
+        let count = |event: &str| events.iter().filter(|found| *found == event).count();
+        // break, continue, return and the end of the loop body leave the scope of b
+        assert_eq!(count("drop b: Box<i32>"), 4);
+        // return and the end of the function leave that of a
+        assert_eq!(count("drop a: Box<i32>"), 2);
+    }
+
+    #[test]
+    fn drop_flags_for_values_moved_on_some_paths() {
+        let src = format!("{}{}
+            pack Pair {{ first: Box<i32>, second: Box<i32> }}
+            fun flagged(c: bool) {{
+                let a = Box::new(1);
+                if c {{ consume(a); }}
+                let m = Box::new(2);
+                consume(m);
+            }}
+            fun partial(p: Pair) {{
+                consume(p.first);
+            }}", BOX, DESTROY);
+        let (body, errs) = elaborate(&src, "flagged");
+        assert_eq!(errs, vec![]);
+        assert_eq!(body.flags.iter().map(|place| place.to_string()).collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(events(&body), vec!["flag0 = true", "use c", "use a", "flag0 = false", "use m", "drop a: Box<i32> if flag0"]);
+        // The flag is set where a gets its value, cleared where it is moved, and tested
+        // once the paths join
+        assert_eq!(body.to_string(), "flag0: a\nblock0: flag0 = true\nblock1: flag0 = false\nblock3: drop a: Box<i32> if flag0\n");
+        let (body, _) = elaborate(&src, "partial");
+        assert_eq!(events(&body), vec!["use p.first", "drop p.second: Box<i32>"]);
+
+        let src = format!("{}{}
+            pack Pair {{ first: Box<i32>, second: Box<i32> }}
+            def Destroy for Pair {{ fun drop(&self) {{ }} }}
+            fun partial(p: Pair) {{
+                consume(p.first);
+            }}", BOX, DESTROY);
+        let (_, errs) = elaborate(&src, "partial");
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(&src), "\
+error: cannot move out of p.first
+ --> 26:25
+   |
+26 |                 consume(p.first);
+   |                         ^^^^^^^ moved here
+ = note: Pair has a def of Destroy, whose drop needs all of its fields
+");
+    }
+}
@@ -0,0 +1,233 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod expression_tests {
+
+    use crate::ast::declarations::Item;
+    use crate::ast::expressions::{ExprKind, Literal, UnaryOp};
+    use crate::ast::statements::StmtKind;
+    use crate::ast::types::{Ty, TyKind};
+    use crate::parsing::declarations::ProgramParser;
+    use crate::parsing::expressions::ExprParser;
+    use crate::parsing::statements::BlockParser;
+    use crate::parsing::Parser;
+
+    /// Parses SRC as an expression and prints it back, so the tree shows in the parentheses
+    fn canonical(src: &str) -> String {
+        ExprParser.run_parser(src).unwrap().to_string()
+    }
+
+    #[test]
+    fn draft_expressions() {
+        match ExprParser.run_parser("String::from(imm)").unwrap().kind {
+            ExprKind::Call(callee, args) => {
+                match callee.kind {
+                    ExprKind::Path(path) => {
+                        assert_eq!(path.segments.len(), 2);
+                        assert_eq!(path.last(), "from");
+                    }
+                    _ => panic!("expected a path")
+                }
+                assert_eq!(args.len(), 1);
+            }
+            _ => panic!("expected a call")
+        }
+        match ExprParser.run_parser("s.as_slice()").unwrap().kind {
+            ExprKind::MethodCall(receiver, method, args) => {
+                assert_eq!(receiver.to_string(), "s");
+                assert_eq!(method.name, "as_slice");
+                assert_eq!(args.len(), 0);
+            }
+            _ => panic!("expected a method call")
+        }
+        match ExprParser.run_parser("ProjectBuild { level: o_level, files: build_files }").unwrap().kind {
+            ExprKind::PackLit(path, fields) => {
+                assert_eq!(path.to_string(), "ProjectBuild");
+                assert_eq!(fields[1].name, "files");
+                assert_eq!(fields[1].value.to_string(), "build_files");
+            }
+            _ => panic!("expected a pack literal")
+        }
+        match ExprParser.run_parser("self.ptr").unwrap().kind {
+            ExprKind::Field(base, field) => {
+                assert_eq!(base.to_string(), "self");
+                assert_eq!(field, "ptr");
+            }
+            _ => panic!("expected a field access")
+        }
+        assert_eq!(canonical("[\"here.tx\", \"there.tx\"]"), "[\"here.tx\", \"there.tx\"]");
+        assert_eq!(canonical("HashMap::from( (1, &i), (2, &j) )"), "HashMap::from((1, &i), (2, &j))");
+        assert_eq!(canonical("ProjectBuild { o_level, build_files, }"), "ProjectBuild { o_level, build_files }");
+    }
+
+    #[test]
+    fn casts() {
+        match ExprParser.run_parser("&t as *T as *u8").unwrap().kind {
+            ExprKind::Cast(inner, ty) => {
+                assert_eq!(ty.to_string(), "*u8");
+                match inner.kind {
+                    ExprKind::Cast(operand, ty) => {
+                        assert_eq!(ty.to_string(), "*T");
+                        assert!(matches!(operand.kind, ExprKind::Unary(UnaryOp::Ref, _)));
+                    }
+                    _ => panic!("expected a nested cast")
+                }
+            }
+            _ => panic!("expected a cast")
+        }
+        match ExprParser.run_parser("alloc as _").unwrap().kind {
+            ExprKind::Cast(_, ty) => assert_eq!(ty, Ty::new(TyKind::Infer)),
+            _ => panic!("expected a cast")
+        }
+        assert_eq!(canonical("&t as * _"), "&t as *_");
+        // Casts bind tighter than binary operators and looser than unary ones
+        assert_eq!(canonical("-x as u8 + y as u8"), "-x as u8 + y as u8");
+        assert_eq!(canonical("-(x as i8)"), "-(x as i8)");
+        assert_eq!(canonical("(a + b) as u64"), "(a + b) as u64");
+        assert_eq!(canonical("a as u8 < b"), "a as u8 < b");
+        assert_eq!(canonical("a as Wrapper < b"), "a as Wrapper < b");
+    }
+
+    #[test]
+    fn generic_calls_without_turbofish() {
+        match ExprParser.run_parser("sizeof<T>()").unwrap().kind {
+            ExprKind::Call(callee, args) => {
+                match callee.kind {
+                    ExprKind::Path(path) => assert_eq!(path.segments[0].generics, vec![Ty::new(TyKind::Userdefined("T".to_string()))]),
+                    _ => panic!("expected a path")
+                }
+                assert_eq!(args.len(), 0);
+            }
+            _ => panic!("expected a call")
+        }
+        assert_eq!(canonical("ptr::read<T>(self.ptr)"), "ptr::read<T>(self.ptr)");
+        assert_eq!(canonical("Box<Vec<i32>>::new(v)"), "Box<Vec<i32>>::new(v)");
+        assert_eq!(canonical("it.collect<Vec<u8>>()"), "it.collect<Vec<u8>>()");
+        // Not followed by ( or ::, so these are comparisons
+        match ExprParser.run_parser("a < b && c > d").unwrap().kind {
+            ExprKind::Binary(op, lhs, rhs) => {
+                assert_eq!(op, "&&");
+                assert_eq!(lhs.to_string(), "a < b");
+                assert_eq!(rhs.to_string(), "c > d");
+            }
+            _ => panic!("expected a binary operator")
+        }
+        assert_eq!(canonical("a < b"), "a < b");
+        assert_eq!(canonical("len<max>=min"), "len < max >= min");
+        assert_eq!(canonical("x < y > z"), "x < y > z");
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(canonical("a + b * c - d"), "a + b * c - d");
+        match ExprParser.run_parser("a - b - c").unwrap().kind {
+            ExprKind::Binary(_, lhs, _) => assert!(matches!(lhs.kind, ExprKind::Binary(..))),
+            _ => panic!("expected a binary operator")
+        }
+        assert_eq!(canonical("a - (b - c)"), "a - (b - c)");
+        assert_eq!(canonical("(a || b) && !c == d"), "(a || b) && !c == d");
+        assert_eq!(canonical("a*-b"), "a * -b");
+        assert_eq!(canonical("*self.ptr"), "*self.ptr");
+        assert_eq!(canonical("(*p).x[i + 1](y)"), "(*p).x[i + 1](y)");
+        match ExprParser.run_parser("a = b = c").unwrap().kind {
+            ExprKind::Assign(_, value) => assert!(matches!(value.kind, ExprKind::Assign(..))),
+            _ => panic!("expected an assignment")
+        }
+        match ExprParser.run_parser("count += 1").unwrap().kind {
+            ExprKind::AssignOp(op, _, value) => {
+                assert_eq!(op, "+");
+                assert!(matches!(value.kind, ExprKind::Literal(Literal::Int(1))));
+            }
+            _ => panic!("expected a compound assignment")
+        }
+        assert_eq!(canonical("x=-1"), "x = -1");
+        assert_eq!(canonical("m >>= 2"), "m >>= 2");
+    }
+
+    #[test]
+    fn tuples_and_fields() {
+        assert_eq!(canonical("(a)"), "a");
+        assert_eq!(canonical("(a,)"), "(a,)");
+        assert_eq!(canonical("pair.0.1"), "pair.0.1");
+        assert_eq!(canonical("Empty {}"), "Empty {}");
+    }
+
+    #[test]
+    fn pack_literals_in_conditions() {
+        // The block of the if is not taken for the fields of a pack literal
+        let block = BlockParser.run_parser("{ if x == y { f(); } while ready { g(); } for p in points { h(p); } }").unwrap();
+        assert_eq!(block.stmts.len(), 3);
+        match &block.stmts[0].kind {
+            StmtKind::Expr(expr) => match &expr.kind {
+                ExprKind::If(cond, _, _) => assert_eq!(cond.to_string(), "x == y"),
+                _ => panic!("expected an if")
+            },
+            _ => panic!("expected an expression statement")
+        }
+        // but they can still be used inside brackets
+        let block = BlockParser.run_parser("{ if (p == Point { x: 1 }) { } if eq(p, Point { x }) { } }").unwrap();
+        assert_eq!(block.stmts.len(), 2);
+    }
+
+    #[test]
+    fn statements_starting_with_blocks() {
+        let block = BlockParser.run_parser("{ unsafe { f(); } *p = 1; if a { } -b }").unwrap();
+        assert_eq!(block.stmts.len(), 4);
+        assert_eq!(block.stmts[1].to_string(), "*p = 1;");
+        assert_eq!(block.tail().unwrap().to_string(), "-b");
+    }
+
+    #[test]
+    fn associated_operators() {
+        let src = "assoc Ord::cmp with <=>;
+                   assoc Concat::concat with ++;
+                   fun f() { a ++ b <=> c + d; x+-y }";
+        let program = ProgramParser.run_parser(src).unwrap();
+        let fun = match &program.items[2] {
+            Item::Fun(fun) => fun,
+            _ => panic!("expected a function")
+        };
+        let body = fun.body.as_ref().unwrap();
+        match &body.stmts[0].kind {
+            // <=> binds like <, so looser than ++ and +
+            StmtKind::Semi(expr) => match &expr.kind {
+                ExprKind::Binary(op, lhs, rhs) => {
+                    assert_eq!(op, "<=>");
+                    assert_eq!(lhs.to_string(), "a ++ b");
+                    assert_eq!(rhs.to_string(), "c + d");
+                }
+                _ => panic!("expected a binary operator")
+            },
+            _ => panic!("expected an expression statement")
+        }
+        // +- was not associated, so it is + and a negation
+        assert_eq!(body.tail().unwrap().to_string(), "x + -y");
+    }
+
+    #[test]
+    fn malformed_expressions() {
+        let err = ExprParser.run_parser("f(a, ").unwrap_err();
+        assert_eq!(err.expected, "an expression");
+        let err = ExprParser.run_parser("x as 3").unwrap_err();
+        assert_eq!(err.expected, "the type to cast to");
+        let err = ExprParser.run_parser("a[1").unwrap_err();
+        assert_eq!(err.expected, "']'");
+        let err = ExprParser.run_parser("P { x: 1 y: 2 }").unwrap_err();
+        assert_eq!(err.expected, "',' or '}'");
+        let err = ExprParser.run_parser("a.").unwrap_err();
+        assert_eq!(err.expected, "a name");
+        let err = ExprParser.run_parser("pair.1x").unwrap_err();
+        assert_eq!(err.expected, "the number of a tuple field");
+    }
+
+    #[test]
+    fn only_decimal_integers() {
+        // Neither the field 5 of 1, nor 0 followed by xff
+        for (src, number) in [("1.5", "1.5"), ("x + 12.25 * 2", "12.25"), ("0xff", "0xff"), ("1e10", "1e10"), ("2u8", "2u8")] {
+            let err = ExprParser.run_parser(src).unwrap_err();
+            assert_eq!(err.expected, format!("a decimal integer instead of {}", number), "{} parsed", src);
+        }
+        assert_eq!(canonical("1_000.count()"), "1000.count()");
+        assert_eq!(canonical("pair.0.1 + 1"), "pair.0.1 + 1");
+    }
+}
@@ -0,0 +1,259 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod interpret_tests {
+
+    use crate::analysis::interpret::{Constant, EvalErr, EvalErrKind, Limits, Sandbox};
+    use crate::analysis::schedule::Scheduler;
+    use crate::parsing::expressions::ExprParser;
+    use crate::parsing::Parser;
+    use crate::tests::{check_source, messages};
+
+    fn run_with(src: &str, limits: Limits) -> (Vec<Constant>, Vec<EvalErr>) {
+        check_source(src, |cx, results| Scheduler { threads: 1, limits }.run(cx, results, &mut Sandbox::default()))
+    }
+
+    fn run(src: &str) -> (Vec<Constant>, Vec<EvalErr>) {
+        run_with(src, Limits::default())
+    }
+
+    /// The constants, written as const items
+    fn constants(src: &str) -> Vec<String> {
+        let (constants, errs) = run(src);
+        assert_eq!(errs, vec![]);
+        constants.iter().map(Constant::to_string).collect()
+    }
+
+    #[test]
+    fn the_draft_build_options() {
+        let src = "pack ProjectBuild { level: u8, size: u32, largest: u32, name: &StrSlice, debug: bool }
+                   interface Add { fun plus(&self, other: Self): Self; }
+                   assoc Add::plus with +;
+                   pack Level { n: u8 }
+                   def Add for Level { fun plus(&self, other: Level): Level { Level { n: self.n + other.n } } }
+                   const BASE: u8 = 1;
+                   fun largest(): u32 {
+                       let files = [0, 0, 0];
+                       let i = 0;
+                       while i < 3 { files[i] = (i as u32 + 1) * 100; i = i + 1; }
+                       let largest = 0;
+                       for file in &files { if *file > largest { largest = *file; } }
+                       largest
+                   }
+                   (#compile_time) fun build_options(): ProjectBuild {
+                       let level = Level { n: BASE } + Level { n: 2 };
+                       let size = 0;
+                       for file in [100, 200, 300] { size = size + file; }
+                       ProjectBuild { level: level.n, size, largest: largest(), name: \"main\", debug: level.n < 2 }
+                   }
+                   #compile_time fun answer(): i64 { -6 * 7 }";
+        assert_eq!(constants(src), vec![
+            "const build_options: ProjectBuild = ProjectBuild { level: 3, size: 600, largest: 300, name: \"main\", debug: false };",
+            "const answer: i64 = -42;"
+        ]);
+    }
+
+    #[test]
+    fn user_casts_call_cast_as() {
+        let src = "interface Cast<T> { fun as(self): T; }
+                   pack Celsius { degrees: i32 }
+                   pack Fahrenheit { degrees: i32 }
+                   def Cast<Fahrenheit> for Celsius { fun as(self): Fahrenheit { Fahrenheit { degrees: self.degrees * 9 / 5 + 32 } } }
+                   #compile_time fun boiling(): Fahrenheit { Celsius { degrees: 100 } as Fahrenheit }
+                   #compile_time fun byte(): u8 { 300 as u8 }";
+        assert_eq!(constants(src), vec![
+            "const boiling: Fahrenheit = Fahrenheit { degrees: 212 };",
+            "const byte: u8 = 44;"
+        ]);
+    }
+
+    #[test]
+    fn constants_read_back_as_expressions() {
+        let src = "pack Inner { c: char, s: &StrSlice }
+                   pack Empty { }
+                   pack Outer { inner: Inner, empty: Empty, byte: u8, flag: bool }
+                   #compile_time fun value(): Outer {
+                       Outer { inner: Inner { c: '\\n', s: \"a \\\"b\\\"\" }, empty: Empty { }, byte: 255, flag: true }
+                   }
+                   #compile_time fun bell(): char { '\\u{7}' }
+                   #compile_time fun text(): &StrSlice { \"é\\u{1b}[0m\\t中\" }";
+        let (constants, errs) = run(src);
+        assert_eq!(errs, vec![]);
+        let values: Vec<String> = constants.iter().map(|constant| constant.value.to_string()).collect();
+        assert_eq!(values, vec![
+            "Outer { inner: Inner { c: '\\n', s: \"a \\\"b\\\"\" }, empty: Empty {}, byte: 255, flag: true }",
+            "'\\u{7}'",
+            "\"é\\u{1b}[0m\\t中\""
+        ]);
+        for value in values {
+            assert_eq!(ExprParser.run_parser(&value).unwrap().to_string(), value);
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let src = "fun get(i: u64): i32 { let a = [1, 2]; a[i] }
+#compile_time fun out_of_bounds(): i32 {
+    let i: u64 = 1;
+    get(i + 1)
+}";
+        let (_, errs) = run(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].kind, EvalErrKind::OutOfBounds { index: "2".to_string(), len: 2 });
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: index 2 is out of bounds for an array of length 2
+ --> 1:40
+  |
+1 | fun get(i: u64): i32 { let a = [1, 2]; a[i] }
+  |                                        ^^^^ out of bounds
+  |
+4 |     get(i + 1)
+  |     ---------- inside this call to get
+ = note: while evaluating out_of_bounds at compile time
+");
+        let src = "pack Box { ptr: *i32 }
+                   #compile_time fun overflow(): u8 { let a: u8 = 200; a + 100 }
+                   #compile_time fun divide(): i32 { let zero = 0; 1 / zero }
+                   #compile_time fun null(): i32 { let p = 0 as *i32; unsafe { *p } }
+                   fun dangling_ref(): *i32 { let x = 1; &x as *i32 }
+                   #compile_time fun dangling(): i32 { let p = dangling_ref(); unsafe { *p } }
+                   #compile_time fun freed(): i32 {
+                       let p = alloc(4, 4) as *i32;
+                       unsafe { *p = 1; dealloc(p); dealloc(p); }
+                       0
+                   }
+                   #compile_time fun bad_dealloc(): i32 { let x = 1; unsafe { dealloc(&x as *i32); } 0 }
+                   #compile_time fun uninitialized(): i32 { let p = alloc(4, 4) as *i32; unsafe { *p } }
+                   #compile_time fun pointer(): Box { Box { ptr: alloc(4, 4) as *i32 } }
+                   #compile_time fun address(): *i32 { 16 as *i32 }";
+        assert_eq!(messages(&run(src).1), vec![
+            "evaluating 200 + 100 overflows u8",
+            "division by zero",
+            "use of a null pointer",
+            "use of a pointer to freed memory",
+            "use of a pointer to freed memory",
+            "dealloc of a pointer alloc didn't give",
+            "read of memory which holds no value",
+            "the result of pointer can't be kept after the compilation",
+            "making a pointer from the address 16 can't be done at compile time"
+        ]);
+    }
+
+    #[test]
+    fn errors_inside_conversions_explain_them() {
+        let src = "pack Small { n: u8 }
+implicit fun shrink(n: u32): Small = Small { n: n as u8 + 200 };
+#compile_time fun small(): u8 {
+    let s: Small = 100 as u32;
+    s.n
+}";
+        let (_, errs) = run(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: evaluating 100 + 200 overflows u8
+ --> 2:49
+  |
+2 | implicit fun shrink(n: u32): Small = Small { n: n as u8 + 200 };
+  |                                                 ^^^^^^^^^^^^^ this overflows u8
+  |
+4 |     let s: Small = 100 as u32;
+  |                    ---------- inside this call to shrink
+ = note: shrink converts u32 to Small, declared as implicit fun shrink(n: u32): Small
+ = note: while evaluating small at compile time
+");
+    }
+
+    #[test]
+    fn unsafe_builtins_go_through_pointers() {
+        let src = "#compile_time fun swapped(): i32 {
+                       let p = alloc(8, 4) as *i32;
+                       unsafe {
+                           ptr::write(p, 40);
+                           let q = transmute<*i32, *u32>(p) as *i32;
+                           let n = ptr::read(q) + 2;
+                           dealloc(p);
+                           n
+                       }
+                   }";
+        assert_eq!(constants(src), vec!["const swapped: i32 = 42;"]);
+        let src = "#compile_time fun null(): i32 { unsafe { ptr::read(0 as *i32) } }
+                   #compile_time fun bytes(): u32 { unsafe { transmute<i32, u32>(1) } }";
+        assert_eq!(messages(&run(src).1), vec!["use of a null pointer", "transmuting a value which is not a pointer can't be done at compile time"]);
+    }
+
+    #[test]
+    fn limits() {
+        let src = "#compile_time fun forever(): i32 { while true { } 0 }
+                   fun count(n: u64): u64 { if n == 0 { 0 } else { count(n - 1) + 1 } }
+                   #compile_time fun deep(): u64 { count(100) }
+                   #compile_time fun shallow(): u64 { count(10) }
+                   #compile_time fun greedy(): i32 { alloc(1 << 20, 8); 0 }
+                   #compile_time fun modest(): i32 { let p = alloc(1 << 10, 8); unsafe { dealloc(p); } 0 }";
+        let limits = Limits { steps: 10_000, memory: 1 << 16, depth: 32 };
+        let (constants, errs) = run_with(src, limits);
+        let constants: Vec<String> = constants.iter().map(Constant::to_string).collect();
+        assert_eq!(constants, vec!["const shallow: u64 = 10;", "const modest: i32 = 0;"]);
+        let kinds: Vec<EvalErrKind> = errs.iter().map(|err| err.kind.clone()).collect();
+        assert_eq!(kinds, vec![EvalErrKind::StepLimit(10_000), EvalErrKind::DepthLimit(32), EvalErrKind::MemoryLimit(1 << 16)]);
+        // The backtrace of a deep recursion is as long as the recursion, but its
+        // diagnostic shows the calls from the same place once
+        assert_eq!(errs[1].calls.len(), 31);
+        let diagnostic = errs[1].to_diagnostic();
+        let labels: Vec<&str> = diagnostic.secondary.iter().map(|label| &label.message[..]).collect();
+        assert_eq!(labels, vec!["inside 30 nested calls to count", "inside this call to count"]);
+        assert_eq!(run(src).0.len(), 4);
+
+        // Calls from many places are cut short
+        let mut src = "fun step0(): u64 { 0 }\n".to_string();
+        for n in 1..40 {
+            src.push_str(&format!("fun step{}(): u64 {{ step{}() + 1 }}\n", n, n - 1));
+        }
+        src.push_str("#compile_time fun deepest(): u64 { step39() }");
+        let (_, errs) = run_with(&src, Limits { depth: 32, ..Limits::default() });
+        assert_eq!(errs[0].calls.len(), 31);
+        let diagnostic = errs[0].to_diagnostic();
+        assert_eq!(diagnostic.secondary.len(), 8);
+        assert_eq!(diagnostic.secondary[0].message, "inside this call to step9");
+        assert_eq!(diagnostic.notes, vec!["inside 23 more calls", "while evaluating deepest at compile time"]);
+
+        assert_eq!(messages(&run("#compile_time fun with_params(n: i32): i32 { n }
+                             #compile_time fun generic<T>(): i32 { 0 }").1), vec![
+            "a function with parameters can't be run at compile time",
+            "a generic function can't be run at compile time"
+        ]);
+    }
+
+    #[test]
+    fn values_are_moved_and_destroyed() {
+        let src = "pack Tracker { count: *i32 }
+                   def Destroy for Tracker { fun drop(&self) { unsafe { *self.count = *self.count + 1; } } }
+                   pack Pair { a: Tracker, b: Tracker }
+                   pack Buffer { data: *u8 }
+                   def Destroy for Buffer { fun drop(&self) { unsafe { dealloc(self.data); } } }
+                   fun consume(t: Tracker) { }
+                   fun keep(t: Tracker): Tracker { t }
+                   #compile_time fun destroyed(): i32 {
+                       let n = 0;
+                       let p = &n as *i32;
+                       {
+                           let a = Tracker { count: p };
+                           let b = Tracker { count: p };
+                           consume(a);
+                           let c = keep(b);
+                           let pair = Pair { a: Tracker { count: p }, b: Tracker { count: p } };
+                           let Pair { a, b: _ } = pair;
+                           Tracker { count: p };
+                           defer consume(c);
+                       }
+                       n
+                   }
+                   #compile_time fun freed(): i32 {
+                       let i = 0;
+                       while i < 1000 { let b = Buffer { data: alloc(1024, 8) }; i = i + 1; }
+                       i
+                   }";
+        let (constants, errs) = run_with(src, Limits { memory: 1 << 16, ..Limits::default() });
+        assert_eq!(errs, vec![]);
+        let constants: Vec<String> = constants.iter().map(Constant::to_string).collect();
+        assert_eq!(constants, vec!["const destroyed: i32 = 5;", "const freed: i32 = 1000;"]);
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod leak_tests {
+
+    use crate::analysis::leaks::{LeakChecker, LeakErr};
+    use crate::tests::{check_source, messages, BOX};
+
+    const FREE: &str = "#allow(leaks) fun free(b: -Box<i32>) { }\n";
+
+    fn check(src: &str) -> Vec<LeakErr> {
+        check_source(src, |cx, results| LeakChecker::check(cx, results))
+    }
+
+    #[test]
+    fn leaked_bindings() {
+        let src = "pack Box<T> { ptr: *T }
+fun leaks(b: Box<i32>) {
+    let j: -Box<i32> = b;
+}";
+        let errs = check(src);
+        assert_eq!(errs.len(), 1);
+        assert!(!errs[0].to_diagnostic().is_error());
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+warning: j is never freed
+ --> 4:1
+  |
+4 | }
+  | ^ j goes out of scope here, still holding its value
+  |
+3 |     let j: -Box<i32> = b;
+  |         - j is declared here with type -Box<i32>
+ = note: -T values aren't dropped: move it out, pass it to a function which frees it or defer that, or put #allow(leaks) on the let if the leak is intended
+");
+        // Parameters own their value too, and giving it away on one path only isn't enough
+        let src = format!("{}{}
+            fun param(j: -Box<i32>) {{ }}
+            fun one_path(c: bool) {{
+                let j: -Box<i32> = Box::new(1);
+                if c {{ free(j); }}
+            }}
+            fun overwritten() {{
+                let j: -Box<i32> = Box::new(1);
+                j = Box::new(2);
+                free(j);
+            }}", BOX, FREE);
+        assert_eq!(messages(&check(&src)), vec!["j is never freed", "j is never freed", "the old value of j is never freed"]);
+    }
+
+    #[test]
+    fn consumed_bindings() {
+        let src = format!("{}{}
+            pack Holder {{ #feature(minus_attrs) b: -Box<i32> }}
+            fun passed() {{ let j: -Box<i32> = Box::new(1); free(j); }}
+            fun deferred() {{ let j: -Box<i32> = Box::new(1); defer free(j); }}
+            fun returned(): -Box<i32> {{ let j: -Box<i32> = Box::new(1); j }}
+            fun stored(): Holder {{ let j: -Box<i32> = Box::new(1); Holder {{ b: j }} }}
+            fun assigned_later(c: bool) {{
+                let j: -Box<i32>;
+                j = Box::new(1);
+                if c {{ free(j); }} else {{ return free(j); }}
+            }}
+            fun in_a_loop(n: i32) {{
+                while n > 0 {{ let j: -Box<i32> = Box::new(n); free(j); }}
+            }}
+            fun borrowed_only(r: &-Box<i32>) {{ let copy: -i32 = 1; }}", BOX, FREE);
+        assert_eq!(messages(&check(&src)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn allowed_leaks() {
+        let src = format!("{}{}
+            fun on_the_let() {{
+                #allow(leaks) let j: -Box<i32> = Box::new(1);
+                let k: -Box<i32> = Box::new(2);
+            }}
+            #allow(leaks)
+            fun on_the_function(j: -Box<i32>) {{ let k: -Box<i32> = Box::new(2); }}", BOX, FREE);
+        let errs = check(&src);
+        assert_eq!(errs.iter().map(|err| err.to_diagnostic().message).collect::<Vec<_>>(), vec!["k is never freed"]);
+    }
+}
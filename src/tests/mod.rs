@@ -1 +1,77 @@
 mod parsing_tests;
+mod pretty_tests;
+mod declaration_tests;
+mod operator_tests;
+mod statement_tests;
+mod expression_tests;
+mod consteval_tests;
+mod attribute_tests;
+mod module_tests;
+mod visitor_tests;
+mod resolve_tests;
+mod typeck_tests;
+mod solver_tests;
+mod mono_tests;
+mod move_tests;
+mod drop_tests;
+mod leak_tests;
+mod borrow_tests;
+mod unsafety_tests;
+mod interpret_tests;
+mod schedule_tests;
+mod driver_tests;
+
+use std::path::Path;
+
+use crate::analysis::modules::{ModuleTree, SourceFiles};
+use crate::analysis::resolve::Resolver;
+use crate::analysis::typeck::{TypeChecker, TypeCx, TypeckResults};
+use crate::diagnostics::Reported;
+
+/// The Box of the draft, which allocates its value and derefs to it
+const BOX: &str = "pack Box<T> { ptr: *T }
+                   interface Deref<T> { type Target; fun deref(&self): &T; }
+                   impls Box<T> {
+                       public fun new(t: T): Box<T> {
+                           let leaking: -T = t;
+                           let alloc: *u8 = alloc(sizeof<T>(), 8);
+                           unsafe {
+                               let alloc: *T = { alloc as _ };
+                               write(alloc, leaking);
+                               Box { ptr: alloc }
+                           }
+                       }
+                       public fun get(&self): *T { self.ptr }
+                   }
+                   def Deref<T> for Box<T> {
+                       type Target = &T;
+                       public fun deref(&self): &T { unsafe { self.ptr as &T } }
+                   }
+                   fun write<T>(p: *T, t: T);\n";
+
+/// Loads the tree rooted at src/main.tx out of FILES (path and source), which must
+/// have no errors
+fn load(files: &[(&str, &str)]) -> ModuleTree {
+    let mut sources = SourceFiles::new();
+    for (path, src) in files {
+        sources.add(*path, src);
+    }
+    let (tree, errs) = ModuleTree::load(&mut sources, Path::new("src/main.tx"));
+    assert_eq!(errs, vec![]);
+    tree
+}
+
+/// Runs PASS on the program SRC, which must resolve and type check
+fn check_source<R>(src: &str, pass: impl FnOnce(&TypeCx, &TypeckResults) -> R) -> R {
+    let tree = load(&[("src/main.tx", src)]);
+    let (resolutions, errs) = Resolver::resolve(&tree);
+    assert_eq!(errs, vec![]);
+    let (cx, results, errs) = TypeChecker::check(&tree, &resolutions);
+    assert_eq!(errs, vec![]);
+    pass(&cx, &results)
+}
+
+/// The messages of the diagnostics of ERRS
+fn messages<E: Reported>(errs: &[E]) -> Vec<String> {
+    errs.iter().map(|err| err.diagnostic().message).collect()
+}
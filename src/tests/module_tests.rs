@@ -0,0 +1,172 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod module_tests {
+
+    use std::path::{Path, PathBuf};
+
+    use crate::analysis::modules::{Def, ModuleErr, ModuleErrKind, ModuleId, ModuleTree, SourceFiles};
+    use crate::ast::declarations::Item;
+    use crate::ast::expressions::ExprKind;
+    use crate::parsing::expressions::ExprParser;
+    use crate::parsing::Parser;
+
+    /// Loads the tree rooted at src/main.tx out of FILES (path and source), none of which are on disk
+    fn load(files: &[(&str, &str)]) -> (ModuleTree, Vec<ModuleErr>) {
+        let mut sources = SourceFiles::new();
+        for (path, src) in files {
+            sources.add(*path, src);
+        }
+        ModuleTree::load(&mut sources, Path::new("src/main.tx"))
+    }
+
+    /// Loads FILES, which must have no errors at all
+    fn load_ok(files: &[(&str, &str)]) -> ModuleTree {
+        let (tree, errs) = load(files);
+        assert_eq!(errs, vec![]);
+        assert_eq!(tree.check_imports(), vec![]);
+        tree
+    }
+
+    fn segments(path: &str) -> Vec<String> {
+        path.split("::").map(str::to_string).collect()
+    }
+
+    /// The name of the item PATH resolves to from FROM
+    fn resolve(tree: &ModuleTree, from: ModuleId, path: &str) -> Result<String, ModuleErrKind> {
+        let (def, _) = tree.resolve_path(from, &segments(path))?;
+        Ok(match def {
+            Def::Module(id) => format!("mod {}", tree.path_of(id)),
+            Def::Item(..) => tree.item(def).unwrap().name().unwrap().to_string()
+        })
+    }
+
+    fn module(tree: &ModuleTree, path: &str) -> ModuleId {
+        tree.modules().find(|(id, _)| tree.path_of(*id) == path).unwrap().0
+    }
+
+    const DRIVER: [(&str, &str); 4] = [
+        ("src/main.tx", "mod ptr;
+                         mod collections;
+                         use collections::HashMap;
+                         fun main() { }"),
+        ("src/ptr.tx", "public fun read<T>(p: *T): T;
+                        fun offset() { }"),
+        ("src/collections/mod.tx", "mod map;
+                                    public use self::map::HashMap;"),
+        ("src/collections/map.tx", "public pack HashMap<K, V> { len: u64 }
+                                    impls HashMap<K, V> { public fun new(): HashMap<K, V>; }")
+    ];
+
+    #[test]
+    fn modules_from_many_files() {
+        let tree = load_ok(&DRIVER);
+        let collections = tree.module(tree.root()).children["collections"];
+        assert_eq!(tree.module(collections).file, PathBuf::from("src/collections/mod.tx"));
+        let map = tree.module(collections).children["map"];
+        assert_eq!(tree.path_of(map), "collections::map");
+        assert_eq!(tree.module(map).file, PathBuf::from("src/collections/map.tx"));
+        assert_eq!(tree.module(map).items.len(), 2);
+    }
+
+    #[test]
+    fn paths_resolve_across_files() {
+        let tree = load_ok(&DRIVER);
+        let root = tree.root();
+        assert_eq!(resolve(&tree, root, "ptr::read"), Ok("read".to_string()));
+        assert_eq!(resolve(&tree, root, "HashMap"), Ok("HashMap".to_string()));
+        assert_eq!(resolve(&tree, root, "collections::HashMap"), Ok("HashMap".to_string()));
+        assert_eq!(resolve(&tree, root, "collections::map"), Err(ModuleErrKind::Private { name: "map".to_string(), scope: "module collections".to_string() }));
+        // new is a method of HashMap, which is left to the type checker
+        let (def, taken) = tree.resolve_path(root, &segments("HashMap::new")).unwrap();
+        assert_eq!(taken, 1);
+        assert!(matches!(tree.item(def), Some(Item::Pack(_))));
+        // The paths of expressions resolve the same way
+        match ExprParser.run_parser("ptr::read<u8>(p)").unwrap().kind {
+            ExprKind::Call(callee, _) => match callee.kind {
+                ExprKind::Path(path) => {
+                    let names: Vec<String> = path.segments.iter().map(|segment| segment.name.clone()).collect();
+                    let (def, _) = tree.resolve_path(root, &names).unwrap();
+                    assert!(matches!(tree.item(def), Some(Item::Fun(_))));
+                }
+                _ => panic!("expected a path")
+            },
+            _ => panic!("expected a call")
+        }
+        assert_eq!(resolve(&tree, root, "ptr::write"), Err(ModuleErrKind::Unresolved { name: "write".to_string(), scope: "module ptr".to_string() }));
+    }
+
+    #[test]
+    fn self_and_super() {
+        let tree = load_ok(&[("src/main.tx", "fun helper() { }
+                                               mod a { fun f() { } mod b { use super::super::helper; fun g() { } } }")]);
+        let b = module(&tree, "a::b");
+        assert_eq!(resolve(&tree, b, "super::f"), Ok("f".to_string()));
+        assert_eq!(resolve(&tree, b, "super::super::helper"), Ok("helper".to_string()));
+        assert_eq!(resolve(&tree, b, "self::g"), Ok("g".to_string()));
+        assert_eq!(resolve(&tree, b, "helper"), Ok("helper".to_string()));
+        assert_eq!(resolve(&tree, b, "super"), Ok("mod a".to_string()));
+        assert_eq!(resolve(&tree, tree.root(), "super::helper"), Err(ModuleErrKind::SuperOfRoot));
+        let err = ExprParser.run_parser("self::super::x").unwrap_err();
+        assert_eq!(err.expected, "a name");
+    }
+
+    #[test]
+    fn visibility_is_enforced() {
+        let tree = load_ok(&DRIVER);
+        let root = tree.root();
+        assert_eq!(resolve(&tree, root, "ptr::offset"), Err(ModuleErrKind::Private { name: "offset".to_string(), scope: "module ptr".to_string() }));
+        // Private items are visible inside their module, and in the modules inside it
+        let map = module(&tree, "collections::map");
+        assert_eq!(resolve(&tree, map, "super::super::ptr::read"), Ok("read".to_string()));
+        let (tree, _) = load(&[("src/main.tx", "mod a { fun secret() { } mod b { fun g() { } } }
+                                                 use a::secret;")]);
+        assert_eq!(resolve(&tree, module(&tree, "a::b"), "super::secret"), Ok("secret".to_string()));
+        let errs = tree.check_imports();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].kind, ModuleErrKind::Private { name: "secret".to_string(), scope: "module a".to_string() });
+    }
+
+    #[test]
+    fn globs_and_renames() {
+        let tree = load_ok(&[("src/main.tx", "mod shapes { public pack Circle { } public pack Square { } pack Hidden { } }
+                                               use shapes::*;
+                                               use shapes::Square as Block;
+                                               pack Circle2 { }")]);
+        let root = tree.root();
+        assert_eq!(resolve(&tree, root, "Circle"), Ok("Circle".to_string()));
+        assert_eq!(resolve(&tree, root, "Block"), Ok("Square".to_string()));
+        assert!(matches!(resolve(&tree, root, "Hidden"), Err(ModuleErrKind::Unresolved { .. })));
+    }
+
+    #[test]
+    fn import_errors() {
+        let (tree, errs) = load(&[("src/main.tx", "use a::x; use b::x; mod a { public use super::b::x; } mod b { public use super::a::x; }")]);
+        let diags: Vec<String> = errs.iter().map(|err| err.to_diagnostic().message).collect();
+        assert_eq!(diags, vec!["x is defined more than once".to_string()]);
+        let errs = tree.check_imports();
+        assert_eq!(errs.len(), 4);
+        assert!(errs.iter().all(|err| err.kind == ModuleErrKind::ImportCycle("x".to_string())));
+        let (tree, errs) = load(&[("src/main.tx", "pack P { } use P::x; use super;")]);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].kind, ModuleErrKind::Unnamed);
+        assert_eq!(tree.check_imports()[0].kind, ModuleErrKind::NotAModule("P".to_string()));
+    }
+
+    #[test]
+    fn missing_and_malformed_files() {
+        let src = "fun main() { }\nmod net;\n";
+        let (_, errs) = load(&[("src/main.tx", src), ("src/broken.tx", "fun f(")]);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "error: file not found for module net
+ --> 2:1
+  |
+2 | mod net;
+  | ^^^^^^^^ declared here
+ = note: looked for src/net.tx and src/net/mod.tx
+");
+        let (_, errs) = load(&[("src/main.tx", "mod broken;"), ("src/broken.tx", "fun f(")]);
+        assert_eq!(errs[0].file, PathBuf::from("src/broken.tx"));
+        assert!(matches!(errs[0].kind, ModuleErrKind::Syntax(_)));
+    }
+}
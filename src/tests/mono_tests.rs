@@ -0,0 +1,123 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod mono_tests {
+
+    use crate::analysis::drops::DropElaborator;
+    use crate::analysis::mono::{MonoErr, MonoItems, Monomorphizer};
+    use crate::tests::{check_source, BOX};
+
+    fn mono(src: &str) -> (MonoItems, Vec<MonoErr>) {
+        check_source(src, |cx, results| Monomorphizer::collect(cx, results, &DropElaborator::elaborate(cx, results).0))
+    }
+
+    fn symbols(items: &MonoItems) -> Vec<&str> {
+        items.funs.iter().map(|instance| instance.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn instances_reachable_from_main() {
+        let src = format!("{}
+            fun unused<T>(t: T) {{ }}
+            fun main() {{
+                let i = Box::new(123);
+                let b: Box<bool> = Box::new(true);
+                let r = b.deref();
+                let again = Box::new(456);
+            }}", BOX);
+        let (items, errs) = mono(&src);
+        assert_eq!(errs, vec![]);
+        assert_eq!(symbols(&items), vec!["main", "Box<i32>::new", "Box<bool>::new", "Box<bool>::deref", "write<i32>", "write<bool>"]);
+        let packs: Vec<String> = items.packs.iter().map(|pack| format!("{:?}", pack.fields.iter().map(|(name, ty)| format!("{}: {}", name, ty)).collect::<Vec<_>>())).collect();
+        assert_eq!(packs, vec!["[\"ptr: *i32\"]", "[\"ptr: *bool\"]"]);
+        let impls: Vec<String> = items.impls.iter().map(|instance| instance.target.to_string()).collect();
+        assert_eq!(impls, vec!["Box<i32>", "Box<bool>", "Box<bool>"]);
+        let new = &items.funs[1];
+        assert_eq!(new.params.iter().map(|ty| ty.to_string()).collect::<Vec<_>>(), vec!["i32"]);
+        assert_eq!(new.ret.to_string(), "Box<i32>");
+        assert_eq!(items.funs[3].ret.to_string(), "&bool");
+        assert_eq!(new.callees.iter().map(|(_, callee)| *callee).collect::<Vec<_>>(), vec![4]);
+        assert_eq!(items.funs[4].parent, Some(1));
+    }
+
+    #[test]
+    fn drops_are_instantiated_for_what_is_destroyed() {
+        let src = format!("{}
+            def Destroy for Box<T> {{ fun drop(&self) {{ }} }}
+            pack Holder<T> {{ first: Box<u8>, t: T, kept: -Box<u16> }}
+            fun keep<T>(t: T) {{ }}
+            fun main() {{
+                let b = Box::new(1);
+                keep(Box::new(2));
+                keep(Holder {{ first: Box::new(3 as u8), t: Box::new(true), kept: Box::new(4 as u16) }});
+            }}", BOX);
+        let (items, errs) = mono(&src);
+        assert_eq!(errs, vec![]);
+        let drops: Vec<&str> = symbols(&items).into_iter().filter(|symbol| symbol.ends_with("::drop")).collect();
+        assert_eq!(drops, vec!["Box<i32>::drop", "Box<bool>::drop", "Box<u8>::drop"]);
+        // main destroys b itself, and keep<Holder<..>> destroys the fields of t backwards
+        let callees = |symbol: &str| {
+            let instance = items.funs.iter().find(|instance| instance.symbol == symbol).unwrap();
+            instance.callees.iter().map(|(_, callee)| items.funs[*callee].symbol.as_str()).filter(|callee| callee.ends_with("::drop")).collect::<Vec<_>>()
+        };
+        assert_eq!(callees("main"), vec!["Box<i32>::drop"]);
+        assert_eq!(callees("keep<Box<i32>>"), vec!["Box<i32>::drop"]);
+        assert_eq!(callees("keep<Holder<Box<bool>>>"), vec!["Box<bool>::drop", "Box<u8>::drop"]);
+    }
+
+    #[test]
+    fn interface_calls_go_to_the_def() {
+        let src = "pack Job { id: i32 }
+                   pack Task { id: i32 }
+                   interface Execute { fun execute(&self); fun describe(&self): i32 { 0 } }
+                   def Execute for Job { fun execute(&self) { } }
+                   def Execute for Task { fun execute(&self) { } fun describe(&self): i32 { self.id } }
+                   fun execute<T>(t: &T) where T: Execute { t.execute(); t.describe(); }
+                   fun main() {
+                       execute(&Job { id: 1 });
+                       execute(&Task { id: 2 });
+                   }";
+        let (items, errs) = mono(src);
+        assert_eq!(errs, vec![]);
+        assert_eq!(symbols(&items), vec!["main", "execute<Job>", "execute<Task>", "Job::execute", "Job::describe", "Task::execute", "Task::describe"]);
+        // Job has the describe of the interface, Task its own
+        assert_ne!(items.funs[4].fun, items.funs[6].fun);
+    }
+
+    #[test]
+    fn infinite_instantiation() {
+        let src = "fun grow<T>(t: T, n: i32) {
+                       grow((t, t), n - 1);
+                   }
+                   fun same<T>(t: T, n: i32) { same(t, n - 1); }
+                   fun main() {
+                       same(true, 3);
+                       grow(1, 3);
+                   }";
+        let (_, errs) = mono(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: infinite instantiation of the function grow
+ --> 2:24
+  |
+2 |                        grow((t, t), n - 1);
+  |                        ^^^^ instantiates it again with new types
+ = note: grow<i32> needs grow<(i32, i32)>, and so on
+");
+
+        let src = "pack Wrap<T> { inner: T }
+                   pack List<T> { value: T, next: *List<Wrap<T>> }
+                   fun main() {
+                       let list: *List<i32> = 0 as *List<i32>;
+                   }";
+        let (_, errs) = mono(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: infinite instantiation of the pack List
+ --> 2:20
+  |
+2 |                    pack List<T> { value: T, next: *List<Wrap<T>> }
+  |                    ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ contains itself with new generic arguments
+ = note: List<i32> contains List<Wrap<i32>>, and so on
+");
+    }
+}
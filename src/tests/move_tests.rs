@@ -0,0 +1,121 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod move_tests {
+
+    use crate::analysis::moves::{MoveChecker, MoveErr};
+    use crate::tests::{check_source, messages};
+
+    fn check(src: &str) -> Vec<MoveErr> {
+        check_source(src, |cx, results| MoveChecker::check(cx, results))
+    }
+
+    #[test]
+    fn the_draft_use_after_move() {
+        let src = "fun other<T>(t: T) where T: !Copy {
+                       let moved = t;
+                       let other = t;
+                   }";
+        let errs = check(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: use of moved t
+ --> 3:36
+  |
+3 |                        let other = t;
+  |                                    ^ used here after the move
+  |
+2 |                        let moved = t;
+  |                                    - t moved here
+ = note: t is moved because its type T is not Copy
+");
+        // Copy values are copied, not moved
+        let src = "fun copies<T>(t: T, i: i32) where T: Copy {
+                       let first = t;
+                       let second = t;
+                       let j = i + i;
+                   }";
+        assert_eq!(messages(&check(src)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn partial_moves() {
+        let src = "pack Name { text: *u8 }
+                   pack Person { name: Name, age: i32 }
+                   fun take(name: Name) { }
+                   fun people(p: Person, q: Person) {
+                       take(p.name);
+                       let age = p.age;
+                       take(p.name);
+                       let whole = p;
+                       take(q.name);
+                       q.name = Name { text: 0 as *u8 };
+                       let again = q;
+                   }";
+        assert_eq!(messages(&check(src)), vec!["use of moved p.name", "use of partially moved p"]);
+    }
+
+    #[test]
+    fn moves_follow_the_control_flow() {
+        let src = "pack Job { id: i32 }
+                   fun run(job: Job) { }
+                   fun maybe(job: Job, c: bool) {
+                       if c { run(job); }
+                       run(job);
+                   }
+                   fun branches(job: Job, c: bool) {
+                       if c { run(job); } else { run(job); }
+                   }
+                   fun reassigned(job: Job) {
+                       let j = job;
+                       run(j);
+                       j = Job { id: 1 };
+                       run(j);
+                   }
+                   fun returned(job: Job, c: bool) {
+                       if c { run(job); return; }
+                       run(job);
+                   }";
+        assert_eq!(messages(&check(src)), vec!["use of moved job"]);
+
+        let src = "pack Job { id: i32 }
+                   fun run(job: Job) { }
+                   fun looped(job: Job, n: i32) {
+                       while n > 0 {
+                           run(job);
+                       }
+                   }";
+        let errs = check(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: use of moved job
+ --> 5:32
+  |
+5 |                            run(job);
+  |                                ^^^ used here after the move
+  |
+5 |                            run(job);
+  |                                --- job moved here, in the previous iteration of the loop
+ = note: job is moved because its type Job is not Copy
+");
+    }
+
+    #[test]
+    fn borrows_and_indirect_places() {
+        let src = "pack Job { id: i32 }
+                   impls Job { fun id(&self): i32 { self.id } }
+                   fun borrowed(job: Job, r: &Job) {
+                       let id = job.id();
+                       let again = &job;
+                       let copied = r.id;
+                       let moved = *r;
+                       let jobs = [job];
+                       let first = jobs[0];
+                       let late = &job;
+                   }
+                   fun uninit() {
+                       let job: Job;
+                       let used = job;
+                   }";
+        assert_eq!(messages(&check(src)), vec!["cannot move out of *r", "cannot move out of jobs[_]", "borrow of moved job", "use of possibly uninitialized job"]);
+    }
+}
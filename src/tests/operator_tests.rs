@@ -0,0 +1,144 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod operator_tests {
+
+    use std::path::Path;
+
+    use crate::analysis::modules::{ModuleTree, SourceFiles};
+    use crate::analysis::resolve::Resolver;
+    use crate::analysis::typeck::TypeChecker;
+    use crate::ast::declarations::{Item, Operator};
+    use crate::ast::operators::{OpRegistryErr, OperatorRegistry};
+    use crate::parsing::declarations::{AssocParser, ProgramParser};
+    use crate::parsing::{Parser, ParsingBaggage, ParsingContext};
+    use crate::tests::load;
+
+    #[test]
+    fn draft_assocs() {
+        let add = AssocParser.run_parser("assoc Add::plus with +").unwrap();
+        assert_eq!((add.interface.as_str(), add.method.as_str()), ("Add", "plus"));
+        assert_eq!(add.op, Operator::Binary("+".to_string()));
+
+        let index = AssocParser.run_parser("assoc Index::index with [ ];").unwrap();
+        assert_eq!(index.op, Operator::Index);
+
+        let spaceship = AssocParser.run_parser("assoc Ord::cmp with <=> fun").unwrap();
+        assert_eq!(spaceship.op, Operator::Binary("<=>".to_string()));
+
+        let err = AssocParser.run_parser("assoc Add with +").unwrap_err();
+        assert_eq!(err.expected, "'::' between the interface and the method");
+        let err = AssocParser.run_parser("assoc Add::plus with plus").unwrap_err();
+        assert_eq!(err.expected, "an operator");
+    }
+
+    #[test]
+    fn program_registers_operators() {
+        let baggage = ParsingBaggage::init();
+        let mut ctx = ParsingContext::new("assoc Add::plus with +\nassoc Index::index with [ ]\nassoc Concat::concat with ++\nfun main() {}");
+        let program = ProgramParser.parse(&baggage, &mut ctx).unwrap();
+        assert_eq!(program.items.iter().filter(|item| matches!(item, Item::Assoc(_))).count(), 3);
+
+        let operators = baggage.operators.borrow();
+        let plus = operators.binary("+").unwrap();
+        assert_eq!((plus.interface.as_str(), plus.method.as_str()), ("Add", "plus"));
+        assert_eq!(plus.declared_at.map(|span| span.row), Some(1));
+        assert_eq!(operators.index().unwrap().method, "index");
+        assert!(operators.is_binary("++"));
+        assert!(!operators.is_binary("<=>"));
+        assert!(operators.binary("-").is_none());
+        // - is builtin on primitives even though nothing associated it
+        assert!(operators.is_binary("-"));
+        assert_eq!(operators.cast().interface, "Cast");
+    }
+
+    #[test]
+    fn loaded_operators_resolve_where_they_are_declared() {
+        let tree = load(&[
+            ("src/main.tx", "mod ops;\nuse ops::Add;\nfun f(a: i32): i32 { a + 1 }"),
+            ("src/ops.tx", "public interface Add { fun plus(&self, other: Self): Self; }\nassoc Add::plus with +")
+        ]);
+        let (_, ops) = tree.modules().nth(1).unwrap();
+        let (interface, assoc) = match (&ops.items[0], &ops.items[1]) {
+            (Item::Interface(interface), Item::Assoc(assoc)) => (interface.id, assoc.id),
+            other => panic!("expected an interface and an assoc, got {:?}", other)
+        };
+        assert_eq!(tree.operators().get("+").and_then(|plus| plus.decl), Some(assoc));
+        assert_eq!(tree.operators().get("as").map(|cast| cast.method.as_str()), Some("as"));
+        assert!(tree.operators().get("[]").is_none());
+
+        let (resolutions, errs) = Resolver::resolve(&tree);
+        assert_eq!(errs, vec![]);
+        let (cx, _, errs) = TypeChecker::check(&tree, &resolutions);
+        assert_eq!(errs, vec![]);
+        let (_, main) = tree.modules().next().unwrap();
+        let f = main.items.last().unwrap().id();
+        assert_eq!(cx.operator("+", f), Some((interface, "plus")));
+        // Nothing declares a Cast interface
+        assert_eq!(cx.operator("as", f), None);
+    }
+
+    #[test]
+    fn operators_declared_in_a_child_module_work_in_its_parent() {
+        // main.tx is parsed before ops.tx, which declares ++
+        let tree = load(&[
+            ("src/main.tx", "mod ops;\nuse ops::{Concat, Text};\nfun f(a: Text, b: Text): Text { a ++ b }"),
+            ("src/ops.tx", "public interface Concat { fun concat(&self, other: Self): Self; }
+                            assoc Concat::concat with ++
+                            public pack Text { public len: u32 }
+                            def Concat for Text { fun concat(&self, other: Text): Text { Text { len: self.len + other.len } } }")
+        ]);
+        assert!(tree.operators().get("++").and_then(|concat| concat.decl).is_some());
+        let (_, main) = tree.modules().next().unwrap();
+        let f = match main.items.last() {
+            Some(Item::Fun(f)) => f,
+            other => panic!("expected f, got {:?}", other)
+        };
+        assert_eq!(f.body.as_ref().map(|body| body.to_string()), Some("{\n    a ++ b\n}".to_string()));
+
+        let (resolutions, errs) = Resolver::resolve(&tree);
+        assert_eq!(errs, vec![]);
+        let (cx, _, errs) = TypeChecker::check(&tree, &resolutions);
+        assert_eq!(errs, vec![]);
+        let (_, ops) = tree.modules().nth(1).unwrap();
+        assert_eq!(cx.operator("++", f.id), Some((ops.items[0].id(), "concat")));
+        // Symbols nothing associates are still not operators
+        let mut sources = SourceFiles::new();
+        sources.add("src/main.tx", "mod ops;\nfun f(a: i32, b: i32): i32 { a ++ b }");
+        sources.add("src/ops.tx", "public interface Concat { fun concat(&self, other: Self): Self; }");
+        let (tree, errs) = ModuleTree::load(&mut sources, Path::new("src/main.tx"));
+        assert!(tree.operators().get("++").is_none());
+        assert_eq!(errs.iter().map(|err| (err.to_diagnostic().message, err.span.row, err.span.col)).collect::<Vec<_>>(),
+                   vec![("expected an expression, found +".to_string(), 2, 33)]);
+    }
+
+    #[test]
+    fn operators_are_associated_once() {
+        let baggage = ParsingBaggage::init();
+        let mut ctx = ParsingContext::new("assoc Add::plus with +\nassoc Sum::add with +");
+        let err = ProgramParser.parse(&baggage, &mut ctx).unwrap_err();
+        assert_eq!(err.expected, "an operator which is not associated yet");
+        assert_eq!(err.row, 2);
+
+        let mut registry = OperatorRegistry::new();
+        let eq = AssocParser.run_parser("assoc Assign::assign with =").unwrap();
+        assert_eq!(registry.declare(&eq), Err(OpRegistryErr::Reserved("=".to_string())));
+        let index = AssocParser.run_parser("assoc Index::index with []").unwrap();
+        assert!(registry.declare(&index).is_ok());
+        match registry.declare(&index) {
+            Err(OpRegistryErr::AlreadyAssociated(Operator::Index, previous)) => assert_eq!(previous.interface, "Index"),
+            other => panic!("expected the second association to fail, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn precedences() {
+        assert!(OperatorRegistry::precedence("*") > OperatorRegistry::precedence("+"));
+        assert!(OperatorRegistry::precedence("+") > OperatorRegistry::precedence("=="));
+        assert!(OperatorRegistry::precedence("&&") > OperatorRegistry::precedence("||"));
+        // Declared operators bind like their first character
+        assert_eq!(OperatorRegistry::precedence("++"), OperatorRegistry::precedence("+"));
+        assert_eq!(OperatorRegistry::precedence("<=>"), OperatorRegistry::precedence("<"));
+        assert!(OperatorRegistry::precedence("@@") > OperatorRegistry::precedence("*"));
+    }
+}
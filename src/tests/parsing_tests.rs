@@ -1,11 +1,12 @@
 
 #[cfg(test)]
+#[allow(clippy::module_inception, clippy::bool_assert_comparison, unused_variables, unused_mut)]
 mod parsing_tests {
 
-    use parsing::literals::NumberParser;
-    use parsing::combinators::OptionParser;
-
-    use crate::*;
+    use crate::parsing::literals::{IdentParser, NumberParser};
+    use crate::parsing::combinators::*;
+    use crate::parsing::types::Type;
+    use crate::ast::types::{Ty, TyKind};
     use crate::parsing::Parser;
 
     #[test]
@@ -147,6 +148,15 @@ mod parsing_tests {
         assert_eq!(t.run_parser("  Pair<&i32, *&HashMap<Mike, Vec<Type1, Type2>, Type3>>").is_ok(), true);
     }
 
+    #[test]
+    fn associated_types_tests() {
+        let t = Type;
+        assert_eq!(t.run_parser("T::Target").map(|ty| ty.to_string()).ok(), Some("T::Target".to_string()));
+        assert_eq!(t.run_parser("&Box<T>::Target::Item").map(|ty| ty.to_string()).ok(), Some("&Box<T>::Target::Item".to_string()));
+        assert_eq!(matches!(t.run_parser("&Self::Target"), Ok(Ty { kind: TyKind::Ref(inner), .. }) if matches!(inner.kind, TyKind::Assoc(..))), true);
+        assert_eq!(t.run_parser("T::").is_err(), true);
+    }
+
     
 }
 
@@ -0,0 +1,249 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod pretty_tests {
+
+    use crate::ast::expressions::{Expr, ExprKind, FieldInit, Literal, Path, PathSegment, UnaryOp};
+    use crate::ast::ids::NodeId;
+    use crate::ast::types::{PrimitiveType, Ty, TyKind};
+    use crate::ast::Span;
+    use crate::parsing::expressions::ExprParser;
+    use crate::parsing::modules::{ModParser, UseParser};
+    use crate::parsing::statements::BlockParser;
+    use crate::parsing::declarations::{AssocParser, DefParser, FunParser, ImplsParser, InterfaceParser, PackParser, ProgramParser};
+    use crate::parsing::types::Type;
+    use crate::parsing::Parser;
+
+    /// Checks that printing the parsed SRC gives back CANONICAL, and that the
+    /// canonical form parses back to a node which prints the same
+    fn assert_round_trip<P>(parser: P, src: &str, canonical: &str)
+        where P: Parser, P::Output: std::fmt::Display
+    {
+        let printed = parser.run_parser(src).unwrap().to_string();
+        assert_eq!(printed, canonical);
+        let reprinted = parser.run_parser(&printed).unwrap().to_string();
+        assert_eq!(reprinted, printed);
+    }
+
+    /// Tiny xorshift generator, so the round-trip tests are random but reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    const PRIMITIVES: [PrimitiveType; 16] = {
+        use PrimitiveType::*;
+        [UInt8, UInt16, UInt32, UInt64, UInt128, SInt8, SInt16, SInt32, SInt64, SInt128,
+         Float32, Float64, Bool, Char, Unit, RefStaticStr]
+    };
+    const NAMES: [&str; 6] = ["Vec", "HashMap", "Box", "_Other_Pack", "StrSlice", "Rc2"];
+
+    fn random_ty(rng: &mut Rng, depth: usize) -> Ty {
+        let choice = if depth == 0 { rng.below(2) } else { rng.below(6) };
+        match choice {
+            0 => Ty::new(TyKind::Primitive(PRIMITIVES[rng.below(PRIMITIVES.len())])),
+            1 => Ty::new(TyKind::Userdefined(NAMES[rng.below(NAMES.len())].to_string())),
+            2 => {
+                let args = (0..rng.below(3) + 1).map(|_| random_ty(rng, depth - 1)).collect();
+                Ty::new(TyKind::Generic(NAMES[rng.below(NAMES.len())].to_string(), args))
+            }
+            3 => Ty::new(TyKind::Ref(Box::new(random_ty(rng, depth - 1)))),
+            4 => Ty::new(TyKind::NoDrop(Box::new(random_ty(rng, depth - 1)))),
+            _ => Ty::new(TyKind::Ptr(Box::new(random_ty(rng, depth - 1)))),
+        }
+    }
+
+    const OPS: [&str; 10] = ["||", "&&", "==", "<", ">=", "|", "<<", "+", "-", "%"];
+    const UNARY: [UnaryOp; 4] = [UnaryOp::Neg, UnaryOp::Not, UnaryOp::Deref, UnaryOp::Ref];
+
+    fn expr(kind: ExprKind) -> Expr {
+        Expr { id: NodeId::DUMMY, kind, span: Span::default() }
+    }
+
+    fn random_path(rng: &mut Rng) -> Path {
+        let segments = (0..rng.below(2) + 1).map(|_| PathSegment { id: NodeId::DUMMY, name: NAMES[rng.below(NAMES.len())].to_string(), generics: Vec::new(), span: Span::default() }).collect();
+        Path { id: NodeId::DUMMY, segments, span: Span::default() }
+    }
+
+    fn random_args(rng: &mut Rng, depth: usize) -> Vec<Expr> {
+        (0..rng.below(3)).map(|_| random_expr(rng, depth - 1)).collect()
+    }
+
+    fn random_expr(rng: &mut Rng, depth: usize) -> Expr {
+        let choice = if depth == 0 { rng.below(3) } else { rng.below(14) };
+        let kind = match choice {
+            0 => ExprKind::Literal(Literal::Int(rng.next() as u128 % 1000)),
+            1 => ExprKind::Path(random_path(rng)),
+            2 => ExprKind::Literal(Literal::Str(["", "a\"b", "\n\\"][rng.below(3)].to_string())),
+            3 => ExprKind::Call(Box::new(random_expr(rng, depth - 1)), random_args(rng, depth)),
+            4 => {
+                let method = PathSegment { id: NodeId::DUMMY, name: "m".to_string(), generics: (0..rng.below(2)).map(|_| random_ty(rng, 1)).collect(), span: Span::default() };
+                ExprKind::MethodCall(Box::new(random_expr(rng, depth - 1)), method, random_args(rng, depth))
+            }
+            5 => ExprKind::Field(Box::new(random_expr(rng, depth - 1)), "f".to_string()),
+            6 => ExprKind::Index(Box::new(random_expr(rng, depth - 1)), Box::new(random_expr(rng, depth - 1))),
+            7 => {
+                let fields = (0..rng.below(3)).map(|i| FieldInit { id: NodeId::DUMMY, name: format!("f{}", i), value: random_expr(rng, depth - 1), span: Span::default() }).collect();
+                ExprKind::PackLit(random_path(rng), fields)
+            }
+            8 => ExprKind::Tuple((0..rng.below(2) + 1).map(|_| random_expr(rng, depth - 1)).collect()),
+            9 => ExprKind::Unary(UNARY[rng.below(UNARY.len())], Box::new(random_expr(rng, depth - 1))),
+            10 | 11 => {
+                let op = OPS[rng.below(OPS.len())].to_string();
+                ExprKind::Binary(op, Box::new(random_expr(rng, depth - 1)), Box::new(random_expr(rng, depth - 1)))
+            }
+            12 => ExprKind::Cast(Box::new(random_expr(rng, depth - 1)), random_ty(rng, 1)),
+            _ => ExprKind::Assign(Box::new(random_expr(rng, depth - 1)), Box::new(random_expr(rng, depth - 1)))
+        };
+        expr(kind)
+    }
+
+    #[test]
+    fn print_types() {
+        let ty = Ty::new(TyKind::Generic("Vec".to_string(), vec![
+            Ty::new(TyKind::Generic("HashMap".to_string(), vec![
+                Ty::new(TyKind::Ref(Box::new(Ty::new(TyKind::Primitive(PrimitiveType::SInt32))))),
+                Ty::new(TyKind::Ptr(Box::new(Ty::new(TyKind::Ptr(Box::new(Ty::new(TyKind::Userdefined("Mike".to_string())))))))),
+            ])),
+            Ty::new(TyKind::Primitive(PrimitiveType::Unit)),
+        ]));
+        assert_eq!(ty.to_string(), "Vec<HashMap<&i32, **Mike>, ()>");
+        assert_eq!(Ty::new(TyKind::Primitive(PrimitiveType::RefStaticStr)).to_string(), "&StaticString");
+    }
+
+    #[test]
+    fn printing_is_canonical() {
+        let ty = Type.run_parser("  Pair< & i32,* &HashMap<Mike,Vec<Type1, Type2>, Type3>>").unwrap();
+        assert_eq!(ty.to_string(), "Pair<&i32, *&HashMap<Mike, Vec<Type1, Type2>, Type3>>");
+    }
+
+    #[test]
+    fn parsed_primitives_are_primitives() {
+        assert_eq!(Type.run_parser("u128").unwrap(), Ty::new(TyKind::Primitive(PrimitiveType::UInt128)));
+        assert_eq!(Type.run_parser("()").unwrap(), Ty::new(TyKind::Primitive(PrimitiveType::Unit)));
+        assert_eq!(Type.run_parser("&StaticString").unwrap(), Ty::new(TyKind::Primitive(PrimitiveType::RefStaticStr)));
+        assert_eq!(Type.run_parser("u128s").unwrap(), Ty::new(TyKind::Userdefined("u128s".to_string())));
+    }
+
+    #[test]
+    fn type_round_trip() {
+        // parse(print(ty)) must give back exactly ty, for any type
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..500 {
+            let ty = random_ty(&mut rng, 4);
+            let printed = ty.to_string();
+            assert_eq!(Type.run_parser(&printed).ok(), Some(ty), "{} did not round trip", printed);
+        }
+    }
+
+    #[test]
+    fn expression_round_trip() {
+        // Printing puts parentheses exactly where the tree needs them, so parsing the
+        // printed expression must give back a tree which prints the same
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..500 {
+            let printed = random_expr(&mut rng, 4).to_string();
+            let reparsed = ExprParser.run_parser(&printed);
+            assert_eq!(reparsed.map(|e| e.to_string()).ok(), Some(printed.clone()), "{} did not round trip", printed);
+        }
+    }
+
+    /// The literal SRC parses to, if it parses to one
+    fn literal(src: &str) -> Option<Literal> {
+        match ExprParser.run_parser(src).ok()?.kind {
+            ExprKind::Literal(literal) => Some(literal),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn literal_round_trip() {
+        // Whatever the char, it must read back as itself, in a char or a string
+        let chars = ['a', '\'', '"', '\\', '\n', '\t', '\r', '\0', '\u{1}', '\u{1b}', '\u{7f}', '\u{85}',
+                     'é', 'ß', '中', '😀', '\u{200b}', '\u{feff}', '\u{10ffff}'];
+        for c in chars {
+            let printed = Literal::Char(c).to_string();
+            assert_eq!(literal(&printed), Some(Literal::Char(c)), "{} did not round trip", printed);
+        }
+        let s: String = chars.iter().collect();
+        let printed = Literal::Str(s.clone()).to_string();
+        assert_eq!(printed, "\"a'\\\"\\\\\\n\\t\\r\\0\\u{1}\\u{1b}\\u{7f}\\u{85}éß中😀\u{200b}\u{feff}\u{10ffff}\"");
+        assert_eq!(literal(&printed), Some(Literal::Str(s)));
+        assert_eq!(literal("'\\u{e9}'"), Some(Literal::Char('é')));
+        for invalid in ["'\\u{}'", "'\\u{d800}'", "'\\u{110000}'", "'\\u{1234567}'", "'\\u41'", "'\\x41'"] {
+            assert!(ExprParser.run_parser(invalid).is_err(), "{} parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        assert_round_trip(PackParser, "#feature: (minus_attrs)\npack RcBox<T> { ptr: -Box<T>, public count: u32, };",
+            "#feature(minus_attrs)\npack RcBox<T> {\n    ptr: -Box<T>,\n    public count: u32\n}");
+        assert_round_trip(PackParser, "public pack Nothing { }", "public pack Nothing {}");
+    }
+
+    #[test]
+    fn fun_round_trip() {
+        assert_round_trip(FunParser, "(#compile_time) public fun f<T:Copy+!Drop>( &self , T t,u: *u8 ) : T where T : Execute,U:!Copy { \n  a;\n\n   b;  }",
+            "#compile_time\npublic fun f<T: Copy + !Drop>(&self, t: T, u: *u8): T where T: Execute, U: !Copy {\n    a;\n    b;\n}");
+        assert_round_trip(FunParser, "fun sig();", "fun sig();");
+        assert_round_trip(FunParser, "fun empty() {}", "fun empty() {}");
+    }
+
+    #[test]
+    fn program_round_trip() {
+        assert_round_trip(ProgramParser, "pack Box<T> { ptr: *T } fun main() { }",
+            "pack Box<T> {\n    ptr: *T\n}\n\nfun main() {}\n");
+    }
+
+    #[test]
+    fn impls_and_def_round_trip() {
+        assert_round_trip(ImplsParser, "impls<T> Box<T> where T:Copy { fun a() {} public fun b(&self) {} };",
+            "impls<T> Box<T> where T: Copy {\n    fun a() {}\n\n    public fun b(&self) {}\n}");
+        assert_round_trip(DefParser, "def Deref for Box<T> { type Target=&T; len:u32=count; size : u32; fun deref(&self): &T; }",
+            "def Deref for Box<T> {\n    type Target = &T;\n\n    len: u32 = count;\n\n    size: u32;\n\n    fun deref(&self): &T;\n}");
+        assert_round_trip(DefParser, "def Copy for Point {}", "def Copy for Point {}");
+    }
+
+    #[test]
+    fn interface_round_trip() {
+        assert_round_trip(InterfaceParser, "public interface Deref<T> : Sized+Other where T:Copy { type Target : Clone; len:u32; fun deref(&self): &Target; };",
+            "public interface Deref<T>: Sized + Other where T: Copy {\n    type Target: Clone;\n\n    len: u32;\n\n    fun deref(&self): &Target;\n}");
+        assert_round_trip(InterfaceParser, "interface Copy {}", "interface Copy {}");
+    }
+
+    #[test]
+    fn assoc_round_trip() {
+        assert_round_trip(AssocParser, "assoc Add::plus with +;", "assoc Add::plus with +");
+        assert_round_trip(AssocParser, "assoc Index::index with [ ]", "assoc Index::index with []");
+    }
+
+    #[test]
+    fn block_round_trip() {
+        assert_round_trip(BlockParser, "{ val s:String=\"a\\\"\\n\"; let (a,_)=pair( 'x' ,'\\'',); defer( drop(j) ) ; return; }",
+            "{\n    val s: String = \"a\\\"\\n\";\n    let (a, _) = pair('x', '\\'');\n    defer(drop(j));\n    return;\n}");
+        assert_round_trip(BlockParser, "{ while go() { if a {b} else if c {} else { unsafe { d(); } } } for P { x:x, y: (_, z) } in ps { break; } tail }",
+            "{\n    while go() {\n        if a {\n            b\n        } else if c {} else {\n            unsafe {\n                d();\n            }\n        }\n    }\n    for P { x, y: (_, z) } in ps {\n        break;\n    }\n    tail\n}");
+        assert_round_trip(BlockParser, "{ if (p == P { x: 1 }) {} ({ a }).x; unsafe { f() } }",
+            "{\n    if (p == P { x: 1 }) {}\n    ({\n        a\n    }.x);\n    unsafe {\n        f()\n    }\n}");
+    }
+
+    #[test]
+    fn module_round_trip() {
+        assert_round_trip(ModParser, "public mod ptr ;", "public mod ptr;");
+        assert_round_trip(ModParser, "mod inner { fun f() {} public pack P {} }", "mod inner {\n    fun f() {}\n\n    public pack P {}\n}");
+        assert_round_trip(UseParser, "use collections :: HashMap;", "use collections::HashMap;");
+        assert_round_trip(UseParser, "public use super::super::a::{self, b as c, d::*, {e},};", "public use super::super::a::{self, b as c, d::*, {e}};");
+        assert_round_trip(UseParser, "use *;", "use *;");
+    }
+}
@@ -0,0 +1,287 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod resolve_tests {
+
+    use std::collections::HashMap;
+
+    use crate::analysis::modules::{ModuleErrKind, ModuleTree};
+    use crate::analysis::resolve::{Builtin, Res, ResolveErr, ResolveErrKind, Resolutions, Resolver};
+    use crate::ast::declarations::{Item, Param};
+    use crate::ast::expressions::{Expr, ExprKind};
+    use crate::ast::ids::NodeId;
+    use crate::ast::statements::{Pattern, PatternKind};
+    use crate::ast::visit::{self, Visitor};
+    use crate::tests::load;
+
+    /// The paths of the expressions, and the names of what can be resolved to
+    #[derive(Default)]
+    struct Names {
+        paths: Vec<(String, NodeId, usize)>, // The path, the id of its expression and its row
+        defs: HashMap<NodeId, String>
+    }
+
+    impl<'ast> Visitor<'ast> for Names {
+        fn visit_item(&mut self, item: &'ast Item) {
+            if let Some(name) = item.name() {
+                self.defs.insert(item.id(), name.to_string());
+            }
+            visit::walk_item(self, item);
+        }
+
+        fn visit_param(&mut self, param: &'ast Param) {
+            self.defs.insert(param.id, format!("param {}", param.name));
+            visit::walk_param(self, param);
+        }
+
+        fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+            if let PatternKind::Binding(name) = &pattern.kind {
+                self.defs.insert(pattern.id, format!("local {} from line {}", name, pattern.span.row));
+            }
+            visit::walk_pattern(self, pattern);
+        }
+
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            if let ExprKind::Path(path) | ExprKind::PackLit(path, _) = &expr.kind {
+                let names: Vec<&str> = path.segments.iter().map(|segment| segment.name.as_str()).collect();
+                self.paths.push((names.join("::"), expr.id, expr.span.row));
+            }
+            visit::walk_expr(self, expr);
+        }
+    }
+
+    fn names(tree: &ModuleTree) -> Names {
+        let mut names = Names::default();
+        for (_, module) in tree.modules() {
+            for item in &module.items {
+                names.visit_item(item);
+            }
+        }
+        names
+    }
+
+    /// What every path of the code resolves to, as path@row => what
+    fn resolved(tree: &ModuleTree, resolutions: &Resolutions) -> Vec<String> {
+        let names = names(tree);
+        names.paths.iter().map(|(path, id, row)| {
+            let what = match resolutions.path(*id) {
+                None => "nothing".to_string(),
+                Some(partial) => {
+                    let res = match partial.res {
+                        Res::Item(id) | Res::Local(id) => names.defs[&id].clone(),
+                        Res::Module(module) => format!("mod {}", tree.path_of(module)),
+                        Res::Builtin(builtin) => format!("builtin {}", builtin.name()),
+                        res => format!("{:?}", res)
+                    };
+                    if partial.unresolved > 0 { format!("{} + {}", res, partial.unresolved) } else { res }
+                }
+            };
+            format!("{}@{} => {}", path, row, what)
+        }).collect()
+    }
+
+    fn resolve(src: &str) -> (ModuleTree, Resolutions, Vec<ResolveErr>) {
+        let tree = load(&[("src/main.tx", src)]);
+        let (resolutions, errs) = Resolver::resolve(&tree);
+        (tree, resolutions, errs)
+    }
+
+    fn errors(src: &str) -> Vec<ResolveErrKind> {
+        resolve(src).2.into_iter().map(|err| err.kind).collect()
+    }
+
+    #[test]
+    fn paths_resolve_to_their_definitions() {
+        let tree = load(&[
+            ("src/main.tx", "mod ptr;
+                             pack String { len: u64 }
+                             impls String { public fun from(n: i32): String; }
+                             const LIMIT: i32 = 10;
+                             fun main(argc: i32) {
+                                 let s = String::from(argc);
+                                 let p = ptr::read<u8>(ptr::null());
+                                 let n = sizeof<String>() + LIMIT;
+                                 drop(s);
+                             }"),
+            ("src/ptr.tx", "public fun read<T>(p: *T): T; public fun null(): *u8;")
+        ]);
+        let (resolutions, errs) = Resolver::resolve(&tree);
+        assert_eq!(errs, vec![]);
+        assert_eq!(resolved(&tree, &resolutions), vec![
+            "String::from@6 => String + 1",
+            "argc@6 => param argc",
+            "ptr::read@7 => read",
+            "ptr::null@7 => null",
+            "sizeof@8 => builtin sizeof",
+            "LIMIT@8 => LIMIT",
+            "drop@9 => builtin drop",
+            "s@9 => local s from line 6"
+        ]);
+    }
+
+    #[test]
+    fn let_shadowing_follows_the_draft() {
+        // The initializer of a let sees the binding it shadows
+        let src = "fun new<T>(t: T) {
+                       let leaking: -T = t;
+                       let alloc: *u8 = alloc(sizeof<T>(), 8);
+                       unsafe {
+                           let alloc: *T = { alloc as _ };
+                           write(alloc, leaking);
+                       }
+                       alloc
+                   }
+                   fun write<T>(p: *T, t: T);";
+        let (tree, resolutions, errs) = resolve(src);
+        assert_eq!(errs, vec![]);
+        assert_eq!(resolved(&tree, &resolutions), vec![
+            "t@2 => param t",
+            "alloc@3 => builtin alloc",
+            "sizeof@3 => builtin sizeof",
+            "alloc@5 => local alloc from line 3",
+            "write@6 => write",
+            "alloc@6 => local alloc from line 5",
+            "leaking@6 => local leaking from line 2",
+            "alloc@8 => local alloc from line 3"
+        ]);
+        // Shadowing in the same block is fine once the first value was used
+        assert_eq!(errors("fun f() { let x = 1; let x = x + 1; x }"), vec![]);
+    }
+
+    #[test]
+    fn unused_values_shadowed_in_their_block() {
+        let src = "fun f() {\n    let x = 1;\n    let x = 2;\n    x\n}";
+        let (_, _, errs) = resolve(src);
+        assert_eq!(errs.len(), 1);
+        let diag = errs[0].to_diagnostic();
+        assert!(!diag.is_error());
+        assert_eq!(diag.render(src), "warning: x is shadowed before it is used
+ --> 3:9
+  |
+3 |     let x = 2;
+  |         ^ shadowed here
+  |
+2 |     let x = 1;
+  |         - this value of x is never used
+ = note: bind it to _ if the value is not needed
+");
+        // A block inside gets its own scope
+        assert_eq!(errors("fun f() { let x = 1; { let x = 2; x; } }"), vec![]);
+    }
+
+    #[test]
+    fn unknown_names_are_reported_with_their_span() {
+        let src = "fun f(a: i32) {\n    let b = a + c;\n}";
+        let (_, _, errs) = resolve(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "error: can't find c in this scope
+ --> 2:17
+  |
+2 |     let b = a + c;
+  |                 ^ not found
+");
+        // The types point at themselves, not at what they are in
+        let src = "pack Box<T> { t: T }\nfun f(o: &Box<Other>) { }";
+        let (_, _, errs) = resolve(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "error: can't find Other in this scope
+ --> 2:15
+  |
+2 | fun f(o: &Box<Other>) { }
+  |               ^^^^^ not found
+");
+        // Locals end with their block, and patterns are resolved too
+        assert_eq!(errors("fun f() { { let y = 1; y; } y }"), vec![ResolveErrKind::Unresolved("y".to_string())]);
+        assert_eq!(errors("fun f() { for i in range(3) { i; } i }"), vec![ResolveErrKind::Unresolved("range".to_string()), ResolveErrKind::Unresolved("i".to_string())]);
+        assert_eq!(errors("fun f(o: Other) { }"), vec![ResolveErrKind::Unresolved("Other".to_string())]);
+        assert_eq!(errors("mod m { } fun f() { m::g(); }"), vec![ResolveErrKind::Path(ModuleErrKind::Unresolved { name: "g".to_string(), scope: "module m".to_string() })]);
+    }
+
+    #[test]
+    fn ambiguous_glob_imports() {
+        let src = "mod a { public fun f() { } }\nmod b { public fun f() { } }\nuse a::*;\nuse b::*;\nfun main() { f(); }";
+        let (_, _, errs) = resolve(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "error: f is ambiguous
+ --> 5:14
+  |
+5 | fun main() { f(); }
+  |              ^ used here
+  |
+3 | use a::*;
+  |     ---- f could come from here
+  |
+4 | use b::*;
+  |     ---- f could come from here
+ = note: import f by name to say which one it is
+");
+        // The same item through two globs is fine
+        assert_eq!(errors("mod a { public fun f() { } } mod b { public use super::a::f; } use a::*; use b::*; fun main() { f(); }"), vec![]);
+    }
+
+    #[test]
+    fn names_of_the_wrong_kind() {
+        let expected = |name: &str, found, expected| ResolveErrKind::Expected { name: name.to_string(), found, expected };
+        assert_eq!(errors("fun g() { } fun f(x: g) { }"), vec![expected("g", "a function", "a type")]);
+        assert_eq!(errors("pack P { } fun f() { P; }"), vec![expected("P", "a pack", "a value")]);
+        assert_eq!(errors("pack P { } fun f<T>() where T: P { }"), vec![expected("P", "a pack", "an interface")]);
+        assert_eq!(errors("interface I { } fun f() { I { }; }"), vec![expected("I", "an interface", "a pack")]);
+        assert_eq!(errors("fun g() { } fun f() { g::h(); }"), vec![expected("g", "a function", "a type or a module")]);
+        assert_eq!(errors("fun f() { Self; }"), vec![ResolveErrKind::NoSelf("Self")]);
+        assert_eq!(errors("fun f() { self; }"), vec![ResolveErrKind::NoSelf("self")]);
+    }
+
+    #[test]
+    fn generics_self_and_implicit_parameters() {
+        let src = "pack Box<T> { ptr: *T }
+                   interface Execute { fun execute(&self); }
+                   impls Box<T> {
+                       public fun get(&self): *T { self.ptr }
+                       public fun same(&self): Self { Self { ptr: self.ptr } }
+                   }
+                   def Execute for Box<T> where T: Execute { fun execute(&self) { } }
+                   fun execute<T>(t: T) where T: Execute + Copy { t.execute(); }";
+        let (tree, resolutions, errs) = resolve(src);
+        assert_eq!(errs, vec![]);
+        let items = &tree.module(tree.root()).items;
+        let (impls, def) = (items[2].id(), items[3].id());
+        assert_eq!(resolutions.implicit_generics.get(impls), Some(&vec!["T".to_string()]));
+        assert_eq!(resolutions.implicit_generics.get(def), Some(&vec!["T".to_string()]));
+        assert_eq!(resolutions.ty_name(impls, "Box"), Some(Res::Item(items[0].id())));
+        assert_eq!(resolutions.ty_name(impls, "T"), Some(Res::Generic { owner: impls, index: 0 }));
+        assert_eq!(resolutions.ty_name(def, "Execute"), Some(Res::Item(items[1].id())));
+        let execute = items[4].id();
+        assert_eq!(resolutions.ty_name(execute, "Copy"), Some(Res::Builtin(Builtin::Copy)));
+        // The functions of the impls see its generics and its Self
+        let (get, same) = match &items[2] {
+            Item::Impls(impls) => (impls.funs[0].id, impls.funs[1].id),
+            _ => panic!("expected impls")
+        };
+        assert_eq!(resolutions.ty_name(get, "T"), Some(Res::Generic { owner: impls, index: 0 }));
+        assert_eq!(resolved(&tree, &resolutions)[..3], [
+            format!("self@4 => SelfValue({:?})", get),
+            format!("Self@5 => SelfTy({:?})", impls),
+            format!("self@5 => SelfValue({:?})", same)
+        ]);
+    }
+
+    #[test]
+    fn shadowed_generics_and_duplicate_bindings() {
+        let src = "pack Box<T> { ptr: *T }\nimpls<T> Box<T> {\n    fun map<T>(self) { }\n}";
+        let (_, _, errs) = resolve(src);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "error: the generic parameter T is already declared
+ --> 3:13
+  |
+3 |     fun map<T>(self) { }
+  |             ^ declared again here
+  |
+2 | impls<T> Box<T> {
+  |       - T is declared here
+");
+        let duplicate = |name: &str| matches!(errors(&format!("fun f(a: i32, b: i32) {{ let (c, c) = (a, b); }} fun g<{0}, {0}>() {{ }}", name))[..],
+            [ResolveErrKind::Duplicate { .. }, ResolveErrKind::Duplicate { .. }]);
+        assert!(duplicate("T"));
+        assert!(matches!(errors("fun f(a: i32, a: i32) { }")[..], [ResolveErrKind::Duplicate { .. }]));
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod schedule_tests {
+
+    use crate::analysis::interpret::{Constant, Sandbox};
+    use crate::analysis::schedule::{tasks, Effect, Scheduler};
+    use crate::tests::check_source;
+
+    const IO: &str = "fun print<T>(t: T);
+                      fun file_size(path: &StrSlice): u64;\n";
+
+    fn effects(src: &str) -> Vec<(String, Effect)> {
+        check_source(src, |cx, results| tasks(cx, results).iter().map(|task| (task.fun.name.clone(), task.effect)).collect())
+    }
+
+    /// The constants, the errors and the sandbox after running the tasks on THREADS threads
+    fn run(src: &str, threads: usize, sandbox: Sandbox) -> (Vec<String>, Vec<String>, Sandbox) {
+        let mut sandbox = sandbox;
+        let scheduler = Scheduler { threads, ..Scheduler::default() };
+        let (constants, errs) = check_source(src, |cx, results| scheduler.run(cx, results, &mut sandbox));
+        let errs = errs.iter().map(|err| err.to_diagnostic().render(src)).collect();
+        (constants.iter().map(Constant::to_string).collect(), errs, sandbox)
+    }
+
+    #[test]
+    fn tasks_are_classified_by_effect() {
+        let src = format!("{}
+            interface Log {{ fun log(&self); }}
+            pack Quiet {{ }}
+            pack Loud {{ }}
+            def Log for Quiet {{ fun log(&self) {{ }} }}
+            def Log for Loud {{ fun log(&self) {{ print(\"loud\"); }} }}
+            fun helper(): i32 {{ print(1); 1 }}
+            fun log_all<T>(t: T) where T: Log {{ t.log(); }}
+            #compile_time fun pure(): i32 {{ let n = 0; while n < 3 {{ n = n + 1; }} n }}
+            #compile_time fun direct(): i32 {{ print(\"hi\"); 0 }}
+            #compile_time fun indirect(): i32 {{ helper() }}
+            #compile_time fun quiet(): i32 {{ log_all(Quiet {{ }}); 0 }}
+            #compile_time fun size(): u64 {{ file_size(\"build.tx\") }}", IO);
+        // Quiet logs nothing, but any def of Log may be the one called
+        assert_eq!(effects(&src), vec![
+            ("pure".to_string(), Effect::Pure),
+            ("direct".to_string(), Effect::Io),
+            ("indirect".to_string(), Effect::Io),
+            ("quiet".to_string(), Effect::Io),
+            ("size".to_string(), Effect::Io)
+        ]);
+        // Only what a task may reach counts: the drops of the values it handles and the
+        // consts it reads
+        let src = format!("{}
+            pack Noisy {{ }}
+            def Destroy for Noisy {{ fun drop(&self) {{ print(\"dropped\"); }} }}
+            pack Holder {{ noisy: Noisy }}
+            fun helper(): i32 {{ print(1); 1 }}
+            const ONE: i32 = helper();
+            const TWO: i32 = 2;
+            fun make(): Holder {{ Holder {{ noisy: Noisy {{ }} }} }}
+            fun forget<T>(t: T) {{ }}
+            #compile_time fun pure(): i32 {{ let n = TWO; n + 1 }}
+            #compile_time fun drops(): i32 {{ Noisy {{ }}; 0 }}
+            #compile_time fun drops_a_field(): i32 {{ make(); 0 }}
+            #compile_time fun drops_generic(): i32 {{ forget(1); 0 }}
+            #compile_time fun reads(): i32 {{ ONE }}", IO);
+        assert_eq!(effects(&src), vec![
+            ("pure".to_string(), Effect::Pure),
+            ("drops".to_string(), Effect::Io),
+            ("drops_a_field".to_string(), Effect::Io),
+            ("drops_generic".to_string(), Effect::Io),
+            ("reads".to_string(), Effect::Io)
+        ]);
+    }
+
+    #[test]
+    fn io_goes_through_the_sandbox() {
+        let src = format!("{}fun missing(): u64 {{ file_size(\"missing.tx\") }}
+#compile_time fun size(): u64 {{ print(\"sizing \"); print(2); file_size(\"build.tx\") }}
+#compile_time fun absent(): u64 {{
+    print(\"looking\");
+    missing()
+}}
+fun unknown();
+#compile_time fun unprovided(): i32 {{ unknown(); 0 }}", IO);
+        let mut sandbox = Sandbox::default();
+        sandbox.files.insert("build.tx".to_string(), "fun main() { }".to_string());
+        let (constants, errs, sandbox) = run(&src, 4, sandbox);
+        assert_eq!(constants, vec!["const size: u64 = 14;"]);
+        assert_eq!(sandbox.output, "sizing 2looking");
+        assert_eq!(errs.len(), 2);
+        assert_eq!(errs[0], "\
+error: there is no file missing.tx in the sandbox
+ --> 3:22
+  |
+3 | fun missing(): u64 { file_size(\"missing.tx\") }
+  |                      ^^^^^^^^^^^^^^^^^^^^^^^ in this compile-time IO
+  |
+7 |     missing()
+  |     --------- inside this call to missing
+ = note: while evaluating absent at compile time
+");
+        assert_eq!(errs[1].lines().next(), Some("error: calling unknown, which the sandbox doesn't provide, can't be done at compile time"));
+    }
+
+    #[test]
+    fn output_is_the_same_whatever_the_threads() {
+        // Pure tasks of very different lengths, which finish in any order, between IO
+        // tasks and failing tasks
+        let mut src = IO.to_string();
+        src.push_str("fun fib(n: u64): u64 { let a = 0; let b = 1; let i = 0; while i < n { let c = a + b; a = b; b = c; i = i + 1; } a }\n");
+        for task in 0..24 {
+            let body = match task % 4 {
+                0 => format!("fib({})", (24 - task) * 3),
+                1 => format!("print(\"task {}\\n\"); {}", task, task),
+                2 => format!("let n: u64 = {}; n / (n - {})", task, task),
+                _ => format!("fib({}) + fib(2)", task)
+            };
+            src.push_str(&format!("#compile_time fun task{}(): u64 {{ {} }}\n", task, body));
+        }
+        let expected = run(&src, 1, Sandbox::default());
+        assert_eq!(expected.0.len(), 18);
+        assert_eq!(expected.0[0], "const task0: u64 = 498454011879264;");
+        assert_eq!(expected.0[1], "const task1: u64 = 1;");
+        assert_eq!(expected.1.len(), 6);
+        assert_eq!(expected.2.output, (0..24).filter(|task| task % 4 == 1).map(|task| format!("task {}\n", task)).collect::<String>());
+        for threads in [2, 3, 8, 0] {
+            for _ in 0..4 {
+                assert_eq!(run(&src, threads, Sandbox::default()), expected);
+            }
+        }
+    }
+}
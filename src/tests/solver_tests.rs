@@ -0,0 +1,265 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod solver_tests {
+
+    use crate::analysis::resolve::Resolver;
+    use crate::analysis::solver::{Solver, SolverErr, SolverErrKind};
+    use crate::analysis::typeck::{TypeChecker, TypeErrKind};
+    use crate::analysis::types::Type;
+    use crate::ast::types::PrimitiveType;
+    use crate::tests::{check_source, load};
+
+    const JOBS: &str = "pack Job { id: i32 }
+                        pack Name { len: u64, ptr: *u8 }
+                        interface Execute { fun execute(&self); }
+                        def Execute for Job { fun execute(&self) { } }
+                        fun execute<T>(owned_something: T) where T: Execute { owned_something.execute(); }
+                        fun execute_borrow<T>(borrowed_something: &T) where T: Execute { borrowed_something.execute(); }
+                        fun other<T>(t: T) where T: !Copy { let moved = t; }
+                        fun other_usecopy<C>(c: C) where C: Copy { let a1 = c; let a2 = c; }\n";
+
+    /// The solver errors of SRC, which has to type check
+    fn solve(src: &str) -> Vec<SolverErr> {
+        check_source(src, |cx, results| Solver::check(cx, results))
+    }
+
+    fn errors(src: &str) -> Vec<SolverErrKind> {
+        solve(src).into_iter().map(|err| err.kind).collect()
+    }
+
+    fn render(src: &str) -> String {
+        let errs = solve(src);
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        errs[0].to_diagnostic().render(src)
+    }
+
+    fn unsatisfied(ty: Type, bound: &str, reason: &str) -> SolverErrKind {
+        SolverErrKind::Unsatisfied { ty, bound: bound.to_string(), reason: reason.to_string() }
+    }
+
+    #[test]
+    fn the_draft_bounds_hold() {
+        let src = format!("{}
+            fun main() {{
+                let job = Job {{ id: 1 }};
+                execute_borrow(&job);
+                execute(job);
+                other(Name {{ len: 0, ptr: 0 as *u8 }});
+                other_usecopy(1);
+                other_usecopy((true, [1, 2], &job));
+            }}
+            fun forward<T>(t: T) where T: !Copy {{ other(t); }}
+            fun copies<T: Copy>(t: T) {{ other_usecopy(t); other_usecopy([t, t]); }}", JOBS);
+        assert_eq!(errors(&src), vec![]);
+    }
+
+    #[test]
+    fn unsatisfied_bounds_point_at_the_call() {
+        let src = format!("{}
+            fun main() {{
+                execute(1);
+                other(1);
+                other_usecopy(Job {{ id: 1 }});
+                other_usecopy((1, Name {{ len: 0, ptr: 0 as *u8 }}));
+            }}
+            fun unknown<T>(t: T) {{ other(t); }}", JOBS);
+        let i32 = Type::Prim(PrimitiveType::SInt32);
+        let errs = errors(&src);
+        assert_eq!(errs.len(), 5);
+        assert_eq!(errs[0], unsatisfied(i32.clone(), "Execute", "required by this call to execute"));
+        assert_eq!(errs[1], unsatisfied(i32, "!Copy", "required by this call to other"));
+        assert!(matches!(&errs[2], SolverErrKind::Unsatisfied { ty: Type::Pack { name, .. }, bound, .. } if name == "Job" && bound == "Copy"));
+        assert!(matches!(&errs[3], SolverErrKind::Unsatisfied { ty: Type::Tuple(_), bound, .. } if bound == "Copy"));
+        // Nothing says T is not Copy: it could be
+        assert!(matches!(&errs[4], SolverErrKind::Unsatisfied { ty: Type::Param { name, .. }, bound, .. } if name == "T" && bound == "!Copy"));
+
+        let src = "interface Execute { fun execute(&self); }
+                   fun execute<T>(t: T) where T: Execute { }
+                   fun main() {
+                       execute(true);
+                   }";
+        assert_eq!(render(src), "\
+error: the bound bool: Execute is not satisfied
+ --> 4:24
+  |
+4 |                        execute(true);
+  |                        ^^^^^^^ required by this call to execute
+");
+    }
+
+    #[test]
+    fn bounds_come_from_defs_and_the_interfaces_extended() {
+        let src = "pack Box<T> { ptr: *T }
+                   interface Eq { fun eq(&self, other: &Self): bool; }
+                   interface Ord: Eq { fun lt(&self, other: &Self): bool; }
+                   fun same<T: Eq>(a: &T, b: &T): bool { a.eq(b) }
+                   fun sorted<T: Ord>(a: &T, b: &T): bool { same(a, b) && a.lt(b) }
+                   def Eq for i32 { fun eq(&self, other: &i32): bool { *self == *other } }
+                   def Eq for Box<T> where T: Eq { fun eq(&self, other: &Box<T>): bool { true } }
+                   fun main(b: Box<i32>, c: Box<bool>) {
+                       same(&b, &b);
+                       same(&c, &c);
+                   }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        assert!(matches!(&errs[0], SolverErrKind::Unsatisfied { ty: Type::Pack { args, .. }, bound, .. } if args[0] == Type::bool() && bound == "Eq"));
+    }
+
+    #[test]
+    fn associated_types_are_normalised() {
+        let src = "pack Box<T> { ptr: *T }
+                   interface Deref { type Target; fun deref(&self): Self::Target; }
+                   def Deref for Box<T> {
+                       type Target = &T;
+                       public fun deref(&self): &T { unsafe { self.ptr as &T } }
+                   }
+                   fun through<T: Deref>(t: &T): T::Target { t.deref() }
+                   fun main(b: Box<bool>) {
+                       let r: &bool = b.deref();
+                       let s: Box<bool>::Target = through(&b);
+                       let copy: bool = *through(&b);
+                   }";
+        assert_eq!(errors(src), vec![]);
+
+        // What T::Target is isn't known inside the function
+        let src = "interface Deref { type Target; fun deref(&self): Self::Target; }
+                   fun through<T: Deref>(t: &T): i32 { t.deref() }";
+        let tree = load(&[("src/main.tx", src)]);
+        let (resolutions, _) = Resolver::resolve(&tree);
+        let (_, _, errs) = TypeChecker::check(&tree, &resolutions);
+        assert_eq!(errs.len(), 1);
+        assert!(matches!(&errs[0].kind, TypeErrKind::Mismatch { found: Type::Assoc(_, name), .. } if name == "Target"));
+    }
+
+    #[test]
+    fn associated_types_keep_their_bounds() {
+        let src = "pack Box<T> { ptr: *T }
+                   pack Name { len: u64 }
+                   interface Deref { type Target: Copy; fun deref(&self): Self::Target; }
+                   def Deref for Box<T> { type Target = &T; fun deref(&self): &T { unsafe { self.ptr as &T } } }
+                   def Deref for Name { type Target = Name; fun deref(&self): Name { Name { len: self.len } } }
+                   fun twice<T: Deref>(t: &T) { other_usecopy(t.deref()); }
+                   fun other_usecopy<C>(c: C) where C: Copy { }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        assert!(matches!(&errs[0], SolverErrKind::Unsatisfied { reason, .. } if reason == "required by the associated type Target of Deref"));
+    }
+
+    #[test]
+    fn overlapping_defs() {
+        let src = "pack Box<T> { ptr: *T }
+                   interface Show { fun show(&self); }
+                   def Show for Box<T> { fun show(&self) { } }
+                   def Show for Box<i32> { fun show(&self) { } }";
+        assert_eq!(render(src), "\
+error: conflicting defs of Show
+ --> 4:20
+  |
+4 |                    def Show for Box<i32> { fun show(&self) { } }
+  |                    ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ def for Box<i32>
+  |
+3 |                    def Show for Box<T> { fun show(&self) { } }
+  |                    ------------------------------------------- overlaps with the def for Box<T>
+ = note: opposite bounds on the generic parameters, like T: Copy and T: !Copy, keep defs apart
+");
+
+        // Negative bounds keep them apart, as does a bound which the other target fails
+        let src = "pack Box<T> { ptr: *T }
+                   pack Name { len: u64 }
+                   interface Show { fun show(&self); }
+                   def Show for Box<T> where T: Copy { fun show(&self) { } }
+                   def Show for Box<T> where T: !Copy { fun show(&self) { } }
+                   def Show for i32 { fun show(&self) { } }
+                   def Show for bool { fun show(&self) { } }
+                   interface Print { fun print(&self); }
+                   def Print for Box<T> where T: Copy { fun print(&self) { } }
+                   def Print for Box<Name> { fun print(&self) { } }";
+        assert_eq!(errors(src), vec![]);
+
+        let src = "pack Box<T> { ptr: *T }
+                   def Copy for Box<T> { }
+                   def Destroy for Box<T> { fun drop(&self) { } }
+                   impls Box<T> { fun get(&self): *T { self.ptr } }
+                   impls Box<i32> { fun get(&self): *i32 { self.ptr } fun other(&self) { } }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 2, "{:?}", errs);
+        assert!(matches!(&errs[0], SolverErrKind::CopyAndDestroy { .. }));
+        assert!(matches!(&errs[1], SolverErrKind::DuplicateFun { name, .. } if name == "get"));
+    }
+
+    #[test]
+    fn defs_supply_what_the_interface_requires() {
+        let src = "pack Vec<T> { ptr: *T, size: u64 }
+                   interface Sized { len: u64; type Item; fun first(&self): Self::Item; fun empty(&self): bool { false } }
+                   def Sized for Vec<T> { len: u64 = size; type Item = T; fun first(&self): T { unsafe { *self.ptr } } }
+                   interface Named { name: u64; }
+                   pack Tag { name: u64 }
+                   def Named for Tag { }";
+        assert_eq!(errors(src), vec![]);
+
+        let src = "pack Vec<T> { ptr: *T, size: u64 }
+                   interface Sized { len: u64; type Item; fun first(&self): Self::Item; fun empty(&self): bool { false } }
+                   def Sized for Vec<T> { }";
+        assert_eq!(solve(src)[0].to_diagnostic().render(src), "\
+error: missing functions first in the def of Sized for Vec<T>
+ --> 3:20
+  |
+3 |                    def Sized for Vec<T> { }
+  |                    ^^^^^^^^^^^^^^^^^^^^^^^^ functions missing
+");
+        let kinds: Vec<&str> = errors(src).iter().map(|err| match err {
+            SolverErrKind::Missing { what, .. } => *what,
+            _ => "other"
+        }).collect();
+        assert_eq!(kinds, vec!["functions", "fields", "associated types"]);
+    }
+
+    #[test]
+    fn defs_match_the_interface() {
+        let src = "pack Vec<T> { ptr: *T, size: u32 }
+                   interface Sized { len: u64; type Item; fun first(&self): Self::Item; }
+                   def Sized for Vec<T> {
+                       len: u64 = size;
+                       type Item = T;
+                       type Other = T;
+                       fun first(&self): *T { self.ptr }
+                       fun last(&self) { }
+                   }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 4, "{:?}", errs);
+        assert!(matches!(&errs[0], SolverErrKind::FieldMismatch { name, .. } if name == "size"));
+        assert!(matches!(&errs[1], SolverErrKind::NotAMember { what: "associated type", name, .. } if name == "Other"));
+        assert!(matches!(&errs[2], SolverErrKind::SignatureMismatch { name, expected, .. } if name == "first" && expected.to_string() == "fun(&Vec<T>): T"));
+        assert!(matches!(&errs[3], SolverErrKind::NotAMember { what: "function", name, .. } if name == "last"));
+
+        let src = "pack Name { ptr: *u8 }
+                   pack Pair { first: i32, name: Name }
+                   interface Eq { fun eq(&self, other: &Self): bool; }
+                   interface Ord: Eq { fun lt(&self, other: &Self): bool; }
+                   def Ord for Name { fun lt(&self, other: &Name): bool { true } }
+                   def Destroy for Name { }
+                   def Copy for Pair { }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 3, "{:?}", errs);
+        assert!(matches!(&errs[0], SolverErrKind::Unsatisfied { reason, .. } if reason == "required because Ord extends Eq"));
+        assert!(matches!(&errs[1], SolverErrKind::Missing { interface, names, .. } if interface == "Destroy" && names == &vec!["drop".to_string()]));
+        assert!(matches!(&errs[2], SolverErrKind::Unsatisfied { reason, .. } if reason == "required because the field name of Pair is copied"));
+    }
+
+    #[test]
+    fn contradictory_bounds() {
+        let src = "fun never<T: Copy>(t: T) where T: !Copy { }";
+        let tree = load(&[("src/main.tx", src)]);
+        let (resolutions, _) = Resolver::resolve(&tree);
+        let (_, _, errs) = TypeChecker::check(&tree, &resolutions);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+error: T is bounded by both Copy and !Copy
+ --> 1:35
+  |
+1 | fun never<T: Copy>(t: T) where T: !Copy { }
+  |                                   ^^^^^ contradicts an earlier bound
+ = note: no type could ever be used here
+");
+    }
+}
@@ -0,0 +1,138 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod statement_tests {
+
+    use crate::ast::expressions::{ExprKind, Literal};
+    use crate::ast::statements::{BindingKind, PatternKind, StmtKind};
+    use crate::parsing::statements::{BlockParser, LetParser, PatternParser, StmtParser};
+    use crate::parsing::Parser;
+
+    #[test]
+    fn bindings() {
+        let binding = LetParser.run_parser("let j: -Box<i32> = new_box(123);").unwrap();
+        assert_eq!(binding.kind, BindingKind::Let);
+        assert!(matches!(&binding.pattern.kind, PatternKind::Binding(name) if name == "j"));
+        assert_eq!(binding.ty.unwrap().to_string(), "-Box<i32>");
+        match binding.init.unwrap().kind {
+            ExprKind::Call(callee, args) => {
+                assert_eq!(callee.to_string(), "new_box");
+                assert!(matches!(args[0].kind, ExprKind::Literal(Literal::Int(123))));
+            }
+            _ => panic!("expected a call")
+        }
+
+        let binding = LetParser.run_parser("val s: String = \"a\\n\";").unwrap();
+        assert_eq!(binding.kind, BindingKind::Val);
+        assert!(matches!(binding.init.unwrap().kind, ExprKind::Literal(Literal::Str(s)) if s == "a\n"));
+
+        let binding = LetParser.run_parser("let uninit: i32;").unwrap();
+        assert!(binding.init.is_none());
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(matches!(PatternParser.run_parser("_").unwrap().kind, PatternKind::Wildcard));
+        match PatternParser.run_parser("(a, _, (b, c),)").unwrap().kind {
+            PatternKind::Tuple(patterns) => {
+                assert_eq!(patterns.len(), 3);
+                assert!(matches!(patterns[1].kind, PatternKind::Wildcard));
+            }
+            _ => panic!("expected a tuple pattern")
+        }
+        match PatternParser.run_parser("ProjectBuild { level, files: (first, _) }").unwrap().kind {
+            PatternKind::Pack(name, fields) => {
+                assert_eq!(name, "ProjectBuild");
+                assert_eq!(fields[0].0, "level");
+                assert_eq!(fields[0].1.to_string(), "level");
+                assert_eq!(fields[1].0, "files");
+            }
+            _ => panic!("expected a pack pattern")
+        }
+    }
+
+    #[test]
+    fn draft_drop_example() {
+        let src = "{
+            let i: Box<i32> = new_box(123);
+            let j: -Box<i32> = new_box(123);
+            defer(drop(j)); // Omitting this would potentially leak memory
+            let b: Box<bool> = new_box(true);
+        }";
+        let block = BlockParser.run_parser(src).unwrap();
+        assert_eq!(block.stmts.len(), 4);
+        match &block.stmts[2].kind {
+            StmtKind::Defer(deferred) => assert_eq!(deferred.to_string(), "drop(j)"),
+            _ => panic!("expected a defer")
+        }
+        assert!(block.tail().is_none());
+    }
+
+    #[test]
+    fn control_flow() {
+        let src = "{
+            while running() { step(); if done() { break; } else if skip() { continue; } }
+            for (k, v) in entries(map) { visit(k, v); }
+            unsafe { dealloc(ptr); }
+            return;
+        }";
+        let block = BlockParser.run_parser(src).unwrap();
+        assert_eq!(block.stmts.len(), 4);
+        match &block.stmts[0].kind {
+            StmtKind::While(cond, body) => {
+                assert_eq!(cond.to_string(), "running()");
+                assert_eq!(body.stmts.len(), 2);
+                match &body.stmts[1].kind {
+                    StmtKind::Expr(expr) => match &expr.kind {
+                        ExprKind::If(_, _, Some(otherwise)) => assert!(matches!(otherwise.kind, ExprKind::If(..))),
+                        _ => panic!("expected an if/else if")
+                    },
+                    _ => panic!("expected an expression statement")
+                }
+            }
+            _ => panic!("expected a while")
+        }
+        assert!(matches!(block.stmts[1].kind, StmtKind::For(..)));
+        assert!(matches!(&block.stmts[2].kind, StmtKind::Expr(e) if matches!(e.kind, ExprKind::Unsafe(_))));
+        assert!(matches!(block.stmts[3].kind, StmtKind::Return(None)));
+    }
+
+    #[test]
+    fn tail_expressions() {
+        let block = BlockParser.run_parser("{ let x = 1; if c { x } else { 2 } }").unwrap();
+        let tail = block.tail().unwrap();
+        assert!(matches!(tail.kind, ExprKind::If(..)));
+
+        let block = BlockParser.run_parser("{ f(); x }").unwrap();
+        assert_eq!(block.tail().unwrap().to_string(), "x");
+
+        // Stray semicolons are empty statements
+        let block = BlockParser.run_parser("{ ;; f();; }").unwrap();
+        assert_eq!(block.stmts.len(), 1);
+        assert!(block.tail().is_none());
+    }
+
+    #[test]
+    fn return_values() {
+        let stmt = StmtParser.run_parser("return build(o_level, build_files);").unwrap();
+        match stmt.kind {
+            StmtKind::Return(Some(value)) => assert_eq!(value.to_string(), "build(o_level, build_files)"),
+            _ => panic!("expected a return with a value")
+        }
+    }
+
+    #[test]
+    fn malformed_statements() {
+        let err = BlockParser.run_parser("{ f() g() }").unwrap_err();
+        assert_eq!(err.expected, "';' after the expression");
+        assert_eq!((err.row, err.col), (1, 7));
+        let err = BlockParser.run_parser("{ let = 3; }").unwrap_err();
+        assert_eq!(err.expected, "a pattern");
+        let err = BlockParser.run_parser("{ for x of xs {} }").unwrap_err();
+        assert_eq!(err.expected, "in");
+        let err = BlockParser.run_parser("{ defer(f()) }").unwrap_err();
+        assert_eq!(err.expected, "';' after defer");
+        let err = BlockParser.run_parser("{ let c = 'ab'; }").unwrap_err();
+        assert_eq!(err.expected, "the closing quote of the literal");
+    }
+}
@@ -0,0 +1,399 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod typeck_tests {
+
+    use crate::analysis::resolve::Resolver;
+    use crate::analysis::typeck::{ImplicitArg, TypeChecker, TypeErr, TypeErrKind};
+    use crate::analysis::types::Type;
+    use crate::ast::declarations::{Item, Param};
+    use crate::ast::statements::StmtKind;
+    use crate::ast::statements::{Pattern, PatternKind};
+    use crate::ast::types::PrimitiveType;
+    use crate::ast::visit::{self, Visitor};
+    use crate::tests::{load, BOX};
+
+    /// The names of the locals and parameters
+    #[derive(Default)]
+    struct Bindings(Vec<(String, crate::ast::ids::NodeId)>);
+
+    impl<'ast> Visitor<'ast> for Bindings {
+        fn visit_param(&mut self, param: &'ast Param) {
+            self.0.push((param.name.clone(), param.id));
+            visit::walk_param(self, param);
+        }
+
+        fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+            if let PatternKind::Binding(name) = &pattern.kind {
+                self.0.push((name.clone(), pattern.id));
+            }
+            visit::walk_pattern(self, pattern);
+        }
+    }
+
+    /// The type errors of SRC, and the types of its bindings as name: type
+    fn check(src: &str) -> (Vec<TypeErr>, Vec<String>) {
+        let tree = load(&[("src/main.tx", src)]);
+        let (resolutions, errs) = Resolver::resolve(&tree);
+        assert_eq!(errs, vec![]);
+        let (_, results, errs) = TypeChecker::check(&tree, &resolutions);
+        let mut bindings = Bindings::default();
+        for (_, module) in tree.modules() {
+            module.items.iter().for_each(|item| bindings.visit_item(item));
+        }
+        let locals = bindings.0.iter().map(|(name, id)| match results.local_types.get(*id) {
+            Some(ty) => format!("{}: {}", name, ty),
+            None => format!("{}: unchecked", name)
+        }).collect();
+        (errs, locals)
+    }
+
+    fn errors(src: &str) -> Vec<TypeErrKind> {
+        check(src).0.into_iter().map(|err| err.kind).collect()
+    }
+
+    fn render(src: &str) -> String {
+        let (errs, _) = check(src);
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        errs[0].to_diagnostic().render(src)
+    }
+
+    fn prim(prim: PrimitiveType) -> Type {
+        Type::Prim(prim)
+    }
+
+    #[test]
+    fn the_draft_box_is_well_typed() {
+        let src = format!("{}
+            fun main(argc: i32, argv: **char) {{
+                let i: Box<i32> = Box::new(123);
+                let j: -Box<i32> = Box::new(123);
+                defer(drop(j));
+                let b = Box::new(true);
+                let r = b.deref();
+                let p = i.get();
+            }}", BOX);
+        let (errs, locals) = check(&src);
+        assert_eq!(errs, vec![]);
+        let locals: Vec<&str> = locals.iter().map(String::as_str).collect();
+        assert_eq!(locals, vec![
+            "t: T", "leaking: -T", "alloc: *u8", "alloc: *T", "p: *T", "t: T",
+            "argc: i32", "argv: **char", "i: Box<i32>", "j: -Box<i32>", "b: Box<bool>", "r: &bool", "p: *i32"
+        ]);
+    }
+
+    #[test]
+    fn locals_are_inferred_from_their_uses() {
+        let src = "pack String { len: u64 }
+                   impls String { public fun from(n: i32): String; }
+                   fun other_usecopy<C>(c: C) where C: Copy {
+                       let a1 = c;
+                       let a2 = c;
+                   }
+                   fun f(imm: i32) {
+                       let s = String::from(imm);
+                       let n = 1;
+                       let wide = 1;
+                       let sum: u64 = wide + s.len;
+                       let pair = (n, [true, false]);
+                       let (x, flags) = pair;
+                       for flag in flags { }
+                   }";
+        let (errs, locals) = check(src);
+        assert_eq!(errs, vec![]);
+        assert_eq!(locals, vec![
+            "n: i32", "c: C", "a1: C", "a2: C", "imm: i32", "s: String", "n: i32", "wide: u64", "sum: u64",
+            "pair: (i32, [bool; 2])", "x: i32", "flags: [bool; 2]", "flag: bool"
+        ]);
+    }
+
+    #[test]
+    fn mismatches_point_at_what_does_not_fit() {
+        let src = format!("{}fun main() {{\n    let i: Box<i32> = Box::new(true);\n}}", BOX);
+        let rendered = render(&src);
+        assert!(rendered.starts_with("error: mismatched types\n --> 21:32\n"));
+        assert!(rendered.ends_with("   |                                ^^^^ expected i32, found bool\n"));
+        // Without an implicit conversion, an integer is not a String
+        let src = "pack String { len: u64 }\nfun main() {\n    let str: String = 123;\n}";
+        assert_eq!(render(src), "error: mismatched types
+ --> 3:23
+  |
+3 |     let str: String = 123;
+  |                       ^^^ expected String, found i32
+");
+        // The draft's deref ends its body with a ; so it gives back ()
+        let src = "pack Box<T> { ptr: *T }\ninterface Deref<T> { fun deref(&self): &T; }\ndef Deref<T> for Box<T> {\n    public fun deref(&self): &T {\n        unsafe { self.ptr as &T };\n    }\n}";
+        let mismatch = |expected: &str, found: Type| matches!(&errors(src)[..], [TypeErrKind::Mismatch { expected: e, found: f }] if e.to_string() == expected && *f == found);
+        assert!(mismatch("&T", Type::unit()));
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        let src = "fun add(a: i32, b: i32): i32 { a + b }
+                   fun f(): bool { return 1; }
+                   fun g() { add(1); add(1, true); let x: i32 = add; 3(); }
+                   fun h<T>(t: T): T { t }
+                   fun k() { h<i32, i32>(1); let b: bool = h(2); }";
+        let i32_ = prim(PrimitiveType::SInt32);
+        assert_eq!(errors(src), vec![
+            TypeErrKind::Mismatch { expected: Type::bool(), found: i32_.clone() },
+            TypeErrKind::ArgCount { expected: 2, found: 1 },
+            TypeErrKind::Mismatch { expected: i32_.clone(), found: Type::bool() },
+            TypeErrKind::Mismatch { expected: i32_.clone(), found: Type::Fun(vec![i32_.clone(), i32_.clone()], Box::new(i32_)) },
+            TypeErrKind::NotCallable(prim(PrimitiveType::SInt32)),
+            TypeErrKind::GenericCount { name: "h".to_string(), expected: 1, found: 2 },
+            TypeErrKind::Mismatch { expected: Type::bool(), found: prim(PrimitiveType::SInt32) }
+        ]);
+        assert_eq!(render("fun add(a: i32, b: i32): i32 { a + b }\nfun g() { add(1); }"), "error: this takes 2 arguments but 1 was given
+ --> 2:11
+  |
+2 | fun g() { add(1); }
+  |           ^^^^^^ expected 2 arguments
+");
+        let (errs, _) = check("fun h<T>(t: T): T { t }\nfun k() { h<i32, i32>(1); }");
+        assert_eq!(errs[0].to_diagnostic().message, "h takes 1 generic argument but 2 were given");
+    }
+
+    #[test]
+    fn literals_fit_in_their_types() {
+        assert_eq!(render("fun f() {\n    let y: i8 = 200;\n}"), "error: literal out of range for i8
+ --> 2:17
+  |
+2 |     let y: i8 = 200;
+  |                 ^^^ 200 doesn't fit in i8
+ = note: i8 goes from -128 to 127
+");
+        let out_of_range = |src| errors(src).into_iter().map(|err| match err {
+            TypeErrKind::LiteralOutOfRange { literal, ty } => format!("{} in {}", literal, ty),
+            other => panic!("expected a literal out of range, got {:?}", other)
+        }).collect::<Vec<_>>();
+        // The types of literals are checked once they are inferred, minus included
+        assert_eq!(out_of_range("fun f() { let a = 256; let b: u8 = a; let c: i8 = -129; }"), vec!["256 in u8", "-129 in i8"]);
+        assert_eq!(out_of_range("fun f(): i64 { let a: i8 = -128; let b: u128 = 340282366920938463463374607431768211455; 2147483648 }"), Vec::<String>::new());
+        assert_eq!(out_of_range("fun f() { let a = 2147483648; }"), vec!["2147483648 in i32"]);
+        assert_eq!(out_of_range("const X: i8 = 128;"), vec!["128 in i8"]);
+    }
+
+    #[test]
+    fn methods_fields_and_bounds() {
+        let src = "interface Execute { count: u32; fun execute(&self): bool; }
+                   pack Task { done: bool }
+                   def Execute for Task { count: u32; fun execute(&self): bool { self.done } }
+                   fun run<T>(t: T): bool where T: Execute { t.execute() }
+                   fun uses<T: Execute>(t: &T): u32 { t.count }
+                   fun f(task: Task) { run(task); task.done; task.missing; task.stop(); 1.execute(); }
+                   fun g<T>(t: T) { t.execute(); }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 4, "{:?}", errs);
+        assert!(matches!(&errs[0], TypeErrKind::NoField { field, .. } if field == "missing"));
+        assert!(matches!(&errs[1], TypeErrKind::NoFun { name, .. } if name == "stop"));
+        assert!(matches!(&errs[2], TypeErrKind::NoFun { name, ty } if name == "execute" && ty.to_string() == "i32"));
+        // Without a bound there is nothing to call on T
+        assert!(matches!(&errs[3], TypeErrKind::NoFun { ty: Type::Param { .. }, .. }));
+    }
+
+    #[test]
+    fn packs_operators_and_casts() {
+        let src = "pack V { x: i32, y: i32 }
+                   interface Add { fun plus(&self, other: Self): Self; }
+                   assoc Add::plus with +;
+                   def Add for V { fun plus(&self, other: V): V { V { x: self.x + other.x, y: self.y + other.y } } }
+                   fun f(a: V, b: V): V { a + b }
+                   fun g(a: V) { V { x: 1 }; V { x: 1, y: 2, x: 3 }; V { x: 1, y: 2, z: 3 }; a - a; }
+                   fun h(a: V) { let p = &a as *V; let q = p as *u8; let c = 65 as u8 as char; true as *u8; }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 5, "{:?}", errs);
+        assert_eq!(errs[0], TypeErrKind::MissingFields { pack: "V".to_string(), fields: vec!["y".to_string()] });
+        assert_eq!(errs[1], TypeErrKind::DuplicateField("x".to_string()));
+        assert!(matches!(&errs[2], TypeErrKind::NoField { ty, field } if ty.to_string() == "V" && field == "z"));
+        assert!(matches!(&errs[3], TypeErrKind::InvalidOperand { op, ty } if op == "-" && ty.to_string() == "V"));
+        assert_eq!(errs[4], TypeErrKind::InvalidCast { from: Type::bool(), to: Type::Ptr(Box::new(prim(PrimitiveType::UInt8))) });
+    }
+
+    #[test]
+    fn casts_desugar_to_cast_as() {
+        let src = "interface Cast<T> { fun as(self): T; }
+                   pack Meters { n: i32 }
+                   def Cast<i32> for Meters { fun as(self): i32 { self.n } }
+                   fun f(m: Meters): i32 { m as i32 }
+                   fun g<T>(t: T): u8 where T: Cast<u8> { t as u8 }
+                   fun h(m: Meters): u8 { m as u8 }";
+        let errs = errors(src);
+        assert_eq!(errs.len(), 1, "{:?}", errs);
+        assert!(matches!(&errs[0], TypeErrKind::InvalidCast { from, to } if from.to_string() == "Meters" && *to == prim(PrimitiveType::UInt8)));
+        // Without a Cast interface in scope only the builtin casts exist
+        assert_eq!(errors("pack Meters { n: i32 } fun f(m: Meters): i32 { m as i32 }").len(), 1);
+    }
+
+    #[test]
+    fn loops_and_unknown_types() {
+        assert_eq!(errors("fun f() { break; }"), vec![TypeErrKind::OutsideLoop("break")]);
+        assert_eq!(errors("fun f() { while true { continue; } }"), vec![]);
+        assert_eq!(errors("fun f() { let v = []; }"), vec![TypeErrKind::AnnotationsNeeded(Some("v".to_string()))]);
+        assert_eq!(errors("fun f() { for i in 3 { } }"), vec![TypeErrKind::NotIterable(prim(PrimitiveType::SInt32))]);
+        assert_eq!(errors("fun f(): i32 { if true { 1 } else { false } }"), vec![TypeErrKind::Mismatch { expected: prim(PrimitiveType::SInt32), found: Type::bool() }]);
+        assert_eq!(errors("fun f(): i32 { if true { return 1; } else { 2 } }"), vec![]);
+        assert_eq!(errors("interface I { } fun f(i: I) { }"), vec![TypeErrKind::InterfaceAsType("I".to_string())]);
+    }
+
+    #[test]
+    fn vals_are_not_assigned_again() {
+        assert_eq!(render("fun f() {\n    val x = 1;\n    x = 2;\n}"), "error: x is a val and can't be assigned again
+ --> 3:5
+  |
+3 |     x = 2;
+  |     ^ assigned here
+  |
+2 |     val x = 1;
+  |         - x is declared here
+ = note: declare x with let to assign it again
+");
+        let is_val = |errs: Vec<TypeErrKind>| errs.iter().map(|err| matches!(err, TypeErrKind::AssignToVal { .. })).collect::<Vec<_>>();
+        // Nor are their fields and elements, or they through compound assignments
+        assert_eq!(is_val(errors("pack P { x: i32 } fun f() { val p = P { x: 1 }; p.x = 2; }")), vec![true]);
+        assert_eq!(is_val(errors("fun f() { val (a, b) = ([1, 2], 3); a[0] = 2; b += 1; }")), vec![true, true]);
+        // What a val reference points to isn't the val
+        assert_eq!(errors("pack P { x: i32 } fun f(p: &P) { val r = p; r.x = 2; }"), vec![]);
+        assert_eq!(errors("fun f() { let x = 1; x = 2; val y = &x; }"), vec![]);
+    }
+
+    const STRING: &str = "pack String { len: u64 }
+                          impls String { public fun from(n: i32): String; }
+                          implicit fun itos(implicit n: i32): String = String::from(n);\n";
+
+    #[test]
+    fn the_draft_implicit_conversion() {
+        let src = format!("{}fun main() {{ val str: String = 123; val same: String = itos(123); }}", STRING);
+        let tree = load(&[("src/main.tx", &src)]);
+        let (resolutions, _) = Resolver::resolve(&tree);
+        let (cx, results, errs) = TypeChecker::check(&tree, &resolutions);
+        assert_eq!(errs, vec![]);
+        let items = &tree.module(tree.root()).items;
+        let (itos, main) = match (&items[2], &items[3]) {
+            (Item::Fun(itos), Item::Fun(main)) => (itos, main),
+            _ => panic!("expected functions")
+        };
+        let inits: Vec<_> = main.body.as_ref().unwrap().stmts.iter().map(|stmt| match &stmt.kind {
+            StmtKind::Let(binding) => binding.init.as_ref().unwrap().id,
+            _ => panic!("expected a let")
+        }).collect();
+        // 123 keeps its type, and is converted to the String the binding wants
+        assert_eq!(results.conversions.get(inits[0]), Some(&itos.id));
+        assert_eq!(results.expr_types.get(inits[0]), Some(&prim(PrimitiveType::SInt32)));
+        assert_eq!(results.conversions.get(inits[1]), None);
+        assert_eq!(cx.explain_implicit(itos.id, &prim(PrimitiveType::SInt32)).unwrap(),
+            "itos converts i32 to String, declared as implicit fun itos(implicit n: i32): String");
+    }
+
+    #[test]
+    fn conversions_are_found_by_name() {
+        let module = "pack String { len: u64 }
+                      mod conv { use super::String; public implicit fun itos(n: i32): String; }";
+        assert_eq!(errors(&format!("{} fun main() {{ let s: String = 1; }}", module)).len(), 1);
+        assert_eq!(errors(&format!("{} use conv::itos; fun main() {{ let s: String = 1; }}", module)), vec![]);
+        assert_eq!(errors(&format!("{} use conv::*; fun main() {{ let s: String = 1; }}", module)), vec![]);
+        // Only conversions to what is expected, and not inside themselves
+        assert_eq!(errors(&format!("{} use conv::itos; fun main() {{ let b: bool = 1; }}", module)).len(), 1);
+        assert_eq!(errors("pack S { } implicit fun loop(n: i32): S = n;").len(), 1);
+    }
+
+    #[test]
+    fn ambiguous_conversions_list_the_candidates() {
+        let src = "pack String { len: u64 }\nimplicit fun itos(n: i32): String;\nimplicit fun show(n: i32): String;\nfun main() {\n    let s: String = 1;\n}";
+        assert_eq!(render(src), "error: ambiguous implicits for a conversion from i32 to String
+ --> 5:21
+  |
+5 |     let s: String = 1;
+  |                     ^ needed here
+  |
+2 | implicit fun itos(n: i32): String;
+  | ---------------------------------- itos fits
+  |
+3 | implicit fun show(n: i32): String;
+  | ---------------------------------- show fits
+ = note: itos converts i32 to String, declared as implicit fun itos(n: i32): String
+ = note: show converts i32 to String, declared as implicit fun show(n: i32): String
+ = note: only one of them can be in scope where it is used
+");
+    }
+
+    #[test]
+    fn implicit_arguments_come_from_scope() {
+        let src = "pack Allocator { }
+                   implicit fun heap(): Allocator;
+                   fun alloc_with(size: u64, implicit a: Allocator): *u8;
+                   fun f() { alloc_with(8); alloc_with(8, heap()); }";
+        let tree = load(&[("src/main.tx", src)]);
+        let (resolutions, _) = Resolver::resolve(&tree);
+        let (_, results, errs) = TypeChecker::check(&tree, &resolutions);
+        assert_eq!(errs, vec![]);
+        let heap = tree.module(tree.root()).items[1].id();
+        let filled: Vec<&Vec<ImplicitArg>> = results.implicit_args.iter().map(|(_, args)| args).collect();
+        assert_eq!(filled, vec![&vec![ImplicitArg::Fun(heap)]]);
+
+        let allocator = "pack Allocator { } fun alloc_with(size: u64, implicit a: Allocator): *u8;";
+        assert_eq!(errors(&format!("{} fun f(implicit a: Allocator) {{ alloc_with(8); }}", allocator)), vec![]);
+        let missing = errors(&format!("{} fun f() {{ alloc_with(8); }}", allocator));
+        assert!(matches!(&missing[..], [TypeErrKind::NoImplicit(ty)] if ty.to_string() == "Allocator"));
+        let ambiguous = errors(&format!("{} implicit fun heap(): Allocator; fun f(implicit a: Allocator) {{ alloc_with(8); }}", allocator));
+        assert!(matches!(&ambiguous[..], [TypeErrKind::AmbiguousImplicit { candidates, .. }] if candidates.len() == 2));
+    }
+
+    #[test]
+    fn malformed_implicits() {
+        let shape = |src: &str| matches!(&errors(src)[..], [TypeErrKind::ImplicitShape(_)]);
+        assert!(shape("implicit fun both(a: i32, b: i32): bool;"));
+        assert!(shape("fun f(implicit a: i32, b: i32);"));
+    }
+
+    #[test]
+    fn extension_methods() {
+        let src = "pack String { len: u64 }
+                   implicit impls i32 { fun double(self): i32 = self * 2; }
+                   implicit impls &StrSlice { fun shout(self): String; }
+                   fun f(n: i32) { let a = n.double(); let b = 3.double(); let s = \"hey\".shout(); }";
+        let (errs, locals) = check(src);
+        assert_eq!(errs, vec![]);
+        assert_eq!(locals[locals.len() - 3..], ["a: i32", "b: i32", "s: String"]);
+        // Inherent and interface methods come first
+        let src = "interface Execute { fun execute(&self): bool; }
+                   pack Task { }
+                   impls Task { fun name(&self): i32; }
+                   def Execute for Task { fun execute(&self): bool; }
+                   implicit impls Task { fun name(&self): bool; fun execute(&self): i32; fun extra(&self): u8; }
+                   fun f(t: Task) { let n = t.name(); let e = t.execute(); let x = t.extra(); }";
+        let (errs, locals) = check(src);
+        assert_eq!(errs, vec![]);
+        assert_eq!(locals[locals.len() - 3..], ["n: i32", "e: bool", "x: u8"]);
+    }
+
+    #[test]
+    fn extensions_are_used_where_they_are_in_scope() {
+        let ext = "mod ext { implicit impls i32 { public fun double(self): i32; } mod inner { fun f() { 1.double(); } } }";
+        let missing = errors(&format!("{} fun f() {{ 1.double(); }}", ext));
+        assert!(matches!(&missing[..], [TypeErrKind::NoFun { name, .. }] if name == "double"));
+        assert_eq!(errors(&format!("{} use ext::*; fun f() {{ 1.double(); }}", ext)), vec![]);
+    }
+
+    #[test]
+    fn ambiguous_extensions() {
+        let src = "mod a { implicit impls i32 { public fun double(self): i32; } }
+mod b { implicit impls i32 { public fun double(self): i32; } }
+use a::*;
+use b::*;
+fun f(n: i32) { n.double(); }";
+        assert_eq!(render(src), "error: ambiguous implicits for the method double of i32
+ --> 5:17
+  |
+5 | fun f(n: i32) { n.double(); }
+  |                 ^^^^^^^^^^ needed here
+  |
+1 | mod a { implicit impls i32 { public fun double(self): i32; } }
+  |         ---------------------------------------------------- implicit impls i32 fits
+  |
+2 | mod b { implicit impls i32 { public fun double(self): i32; } }
+  |         ---------------------------------------------------- implicit impls i32 fits
+ = note: only one of them can be in scope where it is used
+");
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod unsafety_tests {
+
+    use crate::analysis::unsafety::{UnsafeErr, UnsafetyChecker};
+    use crate::tests::{check_source, messages};
+
+    const PTR: &str = "pack Box<T> { ptr: *T }
+                       unsafe fun write<T>(p: *T, t: T);
+                       unsafe fun read<T>(p: *T): T;\n";
+
+    fn check(src: &str) -> Vec<UnsafeErr> {
+        check_source(src, |cx, results| UnsafetyChecker::check(cx, results))
+    }
+
+    #[test]
+    fn the_draft_unsafe_operations() {
+        let src = format!("{}
+            impls Box<T> {{
+                fun new(t: T): Box<T> {{
+                    unsafe {{
+                        let p = alloc(sizeof<T>(), 8) as *T;
+                        write(p, t);
+                        Box {{ ptr: p }}
+                    }}
+                }}
+                fun get(&self): &T {{ unsafe {{ self.ptr as &T }} }}
+                fun take(&self): T {{ read(self.ptr) }}
+            }}
+            fun casts(r: &i32, p: *i32) {{
+                let a = r as *i32;
+                let b = p as *u8;
+                let c = p as *i32;
+                let n = p as u64;
+            }}
+            fun deref(p: *i32): i32 {{ *p }}", PTR);
+        assert_eq!(messages(&check(&src)), vec![
+            "calling the unsafe function read is unsafe",
+            "casting a reference to a pointer is unsafe",
+            "casting between pointer types is unsafe",
+            "dereferencing a raw pointer is unsafe"
+        ]);
+        let errs = check(&src);
+        assert_eq!(errs[3].to_diagnostic().render(&src), "\
+error: dereferencing a raw pointer is unsafe
+ --> 22:39
+   |
+22 |             fun deref(p: *i32): i32 { *p }
+   |                                       ^^ needs an unsafe block
+ = note: the compiler can't check that this is sound: wrap it in unsafe { } to take that on
+");
+    }
+
+    #[test]
+    fn unsafe_functions_and_pointer_access() {
+        let src = format!("{}
+            pack Node {{ value: i32 }}
+            impls Node {{
+                fun value(&self): i32 {{ self.value }}
+                unsafe fun raw(&self): i32 {{ self.value }}
+            }}
+            unsafe fun get(p: *i32): i32 {{ *p }}
+            fun calls(p: *i32, node: *Node, r: &Node) {{
+                let v = get(p);
+                let f = node.value;
+                let m = node.value();
+                let u = r.raw();
+                let fine = r.value();
+                dealloc(p);
+            }}", PTR);
+        assert_eq!(messages(&check(&src)), vec![
+            "calling the unsafe function get is unsafe",
+            "reaching the field value through a raw pointer is unsafe",
+            "calling value through a raw pointer is unsafe",
+            "calling the unsafe function raw is unsafe",
+            "calling the unsafe function dealloc is unsafe"
+        ]);
+    }
+
+    #[test]
+    fn unsafe_builtins() {
+        let src = "fun outside(p: *i32, r: &i32) {
+                       let a = ptr::read(p);
+                       ptr::write(p, 1);
+                       let b = transmute<&i32, *i32>(r);
+                       let size = sizeof<i32>();
+                   }
+                   fun inside(p: *i32): i32 { unsafe { ptr::write(p, 2); ptr::read(transmute<*i32, *i32>(p)) } }";
+        assert_eq!(messages(&check(src)), vec![
+            "calling the unsafe function ptr::read is unsafe",
+            "calling the unsafe function ptr::write is unsafe",
+            "calling the unsafe function transmute is unsafe"
+        ]);
+        // A module named ptr hides them
+        assert_eq!(check("mod ptr { public fun read(p: *i32): i32 { 0 } } fun f(p: *i32): i32 { ptr::read(p) }"), vec![]);
+    }
+
+    #[test]
+    fn unnecessary_unsafe_blocks() {
+        let src = "fun useless() { unsafe { let x = 1; } }
+fun nested(p: *i32): i32 { unsafe { unsafe { *p } } }
+unsafe fun inside(p: *i32): i32 { unsafe { *p } }";
+        let errs = check(src);
+        assert!(errs.iter().all(|err| !err.to_diagnostic().is_error()));
+        assert_eq!(errs.len(), 3);
+        assert_eq!(errs[0].to_diagnostic().render(src), "\
+warning: unnecessary unsafe block
+ --> 1:17
+  |
+1 | fun useless() { unsafe { let x = 1; } }
+  |                 ^^^^^^^^^^^^^^^^^^^^^ nothing in it is unsafe
+");
+        assert_eq!(errs[1].to_diagnostic().render(src), "\
+warning: unnecessary unsafe block
+ --> 2:37
+  |
+2 | fun nested(p: *i32): i32 { unsafe { unsafe { *p } } }
+  |                                     ^^^^^^^^^^^^^ already in unsafe code
+  |
+2 | fun nested(p: *i32): i32 { unsafe { unsafe { *p } } }
+  |                            ------------------------ inside this unsafe block
+");
+        assert_eq!(errs[2].to_diagnostic().secondary[0].message, "inside this unsafe function");
+    }
+}
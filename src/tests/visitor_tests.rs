@@ -0,0 +1,260 @@
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod visitor_tests {
+
+    use std::path::Path;
+
+    use crate::analysis::modules::{ModuleTree, SourceFiles};
+    use crate::ast::declarations::{Item, ModDecl, Program};
+    use crate::ast::expressions::{Expr, ExprKind, Literal};
+    use crate::ast::ids::{IdAssigner, NodeId, NodeMap};
+    use crate::ast::map::{AstMap, Node};
+    use crate::ast::statements::StmtKind;
+    use crate::ast::mut_visit::{self, MutVisitor};
+    use crate::ast::visit::{self, Visitor};
+    use crate::parsing::declarations::ProgramParser;
+    use crate::parsing::Parser;
+
+    const SRC: &str = "pack Point<T: Num> { x: T, y: T }
+                       impls Point<T> { public fun len(&self): T { let (a, _) = (self.x, 1); a * a + self.y } }
+                       mod inner { fun zero(): i32 { if true { 0 } else { -1 } } }";
+
+    fn numbered(src: &str) -> Program {
+        let mut program = ProgramParser.run_parser(src).unwrap();
+        IdAssigner::new().visit_program(&mut program);
+        program
+    }
+
+    fn mapped(program: &Program) -> AstMap<'_> {
+        let mut map = AstMap::new();
+        map.visit_program(program);
+        map
+    }
+
+    /// Collects the ids of every node, in the order they are visited
+    #[derive(Default)]
+    struct Ids(Vec<NodeId>);
+
+    impl<'ast> Visitor<'ast> for Ids {
+        fn visit_id(&mut self, id: &NodeId) {
+            self.0.push(*id);
+        }
+    }
+
+    #[test]
+    fn ids_are_dense_and_in_visiting_order() {
+        let program = ProgramParser.run_parser(SRC).unwrap();
+        let mut ids = Ids::default();
+        ids.visit_program(&program);
+        assert!(ids.0.iter().all(|id| *id == NodeId::DUMMY));
+        let program = numbered(SRC);
+        let mut ids = Ids::default();
+        ids.visit_program(&program);
+        let expected: Vec<NodeId> = (0..ids.0.len() as u32).map(NodeId).collect();
+        assert_eq!(ids.0, expected);
+        assert_eq!(program.id, NodeId(0));
+        match &program.items[0] {
+            Item::Pack(pack) => {
+                assert_eq!(pack.id, NodeId(1));
+                assert_eq!(pack.generics[0].id, NodeId(2));
+                assert_eq!(pack.generics[0].bounds[0].id, NodeId(3));
+                assert_eq!(pack.generics[0].bounds[0].interface.id, NodeId(4));
+                assert_eq!(pack.fields[0].ty.id, NodeId(6));
+                assert_eq!(pack.fields[1].id, NodeId(7));
+            }
+            _ => panic!("expected a pack")
+        }
+    }
+
+    #[test]
+    fn map_finds_nodes_and_parents() {
+        let program = numbered(SRC);
+        let map = mapped(&program);
+        let body = match &program.items[1] {
+            Item::Impls(impls) => impls.funs[0].body.as_ref().unwrap(),
+            _ => panic!("expected impls")
+        };
+        let tail = body.tail().unwrap();
+        assert!(matches!(map.node(tail.id), Node::Expr(expr) if expr.span == tail.span));
+        // The tail is a statement of the body, which is the body of len, in the impls
+        let ancestors: Vec<&str> = map.ancestors(tail.id).map(|id| match map.node(id) {
+            Node::Stmt(_) => "stmt",
+            Node::Block(_) => "block",
+            Node::Fun(fun) => &fun.name,
+            Node::Impls(_) => "impls",
+            Node::Program(_) => "program",
+            _ => "other"
+        }).collect();
+        assert_eq!(ancestors, vec!["stmt", "block", "len", "impls", "program"]);
+        assert!(map.parent(NodeId(0)).is_none());
+        assert!(map.get(NodeId(10_000)).is_none());
+    }
+
+    /// Names the kind of every node the map knows
+    fn kinds(map: &AstMap, ids: &[NodeId]) -> Vec<&'static str> {
+        ids.iter().map(|id| match map.node(*id) {
+            Node::Bound(_) => "bound",
+            Node::WherePredicate(_) => "where",
+            Node::Let(_) => "let",
+            Node::FieldInit(_) => "field init",
+            Node::Path(_) => "path",
+            Node::PathSegment(_) => "segment",
+            Node::Ty(_) => "ty",
+            Node::Attribute(_) => "attribute",
+            Node::UseTree(_) => "use tree",
+            Node::Program(_) => "program",
+            Node::Module(_) => "module",
+            _ => "other"
+        }).collect()
+    }
+
+    #[test]
+    fn every_node_is_in_the_map() {
+        let program = numbered("#![feature(minus_attrs)]
+                                use a::{b, c::*};
+                                #compile_time fun f<T>(x: T): Point<T> where T: Num { let p: Point<T> = Point { x, y: zero() }; p.m<T>() }");
+        let map = mapped(&program);
+        let mut ids = Ids::default();
+        ids.visit_program(&program);
+        assert!(ids.0.iter().all(|id| map.get(*id).is_some()));
+        let kinds = kinds(&map, &ids.0);
+        for kind in ["program", "attribute", "use tree", "bound", "where", "let", "field init", "path", "segment", "ty"].iter() {
+            assert!(kinds.contains(kind), "no {} in the map", kind);
+        }
+        // The type of the let hangs off the let
+        let block = program.items.iter().find_map(|item| match item {
+            Item::Fun(fun) => fun.body.as_ref(),
+            _ => None
+        }).unwrap();
+        let ty = block.stmts.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(local) => local.ty.as_ref(),
+            _ => None
+        }).unwrap();
+        assert!(matches!(map.node(map.parent(ty.id).unwrap()), Node::Let(_)));
+        assert_eq!(map.node(ty.id).span(), ty.span);
+    }
+
+    #[test]
+    fn modules_stand_for_their_programs_in_the_map() {
+        let mut sources = SourceFiles::new();
+        sources.add("src/main.tx", "#![feature(minus_attrs)]\nmod inner { fun zero(): i32 { 0 } }");
+        let (tree, errs) = ModuleTree::load(&mut sources, Path::new("src/main.tx"));
+        assert_eq!(errs, vec![]);
+        let mut map = AstMap::new();
+        for (_, module) in tree.modules() {
+            map.add_module(module);
+        }
+        let (_, root) = tree.modules().next().unwrap();
+        let (_, inner) = tree.modules().nth(1).unwrap();
+        assert!(matches!(map.node(root.id), Node::Module(module) if module.name.is_empty()));
+        assert!(matches!(map.node(root.attrs[0].id), Node::Attribute(attr) if attr.name == "feature"));
+        assert_eq!(map.parent(root.attrs[0].id), Some(root.id));
+        // The items of an inline module hang off the module, not its declaration
+        assert_eq!(map.parent(inner.items[0].id()), Some(inner.id));
+        assert_eq!(map.node(inner.id).span(), root.items[0].span());
+        assert_ne!(root.id, inner.id);
+    }
+
+    /// Counts the literals, without looking into the items of modules
+    #[derive(Default)]
+    struct Literals {
+        count: usize
+    }
+
+    impl<'ast> Visitor<'ast> for Literals {
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            if let ExprKind::Literal(_) = expr.kind {
+                self.count += 1;
+            }
+            visit::walk_expr(self, expr);
+        }
+
+        fn visit_mod(&mut self, _module: &'ast ModDecl) {}
+    }
+
+    #[test]
+    fn visitors_override_what_they_need() {
+        let program = numbered(SRC);
+        let mut literals = Literals::default();
+        literals.visit_program(&program);
+        assert_eq!(literals.count, 1);
+    }
+
+    /// Doubles the integer literals
+    struct Doubler;
+
+    impl MutVisitor for Doubler {
+        fn visit_expr(&mut self, expr: &mut Expr) {
+            if let ExprKind::Literal(Literal::Int(n)) = &mut expr.kind {
+                *n *= 2;
+            }
+            mut_visit::walk_expr(self, expr);
+        }
+    }
+
+    #[test]
+    fn mutable_visitors_rewrite_the_tree() {
+        let mut program = ProgramParser.run_parser("fun f(): i32 { let a = 2; if a > 1 { [3, 4][0] } else { 5 } }").unwrap();
+        Doubler.visit_program(&mut program);
+        assert_eq!(program.to_string(), "fun f(): i32 {
+    let a = 4;
+    if a > 2 {
+        [6, 8][0]
+    } else {
+        10
+    }
+}
+");
+    }
+
+    #[test]
+    fn side_tables_are_keyed_by_id() {
+        let program = numbered("fun f() { g(1, true); }");
+        // A table of the literals found, like the types of the type checker
+        struct Table(NodeMap<String>);
+        impl<'ast> Visitor<'ast> for Table {
+            fn visit_expr(&mut self, expr: &'ast Expr) {
+                if let ExprKind::Literal(literal) = &expr.kind {
+                    self.0.insert(expr.id, format!("{:?}", literal));
+                }
+                visit::walk_expr(self, expr);
+            }
+        }
+        let mut table = Table(NodeMap::new());
+        table.visit_program(&program);
+        let entries: Vec<(NodeId, &String)> = table.0.iter().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].1, "Int(1)");
+        assert_eq!(entries[1].1, "Bool(true)");
+        assert!(entries[0].0 < entries[1].0);
+        assert!(!table.0.contains(NodeId(0)));
+    }
+
+    #[test]
+    fn ids_are_unique_across_the_module_tree() {
+        let mut sources = SourceFiles::new();
+        sources.add("src/main.tx", "mod a; mod b { fun g() { h(); } } fun main() { }");
+        sources.add("src/a.tx", "fun f(x: i32): i32 { x }");
+        let (tree, errs) = ModuleTree::load(&mut sources, Path::new("src/main.tx"));
+        assert_eq!(errs.len(), 0);
+        let mut ids = Ids::default();
+        for (_, module) in tree.modules() {
+            for item in &module.items {
+                ids.visit_item(item);
+            }
+        }
+        let mut sorted = ids.0.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), ids.0.len());
+        assert!(!ids.0.contains(&NodeId::DUMMY));
+        // The items of the inline module are only in the module b
+        let root = tree.module(tree.root());
+        match &root.items[1] {
+            Item::Mod(decl) => assert_eq!(decl.items.as_ref().map(Vec::len), Some(0)),
+            _ => panic!("expected a module")
+        }
+        assert_eq!(tree.module(root.children["b"]).items.len(), 1);
+    }
+}